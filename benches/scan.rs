@@ -0,0 +1,196 @@
+//! 扫描的基准测试：单行解析、单个文件的处理与整个目录的聚合。
+//! 输入由 cccost::fixtures 生成到临时目录中，不读取本机的日志，结果可在不同机器间比较。
+//!
+//! allocations 组以堆分配次数代替时间作为度量，用计数的全局分配器统计，结果在不同机器间应当一致。
+//!
+//! 运行：cargo bench，或 cargo bench -- parse_line 只运行其中一组
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fs;
+use std::hint::black_box;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use cccost::file_processor::FileProcessor;
+use cccost::fixtures::{self, FixtureOptions};
+use cccost::parse::{parse_line, ParseOptions};
+use criterion::measurement::{Measurement, ValueFormatter};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use tempfile::TempDir;
+
+/// 进程启动以来 alloc 与 realloc 的次数，包括 rayon 工作线程中的分配
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+/// 统计分配次数后交给系统分配器
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// 以分配次数为度量的 criterion Measurement
+struct Allocations;
+
+impl Measurement for Allocations {
+    type Intermediate = u64;
+    type Value = u64;
+
+    fn start(&self) -> u64 {
+        ALLOCATIONS.load(Ordering::SeqCst)
+    }
+
+    fn end(&self, start: u64) -> u64 {
+        ALLOCATIONS.load(Ordering::SeqCst) - start
+    }
+
+    fn add(&self, v1: &u64, v2: &u64) -> u64 {
+        v1 + v2
+    }
+
+    fn zero(&self) -> u64 {
+        0
+    }
+
+    fn to_f64(&self, value: &u64) -> f64 {
+        *value as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        &AllocationFormatter
+    }
+}
+
+/// 分配次数原样输出，不换算单位
+struct AllocationFormatter;
+
+impl ValueFormatter for AllocationFormatter {
+    fn scale_values(&self, _typical_value: f64, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+
+    fn scale_throughputs(&self, _typical_value: f64, throughput: &Throughput, values: &mut [f64]) -> &'static str {
+        let (count, unit) = match throughput {
+            Throughput::Bytes(count) | Throughput::BytesDecimal(count) => (*count, "allocs/B"),
+            Throughput::Elements(count) => (*count, "allocs/line"),
+        };
+        values.iter_mut().for_each(|value| *value /= count as f64);
+        unit
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+}
+
+/// 在临时目录中生成数据，返回目录（drop 时删除）与其中所有会话文件
+fn corpus(options: &FixtureOptions) -> (TempDir, Vec<PathBuf>) {
+    let dir = TempDir::new().expect("创建临时目录");
+    fixtures::generate(options, dir.path()).expect("生成测试数据");
+    let mut files = Vec::new();
+    for project in fs::read_dir(dir.path()).expect("读取临时目录") {
+        for file in fs::read_dir(project.expect("读取临时目录").path()).expect("读取项目目录") {
+            files.push(file.expect("读取项目目录").path());
+        }
+    }
+    files.sort();
+    (dir, files)
+}
+
+fn parse(c: &mut Criterion) {
+    let (_dir, files) = corpus(&FixtureOptions { days: 1, sessions_per_day: 1, turns: 500, ..Default::default() });
+    let content = fs::read_to_string(&files[0]).expect("读取会话文件");
+    let lines: Vec<&str> = content.lines().collect();
+    let options = ParseOptions::default();
+
+    let mut group = c.benchmark_group("parse_line");
+    group.throughput(Throughput::Elements(lines.len() as u64));
+    group.bench_function("session", |b| {
+        b.iter(|| {
+            for line in &lines {
+                let _ = black_box(parse_line(black_box(line), &options));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn process_file(c: &mut Criterion) {
+    let (dir, files) = corpus(&FixtureOptions { days: 1, sessions_per_day: 1, turns: 2_000, ..Default::default() });
+    let bytes = fs::metadata(&files[0]).expect("读取会话文件").len();
+
+    let mut group = c.benchmark_group("process_file");
+    group.throughput(Throughput::Bytes(bytes));
+    group.bench_function("4000_lines", |b| {
+        b.iter(|| {
+            let processor = FileProcessor::builder(dir.path().to_path_buf()).with_paths(files.clone()).build();
+            black_box(processor.process_files().expect("处理文件"))
+        })
+    });
+    group.finish();
+}
+
+fn process_directory(c: &mut Criterion) {
+    let (dir, files) = corpus(&FixtureOptions { days: 90, sessions_per_day: 20, turns: 20, ..Default::default() });
+    let bytes: u64 = files.iter().map(|file| fs::metadata(file).map_or(0, |m| m.len())).sum();
+
+    let mut group = c.benchmark_group("process_directory");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(bytes));
+    group.bench_function("90_days", |b| {
+        b.iter(|| black_box(FileProcessor::new(dir.path().to_path_buf()).process_files().expect("处理目录")))
+    });
+    group.finish();
+}
+
+/// 解析单行与处理单个文件的分配次数，优化解析路径时用来确认没有引入多余的分配
+fn allocations(c: &mut Criterion<Allocations>) {
+    let (dir, files) = corpus(&FixtureOptions { days: 1, sessions_per_day: 1, turns: 500, ..Default::default() });
+    let content = fs::read_to_string(&files[0]).expect("读取会话文件");
+    let lines: Vec<&str> = content.lines().collect();
+    let options = ParseOptions::default();
+
+    let mut group = c.benchmark_group("allocations");
+    group.throughput(Throughput::Elements(lines.len() as u64));
+    group.bench_function("parse_line", |b| {
+        b.iter(|| {
+            for line in &lines {
+                let _ = black_box(parse_line(black_box(line), &options));
+            }
+        })
+    });
+    group.bench_function("process_file", |b| {
+        b.iter(|| {
+            let processor = FileProcessor::builder(dir.path().to_path_buf()).with_paths(files.clone()).build();
+            black_box(processor.process_files().expect("处理文件"))
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse, process_file, process_directory);
+criterion_group! {
+    name = allocation_benches;
+    // 分配次数每次都相同，方差为零时无法绘制分布图
+    config = Criterion::default().with_measurement(Allocations).without_plots();
+    targets = allocations
+}
+criterion_main!(benches, allocation_benches);
@@ -0,0 +1,28 @@
+//! 以库的方式使用 cccost：扫描会话日志并按月输出 token 用量与费用。
+//!
+//! cargo run --example monthly [目录]，未指定目录时扫描 ~/.claude/projects
+
+use std::path::PathBuf;
+use cccost::file_processor::FileProcessor;
+use cccost::period::{month_of, summarize_periods};
+
+fn main() {
+    let directory = std::env::args().nth(1).map(PathBuf::from).unwrap_or_else(|| {
+        let home_dir = std::env::var("HOME").unwrap_or_else(|_| String::from("~"));
+        PathBuf::from(home_dir).join(".claude/projects")
+    });
+
+    let output = match FileProcessor::new(directory).process_files() {
+        Ok(output) => output,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let today = chrono::Utc::now().date_naive();
+    for month in summarize_periods(&output.usage, month_of, today) {
+        let cost = month.cost_usd.map_or_else(|| String::from("-"), |cost| format!("${:.2}", cost));
+        println!("{}\t{}\t{}", month.label, month.tokens.total_tokens, cost);
+    }
+}
@@ -0,0 +1,1211 @@
+//! cccost 命令行程序：按解析好的 [`Cli`] 扫描日志并分发到各个命令，main 只负责解析参数。
+//!
+//! [`run`] 返回进程的退出码，由 main 退出。除 0 表示成功、1 表示一般错误外，还有以下状态：3 超出 --token-limit，4 --fail-over-budget 时有项目超出预算，
+//! 5 --strict 时有文件读取失败或处理时崩溃，130 被 Ctrl-C 中断。
+
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt::format::FmtSpan;
+use crate::{backup, blocks, bucketing, daily, distribution, export, file_processor, fixtures, i18n, import, item, merge, period, tr};
+use crate::archive::Archive;
+use crate::backup::BackupMode;
+use crate::blocks::BurnRate;
+use crate::budget::{BudgetReport, ProjectBudgets};
+use crate::cache::FileCache;
+use crate::cache_report::CacheReport;
+use crate::claude_json::ClaudeJson;
+use crate::compare::Comparison;
+use crate::trend::Trend;
+use crate::config::Config;
+use crate::daily::{DailySeries, Streaks, TodaySummary};
+use crate::diagnostics::Diagnostics;
+use crate::doctor::{Doctor, Status};
+use crate::cli::{CacheAction, Cli, Command, GroupBy, ImportFormat, LogFormat, Metric, OutputFormat, SourceSelection, TimelinePeriod};
+use crate::item::GroupUsage;
+use crate::render::{Renderer, Report, ReportMeta};
+use crate::file_processor::{FileOffsets, FileProcessor, ProcessEvent};
+use crate::fixtures::FixtureOptions;
+use crate::limit::LimitStatus;
+use crate::tail::SessionTail;
+use crate::remote::RemoteSource;
+use crate::matrix::ProjectMatrix;
+#[cfg(feature = "notify")]
+use crate::notify::{self, NotifyConditions, NotifyPayload};
+use crate::mapping::FieldMapping;
+use crate::period::PeriodKind;
+use crate::source::{self, CodexSource, CombinedSource, DirectorySource, GeminiSource, PathsSource, SourceInput, SourceTool};
+use crate::table_renderer::{format_bytes, format_cost, format_count, format_duration, CsvRenderer, DEFAULT_VERTICAL_THRESHOLD, DEFAULT_ZEBRA_COLOR, JsonRenderer, MarkdownRenderer, TableRenderer};
+#[cfg(feature = "tui")]
+use crate::tui;
+
+/// 超出 --token-limit 时的退出码，便于脚本报警
+const EXIT_LIMIT_EXCEEDED: i32 = 3;
+
+/// --fail-over-budget 时有项目超出预算的退出码
+const EXIT_OVER_BUDGET: i32 = 4;
+
+/// --strict 时有文件读取失败或处理时崩溃的退出码
+const EXIT_FILE_FAILED: i32 = 5;
+
+/// 被 Ctrl-C 中断时的退出码（128 + SIGINT），与其他失败区分
+const EXIT_INTERRUPTED: i32 = 130;
+
+/// --watch 等待下一次刷新时检查 Ctrl-C 的间隔
+const INTERRUPT_POLL: Duration = Duration::from_millis(100);
+
+/// top 未指定数量时显示的天数
+const DEFAULT_TOP_DAYS: usize = 10;
+
+/// blocks --recent 显示的天数
+const RECENT_BLOCK_DAYS: i64 = 3;
+
+/// 执行命令行给出的命令，输出写到 stdout / stderr，返回进程的退出码，退出码见本模块的说明
+pub fn run(cli: Cli) -> i32 {
+    match execute(cli) {
+        Ok(()) => 0,
+        Err(code) => code,
+    }
+}
+
+/// run 的实际执行；出错或需要以非零状态结束时，在输出说明之后返回 Err(退出码)
+fn execute(mut cli: Cli) -> Result<(), i32> {
+    i18n::init(cli.lang.as_deref());
+    init_tracing(cli.log_level.as_deref(), cli.log_format);
+    install_interrupt_handler();
+
+    if let Some(Command::Cache { action: CacheAction::Clear }) = cli.command {
+        let cache_path = FileCache::default_path();
+        match FileCache::clear(&cache_path) {
+            Ok(()) => println!("{}", tr!("main.cache_cleared", cache_path.display())),
+            Err(e) => {
+                eprintln!("{}", tr!("main.cache_clear_failed", e));
+                return Err(1);
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::GenFixtures { days, sessions_per_day, turns, seed, start, out }) = &cli.command {
+        let options = FixtureOptions { start: *start, days: *days, sessions_per_day: *sessions_per_day, turns: *turns, seed: *seed };
+        match fixtures::generate(&options, out) {
+            Ok(summary) => println!(
+                "{}",
+                tr!("main.fixtures_written", summary.files, summary.lines, format_bytes(summary.bytes), out.display())
+            ),
+            Err(e) => {
+                eprintln!("{}", tr!("main.fixtures_failed", e));
+                return Err(1);
+            }
+        }
+        return Ok(());
+    }
+
+    if cli.source == SourceSelection::All && (cli.stdin || !cli.paths.is_empty()) {
+        eprintln!("{}", tr!("main.source_all_with_paths"));
+        return Err(1);
+    }
+    if cli.source != SourceSelection::Claude && cli.stdin {
+        eprintln!("{}", tr!("main.stdin_claude_only"));
+        return Err(1);
+    }
+    if cli.source != SourceSelection::Claude && cli.remote.is_some() {
+        eprintln!("{}", tr!("main.remote_claude_only"));
+        return Err(1);
+    }
+    if cli.explain.is_some() && cli.command.is_some() {
+        eprintln!("{}", tr!("main.explain_command"));
+        return Err(1);
+    }
+
+    if let Some(Command::Backup { to, link, compress }) = &cli.command {
+        if cli.source != SourceSelection::Claude || cli.stdin || cli.remote.is_some() {
+            eprintln!("{}", tr!("main.backup_claude_only"));
+            return Err(1);
+        }
+        let mode = match (link, compress) {
+            (true, _) => BackupMode::Link,
+            (_, true) => BackupMode::Compress,
+            _ => BackupMode::Copy,
+        };
+        let result = claude_inputs(&cli)
+            .map_err(|e| e.to_string())
+            .and_then(|inputs| backup::backup(inputs, to, mode).map_err(|e| e.to_string()));
+        match result {
+            Ok(summary) => println!(
+                "{}",
+                tr!("main.backed_up", summary.copied, format_bytes(summary.bytes), to.display(), summary.unchanged)
+            ),
+            Err(e) => {
+                eprintln!("{}", tr!("main.backup_failed", e));
+                return Err(1);
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Tail { session, interval, idle }) = &cli.command {
+        if cli.source != SourceSelection::Claude || cli.stdin || cli.remote.is_some() {
+            eprintln!("{}", tr!("main.tail_claude_only"));
+            return Err(1);
+        }
+        let path = claude_inputs(&cli)
+            .map_err(|e| e.to_string())
+            .and_then(|inputs| source::find_session(inputs, session).map_err(|e| e.to_string()));
+        match path {
+            Ok(path) if path.extension().is_some_and(|extension| extension == "gz") => {
+                eprintln!("{}", tr!("main.tail_compressed", path.display()));
+                return Err(1);
+            }
+            Ok(path) => tail(&cli, SessionTail::new(path), Duration::from_secs(*interval), Duration::from_secs(*idle))?,
+            Err(e) => {
+                eprintln!("{}", e);
+                return Err(1);
+            }
+        }
+        return Ok(());
+    }
+
+    // 配置文件有错误时同样需要运行，在读取配置之前处理
+    if let Some(Command::Doctor) = cli.command {
+        let checks = doctor(&cli).run();
+        for check in &checks {
+            println!("{}", check);
+        }
+        let count = |status| checks.iter().filter(|check| check.status == status).count();
+        println!();
+        match (count(Status::Fail), count(Status::Warn)) {
+            (0, 0) => println!("{}", tr!("doctor.passed")),
+            (0, warnings) => println!("{}", tr!("doctor.warnings", warnings)),
+            (failures, _) => {
+                println!("{}", tr!("doctor.failed", failures));
+                return Err(1);
+            }
+        }
+        return Ok(());
+    }
+
+    let config = match Config::load(&Config::default_path()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Err(1);
+        }
+    };
+    let field_mapping = match &cli.source {
+        SourceSelection::Custom(name) => match config.sources.get(name) {
+            Some(mapping) if mapping.directory.is_none() && cli.paths.is_empty() => {
+                eprintln!("{}", tr!("main.mapping_without_directory", name));
+                return Err(1);
+            }
+            Some(mapping) => {
+                let mut mapping = mapping.clone();
+                mapping.label.get_or_insert_with(|| name.clone());
+                Some(mapping)
+            }
+            None => {
+                eprintln!("{}", tr!("main.unknown_mapping", Config::default_path().display(), name));
+                return Err(1);
+            }
+        },
+        _ => None,
+    };
+    let billing_day = cli.billing_day.or(config.billing.day).unwrap_or(1);
+    // 各报告中的"今天"与按日分组使用同一个时区
+    let today = bucketing::today(&cli.timezone);
+    if cli.this_month {
+        cli.since = Some(period::billing_cycle(today, billing_day).0);
+    }
+
+    // blocks --watch 的每次重新扫描只解析各文件新追加的部分
+    let resume = matches!(cli.command, Some(Command::Blocks { watch: Some(_), .. })).then(FileOffsets::default);
+
+    // merge 不扫描本机日志，合并导出的数据之后与默认报告（或 --by-person 的按导出者报告）相同
+    let merging = matches!(cli.command, Some(Command::Merge { .. }));
+    let result = if let Some(Command::Merge { .. }) = cli.command
+        && let Some(Command::Merge { files, by_person }) = cli.command.take() {
+        if cli.group_by != GroupBy::Model {
+            eprintln!("{}", tr!("main.merge_group_by"));
+            return Err(1);
+        }
+        if by_person {
+            cli.group_by = GroupBy::Person;
+        }
+        let mut exports = Vec::with_capacity(files.len());
+        for file in &files {
+            let export = std::fs::read_to_string(file)
+                .map_err(|e| tr!("main.read_failed", file.display(), e))
+                .and_then(|content| merge::parse_export(file, &content).map_err(|e| tr!("main.merge_failed", file.display(), e)));
+            match export {
+                Ok(export) => exports.push(export),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return Err(1);
+                }
+            }
+        }
+        Ok(file_processor::ProcessOutput::from_exports(exports))
+    } else {
+        scan(&cli, field_mapping.as_ref(), !cli.quiet && !cli.machine_readable_output(), resume.as_ref())
+    };
+    let mut output = match result {
+        Ok(output) => output,
+        Err(file_processor::ProcessError::Cancelled) => return Err(interrupted(tr!("main.interrupted"))),
+        Err(e) => {
+            eprintln!("{}", e);
+            return Err(1);
+        }
+    };
+
+    // 归档只写入日志中的数据，不包含 --with-archive 合并进来的部分
+    let archive = Archive::new(Archive::default_path());
+    if let Some(Command::Archive) = cli.command {
+        match archive.append(&output.project_models) {
+            Ok(appended) => println!("{}", tr!("main.archived", appended, archive.path().display())),
+            Err(e) => {
+                eprintln!("{}", tr!("main.archive_write_failed", e));
+                return Err(1);
+            }
+        }
+        return Ok(());
+    }
+    // 合并的数据中项目为导出者的标签，不写入也不合并归档
+    if config.archive.auto
+        && !merging
+        && let Err(e) = archive.append(&output.project_models) {
+        eprintln!("{}", tr!("main.auto_archive_failed", e));
+    }
+    let archive_conflicts = if cli.with_archive && !merging {
+        match archive.load() {
+            Ok(archived) => output.merge_archive(archived),
+            Err(e) => {
+                eprintln!("{}", tr!("main.archive_read_failed", e));
+                Vec::new()
+            }
+        }
+    } else {
+        Vec::new()
+    };
+    // import 在并入导出的数据之后与默认报告相同
+    if let Some(Command::Import { .. }) = cli.command
+        && let Some(Command::Import { format: ImportFormat::Ccusage { file, archive: to_archive } }) = cli.command.take() {
+        let imported = std::fs::read_to_string(&file)
+            .map_err(|e| tr!("main.read_failed", file.display(), e))
+            .and_then(|content| import::parse_ccusage(&content).map_err(|e| tr!("main.import_failed", file.display(), e)));
+        let imported = match imported {
+            Ok(imported) => imported,
+            Err(e) => {
+                eprintln!("{}", e);
+                return Err(1);
+            }
+        };
+        let merged = output.merge_imported(&file, imported);
+        if to_archive {
+            match archive.append(&merged) {
+                Ok(appended) => eprintln!("{}", tr!("main.imported_archived", appended, archive.path().display())),
+                Err(e) => {
+                    eprintln!("{}", tr!("main.archive_write_failed", e));
+                    return Err(1);
+                }
+            }
+        }
+    }
+
+    // 覆盖范围基于全部数据，之后再按 --since / --until 过滤
+    // 上限按当前计费窗口计算，与 --since / --until 无关
+    let limit_status = cli.token_limit.or(config.limit.tokens).map(|limit| {
+        let window = cli.limit_window.or(config.limit.window).unwrap_or_default();
+        LimitStatus::evaluate(&output.usage, limit, window, billing_day, today)
+    });
+    // 项目预算同样按当前计费月计算
+    let budgets = ProjectBudgets::new(&config.budgets.projects);
+    let budget_report = BudgetReport::evaluate(&output.project_models, &budgets, billing_day, today);
+    let project_budgets = (!budgets.is_empty()).then_some(&budget_report);
+    // 合计包括归档与导入的数据，在按 --since / --until 过滤之前取出
+    let explanation = cli.explain.map(|date| output.explain(date));
+    let coverage = output.date_coverage();
+    // 对比时间段和趋势的上一区间在所选范围之外，需要在过滤之前计算；未指定起止日期时以数据覆盖范围为准
+    let selected_range = coverage.map(|(earliest, latest)| {
+        (cli.since.unwrap_or(earliest), cli.until.unwrap_or(latest.max(today)))
+    });
+    let comparison = cli.compare.zip(selected_range).map(|(spec, (start, end))| {
+        Comparison::build(&output.usage, spec, start, end, billing_day)
+    });
+    let trend = selected_range.and_then(|(start, end)| Trend::compute(&output.usage, start, end));
+    let new_models = output.new_models(cli.since, cli.until);
+    output.retain_dates(cli.since, cli.until);
+    let gaps = coverage.filter(|_| cli.show_gaps).map(|(earliest, latest)| {
+        let from = cli.since.map_or(earliest, |since| since.max(earliest));
+        let to = cli.until.map_or(latest, |until| until.min(latest));
+        output.missing_days(from, to)
+    });
+
+    // ~/.claude.json 只是补充信息，读取失败时静默忽略
+    let claude_json = if cli.no_claude_json {
+        ClaudeJson::default()
+    } else {
+        ClaudeJson::load(&ClaudeJson::default_path()).unwrap_or_default()
+    };
+
+    let table_renderer = TableRenderer::new()
+        .with_timezone(cli.timezone)
+        .with_color(cli.color)
+        .with_message_column(cli.messages)
+        .with_activity_column(cli.activity)
+        .with_group_by(cli.group_by)
+        .with_full_model_names(cli.full_model_names)
+        .with_merged_cells(cli.merged_cells)
+        .with_day_subtotals(cli.subtotals)
+        .with_month_subtotals(cli.month_subtotals.then_some(billing_day))
+        .with_layout(cli.table_layout())
+        .with_vertical_threshold(config.display.vertical_below.unwrap_or(DEFAULT_VERTICAL_THRESHOLD))
+        .with_width(cli.width.map(usize::from))
+        .with_all_columns(cli.all_columns)
+        .with_weekday(cli.weekday)
+        .with_week_number(cli.week_number)
+        .with_dim_weekends(cli.dim_weekends)
+        .with_model_colors(config.display.model_colors.clone())
+        .with_meta((!cli.no_footer).then(|| {
+            ReportMeta::new(chrono::Utc::now(), data_sources(&cli, field_mapping.as_ref(), &output.diagnostics), &output.diagnostics, &output.usage)
+                .with_timezone(cli.timezone)
+                .with_label(cli.label.clone())
+        }))
+        .with_style(cli.style.or(config.display.style).unwrap_or_default())
+        .with_zebra(cli.zebra.then(|| config.display.zebra_color.clone().unwrap_or_else(|| DEFAULT_ZEBRA_COLOR.to_string())))
+        .with_split_snapshots(cli.split_snapshots)
+        .with_app_startups(claude_json.num_startups);
+    // 报告写入 stdout，附带的诊断与提示写入 stderr，重定向 stdout 时不会混入报告
+    let mut out = std::io::stdout();
+    let mut err = std::io::stderr();
+    if let Some(explanation) = explanation {
+        if cli.json_output() {
+            check_written(table_renderer.print_json(&explanation, &mut out))?;
+        } else {
+            check_written(table_renderer.render_explain_table(&explanation, &mut out))?;
+        }
+        return Ok(());
+    }
+    match cli.command {
+        Some(Command::Tools { daily, json: true }) => {
+            check_written(table_renderer.print_json(&table_renderer.counts_json("tool", &output.tools, daily), &mut out))?;
+        }
+        Some(Command::Tools { daily, json: false }) => {
+            check_written(table_renderer.render_count_table(&tr!("report.title.tools"), i18n::label("Tool"), &output.tools, daily, &mut out))?;
+        }
+        Some(Command::Stats { stop_reasons, by_version, by_hour, message_sizes, latency, by_weekday, week_start, daily, json }) => {
+            // 未指定任何视图时显示全部；消息大小与用时需要额外的解析，只在明确指定时计算
+            let show_all = !stop_reasons && !by_version && !by_hour && !by_weekday && !message_sizes && !latency;
+            let sizes = message_sizes.then(|| distribution::message_size_stats(&output.message_sizes, |model| table_renderer.model_name(model)));
+            let hours = (by_hour || show_all).then(|| distribution::hour_of_day(&output.hourly));
+            let weekdays = selected_range
+                .filter(|_| by_weekday || show_all)
+                .map(|(start, end)| distribution::weekday_usage(&output.usage, start, end, week_start));
+            if json {
+                let mut sections = serde_json::Map::new();
+                if stop_reasons || show_all {
+                    sections.insert(
+                        "stop_reasons".to_string(),
+                        table_renderer.counts_json("reason", &output.stop_reasons, daily),
+                    );
+                }
+                if by_version || show_all {
+                    sections.insert("versions".to_string(), table_renderer.versions_json(&output.versions));
+                }
+                if let Some(hours) = &hours {
+                    sections.insert("by_hour".to_string(), serde_json::to_value(hours).unwrap_or_default());
+                }
+                if let Some(weekdays) = &weekdays {
+                    sections.insert("by_weekday".to_string(), serde_json::to_value(weekdays).unwrap_or_default());
+                }
+                if let Some(sizes) = &sizes {
+                    sections.insert("message_sizes".to_string(), serde_json::to_value(sizes).unwrap_or_default());
+                }
+                if latency {
+                    sections.insert("latency".to_string(), table_renderer.latency_json(&output.latencies));
+                }
+                sections.insert(
+                    "new_models".to_string(),
+                    serde_json::to_value(&new_models).unwrap_or_default(),
+                );
+                sections.insert("summary".to_string(), serde_json::json!({ "trend": trend }));
+                check_written(table_renderer.print_json(&sections, &mut out))?;
+            } else {
+                if stop_reasons || show_all {
+                    check_written(table_renderer.render_count_table(&tr!("report.title.stop_reasons"), i18n::label("Reason"), &output.stop_reasons, daily, &mut out))?;
+                }
+                if by_version || show_all {
+                    check_written(table_renderer.render_version_table(&output.versions, &mut out))?;
+                }
+                if let Some(hours) = &hours {
+                    check_written(table_renderer.render_hour_of_day_table(hours, &mut out))?;
+                }
+                if let Some(weekdays) = &weekdays {
+                    check_written(table_renderer.render_weekday_table(weekdays, &mut out))?;
+                }
+                if let Some(sizes) = &sizes {
+                    check_written(table_renderer.render_message_size_table(sizes, &mut out))?;
+                }
+                if latency {
+                    check_written(table_renderer.render_latency_table(&output.latencies, &mut out))?;
+                }
+            }
+        }
+        Some(Command::Sessions { sort, limit, json }) => {
+            let mut sessions = std::mem::take(&mut output.sessions);
+            sort_sessions(&mut sessions, sort);
+            if let Some(limit) = limit {
+                sessions.truncate(limit);
+            }
+            if json {
+                check_written(table_renderer.render_session_json(&sessions, &mut out))?;
+            } else {
+                check_written(table_renderer.render_session_table(&sessions, &mut out))?;
+            }
+        }
+        #[cfg(feature = "notify")]
+        Some(Command::Notify { ref webhook, daily_cost_above, budget_above, always, timeout, dry_run }) => {
+            let conditions = NotifyConditions {
+                daily_cost_above: daily_cost_above.or(config.notify.daily_cost_above),
+                budget_above: budget_above.or(config.notify.budget_above),
+                always,
+            };
+            if conditions.is_empty() {
+                eprintln!("{}", tr!("main.notify_no_conditions"));
+                return Err(1);
+            }
+            let webhook = webhook.clone().or_else(|| config.notify.webhook.clone());
+            if webhook.is_none() && !dry_run {
+                eprintln!("{}", tr!("main.notify_no_webhook"));
+                return Err(1);
+            }
+            let mut payload = NotifyPayload::build(&output.usage, limit_status.as_ref(), today, billing_day);
+            let triggered = payload.evaluate(&conditions);
+            if dry_run {
+                if !triggered {
+                    eprintln!("{}", tr!("main.notify_not_triggered"));
+                }
+                // 原样输出将要发送的内容，不经过 print_json 附加 meta
+                println!("{}", serde_json::to_string_pretty(&payload).unwrap_or_default());
+            } else if let (true, Some(webhook)) = (triggered, webhook)
+                && let Err(e) = notify::send(&webhook, &payload, std::time::Duration::from_secs(timeout)) {
+                eprintln!("{}", e);
+                return Err(1);
+            }
+            return Ok(());
+        }
+        #[cfg(feature = "tui")]
+        Some(Command::Tui { refresh }) => {
+            if cli.stdin {
+                eprintln!("{}", tr!("main.tui_stdin"));
+                return Err(1);
+            }
+            let reload = || {
+                let mut output = scan(&cli, field_mapping.as_ref(), false, None).map_err(|e| e.to_string())?;
+                if cli.with_archive
+                    && let Ok(archived) = archive.load() {
+                    output.merge_archive(archived);
+                }
+                output.retain_dates(cli.since, cli.until);
+                Ok(output)
+            };
+            let refresh = refresh.map(std::time::Duration::from_secs);
+            if let Err(e) = tui::run(output, &table_renderer, billing_day, refresh, interrupt_flag(), reload) {
+                eprintln!("{}", tr!("main.tui_failed", e));
+                return Err(1);
+            }
+            if interrupt_flag().load(Ordering::Relaxed) {
+                return Err(interrupted(tr!("main.interrupted")));
+            }
+            return Ok(());
+        }
+        Some(Command::Matrix { months, by, json }) => {
+            let end = selected_range.map_or(today, |(_, end)| end);
+            let matrix = ProjectMatrix::build(&output.project_models, months, end, billing_day, by);
+            if json {
+                check_written(table_renderer.print_json(&matrix, &mut out))?;
+            } else {
+                check_written(table_renderer.render_project_matrix(&matrix, by, &mut out))?;
+            }
+        }
+        Some(Command::CacheReport { week_start, json }) => {
+            let report = CacheReport::build(&output.usage, week_start);
+            if json {
+                check_written(table_renderer.print_json(&report, &mut out))?;
+            } else {
+                check_written(table_renderer.render_cache_report(&report, &mut out))?;
+            }
+        }
+        Some(Command::Timeline { period: granularity, week_start, json, .. }) => {
+            let (kind, periods) = match granularity {
+                TimelinePeriod::Week => (PeriodKind::Week, period::summarize_periods(&output.usage, period::week_of(week_start), today)),
+                TimelinePeriod::Month => (
+                    PeriodKind::Month,
+                    period::summarize_periods(&output.usage, period::billing_month_of(billing_day), today),
+                ),
+            };
+            if json {
+                check_written(table_renderer.print_json(&table_renderer.model_timeline_json(kind, &periods), &mut out))?;
+            } else {
+                check_written(table_renderer.render_model_timeline(kind, &periods, &mut out))?;
+            }
+        }
+        Some(Command::Streaks { min_tokens, json }) => {
+            let Some((start, end)) = selected_range else {
+                println!("{}", tr!("report.no_usage"));
+                return Ok(());
+            };
+            let streaks = Streaks::compute(&DailySeries::from_usage(&output.usage), start, end, min_tokens);
+            if json {
+                check_written(table_renderer.print_json(&streaks, &mut out))?;
+            } else {
+                check_written(table_renderer.render_streaks(&streaks, &mut out))?;
+            }
+        }
+        Some(Command::Today { json }) => {
+            let today = TodaySummary::build(&output.usage, &output.hourly, &output.sessions, chrono::Utc::now().with_timezone(&cli.timezone), billing_day);
+            if json {
+                check_written(table_renderer.print_json(&today, &mut out))?;
+            } else {
+                check_written(table_renderer.render_today(&today, &mut out))?;
+            }
+        }
+        Some(Command::Day { date, hourly: true, json }) => {
+            let hours = daily::hourly_usage(&output.hourly, date);
+            if json {
+                check_written(table_renderer.print_json(&hours, &mut out))?;
+            } else {
+                check_written(table_renderer.render_hourly_table(date, &hours, &mut out))?;
+            }
+        }
+        Some(Command::Day { date, hourly: false, json }) => {
+            let key = date.to_string();
+            output.usage.retain(|daily| daily.date == key);
+            let days = period::summarize_periods(&output.usage, period::day_of, today);
+            if json {
+                check_written(table_renderer.print_json(&table_renderer.daily_json(&days, &DailySeries::default(), None, None, false), &mut out))?;
+            } else {
+                check_written(table_renderer.render_period_table(PeriodKind::Day, &days, true, &mut out))?;
+            }
+        }
+        Some(Command::Top { sessions: Some(count), by, json, .. }) => {
+            let mut sessions = std::mem::take(&mut output.sessions);
+            let total_tokens: u64 = sessions.iter().map(|session| session.aggregate.usage.total()).sum();
+            let total_cost: f64 = sessions.iter().filter_map(|session| session.cost_usd).sum();
+            sort_sessions(&mut sessions, by);
+            sessions.truncate(count);
+            let shares: Vec<f64> = sessions
+                .iter()
+                .map(|session| match by {
+                    Metric::Tokens if total_tokens > 0 => session.aggregate.usage.total() as f64 / total_tokens as f64 * 100.0,
+                    Metric::Cost if total_cost > 0.0 => session.cost_usd.unwrap_or(0.0) / total_cost * 100.0,
+                    _ => 0.0,
+                })
+                .collect();
+            if json {
+                check_written(table_renderer.print_json(&table_renderer.top_sessions_json(&sessions, &shares), &mut out))?;
+            } else {
+                check_written(table_renderer.render_top_sessions_table(&sessions, &shares, &mut out))?;
+            }
+        }
+        Some(Command::Top { days, by, json, .. }) => {
+            let mut summaries = period::summarize_periods(&output.usage, period::day_of, today);
+            let total_tokens: u64 = summaries.iter().map(|day| day.tokens.total_tokens).sum();
+            let total_cost: f64 = summaries.iter().filter_map(|day| day.cost_usd).sum();
+            // 同值时按日期排序，保证输出稳定
+            match by {
+                Metric::Tokens => summaries.sort_by(|a, b| {
+                    b.tokens.total_tokens.cmp(&a.tokens.total_tokens).then(a.start.cmp(&b.start))
+                }),
+                Metric::Cost => summaries.sort_by(|a, b| {
+                    b.cost_usd.unwrap_or(0.0).total_cmp(&a.cost_usd.unwrap_or(0.0)).then(a.start.cmp(&b.start))
+                }),
+            }
+            summaries.truncate(days.unwrap_or(DEFAULT_TOP_DAYS));
+            let share = |day: &period::PeriodSummary| match by {
+                Metric::Tokens if total_tokens > 0 => day.tokens.total_tokens as f64 / total_tokens as f64 * 100.0,
+                Metric::Cost if total_cost > 0.0 => day.cost_usd.unwrap_or(0.0) / total_cost * 100.0,
+                _ => 0.0,
+            };
+            let shares: Vec<f64> = summaries.iter().map(share).collect();
+            if json {
+                check_written(table_renderer.print_json(&table_renderer.top_days_json(&summaries, &shares), &mut out))?;
+            } else {
+                check_written(table_renderer.render_top_days_table(&summaries, &shares, &mut out))?;
+            }
+        }
+        Some(Command::Blocks { active: true, watch, json, .. }) => {
+            if watch.is_some() && cli.stdin {
+                eprintln!("{}", tr!("main.watch_stdin"));
+                return Err(1);
+            }
+            loop {
+                let now = chrono::Utc::now();
+                let mut block = blocks::build_blocks(&output.hourly, now, &cli.timezone).into_iter().find(|block| block.active);
+                if let Some(block) = &mut block {
+                    BurnRate::compute(&output.recent, now).project(block);
+                }
+                if json {
+                    check_written(table_renderer.print_json(&block, &mut out))?;
+                } else {
+                    if watch.is_some() {
+                        // 清屏后从左上角重新输出
+                        print!("\x1b[2J\x1b[H");
+                    }
+                    check_written(table_renderer.render_active_block(block.as_ref(), &mut out))?;
+                }
+                let Some(interval) = watch else {
+                    break;
+                };
+                if !json {
+                    println!("\n{}", tr!("main.watch_hint", interval));
+                }
+                // 等待或重新扫描时按下 Ctrl-C，屏幕上保留的是上一次完整扫描的报告
+                if !wait_unless_interrupted(Duration::from_secs(interval)) {
+                    return Err(interrupted(tr!("main.watch_interrupted")));
+                }
+                match scan(&cli, field_mapping.as_ref(), false, resume.as_ref()) {
+                    Ok(mut next) => {
+                        next.retain_dates(cli.since, cli.until);
+                        output = next;
+                    }
+                    Err(file_processor::ProcessError::Cancelled) => return Err(interrupted(tr!("main.watch_interrupted"))),
+                    Err(e) => eprintln!("{}", tr!("main.rescan_failed", e)),
+                }
+            }
+        }
+        Some(Command::Blocks { recent, json, .. }) => {
+            let now = chrono::Utc::now();
+            let mut blocks = blocks::build_blocks(&output.hourly, now, &cli.timezone);
+            if recent {
+                let cutoff = now - chrono::TimeDelta::days(RECENT_BLOCK_DAYS);
+                blocks.retain(|block| block.start >= cutoff);
+            }
+            if json {
+                check_written(table_renderer.print_json(&blocks, &mut out))?;
+            } else {
+                check_written(table_renderer.render_block_table(&blocks, &mut out))?;
+            }
+        }
+        Some(Command::ByModel { json: true }) => check_written(table_renderer.render_model_total_json(&output.usage, &mut out))?,
+        Some(Command::ByModel { json: false }) => check_written(table_renderer.render_model_total_table(&output.usage, &mut out))?,
+        Some(Command::ByProject { json: true }) => {
+            check_written(table_renderer.render_project_cost_json(&output.project_models, &output.sessions, project_budgets, &mut out))?;
+        }
+        Some(Command::ByProject { json: false }) => {
+            check_written(table_renderer.render_project_cost_table(&output.project_models, &output.sessions, project_budgets, &mut out))?;
+        }
+        Some(Command::Budget { json: true }) => check_written(table_renderer.print_json(&budget_report, &mut out))?,
+        Some(Command::Budget { json: false }) => {
+            if budgets.is_empty() {
+                eprintln!("{}", tr!("main.no_budgets", Config::default_path().display()));
+            }
+            check_written(table_renderer.render_budget_table(&budget_report, &mut out))?;
+        }
+        Some(Command::Monthly { breakdown, json }) => {
+            let months = period::summarize_periods(&output.usage, period::billing_month_of(billing_day), today);
+            if json {
+                check_written(table_renderer.print_json(&table_renderer.periods_json(PeriodKind::Month, &months, breakdown), &mut out))?;
+            } else {
+                check_written(table_renderer.render_period_table(PeriodKind::Month, &months, breakdown, &mut out))?;
+            }
+        }
+        Some(Command::Weekly { last, week_start, breakdown, json }) => {
+            let mut weeks = period::summarize_periods(&output.usage, period::week_of(week_start), today);
+            period::fill_previous_totals(&mut weeks);
+            if let Some(last) = last {
+                weeks.drain(..weeks.len().saturating_sub(last));
+            }
+            if json {
+                check_written(table_renderer.print_json(&table_renderer.periods_json(PeriodKind::Week, &weeks, breakdown), &mut out))?;
+            } else {
+                check_written(table_renderer.render_period_table(PeriodKind::Week, &weeks, breakdown, &mut out))?;
+            }
+        }
+        Some(Command::Projects { json: true }) => {
+            check_written(table_renderer.render_project_json(&output.projects(), &claude_json.project_last_opened, &mut out))?;
+        }
+        Some(Command::Projects { json: false }) => {
+            check_written(table_renderer.render_project_table(&output.projects(), &claude_json.project_last_opened, &mut out))?;
+        }
+        // 渲染使用情况表格
+        _ => {
+            let rows = match cli.group_by {
+                GroupBy::Model => output.usage.iter().cloned().map(GroupUsage::from).collect(),
+                GroupBy::Branch => output.branches.clone(),
+                GroupBy::Project => output.projects(),
+                GroupBy::Source => output.sources.clone(),
+                GroupBy::Person => output.projects(),
+            };
+            let report = Report::new(cli.group_by, rows, &output.usage, today);
+            // 补充的日期从 --since 开始，到 --until（不晚于今天）为止
+            let fill_range = report.range.filter(|_| cli.fill_gaps).map(|(earliest, latest)| {
+                (cli.since.unwrap_or(earliest), cli.until.map_or(latest, |until| until.min(today).max(latest)))
+            });
+            let report = report
+                .with_filled_gaps(fill_range)
+                .with_cumulative(cli.cumulative)
+                .with_moving_average(cli.moving_avg, cli.since)
+                .with_day_change(cli.day_change);
+            if let Some(directory) = &cli.export_dir {
+                let days = table_renderer.daily_json(
+                    &report.days,
+                    &report.series,
+                    report.cumulative,
+                    report.moving_average.as_ref().map(|(_, averages)| averages),
+                    report.day_change,
+                );
+                let days: Vec<(chrono::NaiveDate, serde_json::Value)> = match days {
+                    serde_json::Value::Array(days) => report.days.iter().map(|day| day.start).zip(days).collect(),
+                    _ => Vec::new(),
+                };
+                let index = serde_json::json!({
+                    "days": days.iter().map(|(date, _)| date.to_string()).collect::<Vec<_>>(),
+                    "totals": report.totals,
+                });
+                match export::export_days(directory, &days, &index, cli.prune) {
+                    Ok(summary) => eprintln!(
+                        "{}{}",
+                        tr!("main.exported", days.len(), directory.display(), summary.written, summary.unchanged),
+                        if cli.prune { tr!("main.exported_pruned", summary.pruned) } else { String::new() }
+                    ),
+                    Err(e) => {
+                        eprintln!("{}", tr!("main.export_failed", directory.display(), e));
+                        return Err(1);
+                    }
+                }
+            }
+            let renderer: &dyn Renderer = match cli.output_format() {
+                OutputFormat::Table => &table_renderer,
+                OutputFormat::Json => &JsonRenderer(&table_renderer),
+                OutputFormat::Csv => &CsvRenderer(&table_renderer),
+                OutputFormat::Markdown => &MarkdownRenderer(&table_renderer),
+            };
+            check_written(renderer.render(&report, &mut out))?;
+        }
+    }
+    if cli.command.is_none()
+        && cli.output_format() == OutputFormat::Table
+        && let Some(comparison) = &comparison {
+        check_written(table_renderer.render_comparison_table(comparison, &mut out))?;
+    }
+
+    check_written(table_renderer.render_diagnostics(&output.diagnostics, cli.verbose, cli.max_file_size, &mut err))?;
+    check_written(table_renderer.render_coverage(coverage, cli.since, gaps.as_deref(), &mut err))?;
+    check_written(table_renderer.render_archive_conflicts(&archive_conflicts, &mut err))?;
+    check_written(table_renderer.render_new_models(&new_models, &mut err))?;
+    if let Some(comparison) = &comparison {
+        check_written(table_renderer.render_comparison_summary(comparison, &mut err))?;
+    }
+    if let (None | Some(Command::Stats { .. }), Some(trend)) = (&cli.command, &trend) {
+        check_written(table_renderer.render_trend(trend, &mut err))?;
+    }
+    if let Some(status) = &limit_status {
+        check_written(table_renderer.render_limit_status(status, &mut err))?;
+    }
+    // 来源说明是报告的最后一行；JSON 中已包含在 meta 里，CSV 中不输出以免破坏格式
+    let csv_output = cli.command.is_none() && cli.output_format() == OutputFormat::Csv;
+    if !cli.json_output() && !csv_output {
+        check_written(table_renderer.render_footer(&mut out))?;
+    }
+    if limit_status.as_ref().is_some_and(LimitStatus::exceeded) {
+        return Err(EXIT_LIMIT_EXCEEDED);
+    }
+    if cli.fail_over_budget && budget_report.any_over_budget() {
+        if !cli.json_output() {
+            eprintln!("{}", tr!("main.over_budget", budget_report.projects.iter().filter(|status| status.over_budget).count()));
+        }
+        return Err(EXIT_OVER_BUDGET);
+    }
+    let failed_files = output.diagnostics.read_errors.len() + output.diagnostics.panicked_files.len();
+    if cli.strict && failed_files > 0 {
+        if !cli.json_output() {
+            eprintln!("{}", tr!("main.strict_failed", failed_files));
+        }
+        return Err(EXIT_FILE_FAILED);
+    }
+    Ok(())
+}
+
+/// PATH、--dir 或 ~/.claude/projects 中的 Claude Code 日志文件，与扫描时的查找方式相同
+fn claude_inputs(cli: &Cli) -> Result<Vec<SourceInput>, file_processor::ProcessError> {
+    let directory = cli.claude_projects_dir();
+    let processor = FileProcessor::builder(directory.clone())
+        .with_extra_extensions(cli.extensions.clone())
+        .with_content_sniffing(cli.sniff)
+        .build();
+    match (&cli.dir, cli.paths.is_empty()) {
+        (_, false) => processor.inputs(PathsSource::new(cli.paths.clone(), directory)),
+        (Some(_), true) => processor.inputs(PathsSource::new(vec![directory.clone()], directory)),
+        (None, true) => processor.inputs(DirectorySource::new(directory)),
+    }
+}
+
+/// 跟踪会话直到 idle 时间内没有新记录（为 0 时不限）或按下 Ctrl-C，每 interval 检查一次，最后输出汇总；
+/// 一开始就无法读取会话文件时返回 Err(退出码)
+fn tail(cli: &Cli, mut tail: SessionTail, interval: Duration, idle: Duration) -> Result<(), i32> {
+    let renderer = TableRenderer::new()
+        .with_full_model_names(cli.full_model_names)
+        .with_timezone(cli.timezone)
+        .with_color(cli.color);
+    if let Err(e) = tail.poll() {
+        eprintln!("{}", tr!("main.tail_read_failed", tail.path().display(), e));
+        return Err(1);
+    }
+    let totals = tail.totals();
+    println!(
+        "{}",
+        tr!("main.tail_started", tail.path().display(), totals.messages, format_count(totals.usage.total()), format_cost(totals.cost_usd))
+    );
+
+    let mut last_activity = std::time::Instant::now();
+    let quiet = loop {
+        if !wait_unless_interrupted(interval) {
+            break false;
+        }
+        match tail.poll() {
+            Ok(messages) if !messages.is_empty() => {
+                last_activity = std::time::Instant::now();
+                for message in messages {
+                    println!(
+                        "{}",
+                        tr!(
+                            "main.tail_message",
+                            message.timestamp.map_or_else(|| "--:--:--".to_string(), |timestamp| timestamp.with_timezone(&cli.timezone).format("%H:%M:%S").to_string()),
+                            renderer.model_name(&message.model),
+                            format_count(message.usage.total()),
+                            format_cost(message.cost_usd),
+                            format_count(message.session_tokens),
+                            format_cost(message.session_cost_usd)
+                        )
+                    );
+                }
+            }
+            Ok(_) => {}
+            // 文件可能正被轮换或暂时无法读取，下一次再试
+            Err(e) => eprintln!("{}", tr!("main.tail_read_failed", tail.path().display(), e)),
+        }
+        if !idle.is_zero() && last_activity.elapsed() >= idle {
+            break true;
+        }
+    };
+
+    let totals = tail.totals();
+    let mut models: Vec<String> = totals.models.iter().map(|model| renderer.model_name(model)).collect();
+    models.sort();
+    models.dedup();
+    println!();
+    println!("{}", if quiet { tr!("main.tail_quiet", idle.as_secs()) } else { tr!("main.tail_stopped") });
+    println!(
+        "{}",
+        tr!(
+            "main.tail_summary",
+            totals.messages,
+            format_count(totals.usage.total()),
+            format_cost(totals.cost_usd),
+            totals.duration().map(format_duration).unwrap_or_else(|| "—".to_string()),
+            models.join(", ")
+        )
+    );
+    Ok(())
+}
+
+/// 按命令行选项要扫描的来源配置 doctor 的检查
+fn doctor(cli: &Cli) -> Doctor {
+    let home_dir = PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| String::from("~")));
+    let mut doctor = Doctor::new(Config::default_path(), FileCache::default_path())
+        .with_extra_extensions(cli.extensions.clone())
+        .with_content_sniffing(cli.sniff)
+        .with_max_file_size(cli.max_file_size);
+    let tool = match &cli.source {
+        SourceSelection::Claude | SourceSelection::All => SourceTool::Claude,
+        SourceSelection::Codex => SourceTool::Codex,
+        SourceSelection::Gemini => SourceTool::Gemini,
+        SourceSelection::Custom(name) => {
+            // 配置文件的错误由 doctor 的配置检查报告，这里只取映射
+            let mapping = Config::load(&Config::default_path())
+                .ok()
+                .and_then(|mut config| config.sources.remove(name));
+            let directory = mapping.as_ref().and_then(FieldMapping::directory);
+            if let Some(mapping) = mapping {
+                doctor = doctor.with_field_mapping(mapping);
+            }
+            if cli.paths.is_empty() {
+                return match directory {
+                    Some(directory) => doctor.with_paths(SourceTool::Custom, vec![directory]),
+                    None => doctor,
+                };
+            }
+            SourceTool::Custom
+        }
+    };
+    if !cli.paths.is_empty() {
+        return doctor.with_paths(tool, cli.paths.clone());
+    }
+    if matches!(cli.source, SourceSelection::Claude | SourceSelection::All) {
+        doctor = match &cli.dir {
+            Some(dir) => doctor.with_paths(SourceTool::Claude, vec![dir.clone()]),
+            None => doctor.with_directory(SourceTool::Claude, cli.claude_projects_dir()),
+        };
+    }
+    if matches!(cli.source, SourceSelection::Codex | SourceSelection::All) {
+        doctor = doctor.with_directory(SourceTool::Codex, home_dir.join(".codex/sessions"));
+    }
+    if matches!(cli.source, SourceSelection::Gemini | SourceSelection::All) {
+        doctor = doctor.with_directory(SourceTool::Gemini, home_dir.join(".gemini/tmp"));
+    }
+    doctor
+}
+
+/// 报告的数据来源：标准输入、命令行给出的路径或默认的 ~/.claude/projects、~/.codex/sessions 与 ~/.gemini/tmp（按 --source），
+/// 合并归档时加上归档文件，导入时加上导入的文件；merge 时只有合并的导出文件
+fn data_sources(cli: &Cli, field_mapping: Option<&FieldMapping>, diagnostics: &Diagnostics) -> Vec<String> {
+    if !diagnostics.merged.is_empty() {
+        return diagnostics.merged.iter().map(|export| export.path.display().to_string()).collect();
+    }
+    let mut sources = if cli.stdin {
+        vec!["<stdin>".to_string()]
+    } else if let Some(host) = &cli.remote {
+        vec![format!("{}:{}", host, cli.remote_dir)]
+    } else if !cli.paths.is_empty() {
+        cli.paths.iter().map(|path| path.display().to_string()).collect()
+    } else {
+        let home_dir = PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| String::from("~")));
+        let claude = cli.claude_projects_dir().display().to_string();
+        let codex = home_dir.join(".codex/sessions").display().to_string();
+        let gemini = home_dir.join(".gemini/tmp").display().to_string();
+        match cli.source {
+            SourceSelection::Claude => vec![claude],
+            SourceSelection::Codex => vec![codex],
+            SourceSelection::Gemini => vec![gemini],
+            SourceSelection::All => vec![claude, codex, gemini],
+            SourceSelection::Custom(_) => field_mapping
+                .and_then(FieldMapping::directory)
+                .map(|directory| directory.display().to_string())
+                .into_iter()
+                .collect(),
+        }
+    };
+    if cli.with_archive {
+        sources.push(Archive::default_path().display().to_string());
+    }
+    sources.extend(diagnostics.imported.iter().map(|imported| imported.path.display().to_string()));
+    sources
+}
+
+/// 会话按费用或 token 总量降序排列；费用相同时按 token 总量，再相同时按会话 id
+fn sort_sessions(sessions: &mut [item::SessionSummary], by: Metric) {
+    let tokens = |session: &item::SessionSummary| session.aggregate.usage.total();
+    match by {
+        Metric::Cost => sessions.sort_by(|a, b| {
+            b.cost_usd
+                .unwrap_or(0.0)
+                .total_cmp(&a.cost_usd.unwrap_or(0.0))
+                .then_with(|| tokens(b).cmp(&tokens(a)))
+                .then_with(|| a.session_id.cmp(&b.session_id))
+        }),
+        Metric::Tokens => sessions.sort_by(|a, b| tokens(b).cmp(&tokens(a)).then_with(|| a.session_id.cmp(&b.session_id))),
+    }
+}
+
+/// 按命令行选项扫描 ~/.claude/projects、~/.codex/sessions、~/.gemini/tmp（或指定的路径、标准输入），
+/// --source custom:<名称> 时按 field_mapping 扫描其目录；progress 为真且 stderr 是终端时显示进度条。
+/// 给出 resume 时从上一次扫描各文件解析到的位置继续
+pub fn scan(
+    cli: &Cli,
+    field_mapping: Option<&FieldMapping>,
+    progress: bool,
+    resume: Option<&FileOffsets>,
+) -> Result<file_processor::ProcessOutput, file_processor::ProcessError> {
+    let home_dir = PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| String::from("~")));
+    let claude_projects_dir = cli.claude_projects_dir();
+    // --dir 给出的目录与 PATH 一样递归查找
+    let claude_paths = match &cli.dir {
+        Some(dir) if cli.paths.is_empty() => vec![dir.clone()],
+        _ => cli.paths.clone(),
+    };
+    let codex_sessions_dir = home_dir.join(".codex/sessions");
+    let gemini_dir = home_dir.join(".gemini/tmp");
+
+    let mut builder = FileProcessor::builder(claude_projects_dir.clone())
+        .with_max_file_size(cli.max_file_size)
+        .with_paths(claude_paths.clone())
+        .with_extra_extensions(cli.extensions.clone())
+        .with_content_sniffing(cli.sniff)
+        .with_message_sizes(matches!(cli.command, Some(Command::Stats { message_sizes: true, .. })))
+        .with_latencies(matches!(cli.command, Some(Command::Stats { latency: true, .. })))
+        .with_timestamp_validation((!cli.no_timestamp_validation).then_some(cli.timestamp_floor))
+        .with_explain(cli.explain)
+        .with_timezone(cli.timezone);
+    if cli.stdin {
+        // 管道输入长度未知，不显示进度条，也不使用缓存
+        return builder.build().process_reader(std::io::stdin().lock(), "<stdin>");
+    }
+    builder = builder.with_cancel_flag(Arc::clone(interrupt_flag()));
+    if let Some(host) = &cli.remote {
+        // 远程文件没有可用于缓存指纹的元数据，数量在读完之前也未知，同样不使用缓存、不显示进度条
+        return builder.build().process(RemoteSource::new(host).with_directory(&cli.remote_dir));
+    }
+    if let Some(offsets) = resume {
+        builder = builder.with_resume_offsets(offsets.clone());
+    }
+    if !cli.no_cache {
+        match FileCache::load(FileCache::default_path()) {
+            Ok(cache) => builder = builder.with_cache(cache),
+            // 另一个进程长时间持有缓存的锁时不等待，本次不使用缓存
+            Err(e) => eprintln!("{}", tr!("main.cache_unavailable", e)),
+        }
+    }
+    // 进度条只在 stderr 是终端时显示，避免污染重定向的输出
+    let progress = (progress && std::io::stderr().is_terminal()).then(scan_progress_bar);
+    if let Some(progress) = &progress {
+        builder = builder.with_event_callback(scan_progress(progress.clone()));
+    }
+    if let Some(mapping) = field_mapping {
+        builder = builder.with_field_mapping(mapping.clone());
+    }
+    let processor = builder.build();
+    let result = match &cli.source {
+        SourceSelection::Claude => processor.process_files(),
+        SourceSelection::Codex if cli.paths.is_empty() => processor.process(CodexSource::new(codex_sessions_dir)),
+        SourceSelection::Codex => {
+            processor.process(PathsSource::new(cli.paths.clone(), claude_projects_dir).with_tool(SourceTool::Codex))
+        }
+        SourceSelection::Gemini if cli.paths.is_empty() => processor.process(GeminiSource::new(gemini_dir)),
+        SourceSelection::Gemini => {
+            processor.process(PathsSource::new(cli.paths.clone(), claude_projects_dir).with_tool(SourceTool::Gemini))
+        }
+        SourceSelection::All if cli.dir.is_some() => processor.process(CombinedSource(
+            CombinedSource(PathsSource::new(claude_paths, claude_projects_dir), CodexSource::new(codex_sessions_dir)),
+            GeminiSource::new(gemini_dir),
+        )),
+        SourceSelection::All => processor.process(CombinedSource(
+            CombinedSource(DirectorySource::new(claude_projects_dir), CodexSource::new(codex_sessions_dir)),
+            GeminiSource::new(gemini_dir),
+        )),
+        SourceSelection::Custom(_) => {
+            // 映射没有 directory 时 main 已要求给出 PATH
+            let paths = match field_mapping.and_then(FieldMapping::directory) {
+                Some(directory) if cli.paths.is_empty() => vec![directory],
+                _ => cli.paths.clone(),
+            };
+            processor.process(PathsSource::new(paths, claude_projects_dir).with_tool(SourceTool::Custom))
+        }
+    };
+    // 被取消的扫描没有 Finished 事件，进度条在这里清除
+    if let Some(progress) = progress {
+        progress.finish_and_clear();
+    }
+    result
+}
+
+/// 报告写入失败（如管道已关闭）时输出说明并返回一般错误的退出码，调用方不再继续输出
+fn check_written(result: std::io::Result<()>) -> Result<(), i32> {
+    result.map_err(|e| {
+        eprintln!("{}", tr!("main.write_failed", e));
+        1
+    })
+}
+
+/// Ctrl-C 置位的标志，与扫描及 --watch、tui 的循环共享
+fn interrupt_flag() -> &'static Arc<AtomicBool> {
+    static INTERRUPTED: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+    INTERRUPTED.get_or_init(Arc::default)
+}
+
+/// 第一次 Ctrl-C 只置位 interrupt_flag，由正在进行的扫描或循环自行停止；
+/// 第二次 Ctrl-C 不再等待，立即退出
+fn install_interrupt_handler() {
+    let interrupted = Arc::clone(interrupt_flag());
+    let result = ctrlc::set_handler(move || {
+        if interrupted.swap(true, Ordering::SeqCst) {
+            restore_cursor();
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+    });
+    if let Err(e) = result {
+        tracing::warn!("无法安装 Ctrl-C 处理函数: {}", e);
+    }
+}
+
+/// 等待 duration，期间按下 Ctrl-C 时提前返回 false
+fn wait_unless_interrupted(duration: Duration) -> bool {
+    let deadline = std::time::Instant::now() + duration;
+    while !interrupt_flag().load(Ordering::Relaxed) {
+        let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+        if remaining.is_zero() {
+            return true;
+        }
+        std::thread::sleep(remaining.min(INTERRUPT_POLL));
+    }
+    false
+}
+
+/// 恢复终端光标后输出 message，返回 EXIT_INTERRUPTED
+fn interrupted(message: String) -> i32 {
+    restore_cursor();
+    eprintln!("{}", message);
+    EXIT_INTERRUPTED
+}
+
+/// 进度条绘制期间被中断时光标可能仍处于隐藏状态
+fn restore_cursor() {
+    if std::io::stderr().is_terminal() {
+        eprint!("\x1b[?25h");
+    }
+}
+
+/// 扫描文件时使用的进度条：已处理文件数 / 总数，附带已读取字节数
+fn scan_progress_bar() -> ProgressBar {
+    let progress = ProgressBar::with_draw_target(Some(0), ProgressDrawTarget::stderr());
+    if let Ok(style) = ProgressStyle::with_template(&format!("{{spinner}} [{{bar:30}}] {{pos}}/{{len}} {} ({{msg}})", i18n::label("files"))) {
+        progress.set_style(style.progress_chars("=> "));
+    }
+    progress
+}
+
+/// 由扫描事件驱动进度条，扫描结束时清除进度条，保证其不会与之后的表格输出交错
+fn scan_progress(progress: ProgressBar) -> impl Fn(&ProcessEvent) + Send + Sync + 'static {
+    let processed_bytes = AtomicU64::new(0);
+    move |event| match event {
+        ProcessEvent::ScanStarted { file_count } => progress.set_length(*file_count as u64),
+        ProcessEvent::FileProcessed { bytes, .. } => {
+            let total = processed_bytes.fetch_add(*bytes, Ordering::Relaxed) + bytes;
+            progress.set_message(format_bytes(total));
+            progress.inc(1);
+        }
+        ProcessEvent::FileFailed { .. } => progress.inc(1),
+        ProcessEvent::Finished { .. } => progress.finish_and_clear(),
+    }
+}
+
+/// 初始化 tracing，只有通过 --log-level 或 RUST_LOG 显式启用时才输出日志
+fn init_tracing(log_level: Option<&str>, log_format: LogFormat) {
+    let filter = match log_level {
+        Some(level) => match EnvFilter::try_new(level) {
+            Ok(filter) => filter,
+            Err(e) => {
+                eprintln!("{}", tr!("main.invalid_log_level", level, e));
+                return;
+            }
+        },
+        None => match EnvFilter::try_from_default_env() {
+            Ok(filter) => filter,
+            Err(_) => return,
+        },
+    };
+
+    // span 关闭时输出其耗时，便于定位慢在哪里
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .with_span_events(FmtSpan::CLOSE);
+    match log_format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
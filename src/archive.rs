@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use crate::item::{ProjectModelUsage, UsageAggregate};
+use crate::lock::FileLock;
+
+/// 归档键：(项目路径, 模型, 日期)
+pub type ArchiveKey = (String, String, String);
+
+/// 归档中的一条记录，每行一个 JSON 对象
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct ArchiveRecord {
+    project: String,
+    model: String,
+    date: String,
+    aggregate: UsageAggregate,
+}
+
+/// 只追加的历史归档，使 Claude Code 清理旧日志后历史数据仍然可用。
+/// 同一个键可能出现多次，读取时以最后一条为准。读取取共享锁，追加取排他锁，
+/// 等待锁超时时返回 ErrorKind::TimedOut
+pub struct Archive {
+    path: PathBuf,
+}
+
+impl Archive {
+    /// 默认路径：$XDG_DATA_HOME/cccost/archive 或 ~/.local/share/cccost/archive
+    pub fn default_path() -> PathBuf {
+        let data_dir = std::env::var("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| {
+                let home_dir = std::env::var("HOME").unwrap_or_else(|_| String::from("~"));
+                PathBuf::from(home_dir).join(".local").join("share")
+            });
+        data_dir.join("cccost").join("archive").join("aggregates.jsonl")
+    }
+
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// 读取归档，文件不存在时为空；无法解析的行（例如写到一半的最后一行）直接忽略
+    pub fn load(&self) -> io::Result<HashMap<ArchiveKey, UsageAggregate>> {
+        if !self.path.try_exists()? {
+            return Ok(HashMap::new());
+        }
+        let _lock = FileLock::shared(&self.path)?;
+        self.read()
+    }
+
+    /// 不加锁读取归档，调用方持有锁
+    fn read(&self) -> io::Result<HashMap<ArchiveKey, UsageAggregate>> {
+        let file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut records = HashMap::new();
+        for line in BufReader::new(file).lines() {
+            if let Ok(record) = serde_json::from_str::<ArchiveRecord>(&line?) {
+                records.insert((record.project, record.model, record.date), record.aggregate);
+            }
+        }
+        Ok(records)
+    }
+
+    /// 追加归档中没有、或比归档值更大的记录，返回追加的条数。
+    /// 重复运行不会写入新内容；日志被部分清理后较小的值不会覆盖已归档的较大值
+    pub fn append(&self, live: &[ProjectModelUsage]) -> io::Result<usize> {
+        // 比较与追加之间不能有其他进程写入，否则两个进程可能追加同样的记录
+        let _lock = FileLock::exclusive(&self.path)?;
+        let archived = self.read()?;
+        let new_records: Vec<ArchiveRecord> = live
+            .iter()
+            .filter(|live| {
+                let key = (live.project.clone(), live.model.clone(), live.date.clone());
+                match archived.get(&key) {
+                    Some(existing) => live.aggregate.usage.total() > existing.usage.total(),
+                    None => true,
+                }
+            })
+            .map(|live| ArchiveRecord {
+                project: live.project.clone(),
+                model: live.model.clone(),
+                date: live.date.clone(),
+                aggregate: live.aggregate.clone(),
+            })
+            .collect();
+        if new_records.is_empty() {
+            return Ok(0);
+        }
+
+        let mut content = String::new();
+        for record in &new_records {
+            content.push_str(&serde_json::to_string(record).map_err(io::Error::other)?);
+            content.push('\n');
+        }
+        // 一次写入全部新记录，减少中断时留下半条记录的可能
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(content.as_bytes())?;
+        Ok(new_records.len())
+    }
+}
@@ -0,0 +1,214 @@
+//! 供 tokio 程序使用的异步扫描接口（需要 async feature）
+//!
+//! 解析仍由 rayon 并行完成，整个扫描在 tokio 的阻塞线程池中运行，不占用异步工作线程。
+//! 扫描可以随时取消：尚未开始的文件不再解析，正在解析的文件完成后立即返回
+//! [`ProcessError::Cancelled`]。被取消的 FileProcessor 随任务一起丢弃，缓存也不会写入，
+//! 不完整的结果不会被任何调用方看到。
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::{Interval, MissedTickBehavior};
+use crate::file_processor::{FileProcessor, ProcessError, ProcessOutput};
+
+/// 取消一次异步扫描，可以克隆后交给其他任务
+#[derive(Debug, Clone)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    /// 请求取消扫描；扫描已结束时没有效果
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// 是否已请求取消
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// 进行中的异步扫描，await 得到扫描结果。
+///
+/// 被丢弃时（例如在 tokio::select! 中落选）同样会取消扫描
+#[derive(Debug)]
+pub struct ScanTask {
+    cancel: CancelHandle,
+    task: JoinHandle<Result<ProcessOutput, ProcessError>>,
+}
+
+impl ScanTask {
+    /// 请求取消扫描，之后 await 得到 ProcessError::Cancelled
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    /// 用于在其他任务中取消本次扫描的句柄
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+}
+
+impl Future for ScanTask {
+    type Output = Result<ProcessOutput, ProcessError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match Pin::new(&mut self.task).poll(cx) {
+            Poll::Ready(Ok(result)) => Poll::Ready(result),
+            // 扫描中的 panic 原样传给等待方
+            Poll::Ready(Err(e)) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            // 运行时关闭导致任务被终止，按取消处理
+            Poll::Ready(Err(_)) => Poll::Ready(Err(ProcessError::Cancelled)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for ScanTask {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+impl FileProcessor {
+    /// 在 tokio 的阻塞线程池中执行 process_files，返回可 await、可取消的任务。
+    /// 必须在 tokio 运行时中调用
+    pub fn process_files_async(self) -> ScanTask {
+        let cancel = CancelHandle(self.cancel_flag());
+        let task = tokio::task::spawn_blocking(move || self.process_files());
+        ScanTask { cancel, task }
+    }
+}
+
+/// 定时重新扫描，每次由 make_processor 创建新的 FileProcessor（可复用同一个缓存文件）。
+/// 与 blocks --watch 相同，但不阻塞线程
+#[derive(Debug)]
+pub struct Watch<F> {
+    make_processor: F,
+    interval: Interval,
+}
+
+/// 每隔 period 扫描一次，第一次扫描立即开始；扫描耗时超过 period 时下一次顺延，不会连续补扫。
+/// 必须在启用了 time 驱动的 tokio 运行时中调用
+pub fn watch<F>(make_processor: F, period: Duration) -> Watch<F>
+where
+    F: FnMut() -> FileProcessor,
+{
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    Watch { make_processor, interval }
+}
+
+impl<F> Watch<F>
+where
+    F: FnMut() -> FileProcessor,
+{
+    /// 等到下一个周期并完成一次扫描。返回的 future 被丢弃时，进行中的扫描随之取消
+    pub async fn next(&mut self) -> Result<ProcessOutput, ProcessError> {
+        self.interval.tick().await;
+        (self.make_processor)().process_files_async().await
+    }
+}
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use std::sync::mpsc;
+    use std::thread;
+    use crate::cache::FileCache;
+    use super::*;
+
+    /// 在数据目录 root 中写入一个有 count 条助手记录的会话
+    fn write_session(root: &Path, count: usize) {
+        let project = root.join("-home-dev-app");
+        fs::create_dir_all(&project).unwrap();
+        let content: String = (0..count)
+            .map(|i| {
+                format!(
+                    r#"{{"type":"assistant","timestamp":"2025-01-01T10:00:00Z","sessionId":"s1","message":{{"id":"msg_{}","model":"claude-sonnet-4-20250514","usage":{{"input_tokens":10,"output_tokens":5}}}}}}"#,
+                    i
+                ) + "\n"
+            })
+            .collect();
+        fs::write(project.join("s1.jsonl"), content).unwrap();
+    }
+
+    /// 每条记录都要等待一段时间的扫描，用于在扫描进行中取消；返回的接收端在扫描线程结束、
+    /// FileProcessor 被丢弃后断开
+    fn slow_processor(root: &Path, cache_path: &Path) -> (FileProcessor, mpsc::Receiver<()>) {
+        let (sender, receiver) = mpsc::channel();
+        let processor = FileProcessor::builder(root.to_path_buf())
+            .with_cache(FileCache::load(cache_path.to_path_buf()).unwrap())
+            .with_filter(move |_| {
+                sender.send(()).ok();
+                thread::sleep(Duration::from_millis(20));
+                true
+            })
+            .build();
+        (processor, receiver)
+    }
+
+    /// 等待扫描线程结束
+    async fn wait_for_scan_thread(receiver: mpsc::Receiver<()>) {
+        tokio::task::spawn_blocking(move || while receiver.recv().is_ok() {}).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn scan_completes_and_writes_the_cache() {
+        let root = tempfile::tempdir().unwrap();
+        write_session(root.path(), 3);
+        let cache_path = root.path().join("cache/file_aggregates.json");
+        let processor = FileProcessor::builder(root.path().to_path_buf())
+            .with_cache(FileCache::load(cache_path.clone()).unwrap())
+            .build();
+
+        let output = processor.process_files_async().await.unwrap();
+
+        assert_eq!(output.usage.len(), 1);
+        assert_eq!(output.usage[0].usage.usage.output_tokens, Some(15));
+        assert!(cache_path.exists());
+    }
+
+    #[tokio::test]
+    async fn cancelling_in_select_returns_cancelled_without_writing_the_cache() {
+        let root = tempfile::tempdir().unwrap();
+        write_session(root.path(), 200);
+        let cache_path = root.path().join("cache/file_aggregates.json");
+        let (processor, receiver) = slow_processor(root.path(), &cache_path);
+
+        let mut task = processor.process_files_async();
+        tokio::select! {
+            _ = &mut task => panic!("the scan should still be running"),
+            _ = tokio::time::sleep(Duration::from_millis(100)) => task.cancel(),
+        }
+
+        assert!(matches!(task.await, Err(ProcessError::Cancelled)));
+        wait_for_scan_thread(receiver).await;
+        assert!(!cache_path.exists());
+    }
+
+    #[tokio::test]
+    async fn dropping_the_task_in_select_cancels_the_scan() {
+        let root = tempfile::tempdir().unwrap();
+        write_session(root.path(), 200);
+        let cache_path = root.path().join("cache/file_aggregates.json");
+        let (processor, receiver) = slow_processor(root.path(), &cache_path);
+
+        // 超时分支胜出，落选的 ScanTask 随 select! 一起丢弃
+        let finished = tokio::select! {
+            _ = processor.process_files_async() => true,
+            _ = tokio::time::sleep(Duration::from_millis(100)) => false,
+        };
+
+        assert!(!finished);
+        // 200 条记录完整扫描需要 4 秒，取消后扫描线程很快结束
+        let started = std::time::Instant::now();
+        wait_for_scan_thread(receiver).await;
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(!cache_path.exists());
+    }
+}
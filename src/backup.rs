@@ -0,0 +1,154 @@
+//! cccost backup：把 Claude Code 的会话日志复制到备份目录，避免被 Claude Code 定期清理后丢失历史。
+//!
+//! 备份目录的结构为 `<项目目录>/<YYYY-MM>/<文件名>`，月份取自文件中第一条记录的时间，
+//! 同一个会话文件之后被追加也总是备份到同一位置。第一级仍是项目目录，用 `cccost --dir <备份目录>` 即可按原样统计。
+//! 备份的修改时间与原文件相同，大小与修改时间都相同的文件视为已备份而跳过，重复运行只复制有变化的文件。
+//! 压缩的备份为 `.jsonl.gz`，解析时自动解压；复制与压缩都先写入临时文件再重命名，中断不会留下不完整的备份
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use chrono::{DateTime, Utc};
+use flate2::Compression;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use serde::Serialize;
+use serde_json::Value;
+use crate::source::{is_gzip, SourceInput};
+
+/// 没有可识别时间戳的文件所在的目录
+const UNDATED: &str = "undated";
+
+/// 查找第一条记录的时间时最多读取的行数
+const DATE_SCAN_LINES: usize = 100;
+
+/// 备份文件的写入方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BackupMode {
+    /// 复制
+    #[default]
+    Copy,
+    /// 硬链接，不占用额外空间；原文件被删除后备份仍然保留。备份目录需与日志在同一文件系统
+    Link,
+    /// gzip 压缩为 .gz
+    Compress,
+}
+
+/// 一次备份的结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BackupSummary {
+    /// 新备份或有变化而重新备份的文件数
+    pub copied: usize,
+    /// 这些文件的原始字节数
+    pub bytes: u64,
+    /// 大小与修改时间都没有变化而跳过的文件数
+    pub unchanged: usize,
+}
+
+/// 把 inputs 中的日志文件备份到 to，to 不存在时创建。
+/// 文件直接位于数据目录下（没有项目目录）时以其所在目录的名称作为项目目录
+pub fn backup(inputs: Vec<SourceInput>, to: &Path, mode: BackupMode) -> io::Result<BackupSummary> {
+    let mut summary = BackupSummary::default();
+    for input in inputs {
+        let SourceInput::File { path, metadata, project_dir, .. } = input else {
+            continue;
+        };
+        let project_dir = project_dir
+            .or_else(|| path.parent().and_then(Path::file_name).map(|name| name.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| UNDATED.to_string());
+        let directory = to.join(project_dir).join(month(&path));
+        let Some(file_name) = path.file_name() else {
+            continue;
+        };
+        let plain = directory.join(file_name);
+        let compressed = directory.join(format!("{}.gz", file_name.to_string_lossy()));
+        // 源文件本身已压缩时原样复制
+        let compress = mode == BackupMode::Compress && !is_gzip(&path);
+        let (target, other) = if compress { (compressed, plain) } else { (plain, compressed) };
+
+        if is_backed_up(&target, &metadata, compress) {
+            summary.unchanged += 1;
+            continue;
+        }
+        fs::create_dir_all(&directory)?;
+        match mode {
+            BackupMode::Link => link(&path, &target)?,
+            _ if compress => write_atomically(&target, &metadata, |file| {
+                let mut encoder = GzEncoder::new(file, Compression::default());
+                io::copy(&mut File::open(&path)?, &mut encoder)?;
+                encoder.finish()
+            })?,
+            _ => write_atomically(&target, &metadata, |mut file| {
+                io::copy(&mut File::open(&path)?, &mut file)?;
+                Ok(file)
+            })?,
+        }
+        // 切换压缩方式后删除另一种形式的旧备份，避免同一会话被统计两次
+        if other.exists() {
+            fs::remove_file(&other)?;
+        }
+        summary.copied += 1;
+        summary.bytes += metadata.len();
+    }
+    Ok(summary)
+}
+
+/// 备份是否与原文件的大小和修改时间相同；压缩的备份按 gzip 尾部记录的原始大小（模 2^32）比较
+fn is_backed_up(target: &Path, source: &fs::Metadata, compressed: bool) -> bool {
+    let Ok(backup) = fs::metadata(target) else {
+        return false;
+    };
+    if backup.modified().ok() != source.modified().ok() {
+        return false;
+    }
+    if !compressed {
+        return backup.len() == source.len();
+    }
+    let original_size = File::open(target).and_then(|mut file| {
+        file.seek(SeekFrom::End(-4))?;
+        let mut size = [0; 4];
+        file.read_exact(&mut size)?;
+        Ok(u32::from_le_bytes(size))
+    });
+    original_size.is_ok_and(|size| size == source.len() as u32)
+}
+
+/// 写入 target 旁的临时文件，设置与原文件相同的修改时间后重命名
+fn write_atomically(target: &Path, source: &fs::Metadata, write: impl FnOnce(File) -> io::Result<File>) -> io::Result<()> {
+    let file_name = target.file_name().and_then(|name| name.to_str()).unwrap_or("backup");
+    let temporary = target.with_file_name(format!(".{}.tmp", file_name));
+    let mut file = write(File::create(&temporary)?)?;
+    file.flush()?;
+    file.set_modified(source.modified()?)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&temporary, target)
+}
+
+/// 以硬链接替换 target。原文件被重写（而不是追加）后链接指向旧内容，此时大小或修改时间不同，会重新链接
+fn link(source: &Path, target: &Path) -> io::Result<()> {
+    let file_name = target.file_name().and_then(|name| name.to_str()).unwrap_or("backup");
+    let temporary = target.with_file_name(format!(".{}.tmp", file_name));
+    let _ = fs::remove_file(&temporary);
+    fs::hard_link(source, &temporary)?;
+    fs::rename(&temporary, target)
+}
+
+/// 文件中第一条带时间戳的记录所在的月份（YYYY-MM），没有时为 undated
+fn month(path: &Path) -> String {
+    let Ok(file) = File::open(path) else {
+        return UNDATED.to_string();
+    };
+    let reader: Box<dyn BufRead> = if is_gzip(path) {
+        Box::new(BufReader::new(MultiGzDecoder::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    };
+    reader
+        .lines()
+        .take(DATE_SCAN_LINES)
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<Value>(&line).ok())
+        .find_map(|value| value.get("timestamp")?.as_str()?.parse::<DateTime<Utc>>().ok())
+        .map_or_else(|| UNDATED.to_string(), |timestamp| timestamp.format("%Y-%m").to_string())
+}
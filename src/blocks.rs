@@ -0,0 +1,286 @@
+//! 按 5 小时计费窗口划分用量，以及活跃窗口的消耗速率与外推
+
+use std::collections::{BTreeMap, BTreeSet};
+use chrono::{DateTime, NaiveDateTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use crate::item::{DailyUsage, UsageAggregate};
+use crate::period::TokenTotals;
+use crate::pricing::cost_for;
+
+/// 订阅用量限制的计费窗口长度
+pub const BLOCK_HOURS: i64 = 5;
+
+/// 计算消耗速率的时间范围：截至当前的最近 15 分钟
+pub const BURN_RATE_MINUTES: i64 = 15;
+
+/// 一个 5 小时计费窗口内的用量
+#[derive(Debug, Clone, Serialize)]
+pub struct Block {
+    /// 窗口开始时间：首条记录所在的整点
+    pub start: DateTime<Utc>,
+    /// 窗口结束时间（不含），即开始时间后 5 小时
+    pub end: DateTime<Utc>,
+    /// 窗口内第一条记录的时间
+    pub first_activity: DateTime<Utc>,
+    /// 窗口内最后一条记录的时间
+    pub last_activity: DateTime<Utc>,
+    /// 当前时间是否仍在该窗口内
+    pub active: bool,
+    /// 活跃窗口已经过的秒数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elapsed_seconds: Option<i64>,
+    /// 活跃窗口剩余的秒数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining_seconds: Option<i64>,
+    /// 窗口内使用过的模型，按名称排序
+    pub models: Vec<String>,
+    /// 计入统计的消息数
+    pub messages: u64,
+    /// 窗口内的 token 合计
+    #[serde(flatten)]
+    pub tokens: TokenTotals,
+    /// 有价格的模型的费用合计，没有任何模型有价格时为 None
+    pub cost_usd: Option<f64>,
+    /// 活跃窗口按当前速率外推到结束时的用量，由 BurnRate::project 填充
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub projection: Option<Projection>,
+}
+
+impl Block {
+    fn new(start: DateTime<Utc>, first_activity: DateTime<Utc>) -> Self {
+        Self {
+            start,
+            end: start + TimeDelta::hours(BLOCK_HOURS),
+            first_activity,
+            last_activity: first_activity,
+            active: false,
+            elapsed_seconds: None,
+            remaining_seconds: None,
+            models: Vec::new(),
+            messages: 0,
+            tokens: TokenTotals::default(),
+            cost_usd: None,
+            projection: None,
+        }
+    }
+}
+
+/// 将按小时的 (模型, 小时键) 使用量划分为 5 小时窗口。
+///
+/// 窗口从首条记录所在的整点开始，持续 5 小时；时间恰好等于结束时间或更晚的记录开始新的窗口。
+/// 与上一条记录间隔达到 5 小时的空闲必然越过结束时间，因此同样开始新窗口，
+/// 窗口之间的空闲时段不单独列出。窗口边界总在整点上，同一小时的记录必然属于同一窗口，
+/// 按小时聚合不会损失精度。小时键是 tz 中的本地时间（见 FileProcessorBuilder::with_timezone），
+/// 先换算回 UTC；夏令时结束时重复的那一小时按较早的一次计。
+pub fn build_blocks(hourly: &[DailyUsage], now: DateTime<Utc>, tz: &Tz) -> Vec<Block> {
+    let mut hours: BTreeMap<DateTime<Utc>, Vec<(&String, &UsageAggregate)>> = BTreeMap::new();
+    for DailyUsage { model, date: hour, usage: aggregate } in hourly {
+        let Ok(hour) = NaiveDateTime::parse_from_str(&format!("{}:00", hour), "%Y-%m-%dT%H:%M") else {
+            continue;
+        };
+        let Some(hour) = tz.from_local_datetime(&hour).earliest() else {
+            continue;
+        };
+        hours.entry(hour.with_timezone(&Utc)).or_default().push((model, aggregate));
+    }
+
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut models: Vec<BTreeSet<String>> = Vec::new();
+    for (hour, entries) in hours {
+        let first_seen = entries
+            .iter()
+            .filter_map(|(_, aggregate)| aggregate.first_seen)
+            .min()
+            .unwrap_or(hour);
+        let last_seen = entries
+            .iter()
+            .filter_map(|(_, aggregate)| aggregate.last_seen)
+            .max()
+            .unwrap_or(hour);
+
+        if blocks.last().is_none_or(|block| hour >= block.end) {
+            blocks.push(Block::new(hour, first_seen));
+            models.push(BTreeSet::new());
+        }
+
+        let (Some(block), Some(block_models)) = (blocks.last_mut(), models.last_mut()) else {
+            continue;
+        };
+        block.last_activity = block.last_activity.max(last_seen);
+        for (model, aggregate) in entries {
+            block_models.insert(model.clone());
+            block.messages += aggregate.messages;
+            block.tokens.add(&aggregate.usage);
+            if let Some(cost) = cost_for(model, &aggregate.usage) {
+                block.cost_usd = Some(block.cost_usd.unwrap_or(0.0) + cost);
+            }
+        }
+    }
+
+    for (block, block_models) in blocks.iter_mut().zip(models) {
+        block.models = block_models.into_iter().collect();
+        if block.start <= now && now < block.end {
+            block.active = true;
+            block.elapsed_seconds = Some((now - block.start).num_seconds());
+            block.remaining_seconds = Some((block.end - now).num_seconds());
+        }
+    }
+    blocks
+}
+
+/// 最近一段时间的消耗速率
+#[derive(Debug, Clone, Serialize)]
+pub struct BurnRate {
+    /// 计算速率所用的时间范围（分钟）
+    pub window_minutes: i64,
+    /// 该时间范围内平均每分钟的 token 数
+    pub tokens_per_minute: f64,
+    /// 有价格的模型按该速率每小时的费用，没有任何模型有价格时为 None
+    pub cost_per_hour: Option<f64>,
+}
+
+/// 按当前速率外推的窗口结束时用量，只是估算
+#[derive(Debug, Clone, Serialize)]
+pub struct Projection {
+    /// 外推所用的消耗速率
+    pub burn_rate: BurnRate,
+    /// 窗口结束时预计的 token 总量
+    pub projected_total_tokens: u64,
+    /// 窗口结束时预计的费用，没有任何模型有价格时为 None
+    pub projected_cost_usd: Option<f64>,
+}
+
+impl BurnRate {
+    /// 由按分钟的 (模型, 分钟键) 使用量计算截至 now 的最近 15 分钟的平均速率。
+    /// 时间范围随 now 滑动而不是取最后一条记录之前的 15 分钟，停止使用后速率逐渐降为 0
+    pub fn compute(recent: &[DailyUsage], now: DateTime<Utc>) -> Self {
+        let since = now - TimeDelta::minutes(BURN_RATE_MINUTES);
+        let mut tokens = 0;
+        let mut cost: Option<f64> = None;
+        for DailyUsage { model, date: minute, usage: aggregate } in recent {
+            let Ok(minute) = NaiveDateTime::parse_from_str(minute, "%Y-%m-%dT%H:%M") else {
+                continue;
+            };
+            let minute = minute.and_utc();
+            // 分钟键是该分钟的起点，与范围起点所在的分钟部分重叠时同样计入
+            if minute + TimeDelta::minutes(1) <= since || minute > now {
+                continue;
+            }
+            tokens += aggregate.usage.total();
+            if let Some(model_cost) = cost_for(model, &aggregate.usage) {
+                cost = Some(cost.unwrap_or(0.0) + model_cost);
+            }
+        }
+        let minutes = BURN_RATE_MINUTES as f64;
+        Self {
+            window_minutes: BURN_RATE_MINUTES,
+            tokens_per_minute: tokens as f64 / minutes,
+            cost_per_hour: cost.map(|cost| cost / minutes * 60.0),
+        }
+    }
+
+    /// 为活跃窗口填充按该速率外推到窗口结束时的用量
+    pub fn project(&self, block: &mut Block) {
+        let Some(remaining) = block.remaining_seconds else {
+            return;
+        };
+        let remaining_minutes = remaining as f64 / 60.0;
+        block.projection = Some(Projection {
+            burn_rate: self.clone(),
+            projected_total_tokens: block.tokens.total_tokens + (self.tokens_per_minute * remaining_minutes).round() as u64,
+            projected_cost_usd: match (block.cost_usd, self.cost_per_hour) {
+                (Some(cost), Some(rate)) => Some(cost + rate * remaining_minutes / 60.0),
+                (cost, rate) => cost.or(rate.map(|rate| rate * remaining_minutes / 60.0)),
+            },
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::item::Usage;
+    use super::*;
+
+    const MODEL: &str = "claude-sonnet-4-20250514";
+
+    fn time(value: &str) -> DateTime<Utc> {
+        value.parse().unwrap()
+    }
+
+    /// 一个小时键（或分钟键）下的用量，最早/最晚时间为 first 与 last
+    fn usage(key: &str, output_tokens: u64, first: &str, last: &str) -> DailyUsage {
+        DailyUsage {
+            date: key.to_string(),
+            model: MODEL.to_string(),
+            usage: UsageAggregate {
+                usage: Usage { output_tokens: Some(output_tokens), ..Usage::default() },
+                messages: 1,
+                first_seen: Some(time(first)),
+                last_seen: Some(time(last)),
+            },
+        }
+    }
+
+    #[test]
+    fn blocks_start_on_the_hour_and_last_five_hours() {
+        let hourly = [
+            usage("2025-09-10T09", 1, "2025-09-10T09:12:00Z", "2025-09-10T09:50:00Z"),
+            usage("2025-09-10T13", 2, "2025-09-10T13:05:00Z", "2025-09-10T13:59:00Z"),
+            // 恰好在结束时间开始新的窗口
+            usage("2025-09-10T14", 4, "2025-09-10T14:00:00Z", "2025-09-10T14:30:00Z"),
+        ];
+
+        let blocks = build_blocks(&hourly, time("2025-09-11T00:00:00Z"), &Tz::UTC);
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!((blocks[0].start, blocks[0].end), (time("2025-09-10T09:00:00Z"), time("2025-09-10T14:00:00Z")));
+        assert_eq!(blocks[0].first_activity, time("2025-09-10T09:12:00Z"));
+        assert_eq!(blocks[0].last_activity, time("2025-09-10T13:59:00Z"));
+        assert_eq!((blocks[0].messages, blocks[0].tokens.total_tokens), (2, 3));
+        assert_eq!(blocks[1].start, time("2025-09-10T14:00:00Z"));
+        assert_eq!(blocks[1].tokens.total_tokens, 4);
+        assert!(blocks.iter().all(|block| !block.active));
+        assert_eq!(blocks[0].models, [MODEL]);
+    }
+
+    #[test]
+    fn long_idle_gap_starts_a_new_block() {
+        let hourly = [
+            usage("2025-09-10T09", 1, "2025-09-10T09:00:00Z", "2025-09-10T09:00:00Z"),
+            usage("2025-09-10T20", 1, "2025-09-10T20:30:00Z", "2025-09-10T20:30:00Z"),
+        ];
+        let blocks = build_blocks(&hourly, time("2025-09-10T21:00:00Z"), &Tz::UTC);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1].start, time("2025-09-10T20:00:00Z"));
+    }
+
+    #[test]
+    fn local_hour_keys_are_converted_back_to_utc() {
+        // Asia/Shanghai 的 2025-09-10T17 即 UTC 的 09 点
+        let hourly = [usage("2025-09-10T17", 1, "2025-09-10T09:12:00Z", "2025-09-10T09:50:00Z")];
+        let blocks = build_blocks(&hourly, time("2025-09-11T00:00:00Z"), &Tz::Asia__Shanghai);
+        assert_eq!((blocks[0].start, blocks[0].end), (time("2025-09-10T09:00:00Z"), time("2025-09-10T14:00:00Z")));
+    }
+
+    #[test]
+    fn active_block_is_projected_at_the_recent_burn_rate() {
+        let now = time("2025-09-10T10:00:00Z");
+        let hourly = [usage("2025-09-10T09", 3_000, "2025-09-10T09:10:00Z", "2025-09-10T09:55:00Z")];
+        // 15 分钟之前的记录不计入速率
+        let recent = [
+            usage("2025-09-10T09:40", 1_000_000, "2025-09-10T09:40:00Z", "2025-09-10T09:40:00Z"),
+            usage("2025-09-10T09:50", 1_500, "2025-09-10T09:50:00Z", "2025-09-10T09:50:00Z"),
+        ];
+
+        let mut block = build_blocks(&hourly, now, &Tz::UTC).into_iter().next().unwrap();
+        assert!(block.active);
+        assert_eq!(block.elapsed_seconds, Some(3_600));
+        assert_eq!(block.remaining_seconds, Some(4 * 3_600));
+
+        let rate = BurnRate::compute(&recent, now);
+        assert_eq!(rate.tokens_per_minute, 100.0);
+        rate.project(&mut block);
+        assert_eq!(block.projection.unwrap().projected_total_tokens, 3_000 + 100 * 240);
+    }
+}
@@ -0,0 +1,198 @@
+//! 将时间戳按日、周、月、季度或年归入时间段，日期按指定的时区计算
+
+use std::fmt;
+use chrono::{DateTime, Datelike, FixedOffset, Months, NaiveDate, NaiveDateTime, Utc, Weekday};
+use chrono_tz::Tz;
+use crate::period::{day_of, month_of, week_of};
+
+/// 时间段的粒度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Granularity {
+    /// 按日，标签如 2025-09-01
+    Day,
+    /// 按周（从周一开始），标签为 ISO 周，如 2025-W36
+    Week,
+    /// 按自然月，标签如 2025-09
+    Month,
+    /// 按季度，标签如 2025-Q3
+    Quarter,
+    /// 按年，标签如 2025
+    Year,
+}
+
+/// 时间戳所属的时间段。按起始日期排序，即按时间先后；Display 输出表格中使用的标签
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BucketKey {
+    /// 时间段的第一天
+    pub start: NaiveDate,
+    /// 时间段的最后一天
+    pub end: NaiveDate,
+    label: String,
+}
+
+impl BucketKey {
+    /// 日期所在的时间段
+    pub fn of_date(date: NaiveDate, granularity: Granularity) -> Self {
+        let (label, start, end) = match granularity {
+            Granularity::Day => day_of(date),
+            Granularity::Week => week_of(Weekday::Mon)(date),
+            Granularity::Month => month_of(date),
+            Granularity::Quarter => quarter_of(date),
+            Granularity::Year => year_of(date),
+        };
+        Self { start, end, label }
+    }
+}
+
+impl fmt::Display for BucketKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.label)
+    }
+}
+
+/// 无法识别的时间戳
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// 原始的时间戳
+    pub timestamp: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "无法解析时间戳: {}", self.timestamp)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// 时间戳所属的时间段。
+///
+/// 带时区偏移的时间戳（日志中的 2025-09-01T23:30:00.000Z 等）先换算为 tz 的本地时间再取日期，
+/// 夏令时的切换由时区数据处理；不带偏移的时间戳（2025-09-01T23:30:00、2025-09-01 23:30:00）
+/// 和纯日期视为已经是 tz 的本地时间，直接取其日期
+pub fn bucket(timestamp: &str, granularity: Granularity, tz: &Tz) -> Result<BucketKey, ParseError> {
+    local_date(timestamp.trim(), tz)
+        .map(|date| BucketKey::of_date(date, granularity))
+        .ok_or_else(|| ParseError { timestamp: timestamp.to_string() })
+}
+
+/// tz 中的今天，与按日分组使用同一个时区，"今天"的那一行才与报告中的日期一致
+pub fn today(tz: &Tz) -> NaiveDate {
+    Utc::now().with_timezone(tz).date_naive()
+}
+
+fn local_date(timestamp: &str, tz: &Tz) -> Option<NaiveDate> {
+    if let Ok(datetime) = timestamp.parse::<DateTime<FixedOffset>>() {
+        return Some(datetime.with_timezone(tz).date_naive());
+    }
+    if let Ok(datetime) = timestamp.parse::<NaiveDateTime>() {
+        return Some(datetime.date());
+    }
+    if let Ok(datetime) = NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S%.f") {
+        return Some(datetime.date());
+    }
+    timestamp.parse::<NaiveDate>().ok()
+}
+
+/// 日期所在的季度：(标签, 第一天, 最后一天)
+fn quarter_of(date: NaiveDate) -> (String, NaiveDate, NaiveDate) {
+    let quarter = date.month0() / 3;
+    let start = NaiveDate::from_ymd_opt(date.year(), quarter * 3 + 1, 1).unwrap_or(date);
+    let end = start
+        .checked_add_months(Months::new(3))
+        .and_then(|next| next.pred_opt())
+        .unwrap_or(date);
+    (format!("{}-Q{}", date.year(), quarter + 1), start, end)
+}
+
+/// 日期所在的年：(标签, 第一天, 最后一天)
+fn year_of(date: NaiveDate) -> (String, NaiveDate, NaiveDate) {
+    let start = NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap_or(date);
+    let end = NaiveDate::from_ymd_opt(date.year(), 12, 31).unwrap_or(date);
+    (date.year().to_string(), start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        value.parse().unwrap()
+    }
+
+    fn label(timestamp: &str, granularity: Granularity, tz: Tz) -> String {
+        bucket(timestamp, granularity, &tz).unwrap().to_string()
+    }
+
+    #[test]
+    fn midnight_belongs_to_the_day_it_starts() {
+        assert_eq!(label("2025-09-01T23:59:59.999Z", Granularity::Day, Tz::UTC), "2025-09-01");
+        assert_eq!(label("2025-09-02T00:00:00Z", Granularity::Day, Tz::UTC), "2025-09-02");
+        // 同一时刻在东八区已是第二天，在洛杉矶仍是前一天
+        assert_eq!(label("2025-09-01T16:00:00Z", Granularity::Day, Tz::Asia__Shanghai), "2025-09-02");
+        assert_eq!(label("2025-09-01T06:59:59Z", Granularity::Day, Tz::America__Los_Angeles), "2025-08-31");
+        assert_eq!(label("2025-09-02T00:30:00+08:00", Granularity::Day, Tz::UTC), "2025-09-01");
+        // 月末的最后一秒换算后跨月、跨年
+        assert_eq!(label("2025-12-31T20:00:00Z", Granularity::Year, Tz::Asia__Tokyo), "2026");
+        assert_eq!(label("2025-10-01T03:00:00Z", Granularity::Month, Tz::America__New_York), "2025-09");
+    }
+
+    #[test]
+    fn dst_transitions_use_the_offset_in_effect() {
+        let new_york = Tz::America__New_York;
+        // 2025-03-09 02:00 切换到夏令时（UTC-4），之前为 UTC-5
+        assert_eq!(label("2025-03-09T04:59:00Z", Granularity::Day, new_york), "2025-03-08");
+        assert_eq!(label("2025-03-09T05:00:00Z", Granularity::Day, new_york), "2025-03-09");
+        assert_eq!(label("2025-03-10T03:59:00Z", Granularity::Day, new_york), "2025-03-09");
+        assert_eq!(label("2025-03-10T04:00:00Z", Granularity::Day, new_york), "2025-03-10");
+        // 2025-11-02 02:00 回到标准时间，当天有 25 小时
+        assert_eq!(label("2025-11-02T03:59:00Z", Granularity::Day, new_york), "2025-11-01");
+        assert_eq!(label("2025-11-03T04:59:00Z", Granularity::Day, new_york), "2025-11-02");
+        assert_eq!(label("2025-11-03T05:00:00Z", Granularity::Day, new_york), "2025-11-03");
+    }
+
+    #[test]
+    fn naive_timestamps_are_already_local() {
+        let tokyo = Tz::Asia__Tokyo;
+        assert_eq!(label("2025-09-01T23:30:00", Granularity::Day, tokyo), "2025-09-01");
+        assert_eq!(label("2025-09-01 23:30:00.123", Granularity::Day, tokyo), "2025-09-01");
+        assert_eq!(label("2025-09-01", Granularity::Day, tokyo), "2025-09-01");
+        assert_eq!(label(" 2025-09-01T23:30:00Z ", Granularity::Day, tokyo), "2025-09-02");
+        assert_eq!(bucket("yesterday", Granularity::Day, &tokyo), Err(ParseError { timestamp: "yesterday".to_string() }));
+        assert_eq!(bucket("2025-02-30", Granularity::Day, &tokyo).unwrap_err().to_string(), "无法解析时间戳: 2025-02-30");
+    }
+
+    #[test]
+    fn each_granularity_has_its_label_and_range() {
+        let key = |granularity| BucketKey::of_date(date("2025-08-31"), granularity);
+        let ranges = [
+            (Granularity::Day, "2025-08-31", "2025-08-31", "2025-08-31"),
+            (Granularity::Week, "2025-W35", "2025-08-25", "2025-08-31"),
+            (Granularity::Month, "2025-08", "2025-08-01", "2025-08-31"),
+            (Granularity::Quarter, "2025-Q3", "2025-07-01", "2025-09-30"),
+            (Granularity::Year, "2025", "2025-01-01", "2025-12-31"),
+        ];
+        for (granularity, label, start, end) in ranges {
+            let key = key(granularity);
+            assert_eq!((key.to_string(), key.start, key.end), (label.to_string(), date(start), date(end)), "{:?}", granularity);
+        }
+        // ISO 周跨年时标签使用 ISO 年
+        let week = BucketKey::of_date(date("2024-12-31"), Granularity::Week);
+        assert_eq!((week.to_string(), week.start, week.end), ("2025-W01".to_string(), date("2024-12-30"), date("2025-01-05")));
+        assert_eq!(BucketKey::of_date(date("2024-02-10"), Granularity::Quarter).end, date("2024-03-31"));
+        assert_eq!(BucketKey::of_date(date("2025-12-01"), Granularity::Quarter).to_string(), "2025-Q4");
+    }
+
+    #[test]
+    fn keys_sort_chronologically() {
+        let mut keys: Vec<BucketKey> = ["2026-01-02", "2025-12-29", "2025-01-01", "2024-12-31"]
+            .into_iter()
+            .map(|day| BucketKey::of_date(date(day), Granularity::Week))
+            .collect();
+        keys.sort();
+        let labels: Vec<String> = keys.iter().map(ToString::to_string).collect();
+        assert_eq!(labels, ["2025-W01", "2025-W01", "2026-W01", "2026-W01"]);
+        assert!(keys[1] < keys[2]);
+        assert_eq!(keys[0], keys[1]);
+    }
+}
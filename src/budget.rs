@@ -0,0 +1,168 @@
+//! 按项目的月度费用预算：配置文件 [budgets.projects] 为项目设置每个计费月的费用上限（美元）。
+//!
+//! 键按解码后的项目路径匹配，也就是报告中显示的项目，而不是 ~/.claude/projects 下编码后的目录名，
+//! 编码规则变化或目录被重新生成时预算仍然对应同一个项目。键可以是完整路径（/home/dev/web-app）、
+//! 最后一级目录名（web-app）或含 `*`、`?` 的通配模式（/home/dev/client-*）；
+//! 完全相同的键优先，其次是最长的通配模式
+
+use std::collections::{BTreeMap, BTreeSet};
+use chrono::NaiveDate;
+use serde::Serialize;
+use crate::item::ProjectModelUsage;
+use crate::period::billing_cycle;
+use crate::pricing::cost_for;
+
+/// 配置的各项目预算
+#[derive(Debug, Clone, Default)]
+pub struct ProjectBudgets {
+    exact: BTreeMap<String, f64>,
+    // 含通配符的模式，按长度降序，越长越具体
+    patterns: Vec<(String, f64)>,
+}
+
+impl ProjectBudgets {
+    /// budgets 的键为项目名称、完整路径或通配模式，值为每个计费月的费用上限
+    pub fn new(budgets: &BTreeMap<String, f64>) -> Self {
+        let mut exact = BTreeMap::new();
+        let mut patterns = Vec::new();
+        for (key, amount) in budgets {
+            let key = key.trim_end_matches('/').to_string();
+            if key.contains(['*', '?']) {
+                patterns.push((key, *amount));
+            } else {
+                exact.insert(key, *amount);
+            }
+        }
+        patterns.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        Self { exact, patterns }
+    }
+
+    /// 是否没有设置任何预算
+    pub fn is_empty(&self) -> bool {
+        self.exact.is_empty() && self.patterns.is_empty()
+    }
+
+    /// 项目（解码后的路径）的预算，没有匹配的键时为 None
+    pub fn budget_for(&self, project: &str) -> Option<f64> {
+        let path = project.trim_end_matches('/');
+        let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+        self.exact
+            .get(path)
+            .or_else(|| self.exact.get(name))
+            .copied()
+            .or_else(|| {
+                self.patterns
+                    .iter()
+                    .find(|(pattern, _)| glob_match(pattern, path) || glob_match(pattern, name))
+                    .map(|(_, amount)| *amount)
+            })
+    }
+}
+
+/// 一个项目本计费月的费用与预算
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectBudgetStatus {
+    /// 项目路径
+    pub project: String,
+    /// 本计费月截至今天的估算费用，未知价格的模型不计入
+    pub spent_usd: f64,
+    /// 匹配的预算，没有设置时为 None
+    pub budget_usd: Option<f64>,
+    /// 已用预算的百分比
+    pub percentage: Option<f64>,
+    /// 是否超出预算
+    pub over_budget: bool,
+}
+
+/// 本计费月各项目的费用与预算
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetReport {
+    /// 计费月的第一天
+    pub window_start: NaiveDate,
+    /// 计费月的最后一天
+    pub window_end: NaiveDate,
+    /// 按费用降序；设置了预算但本月没有费用的项目同样列出
+    pub projects: Vec<ProjectBudgetStatus>,
+}
+
+impl BudgetReport {
+    /// 根据 (项目, 模型, 日期) 使用量计算 today 所在计费月（从每月 billing_day 日开始）各项目的费用
+    pub fn evaluate(
+        project_models: &[ProjectModelUsage],
+        budgets: &ProjectBudgets,
+        billing_day: u32,
+        today: NaiveDate,
+    ) -> Self {
+        let (window_start, window_end) = billing_cycle(today, billing_day);
+        let mut spent: BTreeMap<&str, f64> = BTreeMap::new();
+        let mut known = BTreeSet::new();
+        for usage in project_models {
+            known.insert(usage.project.as_str());
+            let in_window = NaiveDate::parse_from_str(&usage.date, "%Y-%m-%d").is_ok_and(|date| date >= window_start && date <= today);
+            if in_window {
+                *spent.entry(&usage.project).or_default() += cost_for(&usage.model, &usage.aggregate.usage).unwrap_or(0.0);
+            }
+        }
+
+        let mut projects: Vec<ProjectBudgetStatus> = known
+            .into_iter()
+            .filter_map(|project| {
+                let spent_usd = spent.get(project).copied();
+                let budget_usd = budgets.budget_for(project);
+                if spent_usd.is_none() && budget_usd.is_none() {
+                    return None;
+                }
+                let spent_usd = spent_usd.unwrap_or(0.0);
+                Some(ProjectBudgetStatus {
+                    project: project.to_string(),
+                    spent_usd,
+                    budget_usd,
+                    percentage: budget_usd.filter(|budget| *budget > 0.0).map(|budget| spent_usd / budget * 100.0),
+                    over_budget: budget_usd.is_some_and(|budget| spent_usd > budget),
+                })
+            })
+            .collect();
+        projects.sort_by(|a, b| b.spent_usd.total_cmp(&a.spent_usd).then_with(|| a.project.cmp(&b.project)));
+        Self { window_start, window_end, projects }
+    }
+
+    /// 项目的状态
+    pub fn get(&self, project: &str) -> Option<&ProjectBudgetStatus> {
+        self.projects.iter().find(|status| status.project == project)
+    }
+
+    /// 是否有项目超出预算
+    pub fn any_over_budget(&self) -> bool {
+        self.projects.iter().any(|status| status.over_budget)
+    }
+}
+
+/// 通配模式匹配：`*` 匹配任意长度（含 /），`?` 匹配一个字符
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // 最近一个 * 的位置及其当时对应的文本位置，失配时回到这里让 * 多匹配一个字符
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
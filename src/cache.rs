@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use serde::{Deserialize, Serialize};
+use dashmap::DashMap;
+use crate::diagnostics::FileStats;
+use crate::item::{SessionSummary, UsageAggregate};
+use crate::lock::FileLock;
+use crate::source::SourceTool;
+
+/// 缓存格式版本，格式变化时递增以丢弃旧缓存
+const CACHE_VERSION: u32 = 17;
+
+/// 单个文件的身份信息，任何一项变化都视为文件已修改
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FileFingerprint {
+    pub size: u64,
+    pub mtime_secs: u64,
+    pub mtime_nanos: u32,
+    /// inode 编号，用于识别被同大小内容替换的文件（非 unix 平台为 0）
+    pub inode: u64,
+}
+
+impl FileFingerprint {
+    pub fn from_metadata(metadata: &fs::Metadata) -> Self {
+        let (mtime_secs, mtime_nanos) = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| (duration.as_secs(), duration.subsec_nanos()))
+            .unwrap_or((0, 0));
+
+        #[cfg(unix)]
+        let inode = std::os::unix::fs::MetadataExt::ino(metadata);
+        #[cfg(not(unix))]
+        let inode = 0;
+
+        Self {
+            size: metadata.len(),
+            mtime_secs,
+            mtime_nanos,
+            inode,
+        }
+    }
+}
+
+/// 某个文件中一个 (名称, 日期) 的使用量合计，名称为模型或版本号等分组维度
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CachedUsage {
+    pub name: String,
+    pub date: String,
+    // 只有按项目或会话区分的键才有，旧缓存中没有这两个字段
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    pub aggregate: UsageAggregate,
+}
+
+/// 某个文件中一个 (项目, 模型, 日期) 的使用量合计
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CachedProjectUsage {
+    pub project: String,
+    pub model: String,
+    pub date: String,
+    pub aggregate: UsageAggregate,
+}
+
+/// 某个文件中一个 (日期, 名称) 的计数，用于工具调用、stop_reason 等
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CachedCount {
+    pub date: String,
+    pub name: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CachedFile {
+    pub fingerprint: FileFingerprint,
+    /// 写入该文件的工具，按另一种格式解析同一文件时不使用缓存；旧缓存中没有该字段，都是 Claude Code 的日志
+    #[serde(default)]
+    pub tool: SourceTool,
+    pub aggregates: Vec<CachedUsage>,
+    pub versions: Vec<CachedUsage>,
+    pub branches: Vec<CachedUsage>,
+    /// 名称为模型、日期为小时键
+    pub hourly: Vec<CachedUsage>,
+    /// 名称为模型、日期为分钟键，只包含解析时最近 5 小时内的记录
+    pub recent: Vec<CachedUsage>,
+    pub projects: Vec<CachedProjectUsage>,
+    pub tools: Vec<CachedCount>,
+    pub stop_reasons: Vec<CachedCount>,
+    pub session: SessionSummary,
+    pub stats: FileStats,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct CacheFile {
+    version: u32,
+    files: HashMap<PathBuf, CachedFile>,
+}
+
+/// 跨运行持久化的单文件聚合缓存
+pub struct FileCache {
+    path: PathBuf,
+    // 上次运行保存的内容，只读
+    previous: HashMap<PathBuf, CachedFile>,
+    // 本次运行中出现的文件，保存时只写入这些，已删除的文件自然被清理
+    current: DashMap<PathBuf, CachedFile>,
+}
+
+impl FileCache {
+    /// 默认缓存路径：$XDG_CACHE_HOME/cccost 或 ~/.cache/cccost
+    pub fn default_path() -> PathBuf {
+        let cache_dir = std::env::var("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| {
+                let home_dir = std::env::var("HOME").unwrap_or_else(|_| String::from("~"));
+                PathBuf::from(home_dir).join(".cache")
+            });
+        cache_dir.join("cccost").join("file_aggregates.json")
+    }
+
+    /// 加载缓存，文件不存在、损坏或版本不匹配时从空缓存开始。
+    /// 另一个进程正在写入、等待锁超时时返回 ErrorKind::TimedOut，调用方应不使用缓存继续
+    pub fn load(path: PathBuf) -> io::Result<Self> {
+        let _lock = match path.try_exists() {
+            Ok(true) => Some(FileLock::shared(&path)?),
+            _ => None,
+        };
+        let previous = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<CacheFile>(&content).ok())
+            .filter(|cache| cache.version == CACHE_VERSION)
+            .map(|cache| cache.files)
+            .unwrap_or_default();
+
+        Ok(Self {
+            path,
+            previous,
+            current: DashMap::new(),
+        })
+    }
+
+    /// 查找未变化文件的聚合结果，命中时同时记入本次运行
+    pub fn lookup(&self, file_path: &Path, fingerprint: &FileFingerprint) -> Option<CachedFile> {
+        let cached = self.previous.get(file_path)?;
+        if &cached.fingerprint != fingerprint {
+            return None;
+        }
+        self.current.insert(file_path.to_path_buf(), cached.clone());
+        Some(cached.clone())
+    }
+
+    pub fn store(&self, file_path: &Path, cached: CachedFile) {
+        self.current.insert(file_path.to_path_buf(), cached);
+    }
+
+    /// 将本次运行的结果写回磁盘，先写临时文件再重命名，避免写到一半的缓存；
+    /// 写入期间持有排他锁，同时运行的进程不会交错写同一个临时文件
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let _lock = FileLock::exclusive(&self.path)?;
+
+        let cache = CacheFile {
+            version: CACHE_VERSION,
+            files: self.current
+                .iter()
+                .map(|entry| (entry.key().clone(), entry.value().clone()))
+                .collect(),
+        };
+        let content = serde_json::to_string(&cache).map_err(io::Error::other)?;
+
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, content)?;
+        fs::rename(&tmp_path, &self.path)
+    }
+
+    /// 删除缓存文件，文件不存在不算错误
+    pub fn clear(path: &Path) -> io::Result<()> {
+        if !path.try_exists()? {
+            return Ok(());
+        }
+        let _lock = FileLock::exclusive(path)?;
+        match fs::remove_file(path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::path::Path;
+    use crate::file_processor::FileProcessor;
+    use super::FileCache;
+
+    fn record(id: &str, output_tokens: u64) -> String {
+        format!(
+            r#"{{"type":"assistant","timestamp":"2025-01-01T10:00:00.000Z","sessionId":"s1","message":{{"id":"{}","model":"claude-sonnet-4-20250514","usage":{{"input_tokens":10,"output_tokens":{}}}}}}}"#,
+            id, output_tokens
+        ) + "\n"
+    }
+
+    /// 使用 cache_path 处的缓存扫描 directory，返回输出 token 合计
+    fn output_tokens(directory: &Path, cache_path: &Path) -> u64 {
+        let cache = FileCache::load(cache_path.to_path_buf()).unwrap();
+        let output = FileProcessor::builder(directory.to_path_buf())
+            .with_cache(cache)
+            .build()
+            .process_files()
+            .unwrap();
+        output.usage.iter().map(|daily| daily.usage.usage.output_tokens.unwrap_or(0)).sum()
+    }
+
+    #[test]
+    fn file_growing_between_runs_is_parsed_again() {
+        let root = tempfile::tempdir().unwrap();
+        let directory = root.path().join("projects");
+        let project = directory.join("-home-dev-app");
+        fs::create_dir_all(&project).unwrap();
+        let cache_path = root.path().join("cache.json");
+        let log = project.join("session.jsonl");
+
+        fs::write(&log, record("msg_1", 100)).unwrap();
+        assert_eq!(output_tokens(&directory, &cache_path), 100);
+        assert!(cache_path.exists());
+
+        let mut content = fs::read_to_string(&log).unwrap();
+        content.push_str(&record("msg_2", 50));
+        fs::write(&log, content).unwrap();
+        assert_eq!(output_tokens(&directory, &cache_path), 150);
+        // 没有变化时使用缓存，结果相同
+        assert_eq!(output_tokens(&directory, &cache_path), 150);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_replaced_with_same_size_content_is_parsed_again() {
+        let root = tempfile::tempdir().unwrap();
+        let directory = root.path().join("projects");
+        let project = directory.join("-home-dev-app");
+        fs::create_dir_all(&project).unwrap();
+        let cache_path = root.path().join("cache.json");
+        let log = project.join("session.jsonl");
+
+        fs::write(&log, record("msg_1", 100)).unwrap();
+        assert_eq!(output_tokens(&directory, &cache_path), 100);
+        let original = fs::metadata(&log).unwrap();
+
+        // 以重命名替换为同样大小的内容，并恢复原来的修改时间，只有 inode 不同
+        let replacement = project.join("session.jsonl.new");
+        fs::write(&replacement, record("msg_1", 200)).unwrap();
+        fs::rename(&replacement, &log).unwrap();
+        File::options().write(true).open(&log).unwrap().set_modified(original.modified().unwrap()).unwrap();
+        let replaced = fs::metadata(&log).unwrap();
+        assert_eq!(replaced.len(), original.len());
+        assert_eq!(replaced.modified().unwrap(), original.modified().unwrap());
+
+        assert_eq!(output_tokens(&directory, &cache_path), 200);
+    }
+}
@@ -0,0 +1,95 @@
+//! 按周统计提示缓存的命中率与节省的费用
+
+use std::collections::BTreeMap;
+use chrono::{NaiveDate, Weekday};
+use serde::Serialize;
+use crate::item::DailyUsage;
+use crate::period::week_of;
+use crate::pricing::pricing_for;
+
+/// 一周内提示缓存的使用情况。整周都没有缓存字段（早于缓存功能的日志）时相关字段为 None
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheWeek {
+    /// ISO 周标签，如 2025-W37
+    pub week: String,
+    /// 周的第一天
+    pub start: NaiveDate,
+    /// 周的最后一天
+    pub end: NaiveDate,
+    /// 普通输入 token 数
+    pub input_tokens: u64,
+    /// 写入缓存的 token 数
+    pub cache_creation_input_tokens: Option<u64>,
+    /// 读取缓存的 token 数
+    pub cache_read_input_tokens: Option<u64>,
+    /// 缓存读取占全部输入（输入 + 缓存写入 + 缓存读取）的比例，0–1
+    pub hit_ratio: Option<f64>,
+    /// 相对于全部按普通输入计费节省的费用：缓存读取的折扣减去缓存写入的溢价；没有价格的模型不计入
+    pub savings_usd: Option<f64>,
+}
+
+/// 按周的缓存报告，以及首尾两周命中率的变化
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheReport {
+    /// 有数据的各周，按时间顺序排列
+    pub weeks: Vec<CacheWeek>,
+    /// 最后一周相对第一周的命中率变化（百分点），按有缓存数据的周计算，不足两周时为 None
+    pub hit_ratio_change_points: Option<f64>,
+}
+
+impl CacheReport {
+    /// 由 (模型, 日期) 使用量按周汇总，比例由每周的原始合计计算，而不是对每天的比例求平均
+    pub fn build(usage: &[DailyUsage], week_start: Weekday) -> Self {
+        let period_of = week_of(week_start);
+        let mut weeks: BTreeMap<NaiveDate, CacheWeek> = BTreeMap::new();
+        for DailyUsage { model, date, usage: aggregate } in usage {
+            let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") else {
+                continue;
+            };
+            let (label, start, end) = period_of(date);
+            let week = weeks.entry(start).or_insert_with(|| CacheWeek {
+                week: label,
+                start,
+                end,
+                input_tokens: 0,
+                cache_creation_input_tokens: None,
+                cache_read_input_tokens: None,
+                hit_ratio: None,
+                savings_usd: None,
+            });
+            let usage = &aggregate.usage;
+            week.input_tokens += usage.input_tokens.unwrap_or(0);
+            if let Some(tokens) = usage.cache_creation_input_tokens {
+                week.cache_creation_input_tokens = Some(week.cache_creation_input_tokens.unwrap_or(0) + tokens);
+            }
+            if let Some(tokens) = usage.cache_read_input_tokens {
+                week.cache_read_input_tokens = Some(week.cache_read_input_tokens.unwrap_or(0) + tokens);
+            }
+            if let Some(pricing) = pricing_for(model)
+                && (usage.cache_creation_input_tokens.is_some() || usage.cache_read_input_tokens.is_some()) {
+                let read = usage.cache_read_input_tokens.unwrap_or(0) as f64;
+                let write = usage.cache_creation_input_tokens.unwrap_or(0) as f64;
+                let savings = (read * (pricing.input - pricing.cache_read) - write * (pricing.cache_write - pricing.input)) / 1_000_000.0;
+                week.savings_usd = Some(week.savings_usd.unwrap_or(0.0) + savings);
+            }
+        }
+
+        let mut weeks: Vec<CacheWeek> = weeks.into_values().collect();
+        for week in &mut weeks {
+            if week.cache_creation_input_tokens.is_none() && week.cache_read_input_tokens.is_none() {
+                continue;
+            }
+            let read = week.cache_read_input_tokens.unwrap_or(0);
+            let total = week.input_tokens + week.cache_creation_input_tokens.unwrap_or(0) + read;
+            week.hit_ratio = (total > 0).then(|| read as f64 / total as f64);
+        }
+
+        let mut ratios = weeks.iter().filter_map(|week| week.hit_ratio);
+        let first = ratios.next();
+        let last = ratios.next_back();
+        Self {
+            hit_ratio_change_points: first.zip(last).map(|(first, last)| (last - first) * 100.0),
+            weeks,
+        }
+    }
+}
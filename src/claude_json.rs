@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, TimeZone, Utc};
+use serde_json::Value;
+
+/// 从 ~/.claude.json 中读取的补充信息。
+/// 该文件的结构随版本变化，读取完全是尽力而为：缺失或格式不符的内容直接忽略，不产生警告
+#[derive(Debug, Default)]
+pub struct ClaudeJson {
+    /// Claude Code 的启动次数
+    pub num_startups: Option<u64>,
+    /// 项目路径 -> 最近一次打开的时间
+    pub project_last_opened: HashMap<String, DateTime<Utc>>,
+}
+
+impl ClaudeJson {
+    pub fn default_path() -> PathBuf {
+        let home_dir = std::env::var("HOME").unwrap_or_else(|_| String::from("~"));
+        PathBuf::from(home_dir).join(".claude.json")
+    }
+
+    /// 读取并解析，文件不存在或无法解析时返回 None
+    pub fn load(path: &Path) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        let root: Value = serde_json::from_str(&content).ok()?;
+
+        let num_startups = root.get("numStartups").and_then(Value::as_u64);
+        let project_last_opened = root
+            .get("projects")
+            .and_then(Value::as_object)
+            .map(|projects| {
+                projects
+                    .iter()
+                    .filter_map(|(path, project)| Some((path.clone(), Self::last_opened(project)?)))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Some(Self {
+            num_startups,
+            project_last_opened,
+        })
+    }
+
+    /// 不同版本使用过的字段名都尝试一遍，值可以是毫秒时间戳或 RFC 3339 字符串
+    fn last_opened(project: &Value) -> Option<DateTime<Utc>> {
+        ["lastOpened", "lastOpenedAt", "lastUsed", "lastUsedAt", "lastSessionTime"]
+            .iter()
+            .filter_map(|key| project.get(*key))
+            .find_map(|value| match value {
+                Value::Number(millis) => millis.as_i64().and_then(|millis| Utc.timestamp_millis_opt(millis).single()),
+                Value::String(text) => text.parse::<DateTime<Utc>>().ok(),
+                _ => None,
+            })
+    }
+}
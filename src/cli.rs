@@ -0,0 +1,900 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use chrono::{NaiveDate, Weekday};
+use chrono_tz::Tz;
+use crate::compare::CompareSpec;
+use crate::limit::LimitWindow;
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+
+/// 统计 Claude Code 本地会话日志中的 token 使用量
+#[derive(Parser, Debug)]
+#[command(name = "cccost", version, about, subcommand_precedence_over_arg = true)]
+pub struct Cli {
+    /// 安静模式，不显示进度条
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// 在诊断摘要中列出有问题的文件
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    /// 日志级别或过滤指令（如 debug、cccost=trace），未指定时读取 RUST_LOG
+    #[arg(long, value_name = "LEVEL")]
+    pub log_level: Option<String>,
+
+    /// 日志输出格式，日志始终写入 stderr
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// 提示信息与表头的语言（如 en、zh），未指定时按 LC_ALL、LC_MESSAGES、LANG 选择，不支持的语言使用英文
+    #[arg(long, value_name = "LANG")]
+    pub lang: Option<String>,
+
+    /// 显示每行的消息条数（Msgs 列）
+    #[arg(long)]
+    pub messages: bool,
+
+    /// 显示每行最早和最晚的活动时间（Active 列）
+    #[arg(long)]
+    pub activity: bool,
+
+    /// 在每日用量的日期前显示星期的缩写（如 Sat 2025-09-13），CSV 中为单独的 weekday 列
+    #[arg(long)]
+    pub weekday: bool,
+
+    /// 在每日用量的日期后增加 ISO 周数列（Wk，如 W37），CSV 中为单独的 iso_week 列（如 2025-W37）。
+    /// 周数按 ISO 8601（周一开始）计算，年末、年初的几天可能属于相邻年份的 W01 或 W52/W53，
+    /// 与 weekly 默认的周标签一致；weekly --week-start sunday 时周日归入下一周，其标签比该行的 Wk 晚一周
+    #[arg(long)]
+    pub week_number: bool,
+
+    /// 每日用量表格中周六、周日的行显示为暗色
+    #[arg(long)]
+    pub dim_weekends: bool,
+
+    /// 跳过文件聚合缓存，重新解析所有文件
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// 只统计该日期（含）之后的数据，格式 YYYY-MM-DD
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    pub since: Option<NaiveDate>,
+
+    /// 只统计该日期（含）之前的数据，格式 YYYY-MM-DD
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    pub until: Option<NaiveDate>,
+
+    /// 显示完整的模型 id，而不是 sonnet4 这样的简化名称
+    #[arg(long)]
+    pub full_model_names: bool,
+
+    /// 每日用量表格中同一天的多个分组合并到一行的单元格中，而不是各占一行
+    #[arg(long)]
+    pub merged_cells: bool,
+
+    /// 每日用量表格中有多个分组的日期附加一行小计
+    #[arg(long, conflicts_with = "merged_cells")]
+    pub subtotals: bool,
+
+    /// 每日用量表格在计费月之间画双线分隔，并为已结束的月份附加一行小计（token 与费用）；
+    /// 月份按 --billing-day 划分
+    #[arg(long)]
+    pub month_subtotals: bool,
+
+    /// 只统计本月的数据，等同于以本月 1 日（或本计费月的起始日）为 --since
+    #[arg(long, conflicts_with_all = ["since", "until"])]
+    pub this_month: bool,
+
+    /// 计费月的起始日（1–31），影响 monthly 的分组、--this-month、本月上限窗口和 --compare previous；
+    /// 月份没有该日时从当月最后一天开始。未指定时使用配置文件中的 billing.day，默认为 1
+    #[arg(long, value_name = "DAY", value_parser = clap::value_parser!(u32).range(1..=31))]
+    pub billing_day: Option<u32>,
+
+    /// 按日（及按小时）分组、判断"今天"与显示活动时间使用的时区，IANA 名称如 Asia/Shanghai、America/New_York
+    #[arg(long, value_name = "TZ", default_value = "UTC", value_parser = parse_timezone)]
+    pub timezone: Tz,
+
+    /// 在每日用量表格中增加从所选范围起始日累计的列，按 token 总量（默认）或费用（--cumulative=cost）累计
+    #[arg(long, value_enum, value_name = "METRIC", num_args = 0..=1, require_equals = true, default_missing_value = "tokens")]
+    pub cumulative: Option<Metric>,
+
+    /// 在每日用量表格中增加 N 天移动平均列，按日历天计算，没有记录的日期按 0 计入
+    #[arg(long, value_name = "DAYS", value_parser = clap::value_parser!(u32).range(1..))]
+    pub moving_avg: Option<u32>,
+
+    /// 在每日用量表格中增加 Δ% 列：当天总量相对前一个日历日的变化
+    #[arg(long)]
+    pub day_change: bool,
+
+    /// 以 JSON 输出每日用量，等同于 --format json
+    #[arg(long, conflicts_with = "format")]
+    pub json: bool,
+
+    /// 每日用量的输出格式
+    #[arg(long, value_enum, value_name = "FORMAT", default_value = "table")]
+    pub format: OutputFormat,
+
+    /// 同时把范围内每天的用量写为目录中的 YYYY-MM-DD.json（与 --json 中每天的对象相同），以及汇总的 index.json；
+    /// 只重写内容有变化的文件
+    #[arg(long, value_name = "DIR")]
+    pub export_dir: Option<PathBuf>,
+
+    /// 与 --export-dir 一起使用：删除目录中不在本次范围内的日期文件
+    #[arg(long, requires = "export_dir")]
+    pub prune: bool,
+
+    /// 与另一时间段对比：previous 为紧邻的上一期（本月对比上月），或 YYYY-MM-DD..YYYY-MM-DD
+    #[arg(long, value_name = "PERIOD")]
+    pub compare: Option<CompareSpec>,
+
+    /// 列出覆盖范围内没有任何记录的日期，用于区分未使用和日志已被清理
+    #[arg(long)]
+    pub show_gaps: bool,
+
+    /// 列出该日期（按 --timezone）的用量来自哪些会话文件：各文件的项目、计入的记录数、按模型的 token 合计与被排除的记录数，
+    /// 用于逐项核对每日表格中这一天的各行；与 --json 一起使用时输出 JSON
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    pub explain: Option<NaiveDate>,
+
+    /// 不输出报告末尾的来源说明（生成时间、数据目录、文件数等），JSON 输出也不附带 meta 字段
+    #[arg(long)]
+    pub no_footer: bool,
+
+    /// 在 JSON 输出的 meta 中记录的人员或机器名，cccost merge 合并多份导出时按它区分
+    #[arg(long, value_name = "NAME")]
+    pub label: Option<String>,
+
+    /// 显示所有数值列，包括所有行都为 0 或未上报的列（默认隐藏这些列）
+    #[arg(long)]
+    pub all_columns: bool,
+
+    /// 在每日用量表格中为范围内（--since 到 --until，默认为最早到最晚的数据）没有记录的日期补充暗色的空行
+    #[arg(long)]
+    pub fill_gaps: bool,
+
+    /// 计费窗口内的 token 上限，显示用量进度并预计达到上限的日期；超出时以退出码 3 结束
+    #[arg(long, value_name = "TOKENS")]
+    pub token_limit: Option<u64>,
+
+    /// token 上限的计费窗口：month（计费月，默认为自然月，见 --billing-day）或滚动天数如 30d
+    #[arg(long, value_name = "WINDOW")]
+    pub limit_window: Option<LimitWindow>,
+
+    /// 有项目本计费月的费用超出配置文件 [budgets.projects] 中的预算时，输出报告后以退出码 4 结束
+    #[arg(long)]
+    pub fail_over_budget: bool,
+
+    /// 有文件读取失败或处理时崩溃（panic）时以退出码 5 结束；默认只输出警告，报告照常完成
+    #[arg(long)]
+    pub strict: bool,
+
+    /// 使用量表格的分组维度
+    #[arg(long, value_enum, default_value_t = GroupBy::Model)]
+    pub group_by: GroupBy,
+
+    /// 每日用量表格的排列方式：auto 在窄终端（默认小于 60 列，配置文件 display.vertical_below）上纵向排列
+    #[arg(long, value_enum, default_value_t = Layout::Auto)]
+    pub layout: Layout,
+
+    /// 纵向排列每日用量，每个 (日期, 分组) 一个键值块，等同于 --layout vertical
+    #[arg(long, conflicts_with = "layout")]
+    pub vertical: bool,
+
+    /// 排版使用的输出宽度（列数），优先于终端宽度，每日用量表格正好占满该宽度（各列的最小宽度之和放不下时除外）；
+    /// 未指定时表格占终端宽度的 70%，输出不是终端时按 COLUMNS 环境变量，没有时按 120
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    pub width: Option<u16>,
+
+    /// 表格边框样式，默认 modern（配置文件 display.style）；markdown 与 --format markdown 输出相同
+    #[arg(long, value_enum)]
+    pub style: Option<TableStyle>,
+
+    /// 何时输出 ANSI 颜色：auto 在输出是终端且没有设置 NO_COLOR 时着色，always 总是着色（如交给 less -R），never 从不着色
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// 每日用量表格隔一天加底色（配置文件 display.zebra_color 设置颜色）；不着色时（见 --color）无效
+    #[arg(long)]
+    pub zebra: bool,
+
+    /// 同一模型的不同快照分行显示，而不是合并为一行
+    #[arg(long)]
+    pub split_snapshots: bool,
+
+    /// 不读取 ~/.claude.json（启动次数、项目最近打开时间）
+    #[arg(long)]
+    pub no_claude_json: bool,
+
+    /// 将历史归档与当前日志合并后统计，弥补已被清理的旧日志
+    #[arg(long)]
+    pub with_archive: bool,
+
+    /// 从 stdin 读取 JSONL，不扫描数据目录
+    #[arg(long)]
+    pub stdin: bool,
+
+    /// 读取哪个工具的日志：claude（默认）、codex、gemini、all，或 custom:<名称> 按配置文件中 [sources.<名称>] 的字段映射读取。
+    /// 指定 PATH 时表示这些路径是该工具的日志；all 不能与 PATH 或 --stdin 同时使用。--group-by source 按工具分行显示
+    #[arg(long, value_name = "SOURCE", default_value_t = SourceSelection::Claude)]
+    pub source: SourceSelection,
+
+    /// 在 json、jsonl 之外额外接受的文件扩展名，可重复或用逗号分隔（如 --ext ndjson）
+    #[arg(long = "ext", value_name = "EXT", value_delimiter = ',')]
+    pub extensions: Vec<String>,
+
+    /// 扩展名不被接受的文件读取开头几 KB，看起来像 JSONL 时同样处理
+    #[arg(long)]
+    pub sniff: bool,
+
+    /// 单文件大小上限，超过的文件会被跳过（如 1G、512M；0 表示不限制）
+    #[arg(long, value_name = "SIZE", default_value = "1G", value_parser = parse_size)]
+    pub max_file_size: u64,
+
+    /// 日期（按 --timezone）早于该日期的记录视为时间戳损坏，不计入统计并在诊断信息中列出；晚于明天的记录同样处理
+    #[arg(long, value_name = "DATE", value_parser = parse_date, default_value_t = crate::file_processor::DEFAULT_TIMESTAMP_FLOOR)]
+    pub timestamp_floor: NaiveDate,
+
+    /// 不检查时间戳，按原样统计所有记录，用于排查损坏的日志
+    #[arg(long)]
+    pub no_timestamp_validation: bool,
+
+    /// Claude Code 的数据目录，代替 ~/.claude/projects，例如 cccost backup 的备份目录。
+    /// 递归查找其中的日志（包括压缩的 .jsonl.gz），第一级目录为项目目录
+    #[arg(long, value_name = "DIR")]
+    pub dir: Option<PathBuf>,
+
+    /// 通过 ssh 统计另一台机器上的日志（如 user@devbox 或 ~/.ssh/config 中的别名），远程不需要安装 cccost。
+    /// 只支持 Claude Code 的日志，不使用缓存；其余筛选与报告选项照常使用
+    #[arg(long, value_name = "HOST", conflicts_with_all = ["stdin", "dir", "paths"])]
+    pub remote: Option<String>,
+
+    /// 与 --remote 一起使用：远程的数据目录，以 ~/ 开始时相对于远程用户的主目录
+    #[arg(long, value_name = "DIR", requires = "remote", default_value = crate::remote::DEFAULT_REMOTE_DIRECTORY)]
+    pub remote_dir: String,
+
+    /// 要统计的文件或目录（目录会递归查找），未指定时扫描 ~/.claude/projects
+    #[arg(value_name = "PATH")]
+    pub paths: Vec<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Cli {
+    /// Claude Code 的数据目录：--dir，未指定时为 ~/.claude/projects
+    pub fn claude_projects_dir(&self) -> PathBuf {
+        self.dir.clone().unwrap_or_else(|| {
+            let home_dir = std::env::var("HOME").unwrap_or_else(|_| String::from("~"));
+            PathBuf::from(home_dir).join(".claude/projects")
+        })
+    }
+
+    /// 每日用量实际使用的输出格式，--json 等同于 --format json
+    pub fn output_format(&self) -> OutputFormat {
+        if self.json {
+            OutputFormat::Json
+        } else {
+            self.format
+        }
+    }
+
+    /// 是否以 JSON 输出：默认命令看 --json / --format json，子命令看各自的 --json
+    pub fn json_output(&self) -> bool {
+        match &self.command {
+            None => self.output_format() == OutputFormat::Json,
+            Some(
+                Command::Tools { json, .. }
+                | Command::Stats { json, .. }
+                | Command::Sessions { json, .. }
+                | Command::Today { json, .. }
+                | Command::Day { json, .. }
+                | Command::Matrix { json, .. }
+                | Command::CacheReport { json, .. }
+                | Command::Timeline { json, .. }
+                | Command::Streaks { json, .. }
+                | Command::Top { json, .. }
+                | Command::Blocks { json, .. }
+                | Command::ByModel { json, .. }
+                | Command::ByProject { json, .. }
+                | Command::Budget { json, .. }
+                | Command::Monthly { json, .. }
+                | Command::Weekly { json, .. }
+                | Command::Projects { json, .. },
+            ) => *json,
+            Some(_) => false,
+        }
+    }
+
+    /// 是否输出供程序读取的 JSON 或 CSV。这时不显示进度条，即使 stderr 是终端：
+    /// 输出通常由脚本读取，stdout 与 stderr 合并时进度条会混入结果
+    pub fn machine_readable_output(&self) -> bool {
+        self.json_output() || (self.command.is_none() && self.output_format() == OutputFormat::Csv)
+    }
+
+    /// 每日用量实际使用的排列方式，--vertical 等同于 --layout vertical
+    pub fn table_layout(&self) -> Layout {
+        if self.vertical {
+            Layout::Vertical
+        } else {
+            self.layout
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+/// timeline 的时间段粒度
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimelinePeriod {
+    Week,
+    Month,
+}
+
+/// 每日用量的输出格式
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// 终端表格
+    Table,
+    /// JSON，每天一项并包含各模型明细
+    Json,
+    /// CSV，每个 (日期, 分组) 一行
+    Csv,
+    /// Markdown 表格
+    Markdown,
+}
+
+/// 何时输出 ANSI 颜色
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// 输出是终端且没有设置 NO_COLOR 时
+    #[default]
+    Auto,
+    /// 总是输出
+    Always,
+    /// 从不输出
+    Never,
+}
+
+/// 每日用量表格的排列方式
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Layout {
+    /// 终端宽度小于阈值时纵向排列，否则为表格
+    #[default]
+    Auto,
+    /// 表格
+    Table,
+    /// 每个 (日期, 分组) 一个键值块，块之间空一行
+    Vertical,
+}
+
+/// 表格的边框样式
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TableStyle {
+    /// Unicode 细线边框
+    #[default]
+    Modern,
+    /// 只用 ASCII 字符的边框
+    Ascii,
+    /// 圆角外框，只在表头下画横线
+    Rounded,
+    /// Markdown 表格
+    Markdown,
+    /// 不画边框，列之间以空格分隔
+    Blank,
+    /// 没有外框，只在表头下画横线（psql 风格）
+    Compact,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    /// 按 (模型, 日期)
+    Model,
+    /// 按 (git 分支, 日期)，没有分支信息的记录归入 "(none)"
+    Branch,
+    /// 按 (项目, 日期)，项目显示为路径的最后两级
+    Project,
+    /// 按 (写入日志的工具, 日期)：claude、codex、gemini 或字段映射的 label，与 --source all 一起使用
+    Source,
+    /// 按 (导出者的标签, 日期)，只用于 merge --by-person
+    #[value(skip)]
+    Person,
+}
+
+impl GroupBy {
+    /// 分组列的表头
+    pub fn label(self) -> &'static str {
+        match self {
+            GroupBy::Model => "Model",
+            GroupBy::Branch => "Branch",
+            GroupBy::Project => "Project",
+            GroupBy::Source => "Source",
+            GroupBy::Person => "Person",
+        }
+    }
+}
+
+/// 要读取的日志
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SourceSelection {
+    /// Claude Code 的日志（~/.claude/projects）
+    #[default]
+    Claude,
+    /// OpenAI Codex CLI 的日志（~/.codex/sessions）
+    Codex,
+    /// Google Gemini CLI 的会话记录（~/.gemini/tmp）
+    Gemini,
+    /// 全部合并统计，不存在的目录被忽略；不包括按字段映射读取的日志
+    All,
+    /// 按配置文件中 [sources.<名称>] 的字段映射读取，见 [`crate::mapping`]
+    Custom(String),
+}
+
+impl FromStr for SourceSelection {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "claude" => Ok(SourceSelection::Claude),
+            "codex" => Ok(SourceSelection::Codex),
+            "gemini" => Ok(SourceSelection::Gemini),
+            "all" => Ok(SourceSelection::All),
+            other => match other.strip_prefix("custom:") {
+                Some(name) if !name.is_empty() => Ok(SourceSelection::Custom(name.to_string())),
+                _ => Err(format!("无效的来源: {}（应为 claude、codex、gemini、all 或 custom:<名称>）", other)),
+            },
+        }
+    }
+}
+
+impl fmt::Display for SourceSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceSelection::Claude => f.write_str("claude"),
+            SourceSelection::Codex => f.write_str("codex"),
+            SourceSelection::Gemini => f.write_str("gemini"),
+            SourceSelection::All => f.write_str("all"),
+            SourceSelection::Custom(name) => write!(f, "custom:{}", name),
+        }
+    }
+}
+
+/// 排行、累计等按数值计算时使用的指标
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    /// 估算费用
+    Cost,
+    /// token 总量
+    Tokens,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// 统计各工具（Bash、Edit、Read 等）的调用次数
+    Tools {
+        /// 按日期分别列出，而不是汇总整个范围
+        #[arg(long)]
+        daily: bool,
+
+        /// 以 JSON 输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 显示统计分布，不指定任何视图时显示全部
+    Stats {
+        /// stop_reason 分布（end_turn、max_tokens、tool_use 等）
+        #[arg(long)]
+        stop_reasons: bool,
+
+        /// 按 Claude Code 版本统计活跃天数和 token 使用量
+        #[arg(long)]
+        by_version: bool,
+
+        /// 按一天中的小时（按 --timezone，0–23）合计 token 用量，附带比例条
+        #[arg(long)]
+        by_hour: bool,
+
+        /// 单条消息的输出 token 和总 token 的 p50/p90/p99 与最大值，按全部和各模型列出。
+        /// 需要逐条保留消息大小，会重新解析所有文件而不使用缓存
+        #[arg(long)]
+        message_sizes: bool,
+
+        /// 按日期和模型列出回复用时（durationMs）与首个 token 延迟（ttftMs）的中位数、p95 以及每秒输出 token 数。
+        /// 没有用时记录的消息不参与；需要逐条保留用时，会重新解析所有文件而不使用缓存
+        #[arg(long)]
+        latency: bool,
+
+        /// 按星期合计 token 用量和费用，并按该星期在范围内出现的次数求平均
+        #[arg(long)]
+        by_weekday: bool,
+
+        /// --by-weekday 的排列从该日开始，如 monday、sunday
+        #[arg(long, value_name = "DAY", default_value = "monday", value_parser = parse_weekday)]
+        week_start: Weekday,
+
+        /// 按日期分别列出
+        #[arg(long)]
+        daily: bool,
+
+        /// 以 JSON 输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 列出各会话的时长、项目、模型、token 使用量和费用
+    Sessions {
+        /// 排序依据，均按降序
+        #[arg(long, value_enum, default_value_t = Metric::Cost)]
+        sort: Metric,
+
+        /// 只显示排序后的前 N 个会话
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+
+        /// 以 JSON 输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 今天截至目前的用量概要：token、费用、活跃会话、与昨天同一时刻的对比以及本月累计
+    Today {
+        /// 以 JSON 输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 查看某一天的用量，按模型或按小时列出
+    Day {
+        /// 日期，格式 YYYY-MM-DD
+        #[arg(value_parser = parse_date)]
+        date: NaiveDate,
+
+        /// 按小时列出（按 --timezone），没有活动的小时显示为 0
+        #[arg(long)]
+        hourly: bool,
+
+        /// 以 JSON 输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 项目 × 月的透视表：每行一个项目，每列一个月，附带行合计和列合计
+    Matrix {
+        /// 显示截至所选范围末尾（默认今天）的最近 N 个月
+        #[arg(long, value_name = "N", default_value_t = 6, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+        months: usize,
+
+        /// 单元格显示 token 总量或费用，行也按该值降序排列
+        #[arg(long, value_enum, default_value_t = Metric::Tokens)]
+        by: Metric,
+
+        /// 以 JSON 输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 按周列出提示缓存的写入、读取、命中率和节省的费用，并比较首尾两周的命中率
+    CacheReport {
+        /// 每周的起始日，如 monday、sunday
+        #[arg(long, value_name = "DAY", default_value = "monday", value_parser = parse_weekday)]
+        week_start: Weekday,
+
+        /// 以 JSON 输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 各周或各月中不同模型的 token 占比，以堆叠条形图显示模型的切换过程
+    Timeline {
+        /// 按模型拆分（目前唯一支持的维度），--split-snapshots 时区分同一模型的不同快照
+        #[arg(long)]
+        by_model: bool,
+
+        /// 时间段的粒度
+        #[arg(long, value_enum, default_value_t = TimelinePeriod::Month)]
+        period: TimelinePeriod,
+
+        /// 每周的起始日，只在 --period week 时使用
+        #[arg(long, value_name = "DAY", default_value = "monday", value_parser = parse_weekday)]
+        week_start: Weekday,
+
+        /// 以 JSON 输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 连续使用天数：当前和最长的连续活跃天数、活跃天数占比以及最长的间隔
+    Streaks {
+        /// 当天 token 总量超过该值才算活跃
+        #[arg(long, value_name = "TOKENS", default_value_t = 0)]
+        min_tokens: u64,
+
+        /// 以 JSON 输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 列出用量最高的若干天及其各模型用量，或用量最高的会话
+    Top {
+        /// 显示的天数，未指定 --sessions 时默认 10
+        #[arg(long, value_name = "N")]
+        days: Option<usize>,
+
+        /// 改为列出用量最高的 N 个会话
+        #[arg(long, value_name = "N", conflicts_with = "days")]
+        sessions: Option<usize>,
+
+        /// 排序依据，均按降序
+        #[arg(long, value_enum, default_value_t = Metric::Tokens)]
+        by: Metric,
+
+        /// 以 JSON 输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 按 5 小时计费窗口列出用量，标出当前活跃的窗口
+    Blocks {
+        /// 只显示最近 3 天内开始的窗口
+        #[arg(long)]
+        recent: bool,
+
+        /// 只显示当前活跃的窗口，附带最近 15 分钟的消耗速率和按该速率外推的窗口结束时用量
+        #[arg(long, conflicts_with = "recent")]
+        active: bool,
+
+        /// 与 --active 一起使用，每隔 N 秒（默认 10）重新扫描并刷新显示，按 Ctrl-C 退出
+        #[arg(long, value_name = "SECS", requires = "active", num_args = 0..=1, require_equals = true,
+              default_missing_value = "10", value_parser = clap::value_parser!(u64).range(1..))]
+        watch: Option<u64>,
+
+        /// 以 JSON 输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 将所选范围内的用量按模型合计，包括费用、使用天数和费用占比
+    ByModel {
+        /// 以 JSON 输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 按项目汇总 token 用量、费用、会话数、活跃天数和费用占比；配置了项目预算时附带本计费月的预算使用情况
+    ByProject {
+        /// 以 JSON 输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 各项目本计费月的费用与配置文件 [budgets.projects] 中的预算，超出预算的项目显示为红色。
+    /// 计费月按 --billing-day 划分，与 --since / --until 无关
+    Budget {
+        /// 以 JSON 输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 按自然月（或 --billing-day 指定的计费月）汇总 token 用量、费用和活跃天数
+    Monthly {
+        /// 在每个月下列出各模型的用量
+        #[arg(long)]
+        breakdown: bool,
+
+        /// 以 JSON 输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 按周汇总 token 用量、费用、活跃天数以及相对上一周的变化
+    Weekly {
+        /// 只显示最近 N 周
+        #[arg(long, value_name = "N")]
+        last: Option<usize>,
+
+        /// 每周的起始日，如 monday、sunday
+        #[arg(long, value_name = "DAY", default_value = "monday", value_parser = parse_weekday)]
+        week_start: Weekday,
+
+        /// 在每周下列出各模型的用量
+        #[arg(long)]
+        breakdown: bool,
+
+        /// 以 JSON 输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 列出各项目的最近活动时间和 token 使用量
+    Projects {
+        /// 以 JSON 输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 交互式界面：浏览每日、每月和按模型的用量，可查看某天的会话并按模型或项目筛选
+    #[cfg(feature = "tui")]
+    Tui {
+        /// 每隔 N 秒自动重新扫描，未指定时只在按 r 时刷新
+        #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+        refresh: Option<u64>,
+    },
+    /// 将本次的 (日期, 模型, 项目) 聚合结果追加到历史归档，重复运行不会重复写入
+    Archive,
+    /// 管理文件聚合缓存
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// 满足条件时把今天、本计费月的用量与 token 上限的使用情况发送到 Slack 兼容的 webhook，适合在 cron 中运行。
+    /// 条件可在配置文件 [notify] 中设置；成功或未满足条件时不输出任何内容，发送失败时以非零状态退出
+    #[cfg(feature = "notify")]
+    Notify {
+        /// webhook 地址，未指定时使用配置文件中的 notify.webhook
+        #[arg(long, value_name = "URL")]
+        webhook: Option<String>,
+
+        /// 今天的费用超过该值（美元）时发送
+        #[arg(long, value_name = "USD")]
+        daily_cost_above: Option<f64>,
+
+        /// token 上限（--token-limit 或配置文件 limit.tokens）已用的百分比超过该值时发送
+        #[arg(long, value_name = "PERCENT")]
+        budget_above: Option<f64>,
+
+        /// 不检查条件，总是发送
+        #[arg(long)]
+        always: bool,
+
+        /// 请求的超时秒数，包括连接与读取响应
+        #[arg(long, value_name = "SECS", default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+        timeout: u64,
+
+        /// 只把将要发送的 JSON 输出到 stdout，不发送
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// 导入其他工具导出的历史用量，与日志合并后按默认报告输出；日志中已有数据的日期使用日志的数据
+    Import {
+        #[command(subcommand)]
+        format: ImportFormat,
+    },
+    /// 合并多份默认报告的 --json 导出（如团队成员或多台机器各自导出），按默认报告输出合计，不扫描本机日志；
+    /// 标签相同的导出相加，格式版本不同的导出无法合并
+    Merge {
+        /// 导出的 JSON 文件，导出时可用 --label 标明人员或机器，未标明时以文件名为标签
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<PathBuf>,
+
+        /// 按导出者分行显示（分组列为 Person），而不是按模型
+        #[arg(long)]
+        by_person: bool,
+    },
+    /// 把 Claude Code 的会话日志备份到目录，按项目与月份存放，重复运行只复制新增或有变化的文件。
+    /// 备份目录可用 cccost --dir <目录> 统计；与扫描相同，可用 PATH、--dir、--ext、--sniff 指定要备份的日志
+    Backup {
+        /// 备份目录，不存在时创建
+        #[arg(long, value_name = "DIR")]
+        to: PathBuf,
+
+        /// 以硬链接代替复制，不占用额外空间；备份目录需与日志在同一文件系统
+        #[arg(long, conflicts_with = "compress")]
+        link: bool,
+
+        /// 以 gzip 压缩备份（.jsonl.gz）
+        #[arg(long)]
+        compress: bool,
+    },
+    /// 检查数据目录、日志文件、配置文件、时区与缓存目录，找出报告为空或不完整的原因；
+    /// 有严重问题时以非零状态退出。与扫描相同，可用 PATH、--source、--ext、--sniff 指定要检查的日志
+    Doctor,
+    /// 像 tail -f 一样跟踪一个会话：每条新的带用量的记录输出一行（时间、模型、本条与会话累计的 token 和费用），
+    /// 会话一段时间没有新记录或按下 Ctrl-C 时输出会话的汇总。与扫描相同，可用 PATH、--dir 指定查找会话的位置
+    Tail {
+        /// 会话 id 或其开头部分（如 sessions 列出的前 8 位）
+        session: String,
+
+        /// 检查新记录的间隔（秒）
+        #[arg(long, value_name = "SECONDS", default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+
+        /// 连续这么多秒没有新记录时视为会话结束，输出汇总后退出；0 表示一直跟踪到按下 Ctrl-C
+        #[arg(long, value_name = "SECONDS", default_value_t = 600)]
+        idle: u64,
+    },
+    /// 生成合成的会话日志，供基准测试与大数据量下的手动测试使用。同样的参数总是生成相同的文件
+    #[command(hide = true)]
+    GenFixtures {
+        /// 天数
+        #[arg(long, default_value_t = 30)]
+        days: u32,
+
+        /// 每天的会话数
+        #[arg(long, default_value_t = 20)]
+        sessions_per_day: u32,
+
+        /// 每个会话的轮数，每轮一条用户消息和一条助手回复
+        #[arg(long, default_value_t = 20)]
+        turns: u32,
+
+        /// 随机数种子
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+
+        /// 第一天，格式 YYYY-MM-DD
+        #[arg(long, value_name = "DATE", default_value = "2025-01-01", value_parser = parse_date)]
+        start: NaiveDate,
+
+        /// 输出目录，不存在时创建，已存在时必须为空
+        #[arg(long, value_name = "DIR")]
+        out: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ImportFormat {
+    /// ccusage daily --json 的输出
+    Ccusage {
+        /// 导出的 JSON 文件
+        file: PathBuf,
+
+        /// 同时把并入报告的数据写入历史归档，之后用 --with-archive 即可使用
+        #[arg(long)]
+        archive: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CacheAction {
+    /// 清空缓存
+    Clear,
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").map_err(|_| format!("无效的日期: {}（应为 YYYY-MM-DD）", value))
+}
+
+fn parse_timezone(value: &str) -> Result<Tz, String> {
+    value.trim().parse().map_err(|_| format!("未知的时区: {}（应为 IANA 名称，如 Asia/Shanghai、UTC）", value))
+}
+
+fn parse_weekday(value: &str) -> Result<Weekday, String> {
+    value.trim().parse().map_err(|_| format!("无效的星期: {}（应为 monday、sunday 等）", value))
+}
+
+/// 解析带可选单位（K/M/G/T，按 1024 进制，可带 B 或 iB 后缀）的字节数
+fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let upper = value.to_ascii_uppercase();
+    let without_suffix = upper
+        .strip_suffix("IB")
+        .or_else(|| upper.strip_suffix('B'))
+        .unwrap_or(&upper);
+
+    let (number, multiplier) = match without_suffix.chars().last() {
+        Some('K') => (&without_suffix[..without_suffix.len() - 1], 1u64 << 10),
+        Some('M') => (&without_suffix[..without_suffix.len() - 1], 1u64 << 20),
+        Some('G') => (&without_suffix[..without_suffix.len() - 1], 1u64 << 30),
+        Some('T') => (&without_suffix[..without_suffix.len() - 1], 1u64 << 40),
+        _ => (without_suffix, 1),
+    };
+
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| format!("无效的大小: {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn machine_readable(args: &[&str]) -> bool {
+        Cli::try_parse_from(std::iter::once("cccost").chain(args.iter().copied())).unwrap().machine_readable_output()
+    }
+
+    #[test]
+    fn json_and_csv_output_is_machine_readable() {
+        assert!(machine_readable(&["--json"]));
+        assert!(machine_readable(&["--format", "json"]));
+        assert!(machine_readable(&["--format", "csv"]));
+        assert!(machine_readable(&["blocks", "--json"]));
+        assert!(!machine_readable(&[]));
+        assert!(!machine_readable(&["--format", "markdown"]));
+        assert!(!machine_readable(&["blocks"]));
+    }
+}
@@ -0,0 +1,103 @@
+//! 解析 OpenAI Codex CLI 的会话日志（~/.codex/sessions 下按 年/月/日 分目录的 rollout-*.jsonl）
+//!
+//! Codex 的记录与 Claude Code 不同：模型、工作目录与版本写在 session_meta、turn_context 记录中，
+//! token 用量在 token_count 事件里，且 input_tokens 已包含命中缓存的 cached_input_tokens。
+//! [`CodexParser`] 按文件内的顺序记住这些上下文，把每个 token_count 事件转换为与 Claude Code 相同的 [`Item`]：
+//! 未命中缓存的输入计为 input_tokens，cached_input_tokens 计为 cache_read_input_tokens；
+//! Codex 没有单独计费的缓存写入，cache_creation_input_tokens 记为 0。
+//!
+//! 内置价格表只有 Claude 模型，OpenAI 模型的价格可以写在 [`crate::pricing::PricingTable`] 的价格文件中
+//! （如 `pattern = "gpt-5"`）。
+
+use serde::Deserialize;
+use serde_json::Value;
+use crate::item::{Item, Usage};
+
+/// 记录中找不到模型时使用的模型名
+pub const UNKNOWN_MODEL: &str = "(unknown)";
+
+/// Codex 上报的一组 token 用量，字段名同时接受 input / cached_input / output 的简写
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct TokenUsage {
+    /// 输入 token 数，包括命中缓存的部分
+    #[serde(default, alias = "input")]
+    pub input_tokens: u64,
+    /// 命中缓存的输入 token 数
+    #[serde(default, alias = "cached_input")]
+    pub cached_input_tokens: u64,
+    /// 输出 token 数，包括推理 token
+    #[serde(default, alias = "output")]
+    pub output_tokens: u64,
+    /// 推理 token 数，已计入 output_tokens
+    #[serde(default)]
+    pub reasoning_output_tokens: u64,
+}
+
+impl From<&TokenUsage> for Usage {
+    /// 转换为 Claude Code 的用量字段
+    fn from(usage: &TokenUsage) -> Self {
+        Usage {
+            input_tokens: Some(usage.input_tokens.saturating_sub(usage.cached_input_tokens)),
+            output_tokens: Some(usage.output_tokens),
+            cache_creation_input_tokens: Some(0),
+            cache_read_input_tokens: Some(usage.cached_input_tokens),
+        }
+    }
+}
+
+/// 逐条解析一个 Codex 会话文件的记录，需要按文件内的顺序调用
+#[derive(Debug, Clone, Default)]
+pub struct CodexParser {
+    model: Option<String>,
+    cwd: Option<String>,
+    version: Option<String>,
+    git_branch: Option<String>,
+    // 上一个 token_count 事件的累计用量；Codex 会重复写入相同的事件，累计用量不变的事件不再计入
+    last_total: Option<TokenUsage>,
+}
+
+impl CodexParser {
+    /// 从一条记录中提取 Item：只有 token_count 事件得到 Item，其他记录只更新上下文，返回 None
+    pub fn parse_value(&mut self, value: &Value) -> Option<Item> {
+        let payload = value.get("payload").unwrap_or(value);
+        let text = |key: &str| payload.get(key).and_then(Value::as_str).map(str::to_string);
+        if let Some(model) = text("model") {
+            self.model = Some(model);
+        }
+        if let Some(cwd) = text("cwd") {
+            self.cwd = Some(cwd);
+        }
+        if let Some(version) = text("cli_version") {
+            self.version = Some(version);
+        }
+        if let Some(branch) = payload.pointer("/git/branch").and_then(Value::as_str) {
+            self.git_branch = Some(branch.to_string());
+        }
+
+        let usage = payload
+            .get("token_usage")
+            .or_else(|| payload.pointer("/info/last_token_usage"))
+            .and_then(|usage| TokenUsage::deserialize(usage).ok())?;
+        if let Some(total) = payload.pointer("/info/total_token_usage").and_then(|total| TokenUsage::deserialize(total).ok()) {
+            if self.last_total.as_ref() == Some(&total) {
+                return None;
+            }
+            self.last_total = Some(total);
+        }
+        let timestamp = value.get("timestamp").and_then(Value::as_str)?;
+
+        Some(Item {
+            message_id: None,
+            model: self.model.clone().unwrap_or_else(|| UNKNOWN_MODEL.to_string()),
+            timestamp: timestamp.to_string(),
+            usage: Some(Usage::from(&usage)),
+            tools: Vec::new(),
+            stop_reason: None,
+            version: self.version.clone(),
+            git_branch: self.git_branch.clone(),
+            cwd: self.cwd.clone(),
+            duration_ms: None,
+            ttft_ms: None,
+        })
+    }
+}
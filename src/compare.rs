@@ -0,0 +1,171 @@
+//! 所选范围与另一个时间段的逐日对比
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use chrono::{Days, NaiveDate};
+use serde::Serialize;
+use crate::item::DailyUsage;
+use crate::period::billing_cycle;
+use crate::pricing::cost_for;
+
+/// --compare 的取值：紧邻的上一个等长时间段，或显式指定的日期范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareSpec {
+    /// 紧邻的上一个等长时间段；所选范围恰为一个计费月时取上一个计费月
+    Previous,
+    /// 显式指定的日期范围（含首尾）
+    Range(NaiveDate, NaiveDate),
+}
+
+impl FromStr for CompareSpec {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("previous") {
+            return Ok(CompareSpec::Previous);
+        }
+        let invalid = || format!("无效的对比范围: {}（应为 previous 或 YYYY-MM-DD..YYYY-MM-DD）", value);
+        let (start, end) = value.split_once("..").ok_or_else(invalid)?;
+        let parse = |date: &str| NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").map_err(|_| invalid());
+        let (start, end) = (parse(start)?, parse(end)?);
+        if start > end {
+            return Err(invalid());
+        }
+        Ok(CompareSpec::Range(start, end))
+    }
+}
+
+/// 一个时间段的合计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RangeTotals {
+    /// 第一天
+    pub start: NaiveDate,
+    /// 最后一天
+    pub end: NaiveDate,
+    /// token 总量
+    pub total_tokens: u64,
+    /// 有价格的模型的费用合计，没有任何模型有价格时为 None
+    pub cost_usd: Option<f64>,
+    /// 有用量的天数
+    pub active_days: u64,
+}
+
+/// 所选范围内的一天与对比时间段中对应的一天
+#[derive(Debug, Clone, Serialize)]
+pub struct DayComparison {
+    /// 所选范围内的日期
+    pub date: NaiveDate,
+    /// 对比时间段中对齐的日期，超出对比时间段（如 31 日对应上月没有的日期）时为 None
+    pub compared_to: Option<NaiveDate>,
+    /// 当天的 token 总量
+    pub total_tokens: u64,
+    /// 对比日期的 token 总量
+    pub previous_tokens: u64,
+    /// 当天的费用
+    pub cost_usd: Option<f64>,
+    /// 对比日期的费用
+    pub previous_cost: Option<f64>,
+}
+
+/// 所选范围与对比时间段的合计以及逐日对比
+#[derive(Debug, Clone, Serialize)]
+pub struct Comparison {
+    /// 所选范围的合计
+    pub current: RangeTotals,
+    /// 对比时间段的合计
+    pub previous: RangeTotals,
+    /// 所选范围内的每一天，按日期排列
+    pub days: Vec<DayComparison>,
+}
+
+#[derive(Default)]
+struct DayTotals {
+    tokens: u64,
+    cost: Option<f64>,
+}
+
+impl Comparison {
+    /// 以 [start, end] 为所选范围构建对比。
+    ///
+    /// 所选范围恰好是一个计费月（或本期截至今天）且对比上一期时，对比上一个完整的计费月；
+    /// 其余情况对比等长的时间段。两种情况都按距起始日的天数对齐，
+    /// 计费月从每月 billing_day 日开始，为 1 时即自然月，按日期中的日对齐
+    pub fn build(
+        usage: &[DailyUsage],
+        spec: CompareSpec,
+        start: NaiveDate,
+        end: NaiveDate,
+        billing_day: u32,
+    ) -> Self {
+        let (cycle_start, cycle_end) = billing_cycle(start, billing_day);
+        let billing_month = spec == CompareSpec::Previous && cycle_start == start && end <= cycle_end;
+
+        let (previous_start, previous_end) = match spec {
+            CompareSpec::Range(previous_start, previous_end) => (previous_start, previous_end),
+            CompareSpec::Previous if billing_month => billing_cycle(start.pred_opt().unwrap_or(start), billing_day),
+            CompareSpec::Previous => {
+                let length = (end - start).num_days() as u64 + 1;
+                let previous_end = start.pred_opt().unwrap_or(start);
+                (previous_end.checked_sub_days(Days::new(length - 1)).unwrap_or(previous_end), previous_end)
+            }
+        };
+
+        let mut daily: BTreeMap<NaiveDate, DayTotals> = BTreeMap::new();
+        for DailyUsage { model, date, usage: aggregate } in usage {
+            let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") else {
+                continue;
+            };
+            let day = daily.entry(date).or_default();
+            day.tokens += aggregate.usage.total();
+            if let Some(cost) = cost_for(model, &aggregate.usage) {
+                day.cost = Some(day.cost.unwrap_or(0.0) + cost);
+            }
+        }
+
+        let totals = |start: NaiveDate, end: NaiveDate| {
+            let mut totals = RangeTotals { start, end, ..RangeTotals::default() };
+            for (_, day) in daily.range(start..=end) {
+                totals.total_tokens += day.tokens;
+                totals.active_days += u64::from(day.tokens > 0);
+                if let Some(cost) = day.cost {
+                    totals.cost_usd = Some(totals.cost_usd.unwrap_or(0.0) + cost);
+                }
+            }
+            totals
+        };
+
+        let align = |date: NaiveDate| -> Option<NaiveDate> {
+            let aligned = previous_start.checked_add_days(Days::new((date - start).num_days() as u64))?;
+            (aligned <= previous_end).then_some(aligned)
+        };
+
+        // 两边任一天有用量即列出
+        let days = start
+            .iter_days()
+            .take_while(|date| *date <= end)
+            .filter_map(|date| {
+                let compared_to = align(date);
+                let current = daily.get(&date);
+                let previous = compared_to.and_then(|aligned| daily.get(&aligned));
+                if current.is_none() && previous.is_none() {
+                    return None;
+                }
+                Some(DayComparison {
+                    date,
+                    compared_to,
+                    total_tokens: current.map_or(0, |day| day.tokens),
+                    previous_tokens: previous.map_or(0, |day| day.tokens),
+                    cost_usd: current.and_then(|day| day.cost),
+                    previous_cost: previous.and_then(|day| day.cost),
+                })
+            })
+            .collect();
+
+        Self {
+            current: totals(start, end),
+            previous: totals(previous_start, previous_end),
+            days,
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use crate::cli::TableStyle;
+use crate::limit::LimitWindow;
+use crate::mapping::FieldMapping;
+
+/// 配置文件 $XDG_CONFIG_HOME/cccost/config.toml（默认 ~/.config/cccost/config.toml），所有项均可省略
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub archive: ArchiveConfig,
+    pub limit: LimitConfig,
+    pub billing: BillingConfig,
+    pub display: DisplayConfig,
+    pub notify: NotifyConfig,
+    pub budgets: BudgetsConfig,
+    /// --source custom:<名称> 使用的字段映射，键为名称
+    pub sources: BTreeMap<String, FieldMapping>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArchiveConfig {
+    /// 每次运行后自动将本次的聚合结果写入归档
+    pub auto: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitConfig {
+    /// 计费窗口内的 token 上限
+    pub tokens: Option<u64>,
+    /// 计费窗口："month" 或 "30d" 形式的滚动天数
+    pub window: Option<LimitWindow>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BillingConfig {
+    /// 计费月的起始日（1–31），月份没有该日时从当月最后一天开始
+    pub day: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    /// --layout auto 时，终端宽度小于该列数则纵向排列每日用量
+    pub vertical_below: Option<usize>,
+    /// 表格边框样式，命令行的 --style 优先
+    pub style: Option<TableStyle>,
+    /// --zebra 的底色，ANSI SGR 参数，如 "48;5;236"（灰色背景）或 "2"（暗淡）
+    pub zebra_color: Option<String>,
+    /// 模型名称的前景色，键为模型系列（opus、sonnet、haiku 或无法识别的系列名称），值为 SGR 参数（如 "38;5;167"）
+    pub model_colors: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotifyConfig {
+    /// cccost notify 默认使用的 webhook 地址
+    pub webhook: Option<String>,
+    /// 今天的费用超过该值（美元）时发送，命令行的 --daily-cost-above 优先
+    pub daily_cost_above: Option<f64>,
+    /// token 上限已用的百分比超过该值时发送，命令行的 --budget-above 优先
+    pub budget_above: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BudgetsConfig {
+    /// 各项目每个计费月的费用上限（美元），键为项目名称、完整路径或含 * ? 的通配模式，见 [`crate::budget`]
+    pub projects: BTreeMap<String, f64>,
+}
+
+impl Config {
+    pub fn default_path() -> PathBuf {
+        let config_dir = std::env::var("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| {
+                let home_dir = std::env::var("HOME").unwrap_or_else(|_| String::from("~"));
+                PathBuf::from(home_dir).join(".config")
+            });
+        config_dir.join("cccost").join("config.toml")
+    }
+
+    /// 读取配置，文件不存在时使用默认值；格式错误时返回错误信息
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(content) => {
+                let config: Self = toml::from_str(&content)
+                    .map_err(|e| format!("配置文件 {} 格式错误: {}", path.display(), e))?;
+                if let Some(day) = config.billing.day
+                    && !(1..=31).contains(&day) {
+                    return Err(format!("配置文件 {} 格式错误: billing.day 应在 1 到 31 之间，实际为 {}", path.display(), day));
+                }
+                if let Some((project, amount)) = config.budgets.projects.iter().find(|(_, amount)| !amount.is_finite() || **amount < 0.0) {
+                    return Err(format!("配置文件 {} 格式错误: budgets.projects.\"{}\" 应为不小于 0 的金额，实际为 {}", path.display(), project, amount));
+                }
+                for (name, mapping) in &config.sources {
+                    mapping.validate()
+                        .map_err(|e| format!("配置文件 {} 格式错误: sources.{}: {}", path.display(), name, e))?;
+                }
+                let colors = config.display.zebra_color.iter().map(|color| ("display.zebra_color".to_string(), color));
+                let model_colors = config.display.model_colors.iter().map(|(family, color)| (format!("display.model_colors.{}", family), color));
+                if let Some((key, color)) = colors.chain(model_colors).find(|(_, color)| !is_sgr(color)) {
+                    return Err(format!("配置文件 {} 格式错误: {} 应为以 ; 分隔的 SGR 参数（如 \"48;5;236\"），实际为 {:?}", path.display(), key, color));
+                }
+                Ok(config)
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("读取配置文件 {} 失败: {}", path.display(), e)),
+        }
+    }
+}
+
+/// 是否为以 ; 分隔的数字组成的 SGR 参数
+fn is_sgr(color: &str) -> bool {
+    !color.is_empty() && color.chars().all(|c| c.is_ascii_digit() || c == ';')
+}
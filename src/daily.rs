@@ -0,0 +1,347 @@
+//! 每日合计及其派生数据：今日概要、按小时用量、连续使用天数
+
+use std::collections::BTreeMap;
+use chrono::{DateTime, Days, NaiveDate, Timelike};
+use chrono_tz::Tz;
+use serde::Serialize;
+use crate::period::{billing_cycle, TokenTotals};
+use crate::cli::Metric;
+use crate::item::{DailyUsage, SessionSummary};
+use crate::pricing::cost_for;
+
+/// 一天的合计
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct DayValue {
+    /// 当天的 token 总量
+    pub tokens: u64,
+    /// 有价格的模型的费用合计，没有任何模型有价格时为 None
+    pub cost_usd: Option<f64>,
+}
+
+impl DayValue {
+    /// 按 metric 取值，费用缺失时为 0
+    pub fn get(&self, metric: Metric) -> f64 {
+        match metric {
+            Metric::Tokens => self.tokens as f64,
+            Metric::Cost => self.cost_usd.unwrap_or(0.0),
+        }
+    }
+}
+
+/// 按日期排列的每日合计，累计、移动平均等派生列都在格式化之前基于它计算
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(transparent)]
+pub struct DailySeries {
+    days: BTreeMap<NaiveDate, DayValue>,
+}
+
+impl DailySeries {
+    /// 由 (模型, 日期) 使用量按日期汇总
+    pub fn from_usage(usage: &[DailyUsage]) -> Self {
+        let mut days: BTreeMap<NaiveDate, DayValue> = BTreeMap::new();
+        for DailyUsage { model, date, usage: aggregate } in usage {
+            let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") else {
+                continue;
+            };
+            let day = days.entry(date).or_default();
+            day.tokens += aggregate.usage.total();
+            if let Some(cost) = cost_for(model, &aggregate.usage) {
+                day.cost_usd = Some(day.cost_usd.unwrap_or(0.0) + cost);
+            }
+        }
+        Self { days }
+    }
+
+    /// 从最早一天起按日期顺序累计，最后一天的值等于总计
+    pub fn cumulative(&self, metric: Metric) -> BTreeMap<NaiveDate, f64> {
+        let mut running = 0.0;
+        self.days
+            .iter()
+            .map(|(date, day)| {
+                running += day.get(metric);
+                (*date, running)
+            })
+            .collect()
+    }
+
+    /// 每天前一个日历日的 token 总量；前一天没有记录时为 0（而不是表格中的上一行）
+    pub fn previous_day_tokens(&self) -> BTreeMap<NaiveDate, u64> {
+        self.days
+            .keys()
+            .map(|date| {
+                let previous = date
+                    .pred_opt()
+                    .and_then(|previous| self.days.get(&previous))
+                    .map_or(0, |day| day.tokens);
+                (*date, previous)
+            })
+            .collect()
+    }
+
+    /// 为 [start, end] 内没有记录的日期补充为 0 的合计，返回补充的日期。
+    /// 累计、移动平均与日变化的数值不受影响，只是这些日期也有了对应的值
+    pub fn fill_gaps(&mut self, start: NaiveDate, end: NaiveDate) -> Vec<NaiveDate> {
+        let gaps: Vec<NaiveDate> = start
+            .iter_days()
+            .take_while(|date| *date <= end)
+            .filter(|date| !self.days.contains_key(date))
+            .collect();
+        for date in &gaps {
+            self.days.insert(*date, DayValue::default());
+        }
+        gaps
+    }
+
+    /// 某天的 token 总量
+    pub fn tokens(&self, date: NaiveDate) -> u64 {
+        self.days.get(&date).map_or(0, |day| day.tokens)
+    }
+
+    /// [start, end] 内各天的合计
+    pub fn range_total(&self, start: NaiveDate, end: NaiveDate) -> DayValue {
+        let mut total = DayValue::default();
+        for (_, day) in self.days.range(start..=end) {
+            total.tokens += day.tokens;
+            if let Some(cost) = day.cost_usd {
+                total.cost_usd = Some(total.cost_usd.unwrap_or(0.0) + cost);
+            }
+        }
+        total
+    }
+
+    /// 以日历天为窗口的移动平均：窗口内没有记录的日期按 0 计入。
+    /// 窗口起点早于 range_start（所选范围的起始日）时数据不完整，该天为 None
+    pub fn moving_average(&self, window: u32, range_start: Option<NaiveDate>) -> BTreeMap<NaiveDate, Option<DayAverage>> {
+        let Some(range_start) = range_start.or_else(|| self.days.keys().next().copied()) else {
+            return BTreeMap::new();
+        };
+        let window = window.max(1);
+        self.days
+            .keys()
+            .map(|date| {
+                let average = date
+                    .checked_sub_days(Days::new(u64::from(window) - 1))
+                    .filter(|start| *start >= range_start)
+                    .map(|start| {
+                        let days = self.days.range(start..=*date).map(|(_, day)| day);
+                        let (tokens, cost) = days.fold((0u64, None::<f64>), |(tokens, cost), day| {
+                            let cost = match (cost, day.cost_usd) {
+                                (None, None) => None,
+                                (cost, day_cost) => Some(cost.unwrap_or(0.0) + day_cost.unwrap_or(0.0)),
+                            };
+                            (tokens + day.tokens, cost)
+                        });
+                        DayAverage {
+                            tokens: tokens as f64 / f64::from(window),
+                            cost_usd: cost.map(|cost| cost / f64::from(window)),
+                        }
+                    });
+                (*date, average)
+            })
+            .collect()
+    }
+}
+
+/// 移动平均窗口内的日均值
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct DayAverage {
+    /// 日均 token 数
+    pub tokens: f64,
+    /// 日均费用，窗口内没有任何费用时为 None
+    pub cost_usd: Option<f64>,
+}
+
+/// 一天中某个小时的用量
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HourUsage {
+    /// 小时（0–23）
+    pub hour: u32,
+    /// 该小时的消息数
+    pub messages: u64,
+    /// 该小时的 token 合计
+    #[serde(flatten)]
+    pub tokens: TokenTotals,
+    /// 该小时的费用，没有任何模型有价格时为 None
+    pub cost_usd: Option<f64>,
+}
+
+/// 将 (模型, 小时键) 使用量中属于 date 的部分按小时汇总，始终返回 0–23 时共 24 项
+pub fn hourly_usage(hourly: &[DailyUsage], date: NaiveDate) -> Vec<HourUsage> {
+    let mut hours: Vec<HourUsage> = (0..24).map(|hour| HourUsage { hour, ..HourUsage::default() }).collect();
+    let prefix = format!("{}T", date.format("%Y-%m-%d"));
+    for DailyUsage { model, date: key, usage: aggregate } in hourly {
+        let Some(hour) = key.strip_prefix(&prefix).and_then(|hour| hour.parse::<usize>().ok()) else {
+            continue;
+        };
+        let Some(entry) = hours.get_mut(hour) else {
+            continue;
+        };
+        entry.messages += aggregate.messages;
+        entry.tokens.add(&aggregate.usage);
+        if let Some(cost) = cost_for(model, &aggregate.usage) {
+            entry.cost_usd = Some(entry.cost_usd.unwrap_or(0.0) + cost);
+        }
+    }
+    hours
+}
+
+/// today 命令的概要：今天截至目前的用量、与昨天同一时刻的对比以及本月累计
+#[derive(Debug, Clone, Serialize)]
+pub struct TodaySummary {
+    /// 今天的日期
+    pub date: NaiveDate,
+    /// 当前所在的小时（UTC），昨天的用量截至该小时结束
+    pub hour: u32,
+    /// 今天的 token 总量
+    pub total_tokens: u64,
+    /// 今天的费用
+    pub cost_usd: Option<f64>,
+    /// 今天的消息数
+    pub messages: u64,
+    /// 今天有活动的会话数
+    pub sessions: u64,
+    /// 昨天截至同一小时的 token 总量
+    pub yesterday_total_tokens: u64,
+    /// 昨天截至同一小时的费用
+    pub yesterday_cost_usd: Option<f64>,
+    /// 本计费月的第一天
+    pub month_start: NaiveDate,
+    /// 本计费月截至目前的 token 总量
+    pub month_total_tokens: u64,
+    /// 本计费月截至目前的费用
+    pub month_cost_usd: Option<f64>,
+}
+
+impl TodaySummary {
+    /// 按小时数据对齐：今天取截至当前的全部用量，昨天取 0 时到当前小时（含）的用量。
+    /// now 的时区应与扫描时的 with_timezone 一致，"今天"与小时键才按同一时区计算
+    pub fn build(
+        usage: &[DailyUsage],
+        hourly: &[DailyUsage],
+        sessions: &[SessionSummary],
+        now: DateTime<Tz>,
+        billing_day: u32,
+    ) -> Self {
+        let date = now.date_naive();
+        let hour = now.hour();
+        let sum_hours = |hours: &[HourUsage]| {
+            hours.iter().fold((0, None, 0), |(tokens, cost, messages), entry| {
+                let cost = match (cost, entry.cost_usd) {
+                    (Some(cost), Some(entry_cost)) => Some(cost + entry_cost),
+                    (cost, entry_cost) => cost.or(entry_cost),
+                };
+                (tokens + entry.tokens.total_tokens, cost, messages + entry.messages)
+            })
+        };
+        let (total_tokens, cost_usd, messages) = sum_hours(&hourly_usage(hourly, date));
+        let yesterday = date.pred_opt().unwrap_or(date);
+        let (yesterday_total_tokens, yesterday_cost_usd, _) =
+            sum_hours(&hourly_usage(hourly, yesterday)[..=hour as usize]);
+
+        let month_start = billing_cycle(date, billing_day).0;
+        let month = DailySeries::from_usage(usage).range_total(month_start, date);
+        let sessions = sessions
+            .iter()
+            .filter(|session| {
+                session.started_at.is_some_and(|started| started.with_timezone(&now.timezone()).date_naive() <= date)
+                    && session.ended_at.is_some_and(|ended| ended.with_timezone(&now.timezone()).date_naive() >= date)
+            })
+            .count() as u64;
+
+        Self {
+            date,
+            hour,
+            total_tokens,
+            cost_usd,
+            messages,
+            sessions,
+            yesterday_total_tokens,
+            yesterday_cost_usd,
+            month_start,
+            month_total_tokens: month.tokens,
+            month_cost_usd: month.cost_usd,
+        }
+    }
+}
+
+/// 一段连续的日期
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DateSpan {
+    /// 第一天
+    pub start: NaiveDate,
+    /// 最后一天
+    pub end: NaiveDate,
+    /// 天数，含首尾
+    pub days: u64,
+}
+
+impl DateSpan {
+    fn new(start: NaiveDate, end: NaiveDate) -> Self {
+        Self { start, end, days: (end - start).num_days() as u64 + 1 }
+    }
+}
+
+/// 所选范围内连续使用的天数统计
+#[derive(Debug, Clone, Serialize)]
+pub struct Streaks {
+    /// 范围的第一天
+    pub start: NaiveDate,
+    /// 范围的最后一天
+    pub end: NaiveDate,
+    /// 活跃日的门槛：当天 token 总量大于该值
+    pub min_tokens: u64,
+    /// 范围内的活跃天数
+    pub active_days: u64,
+    /// 范围内的日历天数
+    pub calendar_days: u64,
+    /// 截至范围末尾仍在延续的连续活跃天数；末尾当天还没有用量时，截至前一天的连续天数仍算作当前
+    pub current: Option<DateSpan>,
+    /// 范围内最长的连续活跃天数
+    pub longest: Option<DateSpan>,
+    /// 两个活跃日之间最长的不活跃时段，不含范围首尾
+    pub longest_gap: Option<DateSpan>,
+}
+
+impl Streaks {
+    /// 按日历天遍历 [start, end]，最长的连续或间隔长度相同时取较早的一段
+    pub fn compute(series: &DailySeries, start: NaiveDate, end: NaiveDate, min_tokens: u64) -> Self {
+        let mut active_days = 0;
+        let mut runs: Vec<DateSpan> = Vec::new();
+        for date in start.iter_days().take_while(|date| *date <= end) {
+            if series.tokens(date) <= min_tokens {
+                continue;
+            }
+            active_days += 1;
+            match runs.last_mut() {
+                Some(run) if run.end.succ_opt() == Some(date) => *run = DateSpan::new(run.start, date),
+                _ => runs.push(DateSpan::new(date, date)),
+            }
+        }
+
+        let longest_by_days = |spans: &mut dyn Iterator<Item = DateSpan>| {
+            spans.fold(None, |longest: Option<DateSpan>, span| match longest {
+                Some(longest) if longest.days >= span.days => Some(longest),
+                _ => Some(span),
+            })
+        };
+        let longest = longest_by_days(&mut runs.iter().copied());
+        let longest_gap = longest_by_days(&mut runs.windows(2).filter_map(|pair| {
+            let gap_start = pair[0].end.succ_opt()?;
+            let gap_end = pair[1].start.pred_opt()?;
+            Some(DateSpan::new(gap_start, gap_end))
+        }));
+        let yesterday = end.pred_opt().unwrap_or(end);
+        let current = runs.last().copied().filter(|run| run.end >= yesterday);
+
+        Self {
+            start,
+            end,
+            min_tokens,
+            active_days,
+            calendar_days: ((end - start).num_days() + 1).max(0) as u64,
+            current,
+            longest,
+            longest_gap,
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! 扫描过程中的解析统计与错误
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+
+/// 单个文件的解析统计
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct FileStats {
+    /// 读取的非空行数
+    pub lines: u64,
+    /// 计入统计的使用记录数
+    pub entries: u64,
+    /// 合法但未计入统计的行，按记录的 type 字段分类
+    pub skipped: BTreeMap<String, u64>,
+    /// 无法解析为 JSON 的行数
+    pub failed_lines: u64,
+    /// 计入统计但缺少某个使用量字段的记录数，按字段名分类
+    pub missing_fields: BTreeMap<String, u64>,
+    /// 被 FileProcessorBuilder::with_filter 注册的条件排除的记录数
+    #[serde(default)]
+    pub filtered: u64,
+    /// message.id 与之前的记录相同、用量未重复计入的记录数
+    #[serde(default)]
+    pub duplicates: u64,
+    /// 文件的格式版本无法识别而整个被跳过（如新版本 Gemini CLI 的会话文件），不按猜测的结构解析
+    #[serde(default)]
+    pub unsupported_schema: bool,
+    /// 时间戳明显不合理而未计入统计的记录，按在文件中的顺序
+    #[serde(default)]
+    pub quarantined: Vec<QuarantinedEntry>,
+}
+
+/// 时间戳早于下限或晚于明天（UTC）而被隔离、未计入统计的记录
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct QuarantinedEntry {
+    /// 在文件中的行号（从 1 开始）；不是逐行的 JSONL（如 JSON 数组）时为 None
+    pub line: Option<u64>,
+    /// 记录中原样的时间戳
+    pub timestamp: String,
+}
+
+impl FileStats {
+    /// 记录一行未计入统计的记录
+    pub fn record_skipped(&mut self, entry_type: &str) {
+        *self.skipped.entry(entry_type.to_string()).or_insert(0) += 1;
+    }
+
+    /// 记录一条缺少 field 字段的记录
+    pub fn record_missing_field(&mut self, field: &str) {
+        *self.missing_fields.entry(field.to_string()).or_insert(0) += 1;
+    }
+
+    /// 并入同一文件另一部分的统计
+    pub fn merge(&mut self, other: FileStats) {
+        self.lines += other.lines;
+        self.entries += other.entries;
+        self.failed_lines += other.failed_lines;
+        self.filtered += other.filtered;
+        self.duplicates += other.duplicates;
+        self.unsupported_schema |= other.unsupported_schema;
+        self.quarantined.extend(other.quarantined);
+        for (entry_type, count) in other.skipped {
+            *self.skipped.entry(entry_type).or_insert(0) += count;
+        }
+        for (field, count) in other.missing_fields {
+            *self.missing_fields.entry(field).or_insert(0) += count;
+        }
+    }
+}
+
+/// 一次扫描的诊断信息汇总
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    /// 每个已解析文件的统计，按路径排序
+    pub files: Vec<(PathBuf, FileStats)>,
+    /// 读取失败的文件及错误信息
+    pub read_errors: Vec<(PathBuf, String)>,
+    /// 解析时发生 panic 的文件及 panic 信息，这些文件的数据不计入统计
+    pub panicked_files: Vec<(PathBuf, String)>,
+    /// 因超过大小上限而被跳过的文件 (路径, 字节数)
+    pub oversized_files: Vec<(PathBuf, u64)>,
+    /// 因文件类型未被处理的文件数，按扩展名分类
+    pub skipped_files: BTreeMap<String, u64>,
+    /// 写入文件聚合缓存失败时的错误信息
+    pub cache_error: Option<String>,
+    /// 并入报告的外部导出数据，见 [`crate::import`]
+    pub imported: Vec<ImportedData>,
+    /// cccost merge 合并的导出，见 [`crate::merge`]；不为空时报告中没有扫描的日志
+    pub merged: Vec<MergedExport>,
+}
+
+/// 一个导入文件并入报告的情况
+#[derive(Debug, Clone, Default)]
+pub struct ImportedData {
+    /// 导入的文件
+    pub path: PathBuf,
+    /// 计入报告的 (模型, 日期) 行数
+    pub rows: usize,
+    /// 计入报告的天数
+    pub days: usize,
+    /// 日志中已有数据、因而没有使用导入数据的日期，按日期排序
+    pub overlapping_dates: Vec<String>,
+}
+
+/// cccost merge 合并的一份导出
+#[derive(Debug, Clone, Default)]
+pub struct MergedExport {
+    /// 导出文件
+    pub path: PathBuf,
+    /// 导出者的标签
+    pub label: String,
+    /// 有数据的天数
+    pub days: usize,
+    /// token 合计
+    pub tokens: u64,
+}
+
+impl Diagnostics {
+    /// 所有文件中计入统计的记录数
+    pub fn total_entries(&self) -> u64 {
+        self.files.iter().map(|(_, stats)| stats.entries).sum()
+    }
+
+    /// 所有文件中被过滤条件排除的记录数
+    pub fn total_filtered(&self) -> u64 {
+        self.files.iter().map(|(_, stats)| stats.filtered).sum()
+    }
+
+    /// 所有文件中因 message.id 重复而未重复计入用量的记录数
+    pub fn total_duplicates(&self) -> u64 {
+        self.files.iter().map(|(_, stats)| stats.duplicates).sum()
+    }
+
+    /// 所有文件中无法解析的行数
+    pub fn total_failed_lines(&self) -> u64 {
+        self.files.iter().map(|(_, stats)| stats.failed_lines).sum()
+    }
+
+    /// 含有无法解析行的文件
+    pub fn files_with_failures(&self) -> impl Iterator<Item = &(PathBuf, FileStats)> {
+        self.files.iter().filter(|(_, stats)| stats.failed_lines > 0)
+    }
+
+    /// 时间戳不合理而被隔离的记录及其所在文件
+    pub fn quarantined(&self) -> impl Iterator<Item = (&PathBuf, &QuarantinedEntry)> {
+        self.files.iter().flat_map(|(path, stats)| stats.quarantined.iter().map(move |entry| (path, entry)))
+    }
+
+    /// 格式版本无法识别而被跳过的文件
+    pub fn unsupported_files(&self) -> impl Iterator<Item = &PathBuf> {
+        self.files.iter().filter(|(_, stats)| stats.unsupported_schema).map(|(path, _)| path)
+    }
+
+    /// 所有文件中缺少各使用量字段的记录数合计
+    pub fn missing_fields(&self) -> BTreeMap<String, u64> {
+        let mut totals = BTreeMap::new();
+        for (_, stats) in &self.files {
+            for (field, count) in &stats.missing_fields {
+                *totals.entry(field.clone()).or_insert(0) += count;
+            }
+        }
+        totals
+    }
+
+    /// 所有文件中跳过的行按类型合计
+    pub fn skipped_by_type(&self) -> BTreeMap<String, u64> {
+        let mut totals = BTreeMap::new();
+        for (_, stats) in &self.files {
+            for (entry_type, count) in &stats.skipped {
+                *totals.entry(entry_type.clone()).or_insert(0) += count;
+            }
+        }
+        totals
+    }
+}
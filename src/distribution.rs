@@ -0,0 +1,208 @@
+//! 用量在一天中各小时、一周中各天的分布，以及单条消息大小的分位数
+
+use std::collections::BTreeMap;
+use chrono::{Datelike, NaiveDate, Weekday};
+use serde::Serialize;
+use crate::file_processor::MessageSize;
+use crate::item::DailyUsage;
+use crate::pricing::cost_for;
+use crate::tr;
+
+/// 一天中某个小时（UTC）在整个范围内的用量合计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HourOfDay {
+    /// 小时（0–23）
+    pub hour: u32,
+    /// 消息数
+    pub messages: u64,
+    /// token 总量
+    pub total_tokens: u64,
+    /// 有价格的模型的费用合计，没有任何模型有价格时为 None
+    pub cost_usd: Option<f64>,
+    /// 占全部 token 的百分比
+    pub share_percent: f64,
+}
+
+/// 将 (模型, 小时键) 使用量按小时（0–23）合计，不区分日期，始终返回 24 项
+pub fn hour_of_day(hourly: &[DailyUsage]) -> Vec<HourOfDay> {
+    let mut hours: Vec<HourOfDay> = (0..24).map(|hour| HourOfDay { hour, ..HourOfDay::default() }).collect();
+    for DailyUsage { model, date: key, usage: aggregate } in hourly {
+        // 小时键形如 2025-09-01T14
+        let Some(entry) = key
+            .get(11..13)
+            .and_then(|hour| hour.parse::<usize>().ok())
+            .and_then(|hour| hours.get_mut(hour)) else {
+            continue;
+        };
+        entry.messages += aggregate.messages;
+        entry.total_tokens += aggregate.usage.total();
+        if let Some(cost) = cost_for(model, &aggregate.usage) {
+            entry.cost_usd = Some(entry.cost_usd.unwrap_or(0.0) + cost);
+        }
+    }
+    let total: u64 = hours.iter().map(|hour| hour.total_tokens).sum();
+    if total > 0 {
+        for hour in &mut hours {
+            hour.share_percent = hour.total_tokens as f64 * 100.0 / total as f64;
+        }
+    }
+    hours
+}
+
+/// 星期几在整个范围内的用量合计，以及按该星期在范围内出现次数计算的平均值
+#[derive(Debug, Clone, Serialize)]
+pub struct WeekdayUsage {
+    /// 星期几
+    #[serde(serialize_with = "serialize_weekday")]
+    pub weekday: Weekday,
+    /// 该星期在所选范围内出现的次数，包括没有用量的日期
+    pub occurrences: u64,
+    /// token 总量
+    pub total_tokens: u64,
+    /// 有价格的模型的费用合计，没有任何模型有价格时为 None
+    pub cost_usd: Option<f64>,
+    /// 每次出现的平均 token 数
+    pub average_tokens: f64,
+    /// 每次出现的平均费用
+    pub average_cost_usd: Option<f64>,
+    /// 占全部 token 的百分比
+    pub share_percent: f64,
+}
+
+fn serialize_weekday<S: serde::Serializer>(weekday: &Weekday, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&weekday.to_string())
+}
+
+/// 将 (模型, 日期) 使用量按星期合计，从 week_start 开始排列，始终返回 7 项。
+/// 平均值除以该星期在 [start, end] 中出现的次数，范围内出现四次和五次的星期可以公平比较
+pub fn weekday_usage(
+    usage: &[DailyUsage],
+    start: NaiveDate,
+    end: NaiveDate,
+    week_start: Weekday,
+) -> Vec<WeekdayUsage> {
+    let mut weekdays: Vec<WeekdayUsage> = (0..7)
+        .map(|offset| WeekdayUsage {
+            weekday: (0..offset).fold(week_start, |weekday, _| weekday.succ()),
+            occurrences: 0,
+            total_tokens: 0,
+            cost_usd: None,
+            average_tokens: 0.0,
+            average_cost_usd: None,
+            share_percent: 0.0,
+        })
+        .collect();
+    let index = |weekday: Weekday| weekday.days_since(week_start) as usize;
+
+    for date in start.iter_days().take_while(|date| *date <= end) {
+        weekdays[index(date.weekday())].occurrences += 1;
+    }
+    for DailyUsage { model, date, usage: aggregate } in usage {
+        let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") else {
+            continue;
+        };
+        if date < start || date > end {
+            continue;
+        }
+        let entry = &mut weekdays[index(date.weekday())];
+        entry.total_tokens += aggregate.usage.total();
+        if let Some(cost) = cost_for(model, &aggregate.usage) {
+            entry.cost_usd = Some(entry.cost_usd.unwrap_or(0.0) + cost);
+        }
+    }
+
+    let total: u64 = weekdays.iter().map(|weekday| weekday.total_tokens).sum();
+    for weekday in &mut weekdays {
+        let occurrences = weekday.occurrences.max(1) as f64;
+        weekday.average_tokens = weekday.total_tokens as f64 / occurrences;
+        weekday.average_cost_usd = weekday.cost_usd.map(|cost| cost / occurrences);
+        if total > 0 {
+            weekday.share_percent = weekday.total_tokens as f64 * 100.0 / total as f64;
+        }
+    }
+    weekdays
+}
+
+/// 星期在语言目录中的键，按周一到周日排列
+const WEEKDAY_KEYS: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+/// 当前语言的星期全称，如 Monday、周一
+pub fn weekday_name(weekday: Weekday) -> String {
+    tr!(&format!("weekday.{}", WEEKDAY_KEYS[weekday.num_days_from_monday() as usize]))
+}
+
+/// 当前语言的星期简称，如 Mon、周一
+pub fn weekday_abbreviation(weekday: Weekday) -> String {
+    tr!(&format!("weekday.short.{}", WEEKDAY_KEYS[weekday.num_days_from_monday() as usize]))
+}
+
+/// 一组值的分位数（最近秩法）
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Percentiles {
+    /// 中位数
+    pub p50: u64,
+    /// 第 90 百分位
+    pub p90: u64,
+    /// 第 99 百分位
+    pub p99: u64,
+    /// 最大值
+    pub max: u64,
+}
+
+impl Percentiles {
+    fn of(mut values: Vec<u64>) -> Self {
+        values.sort_unstable();
+        let rank = |percent: usize| {
+            // 最近秩：第 ceil(p/100 * n) 个值
+            let index = (percent * values.len()).div_ceil(100).max(1) - 1;
+            values.get(index).copied().unwrap_or(0)
+        };
+        Self {
+            p50: rank(50),
+            p90: rank(90),
+            p99: rank(99),
+            max: values.last().copied().unwrap_or(0),
+        }
+    }
+}
+
+/// 单条消息大小的分布；model 为显示名称，None 时是全部模型合计
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageSizeStats {
+    /// 模型的显示名称
+    pub model: Option<String>,
+    /// 参与统计的消息条数
+    pub samples: u64,
+    /// 输出 token 数的分布
+    pub output_tokens: Percentiles,
+    /// 总 token 数的分布
+    pub total_tokens: Percentiles,
+}
+
+/// 全部消息以及每个模型的消息大小分位数，全部合计在前，模型按 name_of 给出的显示名称合并并排列
+pub fn message_size_stats(sizes: &[MessageSize], name_of: impl Fn(&str) -> String) -> Vec<MessageSizeStats> {
+    if sizes.is_empty() {
+        return Vec::new();
+    }
+    let stats = |model: Option<String>, selected: Vec<&MessageSize>| MessageSizeStats {
+        model,
+        samples: selected.len() as u64,
+        output_tokens: Percentiles::of(selected.iter().map(|size| size.output_tokens).collect()),
+        total_tokens: Percentiles::of(selected.iter().map(|size| size.total_tokens).collect()),
+    };
+    let mut by_model: BTreeMap<String, Vec<&MessageSize>> = BTreeMap::new();
+    for size in sizes {
+        by_model.entry(name_of(&size.model)).or_default().push(size);
+    }
+    std::iter::once(stats(None, sizes.iter().collect()))
+        .chain(by_model.into_iter().map(|(model, selected)| stats(Some(model), selected)))
+        .collect()
+}
@@ -0,0 +1,329 @@
+//! cccost doctor：检查扫描依赖的环境，找出报告为空或不完整的原因。
+//!
+//! 依次检查数据目录是否存在、其中有多少项目目录和日志文件、最近的日志能否解析出带使用量的记录、
+//! 配置文件与时区设置能否读取、缓存目录是否可写。每项检查给出结果，有问题时附带可用的选项或环境变量；
+//! 会导致报告为空或无法运行的问题为 [`Status::Fail`]，只影响部分功能的为 [`Status::Warn`]
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono_tz::Tz;
+use crate::config::Config;
+use crate::file_processor::FileProcessor;
+use crate::mapping::FieldMapping;
+use crate::source::{CodexSource, DirectorySource, GeminiSource, PathsSource, SourceInput, SourceTool};
+use crate::tr;
+
+/// 最多尝试解析的最近文件数：最新的文件可能是还没有回复的会话，不能只看一个
+const SAMPLE_FILES: usize = 5;
+
+/// 一项检查的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// 正常
+    Pass,
+    /// 不影响报告，但部分功能不可用或结果可能与预期不同
+    Warn,
+    /// 报告为空或无法运行，doctor 以非零状态退出
+    Fail,
+}
+
+/// 一项检查
+#[derive(Debug, Clone)]
+pub struct Check {
+    /// 结果
+    pub status: Status,
+    /// 检查的内容，如 Config file
+    pub name: String,
+    /// 检查到的情况
+    pub detail: String,
+    /// 有问题时的修复建议
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn new(status: Status, name: String, detail: String) -> Self {
+        Self { status, name, detail, hint: None }
+    }
+
+    fn with_hint(mut self, hint: String) -> Self {
+        self.hint = Some(hint);
+        self
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let marker = match self.status {
+            Status::Pass => "✓",
+            Status::Warn => "!",
+            Status::Fail => "✗",
+        };
+        write!(f, "{} {}: {}", marker, self.name, self.detail)?;
+        if let Some(hint) = &self.hint {
+            write!(f, "\n    → {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+/// 要检查的一个数据来源
+struct Probe {
+    tool: SourceTool,
+    /// 默认的数据目录，或命令行给出的路径
+    paths: Vec<PathBuf>,
+    /// 是否为命令行给出的路径
+    explicit: bool,
+}
+
+/// 检查的配置：要探测的数据来源与扫描时使用的选项
+pub struct Doctor {
+    probes: Vec<Probe>,
+    extensions: Vec<String>,
+    sniff: bool,
+    max_file_size: u64,
+    field_mapping: Option<FieldMapping>,
+    config_path: PathBuf,
+    cache_path: PathBuf,
+}
+
+impl Doctor {
+    /// 检查 config_path 处的配置文件与 cache_path 所在的缓存目录，尚未添加数据来源
+    pub fn new(config_path: PathBuf, cache_path: PathBuf) -> Self {
+        Self {
+            probes: Vec::new(),
+            extensions: Vec::new(),
+            sniff: false,
+            max_file_size: 0,
+            field_mapping: None,
+            config_path,
+            cache_path,
+        }
+    }
+
+    /// 检查 tool 的默认数据目录
+    pub fn with_directory(mut self, tool: SourceTool, directory: PathBuf) -> Self {
+        self.probes.push(Probe { tool, paths: vec![directory], explicit: false });
+        self
+    }
+
+    /// 检查命令行给出的路径，其中的日志按 tool 的格式解析
+    pub fn with_paths(mut self, tool: SourceTool, paths: Vec<PathBuf>) -> Self {
+        self.probes.push(Probe { tool, paths, explicit: true });
+        self
+    }
+
+    /// 与扫描相同的额外扩展名，见 FileProcessorBuilder::with_extra_extensions
+    pub fn with_extra_extensions(mut self, extensions: Vec<String>) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// 与扫描相同的内容嗅探设置
+    pub fn with_content_sniffing(mut self, sniff: bool) -> Self {
+        self.sniff = sniff;
+        self
+    }
+
+    /// 与扫描相同的单文件大小上限，0 表示不限制
+    pub fn with_max_file_size(mut self, max_file_size: u64) -> Self {
+        self.max_file_size = max_file_size;
+        self
+    }
+
+    /// SourceTool::Custom 的文件按 mapping 解析
+    pub fn with_field_mapping(mut self, mapping: FieldMapping) -> Self {
+        self.field_mapping = Some(mapping);
+        self
+    }
+
+    /// 执行全部检查，按显示顺序返回
+    pub fn run(&self) -> Vec<Check> {
+        let mut checks = Vec::new();
+        let mut files = Vec::new();
+        let any_exists = self.probes.iter().any(|probe| probe.paths.iter().any(|path| path.exists()));
+        for probe in &self.probes {
+            let paths = self.check_paths(probe, any_exists);
+            let usable = paths.as_ref().is_none_or(|check| check.status == Status::Pass);
+            checks.extend(paths);
+            if usable {
+                let (check, found) = self.check_files(probe);
+                checks.push(check);
+                files.extend(found);
+            }
+        }
+        if !files.is_empty() {
+            checks.push(self.check_sample(files));
+        }
+        checks.push(self.check_config());
+        checks.push(check_timezone());
+        checks.push(self.check_cache());
+        checks
+    }
+
+    fn processor(&self, directory: PathBuf) -> FileProcessor {
+        let mut builder = FileProcessor::builder(directory)
+            .with_extra_extensions(self.extensions.clone())
+            .with_content_sniffing(self.sniff)
+            .with_max_file_size(self.max_file_size);
+        if let Some(mapping) = &self.field_mapping {
+            builder = builder.with_field_mapping(mapping.clone());
+        }
+        builder.build()
+    }
+
+    /// 数据目录或给出的路径是否存在；其他来源有数据时，缺少的默认目录只是警告
+    fn check_paths(&self, probe: &Probe, any_exists: bool) -> Option<Check> {
+        let name = tr!("doctor.data_directory", probe.tool);
+        let missing: Vec<String> = probe.paths
+            .iter()
+            .filter(|path| !path.exists())
+            .map(|path| path.display().to_string())
+            .collect();
+        if probe.explicit {
+            return (!missing.is_empty()).then(|| {
+                Check::new(Status::Fail, name, tr!("doctor.paths_missing", missing.join(", ")))
+                    .with_hint(tr!("doctor.hint_paths"))
+            });
+        }
+        let directory = probe.paths.first()?.display().to_string();
+        let check = if missing.is_empty() {
+            Check::new(Status::Pass, name, directory)
+        } else if any_exists {
+            Check::new(Status::Warn, name, tr!("doctor.directory_missing", directory)).with_hint(tr!("doctor.hint_other_source"))
+        } else {
+            Check::new(Status::Fail, name, tr!("doctor.directory_missing", directory)).with_hint(tr!("doctor.hint_directory"))
+        };
+        Some(check)
+    }
+
+    /// 按扫描的规则列出日志文件，返回检查结果与找到的 (文件, 格式)
+    fn check_files(&self, probe: &Probe) -> (Check, Vec<(PathBuf, SourceTool)>) {
+        let name = tr!("doctor.log_files", probe.tool);
+        let root = probe.paths.first().cloned().unwrap_or_default();
+        let processor = self.processor(root.clone());
+        let inputs = match (probe.tool, probe.explicit) {
+            (_, true) | (SourceTool::Custom, _) => {
+                processor.inputs(PathsSource::new(probe.paths.clone(), root.clone()).with_tool(probe.tool))
+            }
+            (SourceTool::Claude, false) => processor.inputs(DirectorySource::new(root.clone())),
+            (SourceTool::Codex, false) => processor.inputs(CodexSource::new(root.clone())),
+            (SourceTool::Gemini, false) => processor.inputs(GeminiSource::new(root.clone())),
+        };
+        let files: Vec<(PathBuf, SourceTool)> = match inputs {
+            Ok(inputs) => inputs
+                .into_iter()
+                .filter_map(|input| match input {
+                    SourceInput::File { path, tool, .. } => Some((path, tool)),
+                    SourceInput::Stream { .. } => None,
+                })
+                .collect(),
+            Err(e) => return (Check::new(Status::Fail, name, e.to_string()).with_hint(tr!("doctor.hint_paths")), Vec::new()),
+        };
+
+        let skipped: u64 = processor.diagnostics(None).skipped_files.values().sum();
+        if files.is_empty() {
+            let detail = tr!("doctor.no_files", skipped);
+            return (Check::new(Status::Fail, name, detail).with_hint(tr!("doctor.hint_no_files")), files);
+        }
+        // Claude Code 与 Gemini CLI 每个项目一个目录；Codex 按日期分目录，不统计项目
+        let detail = match (probe.tool, probe.explicit) {
+            (SourceTool::Claude | SourceTool::Gemini, false) => tr!("doctor.files_in_projects", files.len(), count_directories(&root)),
+            _ => tr!("doctor.files", files.len()),
+        };
+        (Check::new(Status::Pass, name, detail), files)
+    }
+
+    /// 从最近修改的文件开始尝试解析，直到找到带使用量的记录
+    fn check_sample(&self, mut files: Vec<(PathBuf, SourceTool)>) -> Check {
+        let name = tr!("doctor.sample");
+        let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+        files.sort_by_cached_key(|(path, _)| std::cmp::Reverse(modified(path)));
+
+        let mut first_failure = None;
+        for (path, tool) in files.into_iter().take(SAMPLE_FILES) {
+            let root = path.parent().map(Path::to_path_buf).unwrap_or_default();
+            let source = PathsSource::new(vec![path.clone()], root.clone()).with_tool(tool);
+            let check = match self.processor(root).process(source) {
+                Ok(output) => {
+                    let diagnostics = &output.diagnostics;
+                    if let Some((_, size)) = diagnostics.oversized_files.first() {
+                        Check::new(Status::Fail, name.clone(), tr!("doctor.sample_oversized", path.display(), size))
+                            .with_hint(tr!("doctor.hint_oversized"))
+                    } else if let Some((_, error)) = diagnostics.read_errors.first() {
+                        Check::new(Status::Fail, name.clone(), tr!("doctor.sample_unreadable", path.display(), error))
+                            .with_hint(tr!("doctor.hint_unreadable"))
+                    } else {
+                        let stats = diagnostics.files.first().map(|(_, stats)| stats.clone()).unwrap_or_default();
+                        if stats.entries > 0 {
+                            return Check::new(
+                                Status::Pass,
+                                name,
+                                tr!("doctor.sample_ok", path.display(), stats.lines, stats.entries),
+                            );
+                        } else if stats.lines > 0 && stats.failed_lines == stats.lines {
+                            Check::new(Status::Fail, name.clone(), tr!("doctor.sample_not_json", path.display(), stats.lines))
+                                .with_hint(tr!("doctor.hint_not_json"))
+                        } else {
+                            Check::new(Status::Fail, name.clone(), tr!("doctor.sample_no_usage", path.display(), stats.lines))
+                                .with_hint(tr!("doctor.hint_no_usage"))
+                        }
+                    }
+                }
+                Err(e) => Check::new(Status::Fail, name.clone(), tr!("doctor.sample_unreadable", path.display(), e))
+                    .with_hint(tr!("doctor.hint_unreadable")),
+            };
+            first_failure.get_or_insert(check);
+        }
+        first_failure.unwrap_or_else(|| Check::new(Status::Fail, name, tr!("doctor.no_files", 0)))
+    }
+
+    /// 配置文件格式错误时其他命令都无法运行
+    fn check_config(&self) -> Check {
+        let name = tr!("doctor.config");
+        let path = self.config_path.display().to_string();
+        match Config::load(&self.config_path) {
+            Ok(_) if self.config_path.exists() => Check::new(Status::Pass, name, tr!("doctor.config_loaded", path)),
+            Ok(_) => Check::new(Status::Pass, name, tr!("doctor.config_default", path)),
+            Err(e) => Check::new(Status::Fail, name, e).with_hint(tr!("doctor.hint_config")),
+        }
+    }
+
+    /// 缓存只用于加速，不可写时扫描照常进行
+    fn check_cache(&self) -> Check {
+        let name = tr!("doctor.cache");
+        let Some(directory) = self.cache_path.parent() else {
+            return Check::new(Status::Pass, name, self.cache_path.display().to_string());
+        };
+        let probe = directory.join(format!(".doctor-{}", std::process::id()));
+        let result = fs::create_dir_all(directory)
+            .and_then(|()| fs::write(&probe, b""))
+            .and_then(|()| fs::remove_file(&probe));
+        match result {
+            Ok(()) => Check::new(Status::Pass, name, tr!("doctor.cache_writable", directory.display())),
+            Err(e) => Check::new(Status::Warn, name, tr!("doctor.cache_not_writable", directory.display(), e))
+                .with_hint(tr!("doctor.hint_cache")),
+        }
+    }
+}
+
+/// 报告按 --timezone 的日期分组；TZ 只影响本机时间的显示，无法识别时给出警告
+fn check_timezone() -> Check {
+    let name = tr!("doctor.timezone");
+    let offset = chrono::Local::now().offset().to_string();
+    match std::env::var("TZ") {
+        // :/etc/localtime 这样以 : 或 / 开头的值指向时区文件，不是时区名
+        Ok(tz) if !tz.is_empty() && !tz.starts_with([':', '/']) && tz.parse::<Tz>().is_err() => {
+            Check::new(Status::Warn, name, tr!("doctor.timezone_invalid", tz)).with_hint(tr!("doctor.hint_timezone"))
+        }
+        Ok(tz) if !tz.is_empty() => Check::new(Status::Pass, name, tr!("doctor.timezone_set", tz, offset)),
+        _ => Check::new(Status::Pass, name, tr!("doctor.timezone_local", offset)),
+    }
+}
+
+/// directory 下的子目录数
+fn count_directories(directory: &Path) -> usize {
+    fs::read_dir(directory)
+        .map(|entries| entries.filter_map(Result::ok).filter(|entry| entry.path().is_dir()).count())
+        .unwrap_or(0)
+}
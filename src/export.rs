@@ -0,0 +1,82 @@
+//! --export-dir：把每日用量写成目录中的小文件，供静态页面等按日读取
+//!
+//! 每天一个 `YYYY-MM-DD.json`，另有汇总的 `index.json`。内容不含生成时间等每次运行都会变化的字段，
+//! 只有内容变化的文件才被重写，rsync 等按修改时间同步的工具不会重复传输未变化的文件。
+//! 每个文件先写入同目录下的临时文件再重命名，读取方不会看到写了一半的文件。
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use chrono::NaiveDate;
+use serde::Serialize;
+use serde_json::Value;
+
+/// 汇总文件的文件名
+pub const INDEX_FILE: &str = "index.json";
+
+/// 一次导出对目录做的修改
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ExportSummary {
+    /// 新建或内容有变化而重写的文件数
+    pub written: usize,
+    /// 内容相同而没有改动的文件数
+    pub unchanged: usize,
+    /// --prune 删除的、不在范围内的日期文件数
+    pub pruned: usize,
+}
+
+/// 把 days 中每天的 JSON 写为 directory/YYYY-MM-DD.json，index 写为 directory/index.json；目录不存在时创建。
+/// prune 为真时删除目录中其他形如 YYYY-MM-DD.json 的文件，其余文件不受影响
+pub fn export_days(directory: &Path, days: &[(NaiveDate, Value)], index: &Value, prune: bool) -> io::Result<ExportSummary> {
+    fs::create_dir_all(directory)?;
+    let mut summary = ExportSummary::default();
+    let mut record = |changed: bool| {
+        if changed {
+            summary.written += 1;
+        } else {
+            summary.unchanged += 1;
+        }
+    };
+    for (date, day) in days {
+        record(write_if_changed(&directory.join(format!("{}.json", date)), day)?);
+    }
+    record(write_if_changed(&directory.join(INDEX_FILE), index)?);
+
+    if prune {
+        let exported: BTreeSet<NaiveDate> = days.iter().map(|(date, _)| *date).collect();
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            let date = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(".json"))
+                .and_then(|stem| NaiveDate::parse_from_str(stem, "%Y-%m-%d").ok());
+            if let Some(date) = date
+                && !exported.contains(&date)
+                && path.is_file() {
+                fs::remove_file(&path)?;
+                summary.pruned += 1;
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// 内容与现有文件不同时原子地写入，返回是否写入
+fn write_if_changed(path: &Path, value: &Value) -> io::Result<bool> {
+    let mut content = serde_json::to_string_pretty(value).map_err(io::Error::other)?;
+    content.push('\n');
+    if fs::read(path).is_ok_and(|existing| existing == content.as_bytes()) {
+        return Ok(false);
+    }
+
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or("export");
+    let temporary = path.with_file_name(format!(".{}.tmp", file_name));
+    let mut file = fs::File::create(&temporary)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&temporary, path)?;
+    Ok(true)
+}
@@ -1,155 +1,2977 @@
+//! 扫描会话日志文件并按模型、日期、项目等维度聚合使用量
+
 use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::any::Any;
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, BufRead, Read, Seek};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex, PoisonError};
+use std::thread;
+use std::sync::atomic::{AtomicBool, Ordering};
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use serde::de::IgnoredAny;
 use serde_json::Value;
-use crate::item::{Item, Usage, LogEntry};
+use tracing::{debug_span, field};
+use crate::archive::ArchiveKey;
+use crate::blocks::BLOCK_HOURS;
+use crate::bucketing::{bucket, Granularity};
+use crate::codex::CodexParser;
+use crate::cache::{CachedCount, CachedFile, CachedProjectUsage, CachedUsage, FileCache, FileFingerprint};
+use crate::diagnostics::{Diagnostics, FileStats, ImportedData, MergedExport, QuarantinedEntry};
+use crate::gemini;
+use crate::tr;
+use crate::import::IMPORT_PROJECT;
+use crate::mapping::FieldMapping;
+use crate::merge::ExportedUsage;
+use crate::item::{DailyCount, DailyUsage, GroupUsage, Item, LogEntry, ProjectModelUsage, SessionSummary, UsageAggregate, UsageKey};
+use crate::parse::{parse_value, ParseOptions};
+use crate::period::TokenTotals;
+use crate::pricing::cost_for;
+use crate::project::{decode_project_dir, encode_project_path};
+use crate::source::{is_gzip, DataSource, DirectorySource, FileFilter, PathsSource, ReaderSource, SourceInput, SourceTool, DEFAULT_EXTENSIONS};
 use dashmap::DashMap;
+use flate2::read::MultiGzDecoder;
 
-pub struct FileProcessor {
-    directory: PathBuf,
-    // 使用 DashMap 替代 Mutex<HashMap>，提供更细粒度的锁
-    collected_items: DashMap<(String, String), Usage>, // (模型, 时间戳键) -> 使用量
+/// 单个文件内的名称或时间戳键，重复出现时共用 [`Interner`] 中的同一个分配
+type Name = Arc<str>;
+
+/// 单个文件内按 (名称, 时间戳键) 的使用量，名称为版本号、分支或模型
+type FileAggregates = HashMap<(Name, Name), UsageAggregate>;
+
+/// 单个文件内按 (项目路径, 模型, 时间戳键) 的使用量
+type ProjectModelAggregates = HashMap<(Name, Name, Name), UsageAggregate>;
+
+/// 按 (时间戳键, 名称) 计数，例如工具调用次数、stop_reason 分布
+type DailyCounts = HashMap<(Name, Name), u64>;
+
+/// 处理解析得到的每个 Item，返回该条是否计入统计
+type ItemCollector<'a> = dyn FnMut(Item, &mut FileResult) -> bool + 'a;
+
+/// 可在解析各块的线程间共享的 ItemCollector
+type SharedCollector<'a> = dyn Fn(Item, &mut FileResult) -> bool + Sync + 'a;
+
+/// 时间戳键的粒度
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum TimeKey {
+    /// YYYY-MM-DD
+    Day,
+    /// YYYY-MM-DDTHH
+    Hour,
+    /// YYYY-MM-DDTHH:MM
+    Minute,
 }
 
-impl FileProcessor {
-    pub fn new(directory: PathBuf) -> Self {
-        Self { 
-            directory,
-            collected_items: DashMap::new(),
+/// 单个文件内的字符串驻留。一个文件中的记录通常只涉及少数几个模型、版本、分支和日期，
+/// 每条记录都分配新的 String 作为聚合键是解析时主要的开销；驻留后同一个值只分配一次，
+/// 聚合键中保存共享的 Name，合并进共享状态时才按键（而不是按记录）转换为 String
+#[derive(Clone, Default)]
+struct Interner {
+    names: HashSet<Name>,
+    // (粒度, 截断后的本地时间) -> 时间戳键；一次扫描只使用一个时区
+    times: HashMap<(TimeKey, i64), Name>,
+}
+
+impl Interner {
+    fn name(&mut self, name: &str) -> Name {
+        if let Some(existing) = self.names.get(name) {
+            return Arc::clone(existing);
         }
+        let name: Name = Arc::from(name);
+        self.names.insert(Arc::clone(&name));
+        name
     }
 
-    pub fn process_files(&self) -> Vec<((String, String), Usage)> {
-        if !self.directory.exists() {
-            println!("目录 {} 不存在", self.directory.display());
-            return Vec::new();
+    /// tz 中本地时间的时间戳键，同一天（小时、分钟）内的时间戳只格式化一次
+    fn time(&mut self, timestamp: DateTime<Utc>, key: TimeKey, tz: &Tz) -> Name {
+        let period = match key {
+            TimeKey::Day => 86_400,
+            TimeKey::Hour => 3_600,
+            TimeKey::Minute => 60,
+        };
+        // 按本地时间截断：偏移不是整小时的时区中，本地的一小时不与 UTC 的一小时对齐
+        let timestamp = timestamp.with_timezone(tz).naive_local();
+        let name = self.times.entry((key, timestamp.and_utc().timestamp().div_euclid(period))).or_insert_with(|| {
+            let formatted = match key {
+                TimeKey::Day => timestamp.date().to_string(),
+                TimeKey::Hour => timestamp.format("%Y-%m-%dT%H").to_string(),
+                TimeKey::Minute => timestamp.format("%Y-%m-%dT%H:%M").to_string(),
+            };
+            Arc::from(formatted)
+        });
+        Arc::clone(name)
+    }
+}
+
+/// 解析单个文件得到的全部结果，合并进共享状态前先在线程内累积
+#[derive(Clone, Default)]
+struct FileResult {
+    aggregates: FileAggregates, // (模型, 时间戳键) -> 使用量
+    versions: FileAggregates, // (版本号, 时间戳键) -> 使用量
+    branches: FileAggregates, // (git 分支, 时间戳键) -> 使用量
+    hourly: FileAggregates, // (模型, 小时键) -> 使用量，用于按 5 小时计费窗口分组
+    recent: FileAggregates, // (模型, 分钟键) -> 使用量，只包含最近 5 小时，用于计算消耗速率
+    project_models: ProjectModelAggregates, // 解析完成后由 pending_projects 得到
+    // 项目目录的编码名，文件直接位于数据目录下时为 None
+    project_dir: Option<String>,
+    // (与项目目录编码一致的 cwd, 模型, 时间戳键) -> 使用量；cwd 不一致（如子目录）的记录暂记为 None
+    pending_projects: HashMap<(Option<Name>, Name, Name), UsageAggregate>,
+    tools: DailyCounts, // (时间戳键, 工具名) -> 调用次数
+    stop_reasons: DailyCounts, // (时间戳键, stop_reason) -> 次数
+    session: SessionTracker,
+    // 已计入用量的 message.id；同一条回复的流式记录带有相同的用量，只计入第一条
+    seen_messages: HashSet<String>,
+    // 分块解析时为 Some：带有使用量的条目按顺序记下，由 parse_content 按文件顺序重放，跨块去重后再计入
+    deferred: Option<Vec<Item>>,
+    stats: FileStats,
+    message_sizes: Vec<MessageSize>, // 只在启用 with_message_sizes 时收集
+    latencies: Vec<MessageLatency>, // 只在启用 with_latencies 时收集
+    line: u64, // 已读取的行数，即 JSONL 中当前记录的行号
+    explained: ExplainedDay, // 只在启用 with_explain 时收集
+    // 写入该输入的工具；Codex 的文件由 codex 按顺序解析
+    tool: SourceTool,
+    codex: CodexParser,
+    names: Interner,
+}
+
+impl FileResult {
+    /// 带有使用量且 message.id 已经计入过的条目；第一次遇到的 id 在这里记下
+    fn is_duplicate(&mut self, item: &Item) -> bool {
+        match &item.message_id {
+            Some(id) if item.usage.is_some() => !self.seen_messages.insert(id.clone()),
+            _ => false,
         }
+    }
 
-        // 获取所有子目录
-        let subdirs: Vec<_> = match fs::read_dir(&self.directory) {
-            Ok(entries) => entries
-                .filter_map(|entry| entry.ok())
-                .map(|entry| entry.path())
-                .filter(|path| path.is_dir())
-                .collect(),
-            Err(e) => {
-                eprintln!("读取目录失败: {}", e);
-                return Vec::new();
-            }
-        };
-
-        // 从所有子目录收集所有文件
-        let all_files: Vec<_> = subdirs
-            .par_iter()
-            .flat_map(|dir| {
-                fs::read_dir(dir)
-                    .ok()
-                    .into_iter()
-                    .flat_map(|entries| entries)
-                    .filter_map(|entry| entry.ok())
-                    .map(|entry| entry.path())
-                    .filter(|path| path.is_file())
-                    .collect::<Vec<_>>()
-            })
-            .collect();
+    /// 确定项目归属：cwd 与目录编码一致时 cwd 即项目路径，
+    /// 其余记录归入本文件中首个一致的 cwd，没有时使用目录名的启发式解码
+    fn resolve_projects(&mut self, decode: impl FnOnce(&str) -> String) {
+        let fallback = self.pending_projects
+            .keys()
+            .filter_map(|(cwd, _, _)| cwd.clone())
+            .min()
+            .or_else(|| self.project_dir.as_deref().map(|encoded| Name::from(decode(encoded))))
+            .unwrap_or_else(|| Name::from("(unknown)"));
 
-        // 并行处理文件
-        all_files.par_iter().for_each(|file_path| {
-            self.process_file(file_path);
-        });
-        
-        // 返回合并后的结果
-        self.get_merged_results()
+        for ((cwd, model, date), aggregate) in self.pending_projects.drain() {
+            let project = cwd.unwrap_or_else(|| fallback.clone());
+            self.project_models
+                .entry((project, model, date))
+                .and_modify(|existing| *existing += &aggregate)
+                .or_insert(aggregate);
+        }
     }
 
-    fn process_file(&self, file_path: &PathBuf) {
-        match fs::read_to_string(file_path) {
-            Ok(content) => {
-                // 检查文件是否为JSON
-                if file_path.extension().and_then(|s| s.to_str()) == Some("json") ||
-                   file_path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
-                    self.print_json_content(&content);
-                }
+    /// 按文件顺序并入后一个分块的结果。各分块有自己的 Interner，键在这里重新驻留；
+    /// 分块的 seen_messages 只用于块内去重，跨块的去重由 parse_content 重放 deferred 时完成
+    fn absorb(&mut self, mut part: FileResult) {
+        // 分块内的行号从块的开头算起
+        for entry in &mut part.stats.quarantined {
+            entry.line = entry.line.map(|line| line + self.line);
+        }
+        self.line += part.line;
+        let names = &mut self.names;
+        for (target, aggregates) in [
+            (&mut self.aggregates, part.aggregates),
+            (&mut self.versions, part.versions),
+            (&mut self.branches, part.branches),
+            (&mut self.hourly, part.hourly),
+            (&mut self.recent, part.recent),
+        ] {
+            for ((name, time), aggregate) in aggregates {
+                FileProcessor::add_aggregate(target, (names.name(&name), names.name(&time)), &aggregate);
             }
-            Err(e) => {
-                eprintln!("读取文件 {} 出错: {}", file_path.display(), e);
+        }
+        for ((cwd, model, date), aggregate) in part.pending_projects {
+            let key = (cwd.map(|cwd| names.name(&cwd)), names.name(&model), names.name(&date));
+            FileProcessor::add_aggregate(&mut self.pending_projects, key, &aggregate);
+        }
+        for (target, counts) in [(&mut self.tools, part.tools), (&mut self.stop_reasons, part.stop_reasons)] {
+            for ((time, name), count) in counts {
+                *target.entry((names.name(&time), names.name(&name))).or_insert(0) += count;
             }
         }
+        self.session.absorb(part.session);
+        self.stats.merge(part.stats);
+        self.message_sizes.extend(part.message_sizes);
+        self.latencies.extend(part.latencies);
+        for (model, aggregate) in part.explained.models {
+            FileProcessor::add_aggregate(&mut self.explained.models, names.name(&model), &aggregate);
+        }
+        self.explained.filtered += part.explained.filtered;
+        self.explained.no_usage += part.explained.no_usage;
     }
+}
 
-    fn print_json_content(&self, content: &str) {
-        // 通过尝试解析第一行来检查是否为JSONL文件
-        let lines: Vec<&str> = content.lines().collect();
-        
-        if lines.is_empty() {
+/// 一个文件中 with_explain 指定日期的记录
+#[derive(Clone, Default)]
+struct ExplainedDay {
+    models: HashMap<Name, UsageAggregate>, // 模型 -> 计入统计的使用量
+    filtered: u64, // 被 with_filter 的条件排除的带用量记录
+    no_usage: u64, // 没有使用量而未计入的记录
+}
+
+impl ExplainedDay {
+    fn is_empty(&self) -> bool {
+        self.models.is_empty() && self.filtered == 0 && self.no_usage == 0
+    }
+}
+
+/// 会话中需要去重和计数的消息
+#[derive(Clone, Copy)]
+enum SessionMessage {
+    User,
+    Assistant,
+}
+
+/// 按文件内的顺序跟踪会话的交互轮次，用户和助手消息分别去重
+#[derive(Clone, Default)]
+struct SessionTracker {
+    session_id: Option<String>,
+    started_at: Option<DateTime<Utc>>,
+    ended_at: Option<DateTime<Utc>>,
+    seen_users: HashSet<String>,
+    seen_assistants: HashSet<String>,
+    // 分块解析时为 Some：消息只在块内去重并按顺序记下，合并时由 absorb 重放，跨块去重并计算轮次
+    deferred: Option<Vec<(SessionMessage, Option<String>)>>,
+    // 已有用户提问、尚未收到助手回复
+    awaiting_reply: bool,
+    turns: u64,
+    user_messages: u64,
+    assistant_messages: u64,
+}
+
+impl SessionTracker {
+    fn observe(&mut self, entry: &LogEntry) {
+        if self.session_id.is_none() {
+            self.session_id = entry.session_id.clone();
+        }
+        if let Ok(timestamp) = entry.timestamp.parse::<DateTime<Utc>>() {
+            self.started_at = Some(self.started_at.map_or(timestamp, |started| started.min(timestamp)));
+            self.ended_at = Some(self.ended_at.map_or(timestamp, |ended| ended.max(timestamp)));
+        }
+
+        match entry.entry_type.as_deref() {
+            Some("user") if entry.message.is_user_prompt() => self.message(SessionMessage::User, entry.uuid.as_ref()),
+            // 同一条回复的流式记录共享 message.id
+            Some("assistant") => self.message(SessionMessage::Assistant, entry.message.id.as_ref().or(entry.uuid.as_ref())),
+            _ => {}
+        }
+    }
+
+    /// 计入一条消息，id 相同的消息只计一次
+    fn message(&mut self, kind: SessionMessage, id: Option<&String>) {
+        let seen = match kind {
+            SessionMessage::User => &mut self.seen_users,
+            SessionMessage::Assistant => &mut self.seen_assistants,
+        };
+        if let Some(id) = id
+            && !seen.insert(id.clone()) {
             return;
         }
-        
-        // 尝试将第一个非空行解析为JSON
-        let first_line = lines.iter().find(|line| !line.trim().is_empty());
-        
-        if let Some(line) = first_line {
-            if serde_json::from_str::<Value>(line).is_ok() {
-                // 这是JSONL格式 - 逐行处理
-                for line in content.lines() {
-                    if line.trim().is_empty() {
-                        continue;
-                    }
-                    
-                    match serde_json::from_str::<Value>(line) {
-                        Ok(json) => {
-                            self.print_json_value(&json, 1);
-                        }
-                        Err(_) => {
-                            // 静默跳过无效行
-                        }
-                    }
+        if let Some(deferred) = &mut self.deferred {
+            deferred.push((kind, id.cloned()));
+            return;
+        }
+        match kind {
+            SessionMessage::User => {
+                self.user_messages += 1;
+                self.awaiting_reply = true;
+            }
+            SessionMessage::Assistant => {
+                self.assistant_messages += 1;
+                if self.awaiting_reply {
+                    self.turns += 1;
+                    self.awaiting_reply = false;
                 }
-            } else {
-                // 尝试作为常规JSON解析
-                match serde_json::from_str::<Value>(content) {
-                    Ok(json) => {
-                        self.print_json_value(&json, 0);
-                    }
-                    Err(_) => {
-                        // 静默跳过无效的JSON
+            }
+        }
+    }
+
+    /// 按文件顺序并入后一个分块的跟踪结果
+    fn absorb(&mut self, part: SessionTracker) {
+        if self.session_id.is_none() {
+            self.session_id = part.session_id;
+        }
+        self.started_at = self.started_at.into_iter().chain(part.started_at).min();
+        self.ended_at = self.ended_at.into_iter().chain(part.ended_at).max();
+        for (kind, id) in part.deferred.unwrap_or_default() {
+            self.message(kind, id.as_ref());
+        }
+    }
+
+    fn finish(&self, fallback_id: String, result: &FileResult) -> SessionSummary {
+        let aggregates = &result.aggregates;
+        let mut models: BTreeMap<&str, u64> = BTreeMap::new();
+        for ((model, _), aggregate) in aggregates {
+            *models.entry(model).or_default() += aggregate.usage.total();
+        }
+        let dominant_model = models
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+            .map(|(model, _)| model.to_string());
+        // 按 (模型, 日期) 分别计算费用再累加，避免合计使用量时 u32 溢出
+        let costs: Vec<f64> = aggregates
+            .iter()
+            .filter_map(|((model, _), aggregate)| cost_for(model, &aggregate.usage))
+            .collect();
+        // 会话跨多个 cwd 时取 token 用量最多的项目
+        let mut projects: HashMap<&str, u64> = HashMap::new();
+        for ((project, _, _), aggregate) in &result.project_models {
+            *projects.entry(project).or_default() += aggregate.usage.total();
+        }
+        let project = projects
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+            .map(|(project, _)| project.to_string());
+
+        SessionSummary {
+            session_id: self.session_id.clone().unwrap_or(fallback_id),
+            turns: self.turns,
+            user_messages: self.user_messages,
+            assistant_messages: self.assistant_messages,
+            aggregate: aggregates
+                .values()
+                .cloned()
+                .fold(UsageAggregate::default(), |total, aggregate| total + aggregate),
+            started_at: self.started_at,
+            ended_at: self.ended_at,
+            project,
+            models: models.into_keys().map(str::to_string).collect(),
+            dominant_model,
+            cost_usd: (!costs.is_empty()).then(|| costs.iter().sum()),
+        }
+    }
+}
+
+/// 读取日志文件内容：去掉开头的 UTF-8 BOM，非法字节按替换字符处理，
+/// 使个别损坏的字节只影响所在的行，而不是让整个文件读取失败。
+/// 写入到一半被截断的末尾字符同样变为替换字符，该行会作为未写完的行被忽略。.gz 文件先解压
+fn read_log_file(path: &Path) -> io::Result<String> {
+    let bytes = if is_gzip(path) {
+        let mut bytes = Vec::new();
+        MultiGzDecoder::new(fs::File::open(path)?).read_to_end(&mut bytes)?;
+        bytes
+    } else {
+        fs::read(path)?
+    };
+    let bytes = bytes.strip_prefix(UTF8_BOM).unwrap_or(&bytes);
+    Ok(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// 内容是否为 JSONL：第一个非空行能单独解析。整个文件是 JSON 数组或多行缩进的对象时第一行无法单独解析
+fn is_jsonl(content: &str) -> bool {
+    content
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .is_some_and(|line| serde_json::from_str::<IgnoredAny>(line.trim_end_matches('\r')).is_ok())
+}
+
+/// panic 的信息：panic! 的参数为字符串时即该字符串
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| tr!("error.panic_unknown"))
+}
+
+/// UTF-8 BOM，经 Windows 工具处理过的文件开头可能带有
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// 默认的单文件大小上限（1GB）
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// 默认的时间戳下限：早于这一天（UTC）的记录视为时间戳损坏
+pub const DEFAULT_TIMESTAMP_FLOOR: NaiveDate = NaiveDate::from_ymd_opt(2023, 1, 1).expect("有效的日期");
+
+/// 默认的并行解析阈值（32MB），达到该大小的 JSONL 文件按行切分后由多个线程解析
+pub const DEFAULT_PARALLEL_PARSE_THRESHOLD: u64 = 32 * 1024 * 1024;
+
+/// 并行解析时每块的最小字节数，块太小时合并结果的开销超过并行带来的收益
+const MIN_PARALLEL_CHUNK: usize = 1024 * 1024;
+
+/// 一次扫描的结果
+#[derive(Debug)]
+pub struct ProcessOutput {
+    /// 合并后的使用量：(模型, 时间戳键) -> 使用量及消息数，按键排序
+    pub usage: Vec<DailyUsage>,
+    /// 按 Claude Code 版本：(版本号, 时间戳键) -> 使用量，按键排序；缺少版本的记录归入 "(unknown)"
+    pub versions: Vec<GroupUsage>,
+    /// 按 git 分支：(分支, 时间戳键) -> 使用量，按键排序；缺少分支的记录归入 "(none)"
+    pub branches: Vec<GroupUsage>,
+    /// 按写入日志的工具：(claude 或 codex, 时间戳键) -> 使用量，按键排序；不包括 merge_archive 并入的归档数据
+    pub sources: Vec<GroupUsage>,
+    /// 按小时：(模型, with_timezone 时区中的小时键 YYYY-MM-DDTHH) -> 使用量，按键排序；时间戳无法解析的记录不计入
+    pub hourly: Vec<DailyUsage>,
+    /// 最近 5 小时按分钟：(模型, UTC 分钟键 YYYY-MM-DDTHH:MM) -> 使用量，按键排序；更早的记录不保留
+    pub recent: Vec<DailyUsage>,
+    /// 按项目和模型的使用量，按 (项目路径, 模型, 日期) 排序
+    pub project_models: Vec<ProjectModelUsage>,
+    /// 工具调用次数，按 (日期, 工具名) 排序
+    pub tools: Vec<DailyCount>,
+    /// stop_reason 分布，按 (日期, stop_reason) 排序
+    pub stop_reasons: Vec<DailyCount>,
+    /// 每个会话文件的汇总，按会话 id 排序
+    pub sessions: Vec<SessionSummary>,
+    /// 逐条消息的大小，只在启用 with_message_sizes 时收集，否则为空
+    pub message_sizes: Vec<MessageSize>,
+    /// 带有用时记录的各条消息，只在启用 with_latencies 时收集，否则为空
+    pub latencies: Vec<MessageLatency>,
+    /// with_explain 指定日期有记录的各文件，按路径排序；未启用时为空
+    pub attributions: Vec<FileAttribution>,
+    /// 扫描过程中各文件的解析统计与错误
+    pub diagnostics: Diagnostics,
+    /// 时间戳键（除分钟键外）所用的时区，即 with_timezone 的设置
+    pub timezone: Tz,
+}
+
+/// 单条计入统计的消息的 token 数，用于计算消息大小的分布
+#[derive(Debug, Clone)]
+pub struct MessageSize {
+    /// 模型名称
+    pub model: String,
+    /// 时间戳键，用于按 --since / --until 过滤
+    pub date: String,
+    /// 输出 token 数
+    pub output_tokens: u64,
+    /// 输入、缓存写入、缓存读取与输出之和
+    pub total_tokens: u64,
+}
+
+/// 单条带有用时记录的消息，用于计算响应时间的分布
+#[derive(Debug, Clone)]
+pub struct MessageLatency {
+    /// 模型名称
+    pub model: String,
+    /// 时间戳键，用于按 --since / --until 过滤
+    pub date: String,
+    /// 回复用时（毫秒）
+    pub duration_ms: u64,
+    /// 首个 token 的延迟（毫秒），没有记录时为 None
+    pub ttft_ms: Option<u64>,
+    /// 输出 token 数
+    pub output_tokens: u64,
+}
+
+/// 一个文件在 with_explain 指定日期的记录
+#[derive(Debug, Clone, Serialize)]
+pub struct FileAttribution {
+    /// 文件路径，标准输入与远程文件为其名称
+    pub path: PathBuf,
+    /// 该日记录所属的项目，跨多个项目时取 token 用量最多的
+    pub project: String,
+    /// 会话 id
+    pub session_id: String,
+    /// 该日计入统计的记录数
+    pub entries: u64,
+    /// 该日按模型的使用量，按模型名称排序
+    pub models: Vec<ModelAttribution>,
+    /// 该日被 FileProcessorBuilder::with_filter 的条件排除的带用量记录数
+    pub filtered: u64,
+    /// 该日有模型但没有使用量、因此未计入的记录数
+    pub no_usage: u64,
+}
+
+/// 一个模型在某一天的使用量
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelAttribution {
+    /// 模型名称
+    pub model: String,
+    /// 计入的记录数
+    pub entries: u64,
+    /// token 用量
+    pub tokens: TokenTotals,
+    /// 估算费用，未知模型为 None
+    pub cost_usd: Option<f64>,
+}
+
+impl ModelAttribution {
+    fn new(model: &str, aggregate: &UsageAggregate) -> Self {
+        let mut tokens = TokenTotals::default();
+        tokens.add(&aggregate.usage);
+        Self {
+            model: model.to_string(),
+            entries: aggregate.messages,
+            tokens,
+            cost_usd: cost_for(model, &aggregate.usage),
+        }
+    }
+}
+
+/// 一天的使用量来自哪些文件，由 ProcessOutput::explain 得到
+#[derive(Debug, Serialize)]
+pub struct DayExplanation {
+    /// 日期（UTC）
+    pub date: NaiveDate,
+    /// 该日有记录的文件，按路径排序
+    pub files: Vec<FileAttribution>,
+    /// 报告中该日按模型的合计，即每日表格中这一天的各行
+    pub models: Vec<ModelAttribution>,
+    /// 合计中不来自任何文件的 token 数，即由归档或导入并入的部分
+    pub unattributed_tokens: u64,
+}
+
+/// 在所选范围内首次出现的模型
+#[derive(Debug, Serialize)]
+pub struct NewModel {
+    /// 模型名称
+    pub model: String,
+    /// 首次出现的日期
+    pub first_seen: String,
+    /// 所选范围内该模型的 token 合计
+    pub total_tokens: u64,
+}
+
+impl ProcessOutput {
+    /// 首次出现在 [since, until] 内、且在此之前已有其他数据可供比较的模型。
+    /// 需要在按日期过滤之前调用；未指定 --since 时所选范围从最早的数据开始，没有可比较的数据
+    pub fn new_models(&self, since: Option<NaiveDate>, until: Option<NaiveDate>) -> Vec<NewModel> {
+        let Some(since) = since else {
+            return Vec::new();
+        };
+        let has_earlier_data = self.usage
+            .iter()
+            .filter_map(|daily| parse_date_key(&daily.date))
+            .any(|date| date < since);
+        if !has_earlier_data {
+            return Vec::new();
+        }
+
+        let mut first_seen: BTreeMap<&str, NaiveDate> = BTreeMap::new();
+        let mut totals: HashMap<&str, u64> = HashMap::new();
+        for DailyUsage { model, date, usage: aggregate } in &self.usage {
+            let Some(date) = parse_date_key(date) else {
+                continue;
+            };
+            first_seen
+                .entry(model)
+                .and_modify(|first| *first = (*first).min(date))
+                .or_insert(date);
+            if date >= since && until.is_none_or(|until| date <= until) {
+                *totals.entry(model).or_insert(0) += aggregate.usage.total();
+            }
+        }
+
+        first_seen
+            .into_iter()
+            .filter(|(_, first)| *first >= since && until.is_none_or(|until| *first <= until))
+            .map(|(model, first)| NewModel {
+                model: model.to_string(),
+                first_seen: first.to_string(),
+                total_tokens: totals.get(model).copied().unwrap_or(0),
+            })
+            .collect()
+    }
+
+    /// date 这一天的使用量来自哪些文件；文件来自 with_explain 收集的记录，
+    /// 合计包括 merge_archive、merge_imported 并入的数据，需要在 retain_dates 之前调用
+    pub fn explain(&mut self, date: NaiveDate) -> DayExplanation {
+        let key = date.to_string();
+        let models: Vec<ModelAttribution> = self.usage
+            .iter()
+            .filter(|daily| daily.date == key)
+            .map(|daily| ModelAttribution::new(&daily.model, &daily.usage))
+            .collect();
+        let files = std::mem::take(&mut self.attributions);
+        let reported: u64 = models.iter().map(|model| model.tokens.total_tokens).sum();
+        let attributed: u64 = files
+            .iter()
+            .flat_map(|file| &file.models)
+            .map(|model| model.tokens.total_tokens)
+            .sum();
+        DayExplanation {
+            date,
+            files,
+            models,
+            unattributed_tokens: reported.saturating_sub(attributed),
+        }
+    }
+
+    /// 按项目汇总的每日使用量，按 (项目路径, 日期) 排序
+    pub fn projects(&self) -> Vec<GroupUsage> {
+        let mut projects: BTreeMap<(String, String), UsageAggregate> = BTreeMap::new();
+        for usage in &self.project_models {
+            let total = projects.entry((usage.project.clone(), usage.date.clone())).or_default();
+            *total += &usage.aggregate;
+        }
+        projects
+            .into_iter()
+            .map(|((group, date), usage)| GroupUsage { group, date, usage })
+            .collect()
+    }
+
+    /// 将归档中的历史数据并入结果。日志中已不存在的键直接加入；
+    /// 两边都有但不一致的键取较大值，并返回这些冲突 (键, 日志中的合计, 归档中的合计)。
+    /// 归档中导入的数据（项目为 IMPORT_PROJECT）与 merge_imported 一样，日志中已有数据的日期整天不使用
+    pub fn merge_archive(&mut self, archived: HashMap<ArchiveKey, UsageAggregate>) -> Vec<(ArchiveKey, u64, u64)> {
+        let live_dates: HashSet<String> = self.usage.iter().map(|daily| daily.date.clone()).collect();
+        let mut merged = self.drain_project_models();
+        let mut conflicts = Vec::new();
+        for (key, archived) in archived {
+            if key.0 == IMPORT_PROJECT && live_dates.contains(&key.2) {
+                continue;
+            }
+            match merged.get(&key) {
+                None => {
+                    merged.insert(key, archived);
+                }
+                Some(live) if live.usage.total() != archived.usage.total() => {
+                    conflicts.push((key.clone(), live.usage.total(), archived.usage.total()));
+                    if archived.usage.total() > live.usage.total() {
+                        merged.insert(key, archived);
                     }
                 }
+                Some(_) => {}
             }
         }
+        conflicts.sort();
+        self.rebuild_usage(merged);
+        conflicts
+    }
+
+    /// 将 path 中导入的按日数据（见 [`crate::import`]）并入结果。日志（以及已并入的数据）中有记录的日期
+    /// 整天使用已有的数据，不与导入数据相加；这些日期与计入的行数记入 diagnostics.imported。
+    /// 返回实际并入的记录，可以再写入归档
+    pub fn merge_imported(&mut self, path: &Path, imported: Vec<(ArchiveKey, UsageAggregate)>) -> Vec<ProjectModelUsage> {
+        let live_dates: HashSet<String> = self.usage.iter().map(|daily| daily.date.clone()).collect();
+        let (overlapping, merged_rows): (Vec<_>, Vec<_>) = imported
+            .into_iter()
+            .partition(|((_, _, date), _)| live_dates.contains(date));
+        let mut overlapping_dates: Vec<String> = overlapping.into_iter().map(|((_, _, date), _)| date).collect();
+        overlapping_dates.sort();
+        overlapping_dates.dedup();
+        let days: HashSet<&str> = merged_rows.iter().map(|((_, _, date), _)| date.as_str()).collect();
+        self.diagnostics.imported.push(ImportedData {
+            path: path.to_path_buf(),
+            rows: merged_rows.len(),
+            days: days.len(),
+            overlapping_dates,
+        });
+
+        let mut merged = self.drain_project_models();
+        for (key, aggregate) in &merged_rows {
+            let entry = merged.entry(key.clone()).or_default();
+            *entry += aggregate;
+        }
+        self.rebuild_usage(merged);
+        merged_rows.into_iter().map(ProjectModelUsage::from).collect()
     }
 
-    fn print_json_value(&self, value: &Value, _indent: usize) {
-        // 尝试反序列化为LogEntry
-        if let Ok(log_entry) = serde_json::from_value::<LogEntry>(value.clone()) {
-            if let Some(item) = Item::from_log_entry(log_entry) {
-                self.collect_item(item);
+    /// 由 cccost merge 读取的各份导出（见 [`crate::merge`]）构造结果：各份导出按 (模型, 日期) 直接相加，
+    /// 标签相同的导出也相加。project_models 中的项目为导出者的标签，因此 projects() 即按导出者的汇总；
+    /// 导出中没有会话、分支、小时等数据，这些字段为空
+    pub fn from_exports(exports: Vec<ExportedUsage>) -> Self {
+        let mut diagnostics = Diagnostics::default();
+        let mut merged: BTreeMap<ArchiveKey, UsageAggregate> = BTreeMap::new();
+        for export in exports {
+            diagnostics.merged.push(MergedExport {
+                path: export.path,
+                label: export.label.clone(),
+                days: export.usage.iter().map(|daily| daily.date.as_str()).collect::<HashSet<_>>().len(),
+                tokens: export.usage.iter().map(|daily| daily.usage.usage.total()).sum(),
+            });
+            for daily in export.usage {
+                let entry = merged.entry((export.label.clone(), daily.model, daily.date)).or_default();
+                *entry += &daily.usage;
             }
         }
+        let mut output = Self {
+            usage: Vec::new(),
+            versions: Vec::new(),
+            branches: Vec::new(),
+            sources: Vec::new(),
+            hourly: Vec::new(),
+            recent: Vec::new(),
+            project_models: Vec::new(),
+            tools: Vec::new(),
+            stop_reasons: Vec::new(),
+            sessions: Vec::new(),
+            message_sizes: Vec::new(),
+            latencies: Vec::new(),
+            attributions: Vec::new(),
+            diagnostics,
+            timezone: Tz::UTC,
+        };
+        output.rebuild_usage(merged);
+        output
     }
-    
-    fn collect_item(&self, item: Item) {
-        let key = (item.model.clone(), item.get_timestamp_key());
-        
-        if let Some(usage) = item.usage {
-            // DashMap 提供了更高效的并发访问
-            self.collected_items
-                .entry(key)
-                .and_modify(|existing| *existing = existing.clone() + usage.clone())
-                .or_insert(usage);
+
+    /// 由合并后的 (项目, 模型, 日期) 重新汇总按模型的使用量
+    fn rebuild_usage(&mut self, merged: BTreeMap<ArchiveKey, UsageAggregate>) {
+        let mut usage: BTreeMap<(String, String), UsageAggregate> = BTreeMap::new();
+        for ((_, model, date), aggregate) in &merged {
+            let total = usage.entry((model.clone(), date.clone())).or_default();
+            *total += aggregate;
         }
+        self.usage = usage
+            .into_iter()
+            .map(|((model, date), usage)| DailyUsage { date, model, usage })
+            .collect();
+        self.project_models = merged.into_iter().map(ProjectModelUsage::from).collect();
     }
-    
-    fn get_merged_results(&self) -> Vec<((String, String), Usage)> {
-        // 直接从 DashMap 转换为 Vec，无需锁
-        let mut sorted_items: Vec<_> = self.collected_items
+
+    /// 取出按项目和模型的使用量，以 (项目, 模型, 日期) 为键以便与归档等数据合并
+    fn drain_project_models(&mut self) -> BTreeMap<ArchiveKey, UsageAggregate> {
+        self.project_models
+            .drain(..)
+            .map(|usage| ((usage.project, usage.model, usage.date), usage.aggregate))
+            .collect()
+    }
+
+    /// 实际找到的最早和最晚的记录日期
+    pub fn date_coverage(&self) -> Option<(NaiveDate, NaiveDate)> {
+        let mut dates = self.usage.iter().filter_map(|daily| parse_date_key(&daily.date));
+        let first = dates.next()?;
+        Some(dates.fold((first, first), |(earliest, latest), date| (earliest.min(date), latest.max(date))))
+    }
+
+    /// 范围内（含两端）没有任何记录的日期
+    pub fn missing_days(&self, from: NaiveDate, to: NaiveDate) -> Vec<NaiveDate> {
+        let active: HashSet<NaiveDate> = self.usage
             .iter()
-            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .filter_map(|daily| parse_date_key(&daily.date))
             .collect();
-        
-        // 按模型和时间戳排序
-        sorted_items.sort_by(|a, b| a.0.cmp(&b.0));
-        
-        sorted_items
+        from.iter_days()
+            .take_while(|date| *date <= to)
+            .filter(|date| !active.contains(date))
+            .collect()
+    }
+
+    /// 只保留日期在 [since, until] 内的数据；会话按文件汇总，不受影响
+    pub fn retain_dates(&mut self, since: Option<NaiveDate>, until: Option<NaiveDate>) {
+        let in_range = |date: &str| match parse_date_key(date) {
+            Some(date) => since.is_none_or(|since| date >= since) && until.is_none_or(|until| date <= until),
+            // 无法解析的日期键只在未指定范围时保留
+            None => since.is_none() && until.is_none(),
+        };
+        self.usage.retain(|daily| in_range(&daily.date));
+        for groups in [&mut self.versions, &mut self.branches, &mut self.sources] {
+            groups.retain(|group| in_range(&group.date));
+        }
+        for aggregates in [&mut self.hourly, &mut self.recent] {
+            aggregates.retain(|daily| in_range(daily.date.get(..10).unwrap_or(&daily.date)));
+        }
+        self.project_models.retain(|usage| in_range(&usage.date));
+        for counts in [&mut self.tools, &mut self.stop_reasons] {
+            counts.retain(|count| in_range(&count.date));
+        }
+        self.message_sizes.retain(|size| in_range(&size.date));
+        self.latencies.retain(|latency| in_range(&latency.date));
+        // 会话按开始日期整体保留或排除
+        self.sessions.retain(|session| match session.started_at {
+            Some(started) => in_range(&started.with_timezone(&self.timezone).date_naive().to_string()),
+            None => since.is_none() && until.is_none(),
+        });
+    }
+}
+
+fn parse_date_key(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+/// FileProcessor::iter_items 返回的迭代器，由后台线程解析文件并逐个送出 Item
+pub struct Items {
+    receiver: mpsc::Receiver<Item>,
+}
+
+impl Iterator for Items {
+    type Item = Item;
+
+    fn next(&mut self) -> Option<Item> {
+        self.receiver.recv().ok()
+    }
+}
+
+/// 重复扫描同一批文件时（如 blocks --watch）各文件已解析到的位置，由各次扫描的 FileProcessor 共享。
+/// 文件被追加后只解析新增的完整行，之前的结果保留在这里，不会重复计入；
+/// 没有写完的末尾行不计入，也不跳过，写完后由下一次扫描解析
+#[derive(Clone, Default)]
+pub struct FileOffsets(Arc<Mutex<HashMap<PathBuf, ResumePoint>>>);
+
+/// 一个文件解析到的位置与截至该位置的结果
+struct ResumePoint {
+    inode: u64,
+    offset: u64,
+    result: FileResult,
+}
+
+/// iter_items 的通道容量：解析领先于消费方时最多缓存的 Item 数
+const ITEM_CHANNEL_CAPACITY: usize = 1024;
+
+/// 导致整个扫描无法进行的错误；单个文件的问题记录在 Diagnostics 中
+#[derive(Debug)]
+pub enum ProcessError {
+    /// 数据目录不存在
+    MissingDirectory(PathBuf),
+    /// 命令行指定的路径不存在
+    MissingPaths(Vec<PathBuf>),
+    /// 读取数据目录失败
+    Io {
+        /// 读取失败的目录
+        path: PathBuf,
+        /// 底层的 I/O 错误
+        source: io::Error,
+    },
+    /// 扫描在完成前被取消
+    Cancelled,
+    /// 通过 ssh 读取远程日志失败，见 [`crate::remote`]
+    Remote {
+        /// ssh 的目标主机
+        host: String,
+        /// 失败原因，包含 ssh 的错误输出
+        message: String,
+    },
+}
+
+impl fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcessError::MissingDirectory(path) => f.write_str(&tr!("error.missing_directory", path.display())),
+            ProcessError::MissingPaths(paths) => {
+                let paths: Vec<String> = paths.iter().map(|path| path.display().to_string()).collect();
+                f.write_str(&tr!("error.missing_paths", paths.join(", ")))
+            }
+            ProcessError::Io { path, source } => f.write_str(&tr!("error.read_directory", path.display(), source)),
+            ProcessError::Cancelled => f.write_str(&tr!("error.cancelled")),
+            ProcessError::Remote { host, message } => f.write_str(&tr!("error.remote", host, message)),
+        }
+    }
+}
+
+impl std::error::Error for ProcessError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ProcessError::MissingDirectory(_)
+            | ProcessError::MissingPaths(_)
+            | ProcessError::Cancelled
+            | ProcessError::Remote { .. } => None,
+            ProcessError::Io { source, .. } => Some(source),
+        }
+    }
+}
+
+/// 扫描过程中的事件，用于显示进度；文件事件在 rayon 工作线程中发出，顺序不固定
+#[derive(Debug)]
+pub enum ProcessEvent<'a> {
+    /// 文件查找完成，即将开始解析
+    ScanStarted {
+        /// 要解析的文件数，不包括超过大小上限的文件和流输入
+        file_count: usize,
+    },
+    /// 一个文件解析完成（或直接使用了缓存结果）
+    FileProcessed {
+        /// 文件路径
+        path: &'a Path,
+        /// 计入统计的记录数
+        entries: u64,
+        /// 文件大小
+        bytes: u64,
+    },
+    /// 一个文件读取失败或解析时发生 panic，同时记录在诊断信息中
+    FileFailed {
+        /// 文件路径
+        path: &'a Path,
+        /// 错误信息
+        error: &'a str,
+    },
+    /// 全部输入处理完成
+    Finished {
+        /// 本次扫描的诊断信息
+        summary: &'a Diagnostics,
+    },
+}
+
+/// 决定一条记录是否计入统计的条件，会被多个工作线程同时调用
+pub type ItemFilter = dyn Fn(&Item) -> bool + Send + Sync;
+
+/// 接收 ProcessEvent 的回调，会被多个工作线程同时调用
+pub type EventCallback = dyn Fn(&ProcessEvent) + Send + Sync;
+
+/// 扫描会话日志并按各维度聚合使用量；过滤条件与要收集的数据由 FileProcessorBuilder 配置
+pub struct FileProcessor {
+    directory: PathBuf,
+    paths: Vec<PathBuf>, // 命令行指定的文件或目录，为空时扫描 directory
+    // 使用 DashMap 替代 Mutex<HashMap>，提供更细粒度的锁
+    collected_items: DashMap<UsageKey, UsageAggregate>,
+    version_items: DashMap<(String, String), UsageAggregate>, // (版本号, 时间戳键) -> 使用量
+    branch_items: DashMap<(String, String), UsageAggregate>, // (git 分支, 时间戳键) -> 使用量
+    source_items: DashMap<(String, String), UsageAggregate>, // (写入日志的工具, 时间戳键) -> 使用量
+    hourly_items: DashMap<(String, String), UsageAggregate>, // (模型, 小时键) -> 使用量
+    recent_items: DashMap<(String, String), UsageAggregate>, // (模型, 分钟键) -> 使用量
+    // 早于该时间的记录不计入 recent_items
+    recent_cutoff: DateTime<Utc>,
+    project_items: DashMap<UsageKey, UsageAggregate>, // 键中总是填入项目路径
+    decoded_projects: DashMap<String, String>, // 项目目录编码名 -> 启发式解码结果
+    tool_counts: DashMap<(String, String), u64>, // (时间戳键, 工具名) -> 调用次数
+    stop_reason_counts: DashMap<(String, String), u64>, // (时间戳键, stop_reason) -> 次数
+    sessions: Mutex<Vec<SessionSummary>>,
+    cache: Option<FileCache>,
+    // 单文件大小上限，超过的文件在统计阶段被跳过；0 表示不限制
+    max_file_size: u64,
+    // 达到该大小的文件分块并行解析；0 表示不分块
+    parallel_threshold: u64,
+    oversized_files: Mutex<Vec<(PathBuf, u64)>>, // (路径, 字节数)
+    // 日期（按 timezone）在此范围之外的记录被隔离，None 时不检查
+    valid_dates: Option<(NaiveDate, NaiveDate)>,
+    extensions: Vec<String>, // 接受的文件扩展名
+    sniff: bool, // 扩展名不在列表中的文件是否按内容判断
+    // 是否逐条记录消息大小；缓存中没有这些数据，启用时所有文件都重新解析
+    collect_message_sizes: bool,
+    message_sizes: Mutex<Vec<MessageSize>>,
+    // 是否逐条记录消息的用时；与消息大小一样不使用缓存
+    collect_latencies: bool,
+    latencies: Mutex<Vec<MessageLatency>>,
+    // 只记录这一天（日期键）各文件的记录来源，None 时不记录
+    explain_date: Option<String>,
+    attributions: Mutex<Vec<FileAttribution>>,
+    skipped_files: DashMap<String, u64>, // 因文件类型跳过的文件：扩展名 -> 文件数
+    // 各工作线程将解析统计汇总到这里，而不是丢弃错误
+    file_stats: DashMap<PathBuf, FileStats>,
+    read_errors: Mutex<Vec<(PathBuf, String)>>,
+    panicked_files: Mutex<Vec<(PathBuf, String)>>,
+    parse_options: ParseOptions,
+    field_mapping: Option<FieldMapping>, // SourceTool::Custom 的文件按此解析
+    // 所有条件都满足的记录才计入统计
+    filters: Vec<Box<ItemFilter>>,
+    events: Option<Box<EventCallback>>,
+    // 置位后尚未开始的文件不再解析，正在解析的文件停在下一行，process 返回 ProcessError::Cancelled
+    cancelled: Arc<AtomicBool>,
+    // 设置时 JSONL 文件从上一次扫描解析到的位置继续
+    offsets: Option<FileOffsets>,
+    // 日期键与小时键所用的时区
+    timezone: Tz,
+}
+
+/// FileProcessor 的配置，各选项都有默认值：扫描数据目录中的 json、jsonl 文件，单文件上限 1GB，不使用缓存
+pub struct FileProcessorBuilder {
+    directory: PathBuf,
+    paths: Vec<PathBuf>,
+    extensions: Vec<String>,
+    sniff: bool,
+    collect_message_sizes: bool,
+    collect_latencies: bool,
+    timestamp_floor: Option<NaiveDate>,
+    explain_date: Option<NaiveDate>,
+    cache: Option<FileCache>,
+    max_file_size: u64,
+    parallel_threshold: u64,
+    parse_options: ParseOptions,
+    field_mapping: Option<FieldMapping>,
+    filters: Vec<Box<ItemFilter>>,
+    events: Option<Box<EventCallback>>,
+    cancelled: Option<Arc<AtomicBool>>,
+    offsets: Option<FileOffsets>,
+    timezone: Tz,
+}
+
+impl FileProcessorBuilder {
+    /// 以 directory 为数据目录的默认配置
+    pub fn new(directory: PathBuf) -> Self {
+        Self {
+            directory,
+            paths: Vec::new(),
+            extensions: DEFAULT_EXTENSIONS.iter().map(|ext| ext.to_string()).collect(),
+            sniff: false,
+            collect_message_sizes: false,
+            collect_latencies: false,
+            timestamp_floor: Some(DEFAULT_TIMESTAMP_FLOOR),
+            explain_date: None,
+            cache: None,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            parallel_threshold: DEFAULT_PARALLEL_PARSE_THRESHOLD,
+            parse_options: ParseOptions::default(),
+            field_mapping: None,
+            filters: Vec::new(),
+            events: None,
+            cancelled: None,
+            offsets: None,
+            timezone: Tz::UTC,
+        }
+    }
+
+    /// 只处理指定的文件或目录，替代默认的数据目录扫描
+    pub fn with_paths(mut self, paths: Vec<PathBuf>) -> Self {
+        self.paths = paths;
+        self
+    }
+
+    /// 在 json、jsonl 之外额外接受的文件扩展名（如 ndjson）
+    pub fn with_extra_extensions(mut self, extensions: Vec<String>) -> Self {
+        for extension in extensions {
+            let extension = extension.trim_start_matches('.').to_string();
+            if !extension.is_empty() && !self.extensions.contains(&extension) {
+                self.extensions.push(extension);
+            }
+        }
+        self
+    }
+
+    /// 扩展名不在接受列表中的文件读取开头几 KB，看起来像 JSONL 时同样处理
+    pub fn with_content_sniffing(mut self, sniff: bool) -> Self {
+        self.sniff = sniff;
+        self
+    }
+
+    /// 逐条记录消息的 token 数，用于计算消息大小的分位数。
+    /// 会占用与消息数成正比的内存，并且不使用缓存中的结果，只应在需要时启用
+    pub fn with_message_sizes(mut self, collect: bool) -> Self {
+        self.collect_message_sizes = collect;
+        self
+    }
+
+    /// 逐条记录带有 durationMs 的消息的用时，用于计算响应时间的分位数；没有用时记录的消息不参与，
+    /// 也不影响 token 统计。缓存中没有这些数据，启用时不使用缓存中的结果，只应在需要时启用
+    pub fn with_latencies(mut self, collect: bool) -> Self {
+        self.collect_latencies = collect;
+        self
+    }
+
+    /// 记录 date 这一天（按 with_timezone 的时区）每个文件计入、排除的记录，结果见 ProcessOutput::attributions。
+    /// 与 with_message_sizes 一样不使用缓存中的结果；None 时不记录，没有额外开销
+    pub fn with_explain(mut self, date: Option<NaiveDate>) -> Self {
+        self.explain_date = date;
+        self
+    }
+
+    /// 按 mapping 解析 SourceTool::Custom 的文件；映射可能随配置改变，这些文件不使用缓存
+    pub fn with_field_mapping(mut self, mapping: FieldMapping) -> Self {
+        self.field_mapping = Some(mapping);
+        self
+    }
+
+    /// 启用文件聚合缓存，未变化的文件直接使用缓存结果
+    pub fn with_cache(mut self, cache: FileCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// 设置单文件大小上限，0 表示不限制
+    pub fn with_max_file_size(mut self, max_file_size: u64) -> Self {
+        self.max_file_size = max_file_size;
+        self
     }
-}
\ No newline at end of file
+
+    /// 日期早于 floor 或晚于明天（按 with_timezone 的时区）的记录被隔离：不计入统计，逐条记录在 FileStats::quarantined 中。
+    /// 默认下限为 DEFAULT_TIMESTAMP_FLOOR；None 时不检查，保留原始数据。缓存中是按默认下限得到的结果，使用其他设置时不使用缓存
+    pub fn with_timestamp_validation(mut self, floor: Option<NaiveDate>) -> Self {
+        self.timestamp_floor = floor;
+        self
+    }
+
+    /// 达到该大小的 JSONL 文件按行切分成多块并行解析，结果与按顺序解析相同；0 表示总是按顺序解析。
+    /// Codex 与 Gemini CLI 的日志需要按顺序解析，不受影响
+    pub fn with_parallel_parse_threshold(mut self, threshold: u64) -> Self {
+        self.parallel_threshold = threshold;
+        self
+    }
+
+    /// 设置解析每行记录时的容错选项，默认为 ParseOptions::default()；使用其他选项时不使用缓存
+    pub fn with_parse_options(mut self, options: ParseOptions) -> Self {
+        self.parse_options = options;
+        self
+    }
+
+    /// 只统计 predicate 返回 true 的记录，可多次调用，记录需满足全部条件。
+    /// 条件在解析之后、聚合之前执行，被排除的记录数见 FileStats::filtered。
+    /// 缓存中的结果没有经过过滤，注册了条件时不使用缓存
+    pub fn with_filter(mut self, predicate: impl Fn(&Item) -> bool + Send + Sync + 'static) -> Self {
+        self.filters.push(Box::new(predicate));
+        self
+    }
+
+    /// 扫描过程中的每个 ProcessEvent 都交给 callback，例如用于驱动进度条
+    pub fn with_event_callback(mut self, callback: impl Fn(&ProcessEvent) + Send + Sync + 'static) -> Self {
+        self.events = Some(Box::new(callback));
+        self
+    }
+
+    /// 与调用方共享的取消标志（如由 Ctrl-C 处理函数置位），置位后扫描尽快返回 ProcessError::Cancelled
+    pub fn with_cancel_flag(mut self, cancelled: Arc<AtomicBool>) -> Self {
+        self.cancelled = Some(cancelled);
+        self
+    }
+
+    /// 与之前的扫描共享各文件解析到的位置，追加写入的文件只解析新增的部分。
+    /// 各次扫描的其他选项应当相同；.gz 文件和 Gemini CLI 的会话文件总是完整解析
+    pub fn with_resume_offsets(mut self, offsets: FileOffsets) -> Self {
+        self.offsets = Some(offsets);
+        self
+    }
+
+    /// 按 tz 中的本地日期和小时分组，默认为 UTC。最近 5 小时的分钟键总是 UTC；
+    /// 缓存中是按 UTC 分组的结果，使用其他时区时不使用缓存
+    pub fn with_timezone(mut self, tz: Tz) -> Self {
+        self.timezone = tz;
+        self
+    }
+
+    /// 按当前配置创建 FileProcessor
+    pub fn build(self) -> FileProcessor {
+        FileProcessor {
+            directory: self.directory,
+            paths: self.paths,
+            collected_items: DashMap::new(),
+            version_items: DashMap::new(),
+            hourly_items: DashMap::new(),
+            recent_items: DashMap::new(),
+            recent_cutoff: Utc::now() - TimeDelta::hours(BLOCK_HOURS),
+            branch_items: DashMap::new(),
+            source_items: DashMap::new(),
+            project_items: DashMap::new(),
+            decoded_projects: DashMap::new(),
+            tool_counts: DashMap::new(),
+            stop_reason_counts: DashMap::new(),
+            sessions: Mutex::new(Vec::new()),
+            cache: self.cache,
+            max_file_size: self.max_file_size,
+            parallel_threshold: self.parallel_threshold,
+            oversized_files: Mutex::new(Vec::new()),
+            valid_dates: self.timestamp_floor.map(|floor| (floor, (Utc::now() + TimeDelta::days(1)).with_timezone(&self.timezone).date_naive())),
+            extensions: self.extensions,
+            sniff: self.sniff,
+            collect_message_sizes: self.collect_message_sizes,
+            message_sizes: Mutex::new(Vec::new()),
+            collect_latencies: self.collect_latencies,
+            latencies: Mutex::new(Vec::new()),
+            explain_date: self.explain_date.map(|date| date.to_string()),
+            attributions: Mutex::new(Vec::new()),
+            skipped_files: DashMap::new(),
+            file_stats: DashMap::new(),
+            read_errors: Mutex::new(Vec::new()),
+            panicked_files: Mutex::new(Vec::new()),
+            parse_options: self.parse_options,
+            field_mapping: self.field_mapping,
+            filters: self.filters,
+            events: self.events,
+            cancelled: self.cancelled.unwrap_or_default(),
+            offsets: self.offsets,
+            timezone: self.timezone,
+        }
+    }
+}
+
+impl FileProcessor {
+    /// 以默认配置扫描 directory 下的所有日志文件
+    pub fn new(directory: PathBuf) -> Self {
+        FileProcessorBuilder::new(directory).build()
+    }
+
+    /// 以 directory 为数据目录的配置，设置选项后由 build 创建 FileProcessor
+    pub fn builder(directory: PathBuf) -> FileProcessorBuilder {
+        FileProcessorBuilder::new(directory)
+    }
+
+    /// 与 process 共享的取消标志，置位后正在进行的扫描尽快返回 ProcessError::Cancelled
+    #[cfg(feature = "async")]
+    pub(crate) fn cancel_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.cancelled)
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// 有回调时发出事件
+    fn emit(&self, event: ProcessEvent) {
+        if let Some(callback) = &self.events {
+            callback(&event);
+        }
+    }
+
+    /// 本次扫描的诊断信息，各列表按路径排序
+    pub(crate) fn diagnostics(&self, cache_error: Option<String>) -> Diagnostics {
+        let mut files: Vec<_> = self.file_stats
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect();
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut read_errors = self.read_errors.lock().unwrap_or_else(PoisonError::into_inner).clone();
+        read_errors.sort();
+
+        let mut panicked_files = self.panicked_files.lock().unwrap_or_else(PoisonError::into_inner).clone();
+        panicked_files.sort();
+
+        let mut oversized_files = self.oversized_files.lock().unwrap_or_else(PoisonError::into_inner).clone();
+        oversized_files.sort();
+
+        let skipped_files = self.skipped_files
+            .iter()
+            .map(|entry| (entry.key().clone(), *entry.value()))
+            .collect();
+
+        Diagnostics {
+            files,
+            read_errors,
+            panicked_files,
+            oversized_files,
+            skipped_files,
+            cache_error,
+            imported: Vec::new(),
+            merged: Vec::new(),
+        }
+    }
+
+    /// 列出 source 中按当前的扩展名与内容嗅探设置会被处理的输入，不读取内容；
+    /// 不处理的文件按扩展名计入诊断信息
+    pub fn inputs(&self, source: impl DataSource) -> Result<Vec<SourceInput>, ProcessError> {
+        let filter = FileFilter {
+            extensions: &self.extensions,
+            sniff: self.sniff,
+            skipped: &self.skipped_files,
+        };
+        source.inputs(&filter)
+    }
+
+    /// 扫描数据目录（或 with_paths 指定的路径）并解析所有文件，返回合并后的使用量
+    pub fn process_files(&self) -> Result<ProcessOutput, ProcessError> {
+        if self.paths.is_empty() {
+            self.process(DirectorySource::new(self.directory.clone()))
+        } else {
+            self.process(PathsSource::new(self.paths.clone(), self.directory.clone()))
+        }
+    }
+
+    /// 从流（如 stdin）逐行读取 JSONL 并统计，不扫描数据目录也不使用缓存。
+    /// 每次只持有一行，内存占用与输入大小无关；name 用于诊断信息中标识该输入。
+    pub fn process_reader(&self, reader: impl BufRead + 'static, name: &str) -> Result<ProcessOutput, ProcessError> {
+        self.process(ReaderSource::new(name, reader))
+    }
+
+    /// 解析数据源提供的所有输入，返回合并后的使用量。文件并行解析，流按顺序逐行读取
+    pub fn process(&self, source: impl DataSource) -> Result<ProcessOutput, ProcessError> {
+        let discovery_span = debug_span!("discover_files", dir = %self.directory.display(), files = field::Empty);
+        let discovery_guard = discovery_span.enter();
+
+        let mut all_files = Vec::new();
+        let mut streams = Vec::new();
+        for input in self.discover(source)? {
+            match input {
+                SourceInput::File { path, metadata, project_dir, tool } => all_files.push((path, metadata, project_dir, tool)),
+                SourceInput::Stream { name, project_dir, reader } => streams.push((name, project_dir, reader)),
+            }
+        }
+
+        discovery_span.record("files", all_files.len());
+        drop(discovery_guard);
+        drop(discovery_span);
+
+        self.emit(ProcessEvent::ScanStarted { file_count: all_files.len() });
+
+        // 并行处理文件；单个文件解析时的 panic 只记入诊断信息，不影响其他文件
+        all_files.par_iter().for_each(|(file_path, metadata, project_dir, tool)| {
+            // 已取消时跳过尚未开始的文件，正在解析的文件在 print_json_content 中停止
+            if !self.is_cancelled() {
+                self.isolate(file_path, || self.process_file(file_path, metadata, project_dir.clone(), *tool));
+            }
+        });
+
+        let only_streams = all_files.is_empty() && !streams.is_empty();
+        for (name, project_dir, reader) in streams {
+            if self.is_cancelled() {
+                break;
+            }
+            let path = PathBuf::from(&name);
+            self.isolate(&path, || self.process_stream(reader, &name, project_dir)).transpose()?;
+        }
+
+        // 取消时结果不完整，既不写入缓存也不返回
+        if self.is_cancelled() {
+            return Err(ProcessError::Cancelled);
+        }
+
+        // 缓存写入失败不影响本次结果，只记录在诊断信息中；只有流输入时缓存没有变化，不必写入
+        let cache_error = self.cache
+            .as_ref()
+            .filter(|_| !only_streams)
+            .and_then(|cache| cache.save().err())
+            .map(|e| e.to_string());
+
+        let output = self.collect_output(cache_error);
+        self.emit(ProcessEvent::Finished { summary: &output.diagnostics });
+        Ok(output)
+    }
+
+    /// 列出 source 中要处理的输入；超过 with_max_file_size 的文件在读取内容之前排除并记入诊断信息，
+    /// 避免损坏的超大文件耗尽内存
+    fn discover(&self, source: impl DataSource) -> Result<Vec<SourceInput>, ProcessError> {
+        let mut inputs = self.inputs(source)?;
+        inputs.retain(|input| match input {
+            SourceInput::File { path, metadata, .. } if self.max_file_size > 0 && metadata.len() > self.max_file_size => {
+                self.oversized_files.lock().unwrap_or_else(PoisonError::into_inner).push((path.clone(), metadata.len()));
+                false
+            }
+            _ => true,
+        });
+        Ok(inputs)
+    }
+
+    /// 执行一个输入的处理；其中发生 panic 时记录该输入与 panic 信息并返回 None，继续处理其他输入。
+    /// panic 发生在合并进共享状态之前时该输入的数据完全不计入，缓存也不会写入。
+    /// panic 时持有的锁会被标记为 poisoned，其中的数据仍然有效，各处取锁时都忽略该标记，不会让其他输入跟着失败
+    fn isolate<T>(&self, path: &Path, process: impl FnOnce() -> T) -> Option<T> {
+        match panic::catch_unwind(AssertUnwindSafe(process)) {
+            Ok(result) => Some(result),
+            Err(payload) => {
+                let message = panic_message(payload.as_ref());
+                self.emit(ProcessEvent::FileFailed { path, error: &message });
+                self.panicked_files.lock().unwrap_or_else(PoisonError::into_inner).push((path.to_path_buf(), message));
+                None
+            }
+        }
+    }
+
+    /// 逐行读取一个流并合并进共享状态
+    fn process_stream(&self, mut reader: impl BufRead, name: &str, project_dir: Option<String>) -> Result<(), ProcessError> {
+        let path = PathBuf::from(name);
+        let span = debug_span!("parse_stream", name, entries = field::Empty);
+        let _guard = span.enter();
+
+        let mut result = FileResult {
+            project_dir,
+            ..FileResult::default()
+        };
+        let mut buffer = Vec::new();
+        let mut first_line = true;
+        while !self.is_cancelled() {
+            buffer.clear();
+            match reader.read_until(b'\n', &mut buffer) {
+                Ok(0) => break,
+                Ok(_) => {
+                    // 与文件相同：去掉开头的 BOM，非法字节逐行按替换字符处理
+                    let bytes = if first_line {
+                        buffer.strip_prefix(UTF8_BOM).unwrap_or(&buffer)
+                    } else {
+                        &buffer
+                    };
+                    first_line = false;
+                    self.process_line(&String::from_utf8_lossy(bytes), &mut result, &mut |item, result| self.collect_item(item, result));
+                }
+                Err(source) => return Err(ProcessError::Io { path, source }),
+            }
+        }
+
+        result.resolve_projects(decode_project_dir);
+        span.record("entries", result.stats.entries);
+        let session = result.session.finish(name.to_string(), &result);
+        self.merge_result(&path, result, session);
+        Ok(())
+    }
+
+    /// 逐个返回数据目录（或 with_paths 指定的路径）中解析得到的 Item，不做聚合，顺序不固定。
+    /// 文件的查找、读取与解析与 process_files 相同，只是每个 Item 送入容量有限的通道而不是计入聚合结果，
+    /// 内存占用与文件总量无关；不使用缓存，也不收集诊断信息。Items 被丢弃时剩余的文件不再解析
+    pub fn iter_items(self) -> Result<Items, ProcessError> {
+        let inputs = if self.paths.is_empty() {
+            self.discover(DirectorySource::new(self.directory.clone()))?
+        } else {
+            self.discover(PathsSource::new(self.paths.clone(), self.directory.clone()))?
+        };
+        let files: Vec<(PathBuf, Option<String>, SourceTool)> = inputs
+            .into_iter()
+            .filter_map(|input| match input {
+                SourceInput::File { path, project_dir, tool, .. } => Some((path, project_dir, tool)),
+                SourceInput::Stream { .. } => None,
+            })
+            .collect();
+
+        let (sender, receiver) = mpsc::sync_channel(ITEM_CHANNEL_CAPACITY);
+        thread::spawn(move || {
+            // 接收端已被丢弃时不再解析剩余的文件
+            let closed = AtomicBool::new(false);
+            let send = |item: Item, _: &mut FileResult| {
+                let counted = item.usage.is_some();
+                if !closed.load(Ordering::Relaxed) && sender.send(item).is_err() {
+                    closed.store(true, Ordering::Relaxed);
+                }
+                counted
+            };
+            files.par_iter().for_each(|(path, project_dir, tool)| {
+                if !closed.load(Ordering::Relaxed) && !self.is_cancelled() {
+                    // 与 process_files 相同，读取失败或 panic 的文件跳过
+                    self.isolate(path, || self.parse_file(path, project_dir.clone(), *tool, &send));
+                }
+            });
+        });
+        Ok(Items { receiver })
+    }
+
+    /// 将各共享状态整理为最终结果
+    fn collect_output(&self, cache_error: Option<String>) -> ProcessOutput {
+        // 返回合并后的结果
+        let usage = debug_span!("merge_results", keys = self.collected_items.len())
+            .in_scope(|| self.get_merged_results());
+        let to_daily = |(model, date), usage| DailyUsage { date, model, usage };
+        let to_group = |(group, date), usage| GroupUsage { group, date, usage };
+        let versions = Self::sorted_aggregates(&self.version_items, to_group);
+        let hourly = Self::sorted_aggregates(&self.hourly_items, to_daily);
+        let recent = Self::sorted_aggregates(&self.recent_items, to_daily);
+        let branches = Self::sorted_aggregates(&self.branch_items, to_group);
+        let sources = Self::sorted_aggregates(&self.source_items, to_group);
+        let mut project_models: Vec<ProjectModelUsage> = self.project_items
+            .iter()
+            .map(|entry| {
+                let UsageKey { model, date, project, .. } = entry.key().clone();
+                ProjectModelUsage { project: project.unwrap_or_default(), model, date, aggregate: entry.value().clone() }
+            })
+            .collect();
+        project_models.sort_by(|a, b| (&a.project, &a.model, &a.date).cmp(&(&b.project, &b.model, &b.date)));
+
+        let mut sessions = std::mem::take(&mut *self.sessions.lock().unwrap_or_else(PoisonError::into_inner));
+        sessions.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+        let mut attributions = std::mem::take(&mut *self.attributions.lock().unwrap_or_else(PoisonError::into_inner));
+        attributions.sort_by(|a, b| a.path.cmp(&b.path));
+
+        ProcessOutput {
+            usage,
+            versions,
+            branches,
+            sources,
+            hourly,
+            recent,
+            project_models,
+            tools: Self::sorted_counts(&self.tool_counts),
+            stop_reasons: Self::sorted_counts(&self.stop_reason_counts),
+            sessions,
+            message_sizes: std::mem::take(&mut *self.message_sizes.lock().unwrap_or_else(PoisonError::into_inner)),
+            latencies: std::mem::take(&mut *self.latencies.lock().unwrap_or_else(PoisonError::into_inner)),
+            attributions,
+            diagnostics: self.diagnostics(cache_error),
+            timezone: self.timezone,
+        }
+    }
+
+    fn process_file(&self, file_path: &Path, metadata: &fs::Metadata, project_dir: Option<String>, tool: SourceTool) {
+        // 日志未启用时 span 为禁用状态，字段不会被求值
+        let span = debug_span!(
+            "parse_file",
+            path = %file_path.display(),
+            bytes = metadata.len(),
+            entries = field::Empty,
+            cached = field::Empty,
+        );
+        let _guard = span.enter();
+
+        // 在读取内容前获取文件指纹，读取期间被修改的文件下次运行会重新解析
+        let fingerprint = match &self.cache {
+            // 缓存中是按默认下限检查时间戳、按默认选项解析、按 UTC 分组、没有经过过滤的结果
+            Some(cache) if self.filters.is_empty()
+                && self.timezone == Tz::UTC
+                && self.parse_options == ParseOptions::default()
+                && tool != SourceTool::Custom
+                && self.valid_dates.is_some_and(|(floor, _)| floor == DEFAULT_TIMESTAMP_FLOOR) => {
+                let fingerprint = FileFingerprint::from_metadata(metadata);
+                if !self.collect_message_sizes
+                    && !self.collect_latencies
+                    && self.explain_date.is_none()
+                    && let Some(cached) = cache
+                        .lookup(file_path, &fingerprint)
+                        .filter(|cached| cached.tool == tool && !self.releases_quarantined(&cached.stats)) {
+                    for entry in cached.aggregates {
+                        Self::merge_aggregate(&self.source_items, (tool.to_string(), entry.date.clone()), entry.aggregate.clone());
+                        let key = UsageKey::new(entry.name, entry.date)
+                            .with_project(entry.project)
+                            .with_session(entry.session);
+                        Self::merge_aggregate(&self.collected_items, key, entry.aggregate);
+                    }
+                    for entry in cached.versions {
+                        Self::merge_aggregate(&self.version_items, (entry.name, entry.date), entry.aggregate);
+                    }
+                    for entry in cached.branches {
+                        Self::merge_aggregate(&self.branch_items, (entry.name, entry.date), entry.aggregate);
+                    }
+                    for entry in cached.hourly {
+                        Self::merge_aggregate(&self.hourly_items, (entry.name, entry.date), entry.aggregate);
+                    }
+                    for entry in cached.recent {
+                        Self::merge_aggregate(&self.recent_items, (entry.name, entry.date), entry.aggregate);
+                    }
+                    for entry in cached.projects {
+                        let key = UsageKey::new(entry.model, entry.date).with_project(Some(entry.project));
+                        Self::merge_aggregate(&self.project_items, key, entry.aggregate);
+                    }
+                    Self::merge_cached_counts(&self.tool_counts, cached.tools);
+                    Self::merge_cached_counts(&self.stop_reason_counts, cached.stop_reasons);
+                    self.sessions.lock().unwrap_or_else(PoisonError::into_inner).push(cached.session);
+                    span.record("entries", cached.stats.entries);
+                    span.record("cached", true);
+                    self.emit(ProcessEvent::FileProcessed {
+                        path: file_path,
+                        entries: cached.stats.entries,
+                        bytes: metadata.len(),
+                    });
+                    self.file_stats.insert(file_path.to_path_buf(), cached.stats);
+                    return;
+                }
+                Some(fingerprint)
+            }
+            _ => None,
+        };
+
+        let parsed = match &self.offsets {
+            Some(offsets) => self.resume_file(file_path, metadata, project_dir, tool, offsets),
+            None => self.parse_file(file_path, project_dir, tool, &|item, result| self.collect_item(item, result)),
+        };
+        match parsed {
+            Ok(mut result) => {
+                result.resolve_projects(|encoded| {
+                    self.decoded_projects
+                        .entry(encoded.to_string())
+                        .or_insert_with(|| decode_project_dir(encoded))
+                        .clone()
+                });
+
+                // 没有任何提问的文件（只有摘要或工具记录）同样作为 0 轮的会话保留
+                let fallback_id = file_path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let session = result.session.finish(fallback_id, &result);
+                // 取消时结果可能不完整，不写入缓存也不合并
+                if self.is_cancelled() {
+                    return;
+                }
+
+                if let (Some(cache), Some(fingerprint)) = (&self.cache, fingerprint) {
+                    cache.store(file_path, CachedFile {
+                        fingerprint,
+                        tool,
+                        aggregates: Self::to_cached_usage(&result.aggregates),
+                        versions: Self::to_cached_usage(&result.versions),
+                        branches: Self::to_cached_usage(&result.branches),
+                        hourly: Self::to_cached_usage(&result.hourly),
+                        recent: Self::to_cached_usage(&result.recent),
+                        projects: result.project_models
+                            .iter()
+                            .map(|((project, model, date), aggregate)| CachedProjectUsage {
+                                project: project.to_string(),
+                                model: model.to_string(),
+                                date: date.to_string(),
+                                aggregate: aggregate.clone(),
+                            })
+                            .collect(),
+                        tools: Self::to_cached_counts(&result.tools),
+                        stop_reasons: Self::to_cached_counts(&result.stop_reasons),
+                        session: session.clone(),
+                        stats: result.stats.clone(),
+                    });
+                }
+
+                span.record("entries", result.stats.entries);
+                self.emit(ProcessEvent::FileProcessed {
+                    path: file_path,
+                    entries: result.stats.entries,
+                    bytes: metadata.len(),
+                });
+                self.merge_result(file_path, result, session);
+            }
+            Err(e) => {
+                let error = e.to_string();
+                self.emit(ProcessEvent::FileFailed { path: file_path, error: &error });
+                self.read_errors.lock().unwrap_or_else(PoisonError::into_inner).push((file_path.to_path_buf(), error));
+            }
+        }
+    }
+
+    /// 将单个输入解析得到的结果合并进共享状态
+    fn merge_result(&self, path: &Path, result: FileResult, session: SessionSummary) {
+        let attribution = (!result.explained.is_empty()).then(|| self.attribution(path, &result, &session));
+        let tool = match (result.tool, &self.field_mapping) {
+            (SourceTool::Custom, Some(FieldMapping { label: Some(label), .. })) => label.clone(),
+            (tool, _) => tool.to_string(),
+        };
+        for ((model, date), aggregate) in result.aggregates {
+            Self::merge_aggregate(&self.source_items, (tool.clone(), date.to_string()), aggregate.clone());
+            Self::merge_aggregate(&self.collected_items, UsageKey::new(&*model, &*date), aggregate);
+        }
+        for ((name, date), aggregate) in result.versions {
+            Self::merge_aggregate(&self.version_items, (name.to_string(), date.to_string()), aggregate);
+        }
+        for ((name, date), aggregate) in result.branches {
+            Self::merge_aggregate(&self.branch_items, (name.to_string(), date.to_string()), aggregate);
+        }
+        for ((model, hour), aggregate) in result.hourly {
+            Self::merge_aggregate(&self.hourly_items, (model.to_string(), hour.to_string()), aggregate);
+        }
+        for ((model, minute), aggregate) in result.recent {
+            Self::merge_aggregate(&self.recent_items, (model.to_string(), minute.to_string()), aggregate);
+        }
+        for ((project, model, date), aggregate) in result.project_models {
+            let key = UsageKey::new(&*model, &*date).with_project(Some(project.to_string()));
+            Self::merge_aggregate(&self.project_items, key, aggregate);
+        }
+        Self::merge_counts(&self.tool_counts, result.tools);
+        Self::merge_counts(&self.stop_reason_counts, result.stop_reasons);
+        self.sessions.lock().unwrap_or_else(PoisonError::into_inner).push(session);
+        self.message_sizes.lock().unwrap_or_else(PoisonError::into_inner).extend(result.message_sizes);
+        self.latencies.lock().unwrap_or_else(PoisonError::into_inner).extend(result.latencies);
+        if let Some(attribution) = attribution {
+            self.attributions.lock().unwrap_or_else(PoisonError::into_inner).push(attribution);
+        }
+        self.file_stats.insert(path.to_path_buf(), result.stats);
+    }
+
+    /// 由 result 中 with_explain 指定日期的记录得到该文件的 FileAttribution
+    fn attribution(&self, path: &Path, result: &FileResult, session: &SessionSummary) -> FileAttribution {
+        let explained = &result.explained;
+        let mut models: Vec<ModelAttribution> = explained.models
+            .iter()
+            .map(|(model, aggregate)| ModelAttribution::new(model, aggregate))
+            .collect();
+        models.sort_by(|a, b| a.model.cmp(&b.model));
+        // 项目已在 resolve_projects 中确定，取该日 token 用量最多的
+        let mut projects: HashMap<&str, u64> = HashMap::new();
+        for ((project, _, date), aggregate) in &result.project_models {
+            if self.explains(date) {
+                *projects.entry(project).or_default() += aggregate.usage.total();
+            }
+        }
+        FileAttribution {
+            path: path.to_path_buf(),
+            project: projects
+                .into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+                .map(|(project, _)| project.to_string())
+                .or_else(|| session.project.clone())
+                .unwrap_or_else(|| "(unknown)".to_string()),
+            session_id: session.session_id.clone(),
+            entries: models.iter().map(|model| model.entries).sum(),
+            models,
+            filtered: explained.filtered,
+            no_usage: explained.no_usage,
+        }
+    }
+
+    /// 读取并解析一个文件，每个 Item 交给 collect；process_files 由此把 Item 计入聚合结果，iter_items 由此把 Item 送出
+    fn parse_file(&self, path: &Path, project_dir: Option<String>, tool: SourceTool, collect: &SharedCollector) -> io::Result<FileResult> {
+        let content = read_log_file(path)?;
+        let mut result = FileResult {
+            project_dir,
+            tool,
+            ..FileResult::default()
+        };
+        self.parse_content(&content, &mut result, collect);
+        Ok(result)
+    }
+
+    /// 从 offsets 中记录的位置继续解析 JSONL 文件，只计入新增的完整行，并记下新的位置与截至该位置的结果；
+    /// 文件变短或被替换时从头解析
+    fn resume_file(
+        &self,
+        path: &Path,
+        metadata: &fs::Metadata,
+        project_dir: Option<String>,
+        tool: SourceTool,
+        offsets: &FileOffsets,
+    ) -> io::Result<FileResult> {
+        if is_gzip(path) || tool == SourceTool::Gemini {
+            return self.parse_file(path, project_dir, tool, &|item, result| self.collect_item(item, result));
+        }
+        let inode = FileFingerprint::from_metadata(metadata).inode;
+        let previous = offsets.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(path)
+            .filter(|point| {
+                point.inode == inode && point.offset <= metadata.len() && !self.releases_quarantined(&point.result.stats)
+            });
+
+        let mut file = fs::File::open(path)?;
+        let mut bytes = Vec::new();
+        let (mut result, offset) = match previous {
+            Some(point) => {
+                file.seek(io::SeekFrom::Start(point.offset))?;
+                (point.result, point.offset)
+            }
+            None => (FileResult { project_dir, tool, ..FileResult::default() }, 0),
+        };
+        file.read_to_end(&mut bytes)?;
+
+        // 与 print_json_content 相同：没有换行结尾的最后一行能解析时才计入，否则留到下一次
+        let complete = match bytes.iter().rposition(|&byte| byte == b'\n') {
+            Some(position) if serde_json::from_slice::<IgnoredAny>(&bytes[position + 1..]).is_err() => position + 1,
+            None if serde_json::from_slice::<IgnoredAny>(&bytes).is_err() => 0,
+            _ => bytes.len(),
+        };
+        let content = if offset == 0 {
+            bytes[..complete].strip_prefix(UTF8_BOM).unwrap_or(&bytes[..complete])
+        } else {
+            &bytes[..complete]
+        };
+        let content = String::from_utf8_lossy(content);
+        if offset == 0 {
+            self.parse_content(&content, &mut result, &|item, result| self.collect_item(item, result));
+            // 整个文件是一个 JSON 文档时追加的内容无法单独解析，不记录位置
+            if !is_jsonl(&content) {
+                return Ok(result);
+            }
+        } else {
+            for line in content.lines() {
+                if self.is_cancelled() {
+                    break;
+                }
+                self.process_line(line, &mut result, &mut |item, result| self.collect_item(item, result));
+            }
+        }
+
+        // 取消时结果不完整，下一次从头解析
+        if !self.is_cancelled() {
+            let point = ResumePoint { inode, offset: offset + complete as u64, result: result.clone() };
+            offsets.0.lock().unwrap_or_else(PoisonError::into_inner).insert(path.to_path_buf(), point);
+        }
+        Ok(result)
+    }
+
+    /// 解析一个文件的全部内容，每个 Item 交给 collect。很大的 JSONL 文件按行切分成多块，
+    /// 各块在线程内解析到自己的 FileResult，再按文件顺序并入 result
+    fn parse_content(&self, content: &str, result: &mut FileResult, collect: &SharedCollector) {
+        let chunks = self.split_chunks(content, result.tool);
+        if chunks.len() < 2 {
+            self.print_json_content(content, result, &mut |item, result| collect(item, result));
+            return;
+        }
+
+        let parts: Vec<FileResult> = chunks
+            .into_par_iter()
+            .map(|chunk| {
+                let mut part = FileResult {
+                    project_dir: result.project_dir.clone(),
+                    tool: result.tool,
+                    session: SessionTracker { deferred: Some(Vec::new()), ..SessionTracker::default() },
+                    deferred: Some(Vec::new()),
+                    ..FileResult::default()
+                };
+                self.print_json_content(chunk, &mut part, &mut |item, part| collect(item, part));
+                part
+            })
+            .collect();
+        for mut part in parts {
+            let deferred = part.deferred.take().unwrap_or_default();
+            result.absorb(part);
+            for item in deferred {
+                if result.is_duplicate(&item) {
+                    result.stats.entries -= 1;
+                    self.collect_duplicate(&item, result);
+                } else {
+                    collect(item, result);
+                }
+            }
+        }
+    }
+
+    /// 把达到并行解析阈值的 JSONL 内容在行边界处切分，每块以换行结尾（最后一块除外）；
+    /// 不需要切分时返回空。Codex 的记录依赖之前的记录，Gemini CLI 的会话文件是单个 JSON 对象，都只能按顺序解析
+    fn split_chunks<'a>(&self, content: &'a str, tool: SourceTool) -> Vec<&'a str> {
+        if self.parallel_threshold == 0
+            || (content.len() as u64) < self.parallel_threshold
+            || !matches!(tool, SourceTool::Claude | SourceTool::Custom) {
+            return Vec::new();
+        }
+        // 整个文件是 JSON 数组或多行缩进的对象时交给 print_json_content 整体处理
+        if !is_jsonl(content) {
+            return Vec::new();
+        }
+
+        let chunk_size = (content.len() / (rayon::current_num_threads() * 4)).max(MIN_PARALLEL_CHUNK);
+        let bytes = content.as_bytes();
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        while start < bytes.len() {
+            let end = (start + chunk_size).min(bytes.len());
+            let end = bytes[end..]
+                .iter()
+                .position(|&byte| byte == b'\n')
+                .map_or(bytes.len(), |position| end + position + 1);
+            chunks.push(&content[start..end]);
+            start = end;
+        }
+        chunks
+    }
+
+    /// 解析一个文件的全部内容，每条记录得到的 Item 交给 collect，collect 返回该条是否计入统计
+    fn print_json_content(&self, content: &str, result: &mut FileResult, collect: &mut ItemCollector) {
+        if content.trim().is_empty() {
+            return;
+        }
+        if result.tool == SourceTool::Gemini {
+            self.print_gemini_session(content, result, collect);
+            return;
+        }
+
+        // 先尝试整体解析：导出的 JSON 数组或单个对象，可能是多行缩进格式。
+        // JSONL 文件在第一条记录之后就会解析失败，代价很小
+        match serde_json::from_str::<Value>(content) {
+            Ok(Value::Array(values)) => {
+                for value in &values {
+                    result.stats.lines += 1;
+                    self.print_json_value(value, result, collect);
+                }
+                return;
+            }
+            Ok(value @ Value::Object(_)) => {
+                result.stats.lines += 1;
+                self.print_json_value(&value, result, collect);
+                return;
+            }
+            _ => {}
+        }
+
+        // 否则按 JSONL 逐行处理
+        // 文件可能正在被追加写入：没有换行结尾的最后一行若无法解析，视为尚未写完，到此为止且不计为错误
+        let (complete, tail) = match content.rfind('\n') {
+            Some(position) => content.split_at(position + 1),
+            None => ("", content),
+        };
+        for line in complete.lines() {
+            // 大文件解析时间较长，逐行检查取消标志，使 Ctrl-C 能及时生效
+            if self.is_cancelled() {
+                return;
+            }
+            self.process_line(line, result, collect);
+        }
+        if serde_json::from_str::<Value>(tail).is_ok() {
+            self.process_line(tail, result, collect);
+        }
+    }
+
+    /// 处理 JSONL 中的一行，空行忽略；行尾的 \r\n 或 \n 一并去掉
+    fn process_line(&self, line: &str, result: &mut FileResult, collect: &mut ItemCollector) {
+        result.line += 1;
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.trim().is_empty() {
+            return;
+        }
+
+        result.stats.lines += 1;
+        match serde_json::from_str::<Value>(line) {
+            Ok(json) => {
+                self.print_json_value(&json, result, collect);
+            }
+            Err(_) => {
+                // 跳过无效行，但记录下来用于诊断
+                result.stats.failed_lines += 1;
+            }
+        }
+    }
+
+    /// 解析一个 Gemini CLI 会话文件：整个文件是一个 JSON 对象，每条消息计为一行；
+    /// 格式版本无法识别的文件整个跳过并记入诊断，不按猜测的结构解析
+    fn print_gemini_session(&self, content: &str, result: &mut FileResult, collect: &mut ItemCollector) {
+        let Ok(session) = serde_json::from_str::<Value>(content) else {
+            result.stats.lines += 1;
+            result.stats.failed_lines += 1;
+            return;
+        };
+        let Some(messages) = gemini::parse_session(&session) else {
+            result.stats.unsupported_schema = true;
+            return;
+        };
+        for message in messages {
+            result.stats.lines += 1;
+            self.collect_parsed(gemini::parse_message(message), message, result, collect);
+        }
+    }
+
+    fn print_json_value(&self, value: &Value, result: &mut FileResult, collect: &mut ItemCollector) {
+        let item = match result.tool {
+            SourceTool::Claude => {
+                let session = &mut result.session;
+                parse_value(value, &self.parse_options, |entry| session.observe(entry))
+            }
+            SourceTool::Codex => result.codex.parse_value(value),
+            SourceTool::Gemini => gemini::parse_message(value),
+            SourceTool::Custom => self.field_mapping.as_ref().and_then(|mapping| mapping.parse_value(value)),
+        };
+        self.collect_parsed(item, value, result, collect);
+    }
+
+    /// 按过滤条件计入由 value 解析得到的 item；没有得到 item 或未计入的记录按 value 的 type 字段归入跳过的记录
+    fn collect_parsed(&self, item: Option<Item>, value: &Value, result: &mut FileResult, collect: &mut ItemCollector) {
+        if let Some(item) = item {
+            if !self.plausible_date(&item.timestamp) {
+                result.stats.quarantined.push(QuarantinedEntry {
+                    line: (result.line > 0).then_some(result.line),
+                    timestamp: item.timestamp,
+                });
+                return;
+            }
+            if !self.filters.iter().all(|filter| filter(&item)) {
+                // 没有使用量的记录本来就不计入统计，仍按类型归入跳过的记录
+                if item.usage.is_some() {
+                    result.stats.filtered += 1;
+                    if self.explain_date.is_some() && self.explains(&item.get_timestamp_key(&self.timezone)) {
+                        result.explained.filtered += 1;
+                    }
+                    return;
+                }
+            } else if result.is_duplicate(&item) {
+                self.collect_duplicate(&item, result);
+                return;
+            } else if let Some(deferred) = &mut result.deferred
+                && item.usage.is_some() {
+                // 是否与之前的块重复要到重放时才知道，重复时再从 entries 中扣除
+                result.stats.entries += 1;
+                deferred.push(item);
+                return;
+            } else if collect(item, result) {
+                result.stats.entries += 1;
+                return;
+            }
+        }
+
+        // 未计入统计的行按其 type 字段分类
+        let entry_type = value.get("type").and_then(|t| t.as_str()).unwrap_or("unknown");
+        result.stats.record_skipped(entry_type);
+    }
+    
+    /// 条目的时间戳键；时间戳不是带时区的 RFC 3339 格式时按 get_timestamp_key 的规则处理
+    fn date_key(&self, item: &Item, timestamp: Option<DateTime<Utc>>, result: &mut FileResult) -> Name {
+        match timestamp {
+            Some(timestamp) => result.names.time(timestamp, TimeKey::Day, &self.timezone),
+            None => result.names.name(&item.get_timestamp_key(&self.timezone)),
+        }
+    }
+
+    /// 计入条目中的工具调用与 stop_reason，二者与是否有使用量无关。
+    /// 流式记录把同一条回复的各内容块分行写入，message.id 重复的记录中同样要计入
+    fn count_calls(item: &Item, date: &Name, result: &mut FileResult) {
+        for tool in &item.tools {
+            let tool = result.names.name(tool);
+            *result.tools.entry((date.clone(), tool)).or_insert(0) += 1;
+        }
+        if let Some(stop_reason) = &item.stop_reason {
+            let stop_reason = result.names.name(stop_reason);
+            *result.stop_reasons.entry((date.clone(), stop_reason)).or_insert(0) += 1;
+        }
+    }
+
+    /// 计入一条 message.id 重复的条目：用量已随第一条计入，只计工具调用与 stop_reason
+    fn collect_duplicate(&self, item: &Item, result: &mut FileResult) {
+        result.stats.duplicates += 1;
+        let date = self.date_key(item, item.parsed_timestamp(), result);
+        Self::count_calls(item, &date, result);
+    }
+
+    /// 将条目计入聚合结果，返回是否被计入（没有使用量的条目不计入）
+    fn collect_item(&self, item: Item, result: &mut FileResult) -> bool {
+        let timestamp = item.parsed_timestamp();
+        let model = result.names.name(&item.model);
+        let date = self.date_key(&item, timestamp, result);
+        Self::count_calls(&item, &date, result);
+
+        if let Some(usage) = item.usage {
+            for field in usage.missing_fields() {
+                result.stats.record_missing_field(field);
+            }
+            if self.collect_message_sizes {
+                result.message_sizes.push(MessageSize {
+                    model: model.to_string(),
+                    date: date.to_string(),
+                    output_tokens: usage.output_tokens.unwrap_or(0),
+                    total_tokens: usage.total(),
+                });
+            }
+            if self.collect_latencies
+                && let Some(duration_ms) = item.duration_ms {
+                result.latencies.push(MessageLatency {
+                    model: model.to_string(),
+                    date: date.to_string(),
+                    duration_ms,
+                    ttft_ms: item.ttft_ms,
+                    output_tokens: usage.output_tokens.unwrap_or(0),
+                });
+            }
+            let aggregate = UsageAggregate::from_usage(usage, timestamp);
+            let version = result.names.name(item.version.as_deref().unwrap_or("(unknown)"));
+            let branch = result.names.name(item.git_branch.as_deref().filter(|branch| !branch.is_empty()).unwrap_or("(none)"));
+            Self::add_aggregate(&mut result.versions, (version, date.clone()), &aggregate);
+            Self::add_aggregate(&mut result.branches, (branch, date.clone()), &aggregate);
+            if let Some(timestamp) = timestamp {
+                let hour = result.names.time(timestamp, TimeKey::Hour, &self.timezone);
+                Self::add_aggregate(&mut result.hourly, (model.clone(), hour), &aggregate);
+                if timestamp >= self.recent_cutoff {
+                    let minute = result.names.time(timestamp, TimeKey::Minute, &Tz::UTC);
+                    Self::add_aggregate(&mut result.recent, (model.clone(), minute), &aggregate);
+                }
+            }
+            // 只有与项目目录编码一致的 cwd 才能作为项目路径，子目录等情况留待整个文件解析后确定；
+            // Codex 与按映射解析的日志不按项目分目录，cwd 即项目路径
+            let project = item.cwd
+                .filter(|cwd| {
+                    matches!(result.tool, SourceTool::Codex | SourceTool::Custom) || result.project_dir.as_deref() == Some(encode_project_path(cwd).as_str())
+                })
+                .map(|cwd| result.names.name(&cwd));
+            Self::add_aggregate(&mut result.pending_projects, (project, model.clone(), date.clone()), &aggregate);
+            if self.explains(&date) {
+                Self::add_aggregate(&mut result.explained.models, model.clone(), &aggregate);
+            }
+            Self::add_aggregate(&mut result.aggregates, (model, date), &aggregate);
+            true
+        } else {
+            if self.explains(&date) {
+                result.explained.no_usage += 1;
+            }
+            false
+        }
+    }
+
+    /// 记录的日期是否在 with_timestamp_validation 的范围内；时间戳无法解析的记录不在这里排除
+    fn plausible_date(&self, timestamp: &str) -> bool {
+        let Some((floor, latest)) = self.valid_dates else {
+            return true;
+        };
+        let date = match timestamp.parse::<DateTime<Utc>>() {
+            Ok(timestamp) => timestamp.with_timezone(&self.timezone).date_naive(),
+            Err(_) => match bucket(timestamp, Granularity::Day, &self.timezone).ok().and_then(|key| parse_date_key(&key.to_string())) {
+                Some(date) => date,
+                None => return true,
+            },
+        };
+        date >= floor && date <= latest
+    }
+
+    /// 之前隔离的记录中是否有按现在的范围不再隔离的：上限是解析时的明天，
+    /// 之后再用缓存或续扫的结果会一直漏掉这些记录，需要重新解析整个文件
+    fn releases_quarantined(&self, stats: &FileStats) -> bool {
+        stats.quarantined.iter().any(|entry| self.plausible_date(&entry.timestamp))
+    }
+
+    /// 时间戳键是否为 with_explain 指定的日期
+    fn explains(&self, date: &str) -> bool {
+        self.explain_date.as_deref() == Some(date)
+    }
+
+    /// 在原位累加；键只在第一次出现时移入，使用量只在插入时复制
+    fn add_aggregate<K: Eq + std::hash::Hash>(aggregates: &mut HashMap<K, UsageAggregate>, key: K, aggregate: &UsageAggregate) {
+        aggregates
+            .entry(key)
+            .and_modify(|existing| *existing += aggregate)
+            .or_insert_with(|| aggregate.clone());
+    }
+
+    fn merge_counts(target: &DashMap<(String, String), u64>, counts: DailyCounts) {
+        for ((date, name), count) in counts {
+            *target.entry((date.to_string(), name.to_string())).or_insert(0) += count;
+        }
+    }
+
+    fn merge_cached_counts(target: &DashMap<(String, String), u64>, counts: Vec<CachedCount>) {
+        for entry in counts {
+            *target.entry((entry.date, entry.name)).or_insert(0) += entry.count;
+        }
+    }
+
+    fn to_cached_counts(counts: &DailyCounts) -> Vec<CachedCount> {
+        counts
+            .iter()
+            .map(|((date, name), count)| CachedCount {
+                date: date.to_string(),
+                name: name.to_string(),
+                count: *count,
+            })
+            .collect()
+    }
+
+    fn sorted_counts(counts: &DashMap<(String, String), u64>) -> Vec<DailyCount> {
+        let mut sorted: Vec<DailyCount> = counts
+            .iter()
+            .map(|entry| {
+                let (date, name) = entry.key().clone();
+                DailyCount { date, name, count: *entry.value() }
+            })
+            .collect();
+        sorted.sort_by(|a, b| (&a.date, &a.name).cmp(&(&b.date, &b.name)));
+        sorted
+    }
+
+    fn merge_aggregate<K: Eq + std::hash::Hash>(target: &DashMap<K, UsageAggregate>, key: K, aggregate: UsageAggregate) {
+        // DashMap 提供了更高效的并发访问
+        target
+            .entry(key)
+            .and_modify(|existing| *existing += &aggregate)
+            .or_insert(aggregate);
+    }
+
+    fn to_cached_usage(aggregates: &FileAggregates) -> Vec<CachedUsage> {
+        aggregates
+            .iter()
+            .map(|((name, date), aggregate)| CachedUsage {
+                name: name.to_string(),
+                date: date.to_string(),
+                project: None,
+                session: None,
+                aggregate: aggregate.clone(),
+            })
+            .collect()
+    }
+    
+    fn get_merged_results(&self) -> Vec<DailyUsage> {
+        // 按 UsageKey 的顺序（模型、时间戳）排序
+        Self::sorted_aggregates(&self.collected_items, |key, usage| DailyUsage { date: key.date, model: key.model, usage })
+    }
+
+    /// 按键排序后转换为 DailyUsage 或 GroupUsage
+    fn sorted_aggregates<K: Ord + Clone + Eq + std::hash::Hash, T>(
+        aggregates: &DashMap<K, UsageAggregate>,
+        convert: impl Fn(K, UsageAggregate) -> T,
+    ) -> Vec<T> {
+        // 直接从 DashMap 转换为 Vec，无需锁
+        let mut sorted_items: Vec<_> = aggregates
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect();
+        
+        sorted_items.sort_by(|a, b| a.0.cmp(&b.0));
+        
+        sorted_items.into_iter().map(|(key, aggregate)| convert(key, aggregate)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use crate::item::Usage;
+    use super::*;
+
+    const MODEL: &str = "claude-sonnet-4-20250514";
+
+    /// 一条带用量的助手记录
+    fn assistant(id: &str, model: &str, timestamp: &str, output_tokens: u64) -> String {
+        format!(
+            r#"{{"type":"assistant","timestamp":"{}","sessionId":"s1","message":{{"id":"{}","model":"{}","usage":{{"input_tokens":10,"output_tokens":{}}}}}}}"#,
+            timestamp, id, model, output_tokens
+        )
+    }
+
+    /// 在数据目录 directory 的项目目录中写入会话文件 name.jsonl，返回其路径
+    fn write_session(directory: &Path, name: &str, lines: &[String]) -> PathBuf {
+        let project = directory.join("-home-dev-app");
+        fs::create_dir_all(&project).unwrap();
+        let path = project.join(format!("{}.jsonl", name));
+        fs::write(&path, lines.iter().map(|line| format!("{}\n", line)).collect::<String>()).unwrap();
+        path
+    }
+
+    fn output_tokens(usage: &[DailyUsage]) -> u64 {
+        usage.iter().map(|daily| daily.usage.usage.output_tokens.unwrap_or(0)).sum()
+    }
+
+    #[test]
+    fn missing_data_directory_is_an_error() {
+        let root = tempfile::tempdir().unwrap();
+        let missing = root.path().join("projects");
+        match FileProcessor::new(missing.clone()).process_files() {
+            Err(ProcessError::MissingDirectory(path)) => assert_eq!(path, missing),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn missing_paths_are_listed() {
+        let root = tempfile::tempdir().unwrap();
+        let existing = write_session(root.path(), "s1", &[assistant("msg_1", MODEL, "2025-01-01T10:00:00Z", 1)]);
+        let missing = root.path().join("gone.jsonl");
+        let result = FileProcessor::builder(root.path().to_path_buf())
+            .with_paths(vec![existing, missing.clone()])
+            .build()
+            .process_files();
+        match result {
+            Err(ProcessError::MissingPaths(paths)) => assert_eq!(paths, vec![missing]),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn unreadable_data_directory_is_an_io_error() {
+        let root = tempfile::tempdir().unwrap();
+        let file = root.path().join("projects");
+        fs::write(&file, "not a directory").unwrap();
+        let error = FileProcessor::new(file.clone()).process_files().map(|_| ()).unwrap_err();
+        assert!(matches!(&error, ProcessError::Io { path, .. } if *path == file));
+        assert!(std::error::Error::source(&error).is_some());
+    }
+
+    #[test]
+    fn cancelled_scan_is_an_error() {
+        let root = tempfile::tempdir().unwrap();
+        write_session(root.path(), "s1", &[assistant("msg_1", MODEL, "2025-01-01T10:00:00Z", 1)]);
+        let cancelled = Arc::new(AtomicBool::new(true));
+        let result = FileProcessor::builder(root.path().to_path_buf()).with_cancel_flag(cancelled).build().process_files();
+        assert!(matches!(result, Err(ProcessError::Cancelled)));
+    }
+
+    #[test]
+    fn unreadable_file_is_a_diagnostic_not_an_error() {
+        let root = tempfile::tempdir().unwrap();
+        write_session(root.path(), "good", &[assistant("msg_1", MODEL, "2025-01-01T10:00:00Z", 100)]);
+        let broken = root.path().join("-home-dev-app/old.jsonl.gz");
+        fs::write(&broken, "not gzip").unwrap();
+
+        let output = FileProcessor::new(root.path().to_path_buf()).process_files().unwrap();
+
+        assert_eq!(output_tokens(&output.usage), 100);
+        assert_eq!(output.diagnostics.read_errors.len(), 1);
+        assert_eq!(output.diagnostics.read_errors[0].0, broken);
+    }
+
+    #[test]
+    fn activity_times_are_kept_per_day() {
+        let root = tempfile::tempdir().unwrap();
+        // 跨过 UTC 午夜的会话分属两天，各自有自己的最早/最晚时间
+        write_session(root.path(), "s1", &[
+            assistant("msg_1", MODEL, "2025-09-10T09:12:00Z", 1),
+            assistant("msg_2", MODEL, "2025-09-10T23:50:00Z", 1),
+            assistant("msg_3", MODEL, "2025-09-11T00:10:00Z", 1),
+            assistant("msg_4", MODEL, "2025-09-11T18:47:00Z", 1),
+        ]);
+
+        let output = FileProcessor::new(root.path().to_path_buf()).process_files().unwrap();
+
+        let activity: Vec<(String, String, String)> = output.usage
+            .iter()
+            .map(|daily| {
+                let time = |seen: Option<DateTime<Utc>>| seen.unwrap().format("%H:%M").to_string();
+                (daily.date.clone(), time(daily.usage.first_seen), time(daily.usage.last_seen))
+            })
+            .collect();
+        assert_eq!(activity, [
+            ("2025-09-10".to_string(), "09:12".to_string(), "23:50".to_string()),
+            ("2025-09-11".to_string(), "00:10".to_string(), "18:47".to_string()),
+        ]);
+        let json = serde_json::to_value(&output.usage[0]).unwrap();
+        assert_eq!(json["first_seen"], "2025-09-10T09:12:00Z");
+        assert_eq!(json["last_seen"], "2025-09-10T23:50:00Z");
+    }
+
+    #[test]
+    fn activity_is_split_at_midnight_in_the_chosen_timezone() {
+        let root = tempfile::tempdir().unwrap();
+        // 同一个 UTC 日期的记录在 America/New_York（UTC-4）跨过了本地的午夜
+        write_session(root.path(), "s1", &[
+            assistant("msg_1", MODEL, "2025-09-11T01:30:00Z", 1),
+            assistant("msg_2", MODEL, "2025-09-11T03:45:00Z", 1),
+            assistant("msg_3", MODEL, "2025-09-11T04:10:00Z", 1),
+        ]);
+
+        let tz = Tz::America__New_York;
+        let output = FileProcessor::builder(root.path().to_path_buf()).with_timezone(tz).build().process_files().unwrap();
+
+        let activity: Vec<(String, u64, String, String)> = output.usage
+            .iter()
+            .map(|daily| {
+                let time = |seen: Option<DateTime<Utc>>| seen.unwrap().with_timezone(&tz).format("%H:%M").to_string();
+                (daily.date.clone(), daily.usage.messages, time(daily.usage.first_seen), time(daily.usage.last_seen))
+            })
+            .collect();
+        assert_eq!(activity, [
+            ("2025-09-10".to_string(), 2, "21:30".to_string(), "23:45".to_string()),
+            ("2025-09-11".to_string(), 1, "00:10".to_string(), "00:10".to_string()),
+        ]);
+        let hours: Vec<&str> = output.hourly.iter().map(|hourly| hourly.date.as_str()).collect();
+        assert_eq!(hours, ["2025-09-10T21", "2025-09-10T23", "2025-09-11T00"]);
+        // 按 UTC 分组时三条记录都在同一天
+        let utc = FileProcessor::new(root.path().to_path_buf()).process_files().unwrap();
+        assert_eq!(utc.usage.len(), 1);
+        assert_eq!(utc.usage[0].date, "2025-09-11");
+    }
+
+    #[test]
+    fn cloud_model_ids_are_aggregated_with_the_anthropic_id() {
+        let root = tempfile::tempdir().unwrap();
+        write_session(root.path(), "s1", &[
+            assistant("msg_1", MODEL, "2025-09-10T09:00:00Z", 1),
+            assistant("msg_2", "us.anthropic.claude-sonnet-4-20250514-v1:0", "2025-09-10T10:00:00Z", 10),
+            assistant("msg_3", "claude-sonnet-4@20250514", "2025-09-10T11:00:00Z", 100),
+        ]);
+
+        let output = FileProcessor::new(root.path().to_path_buf()).process_files().unwrap();
+
+        assert_eq!(output.usage.len(), 1);
+        assert_eq!(output.usage[0].model, MODEL);
+        assert_eq!(output.usage[0].usage.messages, 3);
+        assert_eq!(output_tokens(&output.usage), 111);
+    }
+
+    /// 解析 content 作为唯一的会话文件，返回 (计入的输出 token, 计入的记录数, 无法解析的行数)
+    fn scan_content(content: impl AsRef<[u8]>) -> (u64, u64, u64) {
+        let root = tempfile::tempdir().unwrap();
+        let path = write_session(root.path(), "s1", &[]);
+        fs::write(&path, content).unwrap();
+        let output = FileProcessor::new(root.path().to_path_buf()).process_files().unwrap();
+        let stats = &output.diagnostics.files[0].1;
+        (output_tokens(&output.usage), stats.entries, stats.failed_lines)
+    }
+
+    /// 重新缩进为多行的 JSON
+    fn pretty(json: &str) -> String {
+        serde_json::to_string_pretty(&serde_json::from_str::<Value>(json).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn pretty_printed_array_export_is_parsed() {
+        let array = format!(
+            "[{}, {}]",
+            assistant("msg_1", MODEL, "2025-09-10T09:00:00Z", 100),
+            assistant("msg_2", MODEL, "2025-09-10T10:00:00Z", 20)
+        );
+        assert!(pretty(&array).starts_with("[\n"));
+        assert_eq!(scan_content(pretty(&array)), (120, 2, 0));
+    }
+
+    #[test]
+    fn pretty_printed_object_export_is_parsed() {
+        let object = pretty(&assistant("msg_1", MODEL, "2025-09-10T09:00:00Z", 100));
+        assert!(object.lines().count() > 1);
+        assert_eq!(scan_content(&object), (100, 1, 0));
+    }
+
+    #[test]
+    fn jsonl_is_parsed_line_by_line() {
+        let jsonl = format!(
+            "{}\n{}\n",
+            assistant("msg_1", MODEL, "2025-09-10T09:00:00Z", 100),
+            assistant("msg_2", MODEL, "2025-09-10T10:00:00Z", 20)
+        );
+        assert_eq!(scan_content(&jsonl), (120, 2, 0));
+    }
+
+    #[test]
+    fn garbage_is_counted_as_failed_lines() {
+        assert_eq!(scan_content("not json\n{ broken\n\n]\n"), (0, 0, 3));
+    }
+
+    /// 两条记录，输出 token 分别为 100 与 20
+    fn two_records(separator: &str) -> Vec<u8> {
+        format!(
+            "{}{}{}{}",
+            assistant("msg_1", MODEL, "2025-09-10T09:00:00Z", 100),
+            separator,
+            assistant("msg_2", MODEL, "2025-09-10T10:00:00Z", 20),
+            separator
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn leading_bom_is_ignored() {
+        let content = [UTF8_BOM, &two_records("\n")].concat();
+        assert_eq!(scan_content(&content), (120, 2, 0));
+        // 只有一个对象的文件同样能整体解析
+        let object = [UTF8_BOM, assistant("msg_1", MODEL, "2025-09-10T09:00:00Z", 7).as_bytes()].concat();
+        assert_eq!(scan_content(&object), (7, 1, 0));
+    }
+
+    #[test]
+    fn crlf_line_endings_are_accepted() {
+        assert_eq!(scan_content(two_records("\r\n")), (120, 2, 0));
+    }
+
+    #[test]
+    fn invalid_utf8_only_loses_its_own_line() {
+        let mut content = two_records("\n");
+        content.extend_from_slice(b"{\"type\":\"user\",\"message\":\"\xff\xfe\"}\n\xc3\x28 garbage\n");
+        content.extend_from_slice(assistant("msg_3", MODEL, "2025-09-10T11:00:00Z", 3).as_bytes());
+        // 非法字节替换为 U+FFFD 后第一行仍是合法的 JSON，只有第二行无法解析
+        assert_eq!(scan_content(&content), (123, 3, 1));
+    }
+
+    #[test]
+    fn streamed_input_tolerates_bom_crlf_and_invalid_bytes() {
+        let mut content = [UTF8_BOM, &two_records("\r\n")].concat();
+        content.extend_from_slice(b"\xff\xfe\r\n");
+        content.extend_from_slice(assistant("msg_3", MODEL, "2025-09-10T11:00:00Z", 3).as_bytes());
+
+        let root = tempfile::tempdir().unwrap();
+        let output = FileProcessor::new(root.path().to_path_buf()).process_reader(io::Cursor::new(content), "<stdin>").unwrap();
+
+        assert_eq!(output_tokens(&output.usage), 123);
+        assert_eq!(output.diagnostics.files[0].1.failed_lines, 1);
+    }
+
+    /// 在 root 中用 configure 配置的 FileProcessor 扫描
+    fn scan_with(root: &Path, configure: impl FnOnce(FileProcessorBuilder) -> FileProcessorBuilder) -> ProcessOutput {
+        configure(FileProcessor::builder(root.to_path_buf())).build().process_files().unwrap()
+    }
+
+    #[test]
+    fn new_matches_the_builder_defaults() {
+        let root = tempfile::tempdir().unwrap();
+        write_session(root.path(), "s1", &[
+            assistant("msg_1", MODEL, "2025-09-10T09:00:00Z", 100),
+            assistant("msg_2", MODEL, "2001-01-01T00:00:00Z", 1),
+        ]);
+        let new = FileProcessor::new(root.path().to_path_buf()).process_files().unwrap();
+        let built = scan_with(root.path(), |builder| builder);
+        assert_eq!(output_tokens(&new.usage), 100);
+        assert_eq!(output_tokens(&built.usage), output_tokens(&new.usage));
+        assert_eq!(new.diagnostics.quarantined().count(), built.diagnostics.quarantined().count());
+        assert!(new.message_sizes.is_empty() && new.latencies.is_empty() && new.attributions.is_empty());
+    }
+
+    #[test]
+    fn extra_extensions_and_sniffing_accept_more_files() {
+        let root = tempfile::tempdir().unwrap();
+        let project = root.path().join("-home-dev-app");
+        fs::create_dir_all(&project).unwrap();
+        let records = |first: &str, second: &str, tokens: u64| {
+            format!(
+                "{}\n{}\n",
+                assistant(first, MODEL, "2025-09-10T09:00:00Z", tokens),
+                assistant(second, MODEL, "2025-09-10T10:00:00Z", tokens)
+            )
+        };
+        fs::write(project.join("a.ndjson"), records("msg_1", "msg_2", 5)).unwrap();
+        // 内容嗅探需要开头至少两行 JSON 对象
+        fs::write(project.join("b.log"), records("msg_3", "msg_4", 1)).unwrap();
+
+        let default = scan_with(root.path(), |builder| builder);
+        assert_eq!(output_tokens(&default.usage), 0);
+        assert_eq!(default.diagnostics.skipped_files.values().sum::<u64>(), 2);
+        let extended = scan_with(root.path(), |builder| builder.with_extra_extensions(vec![".ndjson".to_string()]));
+        assert_eq!(output_tokens(&extended.usage), 10);
+        let sniffed = scan_with(root.path(), |builder| builder.with_content_sniffing(true));
+        assert_eq!(output_tokens(&sniffed.usage), 12);
+    }
+
+    #[test]
+    fn message_sizes_latencies_and_explain_are_opt_in() {
+        let root = tempfile::tempdir().unwrap();
+        let timed = assistant("msg_2", MODEL, "2025-09-10T10:00:00Z", 20).replacen("{", r#"{"durationMs":1500,"#, 1);
+        write_session(root.path(), "s1", &[assistant("msg_1", MODEL, "2025-09-10T09:00:00Z", 100), timed]);
+
+        let output = scan_with(root.path(), |builder| {
+            builder
+                .with_message_sizes(true)
+                .with_latencies(true)
+                .with_explain(NaiveDate::from_ymd_opt(2025, 9, 10))
+        });
+
+        assert_eq!(output.message_sizes.len(), 2);
+        assert_eq!(output.latencies.len(), 1);
+        assert_eq!(output.latencies[0].duration_ms, 1500);
+        assert_eq!(output.attributions.len(), 1);
+        assert_eq!(output.attributions[0].entries, 2);
+    }
+
+    #[test]
+    fn max_file_size_skips_larger_files() {
+        let root = tempfile::tempdir().unwrap();
+        let path = write_session(root.path(), "s1", &[assistant("msg_1", MODEL, "2025-09-10T09:00:00Z", 100)]);
+
+        let output = scan_with(root.path(), |builder| builder.with_max_file_size(10));
+        assert_eq!(output_tokens(&output.usage), 0);
+        assert_eq!(output.diagnostics.oversized_files, vec![(path.clone(), fs::metadata(&path).unwrap().len())]);
+        let unlimited = scan_with(root.path(), |builder| builder.with_max_file_size(0));
+        assert_eq!(output_tokens(&unlimited.usage), 100);
+    }
+
+    #[test]
+    fn timestamp_validation_can_be_disabled() {
+        let root = tempfile::tempdir().unwrap();
+        write_session(root.path(), "s1", &[assistant("msg_1", MODEL, "2001-01-01T00:00:00Z", 1)]);
+
+        assert_eq!(output_tokens(&scan_with(root.path(), |builder| builder).usage), 0);
+        let later_floor = scan_with(root.path(), |builder| builder.with_timestamp_validation(NaiveDate::from_ymd_opt(2000, 1, 1)));
+        assert_eq!(output_tokens(&later_floor.usage), 1);
+        assert_eq!(output_tokens(&scan_with(root.path(), |builder| builder.with_timestamp_validation(None)).usage), 1);
+    }
+
+    #[test]
+    fn parse_options_can_skip_synthetic_records() {
+        let root = tempfile::tempdir().unwrap();
+        write_session(root.path(), "s1", &[
+            assistant("msg_1", MODEL, "2025-09-10T09:00:00Z", 100),
+            assistant("msg_2", "<synthetic>", "2025-09-10T10:00:00Z", 1),
+        ]);
+
+        assert_eq!(output_tokens(&scan_with(root.path(), |builder| builder).usage), 101);
+        let options = ParseOptions { skip_synthetic: true, ..ParseOptions::default() };
+        assert_eq!(output_tokens(&scan_with(root.path(), |builder| builder.with_parse_options(options)).usage), 100);
+    }
+
+    #[test]
+    fn event_callback_sees_the_whole_scan() {
+        let root = tempfile::tempdir().unwrap();
+        write_session(root.path(), "first", &[assistant("msg_1", MODEL, "2025-09-10T09:00:00Z", 1)]);
+        write_session(root.path(), "second", &[assistant("msg_2", MODEL, "2025-09-10T10:00:00Z", 1)]);
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = Arc::clone(&events);
+        scan_with(root.path(), |builder| {
+            builder.with_event_callback(move |event| {
+                let name = match event {
+                    ProcessEvent::ScanStarted { file_count } => format!("started {}", file_count),
+                    ProcessEvent::FileProcessed { entries, .. } => format!("file {}", entries),
+                    ProcessEvent::Finished { .. } => "finished".to_string(),
+                    _ => "other".to_string(),
+                };
+                recorded.lock().unwrap().push(name);
+            })
+        });
+
+        assert_eq!(*events.lock().unwrap(), ["started 2", "file 1", "file 1", "finished"]);
+    }
+
+    #[test]
+    fn aggregation_sums_every_entry_per_model_and_day() {
+        let root = tempfile::tempdir().unwrap();
+        let models = [MODEL, "claude-opus-4-1-20250805", "claude-3-5-haiku-20241022"];
+        let mut expected: BTreeMap<(String, String), (u64, u64)> = BTreeMap::new();
+        for session in 0..4 {
+            let lines: Vec<String> = (0..250u64)
+                .map(|i| {
+                    let model = models[(i as usize + session) % models.len()];
+                    let date = format!("2025-09-{:02}", 10 + i % 3);
+                    let output_tokens = i * 7 % 101 + session as u64;
+                    let (entries, tokens) = expected.entry((model.to_string(), date.clone())).or_default();
+                    *entries += 1;
+                    *tokens += output_tokens;
+                    assistant(&format!("msg_{}_{}", session, i), model, &format!("{}T09:{:02}:00Z", date, i % 60), output_tokens)
+                })
+                .collect();
+            write_session(root.path(), &format!("s{}", session), &lines);
+        }
+
+        let output = scan_with(root.path(), |builder| builder);
+        let actual: BTreeMap<(String, String), (u64, u64)> = output
+            .usage
+            .iter()
+            .map(|daily| {
+                let usage = &daily.usage.usage;
+                // 所有记录都没有缓存字段，合计后仍为未上报
+                assert_eq!((usage.cache_creation_input_tokens, usage.cache_read_input_tokens), (None, None));
+                assert_eq!(usage.input_tokens, Some(10 * daily.usage.messages));
+                ((daily.model.clone(), daily.date.clone()), (daily.usage.messages, usage.output_tokens.unwrap()))
+            })
+            .collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn openai_usage_names_aggregate_like_anthropic_names() {
+        let line = |id: &str, hour: u32, usage: &str| {
+            format!(
+                r#"{{"type":"assistant","timestamp":"2025-09-10T{:02}:00:00Z","sessionId":"s1","message":{{"id":"{}","model":"{}","usage":{}}}}}"#,
+                hour, id, MODEL, usage
+            )
+        };
+        let scan = |prompt: &str, completion: &str| {
+            let root = tempfile::tempdir().unwrap();
+            let lines: Vec<String> = (0..5u32)
+                .map(|i| {
+                    let usage = format!(r#"{{"{}":{},"{}":{}}}"#, prompt, 100 + i, completion, 7 * i);
+                    line(&format!("msg_{}", i), 9 + i, &usage)
+                })
+                .collect();
+            write_session(root.path(), "s1", &lines);
+            scan_with(root.path(), |builder| builder)
+        };
+
+        let anthropic = scan("input_tokens", "output_tokens");
+        let openai = scan("prompt_tokens", "completion_tokens");
+        for output in [&anthropic, &openai] {
+            assert_eq!(output.usage.len(), 1);
+            let usage = &output.usage[0].usage;
+            assert_eq!(usage.messages, 5);
+            assert_eq!(usage.usage, Usage {
+                input_tokens: Some(510),
+                output_tokens: Some(70),
+                cache_creation_input_tokens: None,
+                cache_read_input_tokens: None,
+            });
+            assert_eq!(output.diagnostics.files[0].1.missing_fields.keys().collect::<Vec<_>>(), ["cache_creation_input_tokens", "cache_read_input_tokens"]);
+        }
+        assert_eq!(serde_json::to_value(&openai.usage).unwrap(), serde_json::to_value(&anthropic.usage).unwrap());
+        assert_eq!(serde_json::to_value(&openai.hourly).unwrap(), serde_json::to_value(&anthropic.hourly).unwrap());
+    }
+
+    #[test]
+    fn repeated_message_ids_are_counted_once() {
+        let root = tempfile::tempdir().unwrap();
+        // 流式记录：同一条回复的文本块与 tool_use 块分两行写入，带有相同的 message.id 与用量
+        let tool_block = format!(
+            r#"{{"type":"assistant","timestamp":"2025-09-10T09:00:01Z","sessionId":"s1","message":{{"id":"msg_1","model":"{}","stop_reason":"tool_use","content":[{{"type":"tool_use","id":"t1","name":"Read","input":{{}}}}],"usage":{{"input_tokens":10,"output_tokens":100}}}}}}"#,
+            MODEL
+        );
+        write_session(root.path(), "s1", &[
+            assistant("msg_1", MODEL, "2025-09-10T09:00:00Z", 100),
+            tool_block,
+            assistant("msg_2", MODEL, "2025-09-10T10:00:00Z", 50),
+            assistant("msg_2", MODEL, "2025-09-10T10:00:00Z", 50),
+        ]);
+
+        let output = scan_with(root.path(), |builder| builder);
+        assert_eq!(output.usage.len(), 1);
+        assert_eq!(output.usage[0].usage.messages, 2);
+        assert_eq!(output_tokens(&output.usage), 150);
+        assert_eq!(output.usage[0].usage.usage.input_tokens, Some(20));
+        assert_eq!(output.sessions[0].assistant_messages, 2);
+        assert_eq!(output.project_models[0].aggregate.messages, 2);
+        // 重复记录中的工具调用照常计数
+        assert_eq!(output.tools.iter().map(|count| (count.name.as_str(), count.count)).collect::<Vec<_>>(), [("Read", 1)]);
+        assert_eq!(output.diagnostics.total_entries(), 2);
+        assert_eq!(output.diagnostics.total_duplicates(), 2);
+        assert!(output.diagnostics.files[0].1.skipped.is_empty());
+    }
+
+    #[test]
+    fn every_filter_must_accept_an_entry() {
+        let root = tempfile::tempdir().unwrap();
+        write_session(root.path(), "s1", &[
+            assistant("msg_1", MODEL, "2025-09-10T09:00:00Z", 100),
+            assistant("msg_2", "claude-opus-4-1-20250805", "2025-09-10T10:00:00Z", 50),
+        ]);
+        write_session(root.path(), "s2", &[assistant("msg_3", MODEL, "2025-09-12T09:00:00Z", 7)]);
+        let configure = |builder: FileProcessorBuilder| {
+            builder
+                .with_filter(|item| item.model == MODEL)
+                .with_filter(|item| item.timestamp.as_str() < "2025-09-11")
+        };
+
+        let output = scan_with(root.path(), configure);
+        assert_eq!(output_tokens(&output.usage), 100);
+        assert_eq!(output.diagnostics.total_entries(), 1);
+        assert_eq!(output.diagnostics.total_filtered(), 2);
+        let unfiltered = scan_with(root.path(), |builder| builder);
+        assert_eq!(unfiltered.diagnostics.total_entries(), 3);
+        assert_eq!(unfiltered.diagnostics.total_filtered(), 0);
+
+        let items: Vec<Item> = configure(FileProcessor::builder(root.path().to_path_buf())).build().iter_items().unwrap().collect();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].timestamp, "2025-09-10T09:00:00Z");
+    }
+
+    #[test]
+    fn filtered_scans_bypass_the_cache() {
+        let root = tempfile::tempdir().unwrap();
+        let cache_path = root.path().join("cache.json");
+        write_session(root.path(), "s1", &[
+            assistant("msg_1", MODEL, "2025-09-10T09:00:00Z", 100),
+            assistant("msg_2", MODEL, "2025-09-11T09:00:00Z", 50),
+        ]);
+        let cached = || fs::read_to_string(&cache_path).unwrap_or_default().contains("s1.jsonl");
+        let scan = |filtered: bool| {
+            let mut builder = FileProcessor::builder(root.path().to_path_buf())
+                .with_cache(FileCache::load(cache_path.clone()).unwrap());
+            if filtered {
+                builder = builder.with_filter(|item| item.timestamp.starts_with("2025-09-10"));
+            }
+            output_tokens(&builder.build().process_files().unwrap().usage)
+        };
+
+        // 过滤后的结果不写入缓存，缓存中未过滤的结果也不用于过滤的扫描
+        assert_eq!(scan(true), 100);
+        assert!(!cached());
+        assert_eq!(scan(false), 150);
+        assert!(cached());
+        assert_eq!(scan(true), 100);
+        assert_eq!(scan(false), 150);
+    }
+
+    #[test]
+    fn panic_in_one_file_does_not_stop_the_others() {
+        let root = tempfile::tempdir().unwrap();
+        write_session(root.path(), "first", &[assistant("msg_1", MODEL, "2025-01-01T10:00:00Z", 100)]);
+        write_session(root.path(), "second", &[assistant("msg_2", MODEL, "2025-01-01T11:00:00Z", 50)]);
+        let bad = write_session(root.path(), "bad", &[assistant("msg_3", "claude-boom", "2025-01-01T12:00:00Z", 1000)]);
+
+        let output = FileProcessor::builder(root.path().to_path_buf())
+            .with_filter(|item| {
+                if item.model == "claude-boom" {
+                    panic!("injected panic");
+                }
+                true
+            })
+            .build()
+            .process_files()
+            .unwrap();
+
+        assert_eq!(output_tokens(&output.usage), 150);
+        assert_eq!(output.sessions.len(), 2);
+        assert_eq!(output.diagnostics.panicked_files, vec![(bad, "injected panic".to_string())]);
+    }
+
+    #[test]
+    fn poisoned_lock_does_not_cascade() {
+        let root = tempfile::tempdir().unwrap();
+        write_session(root.path(), "good", &[assistant("msg_1", MODEL, "2025-01-01T10:00:00Z", 100)]);
+        let bad = write_session(root.path(), "bad", &[assistant("msg_2", "claude-boom", "2025-01-01T11:00:00Z", 1000)]);
+        let processor = FileProcessor::builder(root.path().to_path_buf())
+            .with_filter(|item| {
+                if item.model == "claude-boom" {
+                    panic!("injected panic");
+                }
+                true
+            })
+            .build();
+
+        // 持有 panicked_files 的锁时 panic，随后记录这次 panic 时锁已被标记为 poisoned
+        let poisoning = PathBuf::from("poisoning");
+        let result = processor.isolate(&poisoning, || {
+            let _guard = processor.panicked_files.lock().unwrap();
+            panic!("panic while holding a lock");
+        });
+        assert!(result.is_none());
+        assert!(processor.panicked_files.is_poisoned());
+
+        let output = processor.process_files().unwrap();
+        assert_eq!(output_tokens(&output.usage), 100);
+        assert_eq!(
+            output.diagnostics.panicked_files,
+            vec![(bad, "injected panic".to_string()), (poisoning, "panic while holding a lock".to_string())]
+        );
+    }
+
+    #[test]
+    fn streamed_items_fold_to_the_batch_result() {
+        let root = tempfile::tempdir().unwrap();
+        write_session(root.path(), "small", &[
+            assistant("msg_1", MODEL, "2025-01-01T10:00:00Z", 100),
+            assistant("msg_2", "claude-opus-4-1-20250805", "2025-01-02T23:59:59Z", 7),
+            "not json".to_string(),
+        ]);
+        // 超过 MIN_PARALLEL_CHUNK 两倍的文件，两条路径都按块并行解析
+        let lines: Vec<String> = (0..12_000)
+            .map(|i| assistant(&format!("msg_big_{}", i), MODEL, &format!("2025-01-{:02}T12:00:00Z", i % 28 + 1), i % 500))
+            .collect();
+        write_session(root.path(), "large", &lines);
+        let processor = || {
+            FileProcessor::builder(root.path().to_path_buf())
+                .with_parallel_parse_threshold(1)
+                .build()
+        };
+
+        let batch: BTreeMap<(String, String), Usage> = processor()
+            .process_files()
+            .unwrap()
+            .usage
+            .into_iter()
+            .map(|daily| ((daily.model, daily.date), daily.usage.usage))
+            .collect();
+        let streamed = processor().iter_items().unwrap().fold(BTreeMap::new(), |mut totals: BTreeMap<_, Usage>, item| {
+            if let Some(usage) = &item.usage {
+                *totals.entry((item.model.clone(), item.get_timestamp_key(&Tz::UTC))).or_default() += usage;
+            }
+            totals
+        });
+
+        assert_eq!(batch.len(), 29);
+        assert_eq!(streamed, batch);
+    }
+
+    #[test]
+    fn chunked_parse_matches_sequential_parse() {
+        let root = tempfile::tempdir().unwrap();
+        let user = |i: usize| {
+            format!(
+                r#"{{"type":"user","timestamp":"2025-01-01T{:02}:00:00Z","sessionId":"s1","uuid":"u{}","message":{{"role":"user","content":"question {}"}}}}"#,
+                i % 24, i, i
+            )
+        };
+        let mut lines = Vec::new();
+        let mut length = 0;
+        let push = |lines: &mut Vec<String>, length: &mut usize, line: String| {
+            *length += line.len() + 1;
+            lines.push(line);
+        };
+        let mut i = 0;
+        // 每个 message.id 只计一次的输出 token 合计
+        let mut expected_output = 0;
+        while length < MIN_PARALLEL_CHUNK - 2048 {
+            push(&mut lines, &mut length, user(i));
+            push(&mut lines, &mut length, assistant(&format!("msg_{}", i), MODEL, &format!("2025-01-{:02}T12:00:00Z", i % 28 + 1), i as u64 % 300));
+            expected_output += i as u64 % 300;
+            i += 1;
+        }
+        // 这一行跨过第一块的边界，切分时应当整行留在第一块中
+        let padding = "x".repeat(8192);
+        let straddling = format!(
+            r#"{{"type":"assistant","timestamp":"2025-01-05T08:00:00Z","sessionId":"s1","message":{{"id":"msg_straddle","model":"{}","content":[{{"type":"text","text":"{}"}}],"usage":{{"input_tokens":10,"output_tokens":4242}}}}}}"#,
+            MODEL, padding
+        );
+        let (boundary, straddle_index) = (length, lines.len());
+        push(&mut lines, &mut length, straddling.clone());
+        expected_output += 4242;
+        // 之后的块中重复出现前面的消息，并夹杂无法解析的行
+        push(&mut lines, &mut length, assistant("msg_3", MODEL, "2025-01-04T12:00:00Z", 3));
+        push(&mut lines, &mut length, straddling);
+        push(&mut lines, &mut length, "not json".to_string());
+        while length < MIN_PARALLEL_CHUNK * 5 / 2 {
+            push(&mut lines, &mut length, user(i));
+            push(&mut lines, &mut length, assistant(&format!("msg_{}", i), "claude-opus-4-1-20250805", &format!("2025-02-{:02}T06:30:00Z", i % 28 + 1), i as u64 % 70));
+            expected_output += i as u64 % 70;
+            i += 1;
+        }
+        let path = write_session(root.path(), "s1", &lines);
+        let parse = |threshold| {
+            FileProcessor::builder(root.path().to_path_buf())
+                .with_parallel_parse_threshold(threshold)
+                .build()
+                .process_files()
+                .unwrap()
+        };
+
+        let chunked_processor = FileProcessor::builder(root.path().to_path_buf()).with_parallel_parse_threshold(1).build();
+        let content = fs::read_to_string(&path).unwrap();
+        let chunks = chunked_processor.split_chunks(&content, SourceTool::Claude);
+        assert!(chunks.len() >= 2);
+        assert!(boundary < MIN_PARALLEL_CHUNK && chunks[0].len() > MIN_PARALLEL_CHUNK);
+        assert!(chunks[0].ends_with(&format!("{}\n", lines[straddle_index])));
+        assert!(chunks.iter().all(|chunk| chunk.ends_with('\n')));
+        assert_eq!(chunks.concat(), content);
+
+        let sequential = parse(0);
+        let chunked = parse(1);
+        // 费用是浮点数之和，按块累加的顺序不同，只比较到分以下
+        let cost = |output: &ProcessOutput| output.sessions[0].cost_usd.unwrap();
+        assert!((cost(&chunked) - cost(&sequential)).abs() < 1e-6);
+        let json = |output: &ProcessOutput| {
+            let mut sessions = output.sessions.clone();
+            sessions.iter_mut().for_each(|session| session.cost_usd = None);
+            serde_json::json!({
+                "usage": output.usage,
+                "hourly": output.hourly,
+                "project_models": output.project_models,
+                "tools": output.tools,
+                "stop_reasons": output.stop_reasons,
+                "sessions": sessions,
+            })
+        };
+        assert_eq!(json(&chunked), json(&sequential));
+        assert_eq!(format!("{:?}", chunked.diagnostics.files), format!("{:?}", sequential.diagnostics.files));
+        assert_eq!(sequential.sessions[0].user_messages, i as u64);
+        // 后一块中重复的 msg_3 与 msg_straddle 都只计一次
+        for output in [&sequential, &chunked] {
+            assert_eq!(output_tokens(&output.usage), expected_output);
+            assert_eq!(output.usage.iter().map(|daily| daily.usage.messages).sum::<u64>(), i as u64 + 1);
+            assert_eq!(output.sessions[0].assistant_messages, i as u64 + 1);
+            assert_eq!(output.diagnostics.total_duplicates(), 2);
+            assert_eq!(output.diagnostics.total_entries(), i as u64 + 1);
+        }
+    }
+
+    #[test]
+    fn quarantined_future_records_are_counted_once_their_date_arrives() {
+        let root = tempfile::tempdir().unwrap();
+        let cache_path = root.path().join("cache/file_aggregates.json");
+        let future = (Utc::now() + TimeDelta::days(3)).format("%Y-%m-%dT%H:%M:%SZ").to_string();
+        write_session(root.path(), "s1", &[
+            assistant("msg_1", MODEL, "2025-01-01T10:00:00Z", 100),
+            assistant("msg_2", MODEL, &future, 7),
+            assistant("msg_3", MODEL, "2001-01-01T00:00:00Z", 1),
+        ]);
+        let offsets = FileOffsets::default();
+        // days_later 天之后再次扫描：日期上限随之后移
+        let scan = |days_later: i64, resume: bool| {
+            let mut builder = FileProcessor::builder(root.path().join("projects"))
+                .with_paths(vec![root.path().join("-home-dev-app")])
+                .with_cache(FileCache::load(cache_path.clone()).unwrap());
+            if resume {
+                builder = builder.with_resume_offsets(offsets.clone());
+            }
+            let mut processor = builder.build();
+            processor.valid_dates = processor.valid_dates.map(|(floor, latest)| (floor, latest + TimeDelta::days(days_later)));
+            processor.process_files().unwrap()
+        };
+        let quarantined = |output: &ProcessOutput| output.diagnostics.quarantined().count();
+
+        let output = scan(0, true);
+        assert_eq!((output_tokens(&output.usage), quarantined(&output)), (100, 2));
+        assert!(cache_path.exists());
+        let output = scan(0, true);
+        assert_eq!((output_tokens(&output.usage), quarantined(&output)), (100, 2));
+
+        // 缓存与续扫的位置中记录了当时被隔离的记录，日期到了之后不再使用，早于下限的记录仍被隔离
+        let output = scan(3, false);
+        assert_eq!((output_tokens(&output.usage), quarantined(&output)), (107, 1));
+        let output = scan(3, true);
+        assert_eq!((output_tokens(&output.usage), quarantined(&output)), (107, 1));
+    }
+
+    #[test]
+    fn resumed_scan_counts_each_appended_line_once() {
+        let root = tempfile::tempdir().unwrap();
+        let first = assistant("msg_1", MODEL, "2025-01-01T10:00:00Z", 100);
+        let second = assistant("msg_2", MODEL, "2025-01-01T11:00:00Z", 20);
+        let third = assistant("msg_3", MODEL, "2025-01-01T12:00:00Z", 3);
+        let path = write_session(root.path(), "s1", std::slice::from_ref(&first));
+        let offsets = FileOffsets::default();
+        let scan = || {
+            FileProcessor::builder(root.path().to_path_buf())
+                .with_resume_offsets(offsets.clone())
+                .build()
+                .process_files()
+                .unwrap()
+        };
+
+        // 写到一半的行既不计入，也不会因为位置越过它而丢失
+        let (head, tail) = second.split_at(second.len() / 2);
+        fs::write(&path, format!("{}\n{}", first, head)).unwrap();
+        assert_eq!(output_tokens(&scan().usage), 100);
+
+        fs::write(&path, format!("{}\n{}{}\n{}\n", first, head, tail, third)).unwrap();
+        let output = scan();
+        assert_eq!(output_tokens(&output.usage), 123);
+        assert_eq!(output.sessions.len(), 1);
+        // 没有新内容时结果不变
+        assert_eq!(output_tokens(&scan().usage), 123);
+    }
+
+    #[test]
+    fn concurrent_appends_are_neither_double_counted_nor_lost() {
+        use std::io::Write;
+        use std::sync::atomic::AtomicUsize;
+
+        let root = tempfile::tempdir().unwrap();
+        let path = write_session(root.path(), "s1", &[]);
+        let lines: Vec<String> = (0..300)
+            .map(|i| assistant(&format!("msg_{}", i), MODEL, "2025-01-01T10:00:00Z", 1))
+            .collect();
+        let offsets = FileOffsets::default();
+        let scan = || {
+            FileProcessor::builder(root.path().to_path_buf())
+                .with_resume_offsets(offsets.clone())
+                .build()
+                .process_files()
+                .unwrap()
+        };
+
+        // 已开始写入的行数：在每行第一次写入前增加，扫描时已写完的行一定都计入了这个数
+        let started = Arc::new(AtomicUsize::new(0));
+        let writer = {
+            let (path, started) = (path.clone(), Arc::clone(&started));
+            let lines = lines.clone();
+            thread::spawn(move || {
+                let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+                for (i, line) in lines.iter().enumerate() {
+                    // 每行分两次写入，切分的位置各不相同，扫描可能落在两次写入之间
+                    let (head, tail) = line.split_at(i * 7 % line.len());
+                    started.fetch_add(1, Ordering::SeqCst);
+                    file.write_all(head.as_bytes()).unwrap();
+                    file.flush().unwrap();
+                    thread::yield_now();
+                    file.write_all(format!("{}\n", tail).as_bytes()).unwrap();
+                    file.flush().unwrap();
+                }
+            })
+        };
+
+        let mut counted = 0;
+        while !writer.is_finished() {
+            let total = output_tokens(&scan().usage);
+            // 扫描结束后再读取：扫描期间开始写入的行也可能已被计入
+            let started = started.load(Ordering::SeqCst);
+            assert!(total >= counted, "count went down from {} to {}", counted, total);
+            assert!(total as usize <= started, "counted {} lines but only {} were started", total, started);
+            counted = total;
+        }
+        writer.join().unwrap();
+        assert_eq!(output_tokens(&scan().usage), lines.len() as u64);
+    }
+}
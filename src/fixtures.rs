@@ -0,0 +1,217 @@
+//! 合成的会话日志，供基准测试（benches/）与大输入的测试使用，也可通过 cccost gen-fixtures 生成到目录中。
+//!
+//! 目录结构与 ~/.claude/projects 相同：每个项目一个编码后的目录，每个会话一个 JSONL 文件。
+//! 每轮是一条用户消息和一条带工具调用与 token 用量的助手回复，模型、版本、分支、时间间隔和用量由种子决定，
+//! 同样的选项在任何环境下都生成逐字节相同的文件
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use chrono::{NaiveDate, NaiveTime, TimeDelta};
+use serde_json::json;
+use crate::project::encode_project_path;
+use crate::tr;
+
+/// 会话所属的项目，cwd 为 /home/dev/<名称>
+const PROJECTS: [&str; 6] = ["web-app", "api-server", "cli-tool", "data-pipeline", "infra", "docs"];
+
+/// 模型及其权重，大多数回复使用 Sonnet
+const MODELS: [(&str, u64); 4] = [
+    ("claude-sonnet-4-5-20250929", 60),
+    ("claude-sonnet-4-20250514", 15),
+    ("claude-opus-4-1-20250805", 15),
+    ("claude-haiku-4-5-20251001", 10),
+];
+
+const TOOLS: [&str; 8] = ["Bash", "Read", "Edit", "Write", "Grep", "Glob", "TodoWrite", "WebFetch"];
+
+const BRANCHES: [&str; 4] = ["main", "main", "feature/search", "fix/login-timeout"];
+
+/// 生成的选项
+#[derive(Debug, Clone)]
+pub struct FixtureOptions {
+    /// 第一天
+    pub start: NaiveDate,
+    /// 天数
+    pub days: u32,
+    /// 每天的会话数
+    pub sessions_per_day: u32,
+    /// 每个会话的轮数（一条用户消息和一条助手回复）
+    pub turns: u32,
+    /// 随机数种子
+    pub seed: u64,
+}
+
+impl Default for FixtureOptions {
+    /// 30 天、每天 20 个会话、每个会话 20 轮，约 2.4 万条记录
+    fn default() -> Self {
+        Self {
+            start: NaiveDate::from_ymd_opt(2025, 1, 1).unwrap_or_default(),
+            days: 30,
+            sessions_per_day: 20,
+            turns: 20,
+            seed: 0,
+        }
+    }
+}
+
+/// 生成结果的规模
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FixtureSummary {
+    /// 会话文件数
+    pub files: usize,
+    /// 写入的行数
+    pub lines: u64,
+    /// 写入的字节数
+    pub bytes: u64,
+}
+
+/// SplitMix64。不依赖外部的随机数库，输出不随依赖版本变化
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// [low, high) 中的整数
+    fn range(&mut self, low: u64, high: u64) -> u64 {
+        low + self.next() % (high - low).max(1)
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.next() as usize % items.len()]
+    }
+
+    fn weighted<'a>(&mut self, items: &'a [(&'a str, u64)]) -> &'a str {
+        let total: u64 = items.iter().map(|(_, weight)| weight).sum();
+        let mut roll = self.next() % total;
+        for (item, weight) in items {
+            if roll < *weight {
+                return item;
+            }
+            roll -= weight;
+        }
+        items[0].0
+    }
+
+    /// UUID 格式的 id
+    fn uuid(&mut self) -> String {
+        let (a, b) = (self.next(), self.next());
+        format!(
+            "{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}",
+            a >> 32,
+            (a >> 16) & 0xffff,
+            a & 0xfff,
+            ((b >> 48) & 0x3fff) | 0x8000,
+            b & 0xffff_ffff_ffff
+        )
+    }
+}
+
+/// 在 out 中生成会话日志。out 不存在时创建；已存在且不为空时返回错误，避免与其他文件混在一起
+pub fn generate(options: &FixtureOptions, out: &Path) -> io::Result<FixtureSummary> {
+    if out.read_dir().is_ok_and(|mut entries| entries.next().is_some()) {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, tr!("error.fixtures_not_empty", out.display())));
+    }
+    let mut rng = Rng(options.seed);
+    let mut summary = FixtureSummary::default();
+    for day in 0..options.days {
+        let date = options.start + TimeDelta::days(i64::from(day));
+        // 版本号大约每两周升级一次
+        let version = format!("2.0.{}", day / 14);
+        for _ in 0..options.sessions_per_day {
+            let project = *rng.pick(&PROJECTS);
+            let cwd = format!("/home/dev/{}", project);
+            let directory = out.join(encode_project_path(&cwd));
+            fs::create_dir_all(&directory)?;
+            let session_id = rng.uuid();
+            let mut writer = Counting::new(BufWriter::new(File::create(directory.join(format!("{}.jsonl", session_id)))?));
+
+            let branch = *rng.pick(&BRANCHES);
+            let start = NaiveTime::from_hms_opt(rng.range(7, 22) as u32, rng.range(0, 60) as u32, 0).unwrap_or_default();
+            let mut time = date.and_time(start).and_utc();
+            for turn in 0..options.turns {
+                let common = json!({
+                    "sessionId": session_id,
+                    "version": version,
+                    "gitBranch": branch,
+                    "cwd": cwd,
+                });
+                let user = json!({
+                    "type": "user",
+                    "timestamp": time.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+                    "uuid": rng.uuid(),
+                    "message": {"role": "user", "content": format!("Request {} in {}", turn + 1, project)},
+                });
+                writer.line(&merge(user, &common))?;
+
+                time += TimeDelta::milliseconds(rng.range(2_000, 90_000) as i64);
+                let tool = *rng.pick(&TOOLS);
+                let last = turn + 1 == options.turns;
+                let assistant = json!({
+                    "type": "assistant",
+                    "timestamp": time.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+                    "uuid": rng.uuid(),
+                    "message": {
+                        "id": format!("msg_{:024x}", u128::from(rng.next()) << 32 | u128::from(rng.next() >> 32)),
+                        "role": "assistant",
+                        "model": rng.weighted(&MODELS),
+                        "stop_reason": if last { "end_turn" } else { "tool_use" },
+                        "content": [
+                            {"type": "text", "text": "Working on it."},
+                            {"type": "tool_use", "id": format!("toolu_{:016x}", rng.next()), "name": tool, "input": {}},
+                        ],
+                        "usage": {
+                            "input_tokens": rng.range(1, 40),
+                            "output_tokens": rng.range(20, 2_000),
+                            "cache_creation_input_tokens": rng.range(0, 6_000),
+                            "cache_read_input_tokens": rng.range(5_000, 80_000),
+                        },
+                    },
+                });
+                writer.line(&merge(assistant, &common))?;
+                time += TimeDelta::milliseconds(rng.range(5_000, 300_000) as i64);
+            }
+            writer.inner.flush()?;
+            summary.files += 1;
+            summary.lines += writer.lines;
+            summary.bytes += writer.bytes;
+        }
+    }
+    Ok(summary)
+}
+
+/// 将 common 中的字段加入记录
+fn merge(mut record: serde_json::Value, common: &serde_json::Value) -> serde_json::Value {
+    if let (Some(record), Some(common)) = (record.as_object_mut(), common.as_object()) {
+        record.extend(common.iter().map(|(key, value)| (key.clone(), value.clone())));
+    }
+    record
+}
+
+/// 统计写入行数和字节数的写入器
+struct Counting<W: Write> {
+    inner: W,
+    lines: u64,
+    bytes: u64,
+}
+
+impl<W: Write> Counting<W> {
+    fn new(inner: W) -> Self {
+        Self { inner, lines: 0, bytes: 0 }
+    }
+
+    fn line(&mut self, record: &serde_json::Value) -> io::Result<()> {
+        let mut line = serde_json::to_string(record).map_err(io::Error::other)?;
+        line.push('\n');
+        self.inner.write_all(line.as_bytes())?;
+        self.lines += 1;
+        self.bytes += line.len() as u64;
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+//! 解析 Google Gemini CLI 的会话记录（~/.gemini/tmp/<项目哈希>/chats 下的 session-*.json）
+//!
+//! 与 Claude Code、Codex 的 JSONL 不同，Gemini CLI 的每个会话是一个完整的 JSON 对象，
+//! 消息在 messages 数组中，模型回复带有 tokens（或 API 原样的 usageMetadata）用量。
+//! [`parse_session`] 先确认文件的格式版本，再由 [`parse_message`] 逐条转换为与 Claude Code 相同的 [`Item`]：
+//! prompt 中未命中缓存的部分计为 input_tokens，cached 计为 cache_read_input_tokens，
+//! candidates 与 thoughts（同样按输出计费）合计为 output_tokens，cache_creation_input_tokens 记为 0。
+//!
+//! 模型名按 [`normalize_model`] 统一为 gemini-2.5-pro 这样的形式；内置价格表没有 Gemini 模型，
+//! 价格可以写在 [`crate::pricing::PricingTable`] 的价格文件中。
+
+use serde::Deserialize;
+use serde_json::Value;
+use crate::codex::UNKNOWN_MODEL;
+use crate::item::{Item, Usage};
+
+/// 能够识别的会话文件格式版本；没有版本字段的文件视为版本 1
+pub const SUPPORTED_SCHEMA_VERSIONS: [u64; 1] = [1];
+
+/// 一条回复的 token 用量，同时接受 Gemini CLI 的简写与 API 的 usageMetadata 字段名
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct TokenCounts {
+    /// 提示 token 数，包括命中缓存的部分
+    #[serde(default, alias = "input", alias = "promptTokenCount")]
+    pub prompt: u64,
+    /// 回复 token 数，不包括思考
+    #[serde(default, alias = "output", alias = "candidatesTokenCount")]
+    pub candidates: u64,
+    /// 命中缓存的提示 token 数
+    #[serde(default, alias = "cachedContentTokenCount")]
+    pub cached: u64,
+    /// 思考 token 数
+    #[serde(default, alias = "thoughtsTokenCount")]
+    pub thoughts: u64,
+}
+
+impl From<&TokenCounts> for Usage {
+    /// 转换为 Claude Code 的用量字段
+    fn from(counts: &TokenCounts) -> Self {
+        Usage {
+            input_tokens: Some(counts.prompt.saturating_sub(counts.cached)),
+            output_tokens: Some(counts.candidates.saturating_add(counts.thoughts)),
+            cache_creation_input_tokens: Some(0),
+            cache_read_input_tokens: Some(counts.cached),
+        }
+    }
+}
+
+/// 统一 Gemini 的模型名：去掉 models/ 前缀以及 -latest、-001、-preview-06-05、-exp-0827 等版本后缀，
+/// 如 models/gemini-2.5-pro-preview-06-05 为 gemini-2.5-pro
+pub fn normalize_model(model: &str) -> String {
+    let model = model.trim().to_ascii_lowercase();
+    let mut parts: Vec<&str> = model.strip_prefix("models/").unwrap_or(&model).split('-').collect();
+    let is_number = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    // 从末尾去掉日期或版本号，以及它们前面的 preview / exp；至少保留系列与版本两段
+    while parts.len() > 2 {
+        let last = parts[parts.len() - 1];
+        if is_number(last) || matches!(last, "latest" | "preview" | "exp") {
+            parts.pop();
+        } else {
+            break;
+        }
+    }
+    parts.join("-")
+}
+
+/// 会话文件的格式版本：读取 version 或 schemaVersion 字段，没有时为 1
+fn schema_version(session: &Value) -> Option<u64> {
+    match session.get("version").or_else(|| session.get("schemaVersion")) {
+        None => Some(1),
+        Some(version) => version.as_u64().or_else(|| version.as_str()?.split('.').next()?.parse().ok()),
+    }
+}
+
+/// 取出会话文件中的消息；不是会话对象或格式版本无法识别时返回 None，调用方应跳过整个文件而不是猜测其结构
+pub fn parse_session(session: &Value) -> Option<&[Value]> {
+    let version = schema_version(session)?;
+    if !SUPPORTED_SCHEMA_VERSIONS.contains(&version) || session.get("sessionId").is_none() {
+        return None;
+    }
+    session.get("messages")?.as_array().map(Vec::as_slice)
+}
+
+/// 把一条消息转换为 Item；只有带用量和时间戳的模型回复得到 Item，用户消息等返回 None
+pub fn parse_message(message: &Value) -> Option<Item> {
+    let counts = message
+        .get("tokens")
+        .or_else(|| message.get("usageMetadata"))
+        .and_then(|tokens| TokenCounts::deserialize(tokens).ok())?;
+    let timestamp = message.get("timestamp").and_then(Value::as_str)?;
+    let model = message
+        .get("model")
+        .and_then(Value::as_str)
+        .map_or_else(|| UNKNOWN_MODEL.to_string(), normalize_model);
+
+    Some(Item {
+        message_id: None,
+        model,
+        timestamp: timestamp.to_string(),
+        usage: Some(Usage::from(&counts)),
+        tools: Vec::new(),
+        stop_reason: None,
+        version: None,
+        git_branch: None,
+        cwd: None,
+        duration_ms: None,
+        ttft_ms: None,
+    })
+}
@@ -0,0 +1,187 @@
+//! 用户可见消息的多语言目录
+//!
+//! 每种语言是 locales 目录下的一个 TOML 文件，编译时嵌入程序，并在 [`CATALOGS`] 中按语言代码登记。
+//! 消息以键查找，值是模板：`{}` 依次替换为参数，`{0}`、`{1}` 按序号替换，`{{`、`}}` 为字面的花括号。
+//! `[labels]` 表翻译表头等短标签，以英文原文为键，缺少时原样显示。
+//! 当前语言缺少的消息回退到英文，英文中也没有时显示键本身。
+//!
+//! 语言由 [`init`] 在程序开始时确定一次，之后通过 [`tr!`](crate::tr) 与 [`label`] 读取；未初始化时使用英文。
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// 嵌入的消息目录：(语言代码, TOML 内容)，第一项英文是所有语言的后备
+pub const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.toml")),
+    ("zh", include_str!("../locales/zh.toml")),
+];
+
+/// 未指定语言且环境变量中没有可用的语言时使用的语言
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// 一种语言的消息，嵌套的表展开为以 . 连接的键（labels 表除外）
+#[derive(Debug, Default)]
+struct Catalog {
+    code: &'static str,
+    messages: HashMap<String, String>,
+    labels: HashMap<String, String>,
+}
+
+impl Catalog {
+    /// 解析嵌入的目录；目录是随程序发布的数据，格式错误属于编程错误
+    fn parse(code: &'static str, content: &str) -> Self {
+        let table: toml::Table = content
+            .parse()
+            .unwrap_or_else(|e| panic!("locales/{}.toml 格式错误: {}", code, e));
+        let mut catalog = Catalog { code, ..Catalog::default() };
+        for (key, value) in table {
+            match value {
+                toml::Value::Table(labels) if key == "labels" => {
+                    catalog.labels = labels
+                        .into_iter()
+                        .filter_map(|(english, value)| value.as_str().map(|value| (english, value.to_string())))
+                        .collect();
+                }
+                value => flatten(&mut catalog.messages, key, value),
+            }
+        }
+        catalog
+    }
+}
+
+/// 将嵌套的表展开为 a.b.c 形式的键，只保留字符串值
+fn flatten(messages: &mut HashMap<String, String>, key: String, value: toml::Value) {
+    match value {
+        toml::Value::String(text) => {
+            messages.insert(key, text);
+        }
+        toml::Value::Table(table) => {
+            for (child, value) in table {
+                flatten(messages, format!("{}.{}", key, child), value);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 当前语言与英文后备的目录
+struct Catalogs {
+    current: Catalog,
+    fallback: Catalog,
+}
+
+static ACTIVE: OnceLock<Catalogs> = OnceLock::new();
+
+/// 确定使用的语言：requested（--lang）优先，其次依次为 LC_ALL、LC_MESSAGES、LANG；
+/// 没有对应目录的语言回退到英文。只有第一次调用生效，返回实际使用的语言代码
+pub fn init(requested: Option<&str>) -> &'static str {
+    let code = requested
+        .map(str::to_string)
+        .or_else(|| {
+            ["LC_ALL", "LC_MESSAGES", "LANG"]
+                .iter()
+                .filter_map(|name| std::env::var(name).ok())
+                .find(|value| !value.is_empty())
+        })
+        .and_then(|locale| resolve(&locale))
+        .unwrap_or(DEFAULT_LANGUAGE);
+    ACTIVE.get_or_init(|| load(code)).current.code
+}
+
+/// 将 zh_CN.UTF-8、zh-Hans、EN 等语言标识对应到已登记的语言代码，没有对应的目录时为 None
+pub fn resolve(locale: &str) -> Option<&'static str> {
+    let language = locale
+        .split(['_', '-', '.', '@'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    CATALOGS.iter().map(|(code, _)| *code).find(|code| *code == language)
+}
+
+fn load(code: &'static str) -> Catalogs {
+    let (fallback_code, fallback) = CATALOGS[0];
+    let content = CATALOGS.iter().find(|(name, _)| *name == code).map_or(fallback, |(_, content)| content);
+    Catalogs {
+        current: Catalog::parse(code, content),
+        fallback: Catalog::parse(fallback_code, fallback),
+    }
+}
+
+fn active() -> &'static Catalogs {
+    ACTIVE.get_or_init(|| load(DEFAULT_LANGUAGE))
+}
+
+/// 当前使用的语言代码
+pub fn language() -> &'static str {
+    active().current.code
+}
+
+/// 键对应的消息模板，当前语言与英文中都没有时为键本身
+pub fn template(key: &str) -> &str {
+    let catalogs = active();
+    catalogs
+        .current
+        .messages
+        .get(key)
+        .or_else(|| catalogs.fallback.messages.get(key))
+        .map_or(key, String::as_str)
+}
+
+/// 以参数填充键对应的消息模板
+pub fn message(key: &str, args: &[&dyn fmt::Display]) -> String {
+    fill(template(key), args)
+}
+
+/// 表头等短标签的译文，以英文原文查找，没有译文时原样返回
+pub fn label(english: &str) -> &str {
+    active().current.labels.get(english).map_or(english, String::as_str)
+}
+
+/// 填充模板：`{}` 依次取参数，`{N}` 取第 N 个参数，`{{`、`}}` 为字面的花括号；缺少的参数保留原样
+pub fn fill(template: &str, args: &[&dyn fmt::Display]) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut next = 0;
+    let mut rest = template;
+    while let Some(position) = rest.find(['{', '}']) {
+        result.push_str(&rest[..position]);
+        rest = &rest[position..];
+        if let Some(after) = rest.strip_prefix("{{").or_else(|| rest.strip_prefix("}}")) {
+            result.push_str(&rest[..1]);
+            rest = after;
+            continue;
+        }
+        let placeholder = rest.strip_prefix('{').and_then(|after| after.find('}').map(|end| &after[..end]));
+        let index = match placeholder {
+            Some("") => {
+                next += 1;
+                Some(next - 1)
+            }
+            Some(index) => index.parse::<usize>().ok(),
+            None => None,
+        };
+        match (placeholder, index.and_then(|index| args.get(index))) {
+            (Some(placeholder), Some(arg)) => {
+                result.push_str(&arg.to_string());
+                rest = &rest[placeholder.len() + 2..];
+            }
+            _ => {
+                result.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// 读取当前语言的消息：`tr!("key")` 或 `tr!("key", a, b)`，以参数填充模板，返回 String
+#[macro_export]
+macro_rules! tr {
+    ($key:expr) => {
+        $crate::i18n::message($key, &[])
+    };
+    ($key:expr, $($arg:expr),+ $(,)?) => {
+        $crate::i18n::message($key, &[$(&$arg as &dyn ::std::fmt::Display),+])
+    };
+}
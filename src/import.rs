@@ -0,0 +1,101 @@
+//! 导入其他工具导出的历史用量，目前支持 ccusage 的 `ccusage daily --json`
+//!
+//! 导出文件只有按日、按模型的合计，没有项目和会话信息：导入的数据记在项目 [`IMPORT_PROJECT`] 下，
+//! 消息条数记为 0。与日志合并时以日期为单位取舍，某天在日志中已有数据时整天使用日志的数据，见
+//! [`crate::file_processor::ProcessOutput::merge_imported`]。
+
+use std::collections::BTreeMap;
+use serde::Deserialize;
+use serde_json::Value;
+use crate::archive::ArchiveKey;
+use crate::codex::UNKNOWN_MODEL;
+use crate::item::{Usage, UsageAggregate};
+
+/// 导入的数据所属的项目
+pub const IMPORT_PROJECT: &str = "(imported)";
+
+/// ccusage 每日数据中的一个模型
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelBreakdown {
+    model_name: String,
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+    #[serde(default)]
+    cache_creation_tokens: u64,
+    #[serde(default)]
+    cache_read_tokens: u64,
+}
+
+/// ccusage 的一天：有 modelBreakdowns 时按模型导入，否则把当天合计记在 modelsUsed 的唯一模型（或未知模型）下
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CcusageDay {
+    date: String,
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+    #[serde(default)]
+    cache_creation_tokens: u64,
+    #[serde(default)]
+    cache_read_tokens: u64,
+    #[serde(default)]
+    models_used: Vec<String>,
+    #[serde(default)]
+    model_breakdowns: Vec<ModelBreakdown>,
+}
+
+/// 四类 token 都视为已上报
+fn usage(input: u64, output: u64, cache_creation: u64, cache_read: u64) -> Usage {
+    Usage {
+        input_tokens: Some(input),
+        output_tokens: Some(output),
+        cache_creation_input_tokens: Some(cache_creation),
+        cache_read_input_tokens: Some(cache_read),
+    }
+}
+
+/// 解析 `ccusage daily --json` 的输出（顶层的 daily 数组，旧版本的 data 数组，或直接是数组），
+/// 得到 ((IMPORT_PROJECT, 模型, 日期), 使用量)，按键排序；格式不符时返回错误信息
+pub fn parse_ccusage(content: &str) -> Result<Vec<(ArchiveKey, UsageAggregate)>, String> {
+    let value: Value = serde_json::from_str(content).map_err(|e| format!("不是合法的 JSON: {}", e))?;
+    let days = match &value {
+        Value::Array(_) => &value,
+        _ => value
+            .get("daily")
+            .or_else(|| value.get("data"))
+            .ok_or("缺少 daily 数组，请使用 ccusage daily --json 导出")?,
+    };
+    let days = Vec::<CcusageDay>::deserialize(days).map_err(|e| format!("每日数据格式错误: {}", e))?;
+
+    let mut imported: BTreeMap<ArchiveKey, UsageAggregate> = BTreeMap::new();
+    for day in days {
+        if chrono::NaiveDate::parse_from_str(&day.date, "%Y-%m-%d").is_err() {
+            return Err(format!("无法识别的日期: {}", day.date));
+        }
+        let rows: Vec<(String, Usage)> = if day.model_breakdowns.is_empty() {
+            let model = match day.models_used.as_slice() {
+                [model] => model.clone(),
+                _ => UNKNOWN_MODEL.to_string(),
+            };
+            vec![(model, usage(day.input_tokens, day.output_tokens, day.cache_creation_tokens, day.cache_read_tokens))]
+        } else {
+            day.model_breakdowns
+                .into_iter()
+                .map(|model| {
+                    let usage = usage(model.input_tokens, model.output_tokens, model.cache_creation_tokens, model.cache_read_tokens);
+                    (model.model_name, usage)
+                })
+                .collect()
+        };
+        for (model, usage) in rows {
+            let aggregate = UsageAggregate { usage, ..UsageAggregate::default() };
+            let entry = imported.entry((IMPORT_PROJECT.to_string(), model, day.date.clone())).or_default();
+            *entry += &aggregate;
+        }
+    }
+    Ok(imported.into_iter().collect())
+}
@@ -1,62 +1,440 @@
+//! 会话日志记录的数据结构与使用量的聚合类型
+
 use std::fmt;
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
-use std::ops::Add;
+use chrono_tz::Tz;
+use crate::bucketing::{bucket, Granularity};
+use crate::parse::ParseError;
+use serde_json::Value;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
 
+/// 会话日志中的一行记录
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct LogEntry {
+    /// 记录写入的时间（RFC 3339）
     pub timestamp: String,
+    /// API 返回的消息
     pub message: Message,
+    /// 记录类型：user、assistant 等
+    #[serde(rename = "type", default)]
+    pub entry_type: Option<String>,
+    /// 会话 id
+    #[serde(rename = "sessionId", default)]
+    pub session_id: Option<String>,
+    /// 记录 id
+    #[serde(default)]
+    pub uuid: Option<String>,
+    /// 写入该记录的 Claude Code 版本
+    #[serde(default)]
+    pub version: Option<String>,
+    /// 会话所在的 git 分支
+    #[serde(rename = "gitBranch", default)]
+    pub git_branch: Option<String>,
+    /// 记录写入时的工作目录
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// 生成这条回复所用的时间（毫秒），只有部分版本记录
+    #[serde(rename = "durationMs", default)]
+    pub duration_ms: Option<u64>,
+    /// 收到第一个 token 所用的时间（毫秒），只有部分版本记录
+    #[serde(rename = "ttftMs", default)]
+    pub ttft_ms: Option<u64>,
 }
 
+/// 记录中的 API 消息
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Message {
+    /// API 消息 id，同一条回复的多个流式记录共享该 id
+    #[serde(default)]
+    pub id: Option<String>,
+    /// 模型名称
     pub model: Option<String>,
+    /// 本次回复的 token 用量
     pub usage: Option<Usage>,
+    /// 回复结束的原因
+    #[serde(default)]
+    pub stop_reason: Option<String>,
+    /// 消息内容，可能是字符串或内容块数组，保持原样以便宽松解析
+    #[serde(default)]
+    pub content: Option<Value>,
 }
 
+/// 从一行日志中提取的、计入统计的一条消息
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Item {
+    /// API 消息 id，同一条回复的多个流式记录共享该 id，用量只计入其中第一条
+    #[serde(rename = "id", default)]
+    pub message_id: Option<String>,
+    /// 模型名称
     pub model: String,
+    /// 记录写入的时间（RFC 3339）
     pub timestamp: String,
+    /// token 用量
     #[serde(default)]
     pub usage: Option<Usage>,
+    /// 该消息中 tool_use 内容块调用的工具名称
+    #[serde(default)]
+    pub tools: Vec<String>,
+    /// 回复结束的原因（end_turn、max_tokens 等），未知值原样保留
+    #[serde(default)]
+    pub stop_reason: Option<String>,
+    /// Claude Code 版本号
+    #[serde(default)]
+    pub version: Option<String>,
+    /// git 分支
+    #[serde(default)]
+    pub git_branch: Option<String>,
+    /// 工作目录
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// 回复用时（毫秒），没有记录时为 None
+    #[serde(rename = "durationMs", default)]
+    pub duration_ms: Option<u64>,
+    /// 首个 token 的延迟（毫秒），没有记录时为 None
+    #[serde(rename = "ttftMs", default)]
+    pub ttft_ms: Option<u64>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+/// 一次 API 调用或若干次调用合计的 token 用量
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(from = "RawUsage")]
 pub struct Usage {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub input_tokens: Option<u32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub output_tokens: Option<u32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub cache_creation_input_tokens: Option<u32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub cache_read_input_tokens: Option<u32>,
+    // 未上报的字段保持为 None，序列化为 null，与上报了 0 的情况区分
+    /// 输入 token 数
+    pub input_tokens: Option<u64>,
+    /// 输出 token 数
+    pub output_tokens: Option<u64>,
+    /// 写入提示缓存的 token 数
+    pub cache_creation_input_tokens: Option<u64>,
+    /// 读取提示缓存的 token 数
+    pub cache_read_input_tokens: Option<u64>,
 }
 
-impl Item {
-    pub fn from_log_entry(entry: LogEntry) -> Option<Self> {
-        entry.message.model.map(|model| Item {
-            model,
+/// 反序列化时的原始字段，同时接受 OpenAI 风格的 prompt_tokens / completion_tokens
+#[derive(Deserialize)]
+struct RawUsage {
+    input_tokens: Option<u64>,
+    output_tokens: Option<u64>,
+    cache_creation_input_tokens: Option<u64>,
+    cache_read_input_tokens: Option<u64>,
+    prompt_tokens: Option<u64>,
+    completion_tokens: Option<u64>,
+}
+
+impl Usage {
+    /// 四类 token 的合计，未上报的字段按 0 计算
+    pub fn total(&self) -> u64 {
+        [
+            self.input_tokens,
+            self.output_tokens,
+            self.cache_creation_input_tokens,
+            self.cache_read_input_tokens,
+        ]
+        .iter()
+        .map(|tokens| tokens.unwrap_or(0))
+        .sum()
+    }
+
+    /// 未上报的字段名
+    pub fn missing_fields(&self) -> impl Iterator<Item = &'static str> {
+        [
+            ("input_tokens", self.input_tokens),
+            ("output_tokens", self.output_tokens),
+            ("cache_creation_input_tokens", self.cache_creation_input_tokens),
+            ("cache_read_input_tokens", self.cache_read_input_tokens),
+        ]
+        .into_iter()
+        .filter(|(_, tokens)| tokens.is_none())
+        .map(|(field, _)| field)
+    }
+}
+
+impl From<RawUsage> for Usage {
+    fn from(raw: RawUsage) -> Self {
+        // 两种命名同时出现时以 Anthropic 的字段为准
+        Usage {
+            input_tokens: raw.input_tokens.or(raw.prompt_tokens),
+            output_tokens: raw.output_tokens.or(raw.completion_tokens),
+            cache_creation_input_tokens: raw.cache_creation_input_tokens,
+            cache_read_input_tokens: raw.cache_read_input_tokens,
+        }
+    }
+}
+
+/// 某个聚合键下的使用量合计，以及计入的消息条数和最早/最晚活动时间
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct UsageAggregate {
+    /// 合计的使用量
+    pub usage: Usage,
+    /// 计入的消息条数
+    pub messages: u64,
+    /// 最早一条消息的时间
+    #[serde(default)]
+    pub first_seen: Option<DateTime<Utc>>,
+    /// 最晚一条消息的时间
+    #[serde(default)]
+    pub last_seen: Option<DateTime<Utc>>,
+}
+
+impl UsageAggregate {
+    /// 由单条消息的使用量及其时间戳构造
+    pub fn from_usage(usage: Usage, timestamp: Option<DateTime<Utc>>) -> Self {
+        Self {
+            usage,
+            messages: 1,
+            first_seen: timestamp,
+            last_seen: timestamp,
+        }
+    }
+}
+
+/// 聚合使用量的键：模型与日期，以及按分组方式需要时填入的项目和会话
+///
+/// 排序依次比较 model、date、project、session，None 排在 Some 之前；
+/// 每日表格按 (模型, 日期) 的顺序输出正依赖于此，调整字段顺序会改变输出顺序
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct UsageKey {
+    /// 模型名称
+    pub model: String,
+    /// 日期键 YYYY-MM-DD；按小时或分钟汇总时为 YYYY-MM-DDTHH 或 YYYY-MM-DDTHH:MM
+    pub date: String,
+    /// 项目路径，不按项目区分时为 None
+    pub project: Option<String>,
+    /// 会话 id，不按会话区分时为 None
+    pub session: Option<String>,
+}
+
+impl UsageKey {
+    /// 只按模型和日期区分的键
+    pub fn new(model: impl Into<String>, date: impl Into<String>) -> Self {
+        Self { model: model.into(), date: date.into(), project: None, session: None }
+    }
+
+    /// 同时按项目区分
+    pub fn with_project(mut self, project: Option<String>) -> Self {
+        self.project = project;
+        self
+    }
+
+    /// 同时按会话区分
+    pub fn with_session(mut self, session: Option<String>) -> Self {
+        self.session = session;
+        self
+    }
+}
+
+/// 一个模型在某一天的使用量
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DailyUsage {
+    /// 日期键 YYYY-MM-DD；按小时或分钟汇总时为 YYYY-MM-DDTHH 或 YYYY-MM-DDTHH:MM
+    pub date: String,
+    /// 模型名称
+    pub model: String,
+    /// 使用量合计及消息数
+    #[serde(flatten)]
+    pub usage: UsageAggregate,
+}
+
+/// 按模型以外的维度（Claude Code 版本、git 分支或项目）分组的某一天的使用量
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GroupUsage {
+    /// 分组名：版本号、分支名或项目路径
+    pub group: String,
+    /// 日期键 YYYY-MM-DD
+    pub date: String,
+    /// 使用量合计及消息数
+    #[serde(flatten)]
+    pub usage: UsageAggregate,
+}
+
+impl From<DailyUsage> for GroupUsage {
+    /// 以模型名作为分组名
+    fn from(daily: DailyUsage) -> Self {
+        Self { group: daily.model, date: daily.date, usage: daily.usage }
+    }
+}
+
+/// 一个项目中某个模型在某一天的使用量
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ProjectModelUsage {
+    /// 项目路径；导入或合并的数据为对应的标签
+    pub project: String,
+    /// 模型名称
+    pub model: String,
+    /// 日期键 YYYY-MM-DD
+    pub date: String,
+    /// 使用量合计及消息数
+    #[serde(flatten)]
+    pub aggregate: UsageAggregate,
+}
+
+impl From<((String, String, String), UsageAggregate)> for ProjectModelUsage {
+    /// 由 (项目, 模型, 日期) 键及其使用量构造
+    fn from(((project, model, date), aggregate): ((String, String, String), UsageAggregate)) -> Self {
+        Self { project, model, date, aggregate }
+    }
+}
+
+/// 某一天某个名称（工具名或 stop_reason）出现的次数
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DailyCount {
+    /// 日期键 YYYY-MM-DD
+    pub date: String,
+    /// 工具名或 stop_reason
+    pub name: String,
+    /// 出现次数
+    pub count: u64,
+}
+
+/// 单个会话（一个会话日志文件）的汇总
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SessionSummary {
+    /// 会话 id，取自日志文件名
+    pub session_id: String,
+    /// 用户提问 → 助手回复 的交互轮数
+    pub turns: u64,
+    /// 去重后的用户提问数（不含工具结果）
+    pub user_messages: u64,
+    /// 去重后的助手消息数
+    pub assistant_messages: u64,
+    /// 会话内所有计入统计的使用量
+    pub aggregate: UsageAggregate,
+    /// 会话内最早一条记录的时间
+    pub started_at: Option<DateTime<Utc>>,
+    /// 会话内最晚一条记录的时间
+    pub ended_at: Option<DateTime<Utc>>,
+    /// 会话所属的项目路径
+    pub project: Option<String>,
+    /// 会话中使用过的模型，按名称排序
+    pub models: Vec<String>,
+    /// token 用量最多的模型
+    pub dominant_model: Option<String>,
+    /// 有价格的模型的估算费用合计
+    pub cost_usd: Option<f64>,
+}
+
+impl SessionSummary {
+    /// 会话时长：最晚与最早一条记录的时间差
+    pub fn duration(&self) -> Option<chrono::TimeDelta> {
+        Some(self.ended_at? - self.started_at?)
+    }
+
+    /// 平均每轮消耗的 token 数，没有交互轮次时为 None
+    pub fn tokens_per_turn(&self) -> Option<u64> {
+        (self.turns > 0).then(|| self.aggregate.usage.total() / self.turns)
+    }
+}
+
+impl Message {
+    /// 用户消息是否为真正的提问：字符串内容，或包含非 tool_result 的内容块
+    pub fn is_user_prompt(&self) -> bool {
+        match &self.content {
+            Some(Value::String(_)) => true,
+            Some(Value::Array(blocks)) => blocks
+                .iter()
+                .any(|block| block.get("type").and_then(|t| t.as_str()) != Some("tool_result")),
+            _ => false,
+        }
+    }
+}
+
+impl TryFrom<LogEntry> for Item {
+    type Error = ParseError;
+
+    /// 从日志记录构造，模型 id 统一为 Anthropic 的标准 id；没有模型时返回 ParseError::MissingModel
+    fn try_from(entry: LogEntry) -> Result<Self, ParseError> {
+        let tools = entry.message.content
+            .as_ref()
+            .map(Self::tool_names)
+            .unwrap_or_default();
+        let model = entry.message.model.ok_or(ParseError::MissingModel)?;
+
+        Ok(Item {
+            message_id: entry.message.id,
+            model: normalize_model_id(&model),
             timestamp: entry.timestamp,
             usage: entry.message.usage,
+            tools,
+            stop_reason: entry.message.stop_reason,
+            version: entry.version,
+            git_branch: entry.git_branch,
+            cwd: entry.cwd,
+            duration_ms: entry.duration_ms,
+            ttft_ms: entry.ttft_ms,
         })
     }
+}
+
+impl Item {
+
+    /// 从 message.content 中提取 tool_use 块的工具名称，非数组或格式不符的内容直接忽略
+    fn tool_names(content: &Value) -> Vec<String> {
+        content
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|block| block.get("type").and_then(|t| t.as_str()) == Some("tool_use"))
+            .filter_map(|block| block.get("name").and_then(|name| name.as_str()))
+            .map(|name| name.to_string())
+            .collect()
+    }
     
-    pub fn get_timestamp_key(&self) -> String {
-        // 解析时间戳并格式化为同一天（移除时间）
-        if let Ok(dt) = self.timestamp.parse::<DateTime<Utc>>() {
-            dt.format("%Y-%m-%d").to_string()
-        } else {
-            self.timestamp.clone()
+    /// 解析后的时间戳，无法解析时为 None
+    pub fn parsed_timestamp(&self) -> Option<DateTime<Utc>> {
+        self.timestamp.parse::<DateTime<Utc>>().ok()
+    }
+
+    /// 按 tz 中的日期得到的 YYYY-MM-DD 键，时间戳无法解析时原样返回
+    pub fn get_timestamp_key(&self, tz: &Tz) -> String {
+        bucket(&self.timestamp, Granularity::Day, tz)
+            .map(|key| key.to_string())
+            .unwrap_or_else(|_| self.timestamp.clone())
+    }
+}
+
+/// 将 Bedrock / Vertex 等云厂商格式的模型 id 还原为 Anthropic 的标准 id，
+/// 使同一模型合并为一行。无法识别的 id 原样返回。
+///
+/// - Bedrock: `anthropic.claude-sonnet-4-20250514-v1:0`、`us.anthropic.claude-...` -> `claude-sonnet-4-20250514`
+/// - Vertex: `claude-sonnet-4@20250514` -> `claude-sonnet-4-20250514`
+pub fn normalize_model_id(model: &str) -> String {
+    // Bedrock: 可选的跨区域前缀（us.、eu.、apac. 等）+ anthropic. 前缀，以及 -v1:0 版本后缀
+    if let Some(position) = model.find("anthropic.claude-") {
+        let prefix = &model[..position];
+        if prefix.is_empty() || (prefix.ends_with('.') && !prefix[..prefix.len() - 1].contains('.')) {
+            let without_prefix = &model[position + "anthropic.".len()..];
+            return strip_bedrock_version(without_prefix).to_string();
         }
     }
+
+    // Vertex: 使用 @ 分隔快照日期
+    if model.starts_with("claude-")
+        && let Some((name, snapshot)) = model.split_once('@') {
+        return format!("{}-{}", name, snapshot);
+    }
+
+    model.to_string()
+}
+
+/// 去掉 Bedrock 的 -v1:0 形式的版本后缀
+fn strip_bedrock_version(model: &str) -> &str {
+    if let Some(position) = model.rfind("-v") {
+        let suffix = &model[position + 2..];
+        let is_version = suffix
+            .split(':')
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+        if is_version {
+            return &model[..position];
+        }
+    }
+    model
 }
 
 impl fmt::Display for Item {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Model: {}, Date: {}", self.model, self.get_timestamp_key())?;
+        write!(f, "Model: {}, Date: {}", self.model, self.get_timestamp_key(&Tz::UTC))?;
         
         if let Some(ref usage) = self.usage {
             write!(f, ", Usage: {}", usage)?;
@@ -90,35 +468,304 @@ impl fmt::Display for Usage {
     }
 }
 
+/// 合并单个字段：都未上报时仍为 None，只有一方上报时取该值
+fn merge_tokens(a: Option<u64>, b: Option<u64>, add: fn(u64, u64) -> u64) -> Option<u64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(add(a, b)),
+        (a, b) => a.or(b),
+    }
+}
+
+impl Usage {
+    fn combine(&self, other: &Usage, add: fn(u64, u64) -> u64) -> Usage {
+        Usage {
+            input_tokens: merge_tokens(self.input_tokens, other.input_tokens, add),
+            output_tokens: merge_tokens(self.output_tokens, other.output_tokens, add),
+            cache_creation_input_tokens: merge_tokens(
+                self.cache_creation_input_tokens,
+                other.cache_creation_input_tokens,
+                add,
+            ),
+            cache_read_input_tokens: merge_tokens(self.cache_read_input_tokens, other.cache_read_input_tokens, add),
+        }
+    }
+
+    /// 与 + 相同，但各字段溢出时停在 u64::MAX 而不是 panic
+    pub fn saturating_add(&self, other: &Usage) -> Usage {
+        self.combine(other, u64::saturating_add)
+    }
+}
+
 impl Add for Usage {
     type Output = Usage;
 
     fn add(self, other: Usage) -> Usage {
+        &self + &other
+    }
+}
+
+impl Add<&Usage> for &Usage {
+    type Output = Usage;
+
+    fn add(self, other: &Usage) -> Usage {
+        self.combine(other, |a, b| a + b)
+    }
+}
+
+impl AddAssign<&Usage> for Usage {
+    fn add_assign(&mut self, other: &Usage) {
+        *self = &*self + other;
+    }
+}
+
+impl Sum for Usage {
+    fn sum<I: Iterator<Item = Usage>>(iter: I) -> Usage {
+        iter.fold(Usage::default(), |total, usage| total + usage)
+    }
+}
+
+impl<'a> Sum<&'a Usage> for Usage {
+    fn sum<I: Iterator<Item = &'a Usage>>(iter: I) -> Usage {
+        iter.fold(Usage::default(), |mut total, usage| {
+            total += usage;
+            total
+        })
+    }
+}
+
+impl Add for UsageAggregate {
+    type Output = UsageAggregate;
+
+    fn add(mut self, other: UsageAggregate) -> UsageAggregate {
+        self += &other;
+        self
+    }
+}
+
+impl AddAssign<&UsageAggregate> for UsageAggregate {
+    fn add_assign(&mut self, other: &UsageAggregate) {
+        // 最早时间取较小者，最晚时间取较大者，缺失的一方不参与比较
+        self.first_seen = match (self.first_seen, other.first_seen) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.last_seen = match (self.last_seen, other.last_seen) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        self.usage += &other.usage;
+        self.messages += other.messages;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(input: Option<u64>, output: Option<u64>, cache_creation: Option<u64>, cache_read: Option<u64>) -> Usage {
         Usage {
-            input_tokens: match (self.input_tokens, other.input_tokens) {
-                (Some(a), Some(b)) => Some(a + b),
-                (Some(a), None) => Some(a),
-                (None, Some(b)) => Some(b),
-                (None, None) => None,
-            },
-            output_tokens: match (self.output_tokens, other.output_tokens) {
-                (Some(a), Some(b)) => Some(a + b),
-                (Some(a), None) => Some(a),
-                (None, Some(b)) => Some(b),
-                (None, None) => None,
-            },
-            cache_creation_input_tokens: match (self.cache_creation_input_tokens, other.cache_creation_input_tokens) {
-                (Some(a), Some(b)) => Some(a + b),
-                (Some(a), None) => Some(a),
-                (None, Some(b)) => Some(b),
-                (None, None) => None,
-            },
-            cache_read_input_tokens: match (self.cache_read_input_tokens, other.cache_read_input_tokens) {
-                (Some(a), Some(b)) => Some(a + b),
-                (Some(a), None) => Some(a),
-                (None, Some(b)) => Some(b),
-                (None, None) => None,
-            },
+            input_tokens: input,
+            output_tokens: output,
+            cache_creation_input_tokens: cache_creation,
+            cache_read_input_tokens: cache_read,
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn addition_is_commutative() {
+        let a = usage(Some(100), None, Some(5), Some(2_000));
+        let b = usage(Some(7), Some(30), None, Some(u32::MAX as u64));
+        assert_eq!(&a + &b, &b + &a);
+        assert_eq!(a.saturating_add(&b), b.saturating_add(&a));
+    }
+
+    #[test]
+    fn default_is_the_identity() {
+        let a = usage(Some(100), None, Some(0), Some(2_000));
+        assert_eq!(&a + &Usage::default(), a);
+        assert_eq!(&Usage::default() + &a, a);
+        assert_eq!(a.saturating_add(&Usage::default()), a);
+        assert_eq!(Usage::default() + Usage::default(), Usage::default());
+    }
+
+    #[test]
+    fn unreported_fields_stay_unreported() {
+        let sum = usage(Some(1), None, None, Some(0)) + usage(None, None, Some(3), None);
+        assert_eq!(sum, usage(Some(1), None, Some(3), Some(0)));
+        assert_eq!(sum.missing_fields().collect::<Vec<_>>(), ["output_tokens"]);
+    }
+
+    #[test]
+    fn sums_beyond_u32_do_not_overflow() {
+        let month = usage(None, None, None, Some(u32::MAX as u64));
+        let total: Usage = [month.clone(), month.clone(), month].iter().sum();
+        assert_eq!(total.cache_read_input_tokens, Some(3 * u32::MAX as u64));
+        assert_eq!(total.total(), 3 * u32::MAX as u64);
+    }
+
+    #[test]
+    fn saturating_add_stops_at_the_maximum() {
+        let full = usage(Some(u64::MAX), Some(1), None, None);
+        let sum = full.saturating_add(&usage(Some(1), Some(1), None, None));
+        assert_eq!(sum, usage(Some(u64::MAX), Some(2), None, None));
+    }
+
+    #[test]
+    fn aggregates_keep_the_earliest_and_latest_activity() {
+        let at = |time: &str| Some(format!("2025-09-10T{}Z", time).parse::<DateTime<Utc>>().unwrap());
+        let morning = UsageAggregate::from_usage(usage(Some(1), None, None, None), at("09:12:00"));
+        let evening = UsageAggregate::from_usage(usage(Some(2), None, None, None), at("18:47:30"));
+        let undated = UsageAggregate::from_usage(usage(Some(4), None, None, None), None);
+
+        let day = evening.clone() + undated + morning.clone();
+        assert_eq!((day.first_seen, day.last_seen), (at("09:12:00"), at("18:47:30")));
+        assert_eq!(day.messages, 3);
+        assert_eq!(day.usage.input_tokens, Some(7));
+        assert_eq!((morning.clone() + evening.clone()).first_seen, (evening + morning).first_seen);
+    }
+
+    #[test]
+    fn cloud_model_ids_map_to_anthropic_ids() {
+        for (model, expected) in [
+            ("anthropic.claude-sonnet-4-20250514-v1:0", "claude-sonnet-4-20250514"),
+            ("us.anthropic.claude-opus-4-1-20250805-v1:0", "claude-opus-4-1-20250805"),
+            ("apac.anthropic.claude-3-5-haiku-20241022-v2", "claude-3-5-haiku-20241022"),
+            ("claude-sonnet-4@20250514", "claude-sonnet-4-20250514"),
+            ("claude-3-5-sonnet-v2@20241022", "claude-3-5-sonnet-v2-20241022"),
+            // -v 之后不是版本号时不是版本后缀
+            ("anthropic.claude-instant-v", "claude-instant-v"),
+        ] {
+            assert_eq!(normalize_model_id(model), expected, "{}", model);
+        }
+    }
+
+    #[test]
+    fn unrecognized_model_ids_are_unchanged() {
+        for model in [
+            "claude-sonnet-4-20250514",
+            "<synthetic>",
+            "gpt-5-codex",
+            // 多级前缀不是 Bedrock 的跨区域前缀
+            "a.b.anthropic.claude-sonnet-4-20250514-v1:0",
+            // 版本后缀只在 Bedrock 的 id 中去掉
+            "claude-sonnet-4-v1:0",
+        ] {
+            assert_eq!(normalize_model_id(model), model);
+        }
+    }
+
+    fn parse_usage(json: &str) -> Usage {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn openai_field_names_are_accepted() {
+        assert_eq!(
+            parse_usage(r#"{"prompt_tokens":120,"completion_tokens":30,"total_tokens":150}"#),
+            usage(Some(120), Some(30), None, None)
+        );
+    }
+
+    #[test]
+    fn anthropic_field_names_win_when_both_are_present() {
+        let parsed = parse_usage(
+            r#"{"input_tokens":10,"prompt_tokens":999,"output_tokens":5,"completion_tokens":888,"cache_read_input_tokens":7}"#,
+        );
+        assert_eq!(parsed, usage(Some(10), Some(5), None, Some(7)));
+        // 只有一种命名时各取各的
+        assert_eq!(parse_usage(r#"{"input_tokens":10,"completion_tokens":3}"#), usage(Some(10), Some(3), None, None));
+    }
+
+    #[test]
+    fn usage_serializes_with_anthropic_field_names() {
+        let json = serde_json::to_value(parse_usage(r#"{"prompt_tokens":1,"completion_tokens":2}"#)).unwrap();
+        assert_eq!(json["input_tokens"], 1);
+        assert_eq!(json["output_tokens"], 2);
+        assert!(json.get("prompt_tokens").is_none());
+    }
+
+    fn hash_of(key: &UsageKey) -> u64 {
+        use std::hash::{BuildHasher, RandomState};
+        thread_local!(static STATE: RandomState = RandomState::new());
+        STATE.with(|state| state.hash_one(key))
+    }
+
+    #[test]
+    fn usage_keys_are_equal_only_when_every_field_matches() {
+        let key = UsageKey::new("claude-sonnet-4-20250514", "2025-09-10");
+        assert_eq!(key, UsageKey::new("claude-sonnet-4-20250514".to_string(), "2025-09-10".to_string()));
+        assert_eq!(hash_of(&key), hash_of(&key.clone().with_project(None).with_session(None)));
+
+        let with_project = key.clone().with_project(Some("/work/app".to_string()));
+        let with_session = key.clone().with_session(Some("s1".to_string()));
+        assert_ne!(key, with_project);
+        assert_ne!(key, with_session);
+        assert_ne!(with_project, with_session);
+        assert_ne!(key, UsageKey::new("claude-sonnet-4-20250514", "2025-09-11"));
+
+        let keys: std::collections::HashSet<UsageKey> = [key.clone(), with_project.clone(), key.clone(), with_project].into();
+        assert_eq!(keys.len(), 2);
+    }
+
+    #[test]
+    fn usage_keys_sort_by_model_then_date_then_project_and_session() {
+        let mut keys = [
+            UsageKey::new("b", "2025-09-01"),
+            UsageKey::new("a", "2025-09-02").with_project(Some("/z".to_string())),
+            UsageKey::new("a", "2025-09-02"),
+            UsageKey::new("a", "2025-09-02").with_project(Some("/y".to_string())).with_session(Some("s2".to_string())),
+            UsageKey::new("a", "2025-09-02").with_project(Some("/y".to_string())).with_session(Some("s1".to_string())),
+            UsageKey::new("a", "2025-09-01"),
+        ];
+        keys.sort();
+        let order: Vec<(&str, &str, Option<&str>, Option<&str>)> = keys
+            .iter()
+            .map(|key| (key.model.as_str(), key.date.as_str(), key.project.as_deref(), key.session.as_deref()))
+            .collect();
+        assert_eq!(order, [
+            ("a", "2025-09-01", None, None),
+            ("a", "2025-09-02", None, None),
+            ("a", "2025-09-02", Some("/y"), Some("s1")),
+            ("a", "2025-09-02", Some("/y"), Some("s2")),
+            ("a", "2025-09-02", Some("/z"), None),
+            ("b", "2025-09-01", None, None),
+        ]);
+    }
+
+    #[test]
+    fn timestamp_key_is_the_day_in_the_timezone() {
+        let item = |timestamp: &str| -> Item {
+            serde_json::from_value(serde_json::json!({ "model": "claude-sonnet-4-20250514", "timestamp": timestamp })).unwrap()
+        };
+        assert_eq!(item("2025-09-01T23:30:00.000Z").get_timestamp_key(&Tz::UTC), "2025-09-01");
+        assert_eq!(item("2025-09-02T01:30:00+08:00").get_timestamp_key(&Tz::UTC), "2025-09-01");
+        assert_eq!(item("2025-09-01T23:30:00.000Z").get_timestamp_key(&Tz::Asia__Shanghai), "2025-09-02");
+        assert_eq!(item("2025-09-02T01:30:00.000Z").get_timestamp_key(&Tz::America__New_York), "2025-09-01");
+        assert_eq!(item("not a timestamp").get_timestamp_key(&Tz::UTC), "not a timestamp");
+    }
+
+    #[test]
+    fn adding_in_place_matches_adding_by_value() {
+        let usages = [
+            usage(Some(10), Some(5), None, None),
+            usage(None, Some(7), Some(100), None),
+            usage(Some(1), None, None, None),
+            usage(None, None, None, None),
+        ];
+        let mut in_place = Usage::default();
+        for usage in &usages {
+            in_place += usage;
+        }
+        let by_value = usages.iter().cloned().fold(Usage::default(), |total, usage| total + usage);
+        assert_eq!(in_place, by_value);
+        assert_eq!(in_place, usages.iter().sum::<Usage>());
+        assert_eq!(in_place, usages.into_iter().sum::<Usage>());
+        // 任何一方上报过的字段都有值，没有一方上报的仍为 None
+        assert_eq!(in_place, usage(Some(11), Some(12), Some(100), None));
+
+        let mut aggregate = UsageAggregate::default();
+        aggregate += &UsageAggregate::from_usage(usage(Some(1), None, None, None), None);
+        aggregate += &UsageAggregate::from_usage(usage(Some(2), None, None, None), None);
+        assert_eq!((aggregate.usage.input_tokens, aggregate.messages), (Some(3), 2));
+    }
+}
@@ -0,0 +1,72 @@
+//! 响应时间的分布：按日期和模型统计回复用时、首个 token 延迟的中位数与 p95，以及每秒输出 token 数
+
+use std::collections::BTreeMap;
+use serde::Serialize;
+use crate::file_processor::MessageLatency;
+
+/// 响应时间的分布；date 为 None 时是整个范围，model 为显示名称，None 时是全部模型合计
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyStats {
+    /// 日期（UTC）
+    pub date: Option<String>,
+    /// 模型的显示名称
+    pub model: Option<String>,
+    /// 带有用时记录的消息条数
+    pub samples: u64,
+    /// 回复用时的中位数（毫秒）
+    pub duration_p50_ms: u64,
+    /// 回复用时的第 95 百分位（毫秒）
+    pub duration_p95_ms: u64,
+    /// 首个 token 延迟的中位数（毫秒），没有任何记录时为 None
+    pub ttft_p50_ms: Option<u64>,
+    /// 首个 token 延迟的第 95 百分位（毫秒）
+    pub ttft_p95_ms: Option<u64>,
+    /// 输出 token 合计除以用时合计，用时合计为 0 时为 None
+    pub output_tokens_per_second: Option<f64>,
+}
+
+impl LatencyStats {
+    fn of(date: Option<String>, model: Option<String>, selected: &[&MessageLatency]) -> Self {
+        let mut durations: Vec<u64> = selected.iter().map(|latency| latency.duration_ms).collect();
+        durations.sort_unstable();
+        let mut ttfts: Vec<u64> = selected.iter().filter_map(|latency| latency.ttft_ms).collect();
+        ttfts.sort_unstable();
+        let total_ms: u64 = durations.iter().sum();
+        let output_tokens: u64 = selected.iter().map(|latency| latency.output_tokens).sum();
+        Self {
+            date,
+            model,
+            samples: selected.len() as u64,
+            duration_p50_ms: nearest_rank(&durations, 50),
+            duration_p95_ms: nearest_rank(&durations, 95),
+            ttft_p50_ms: (!ttfts.is_empty()).then(|| nearest_rank(&ttfts, 50)),
+            ttft_p95_ms: (!ttfts.is_empty()).then(|| nearest_rank(&ttfts, 95)),
+            output_tokens_per_second: (total_ms > 0).then(|| output_tokens as f64 * 1000.0 / total_ms as f64),
+        }
+    }
+}
+
+/// 已排序的 values 的第 percent 百分位（最近秩：第 ceil(p/100 * n) 个值），values 为空时为 0
+fn nearest_rank(values: &[u64], percent: usize) -> u64 {
+    let index = (percent * values.len()).div_ceil(100).max(1) - 1;
+    values.get(index).copied().unwrap_or(0)
+}
+
+/// 整个范围内全部消息与每个模型的响应时间，随后是每天每个模型的；模型按 name_of 给出的显示名称合并并排列
+pub fn latency_stats(latencies: &[MessageLatency], name_of: impl Fn(&str) -> String) -> Vec<LatencyStats> {
+    if latencies.is_empty() {
+        return Vec::new();
+    }
+    let mut by_model: BTreeMap<String, Vec<&MessageLatency>> = BTreeMap::new();
+    let mut by_day: BTreeMap<(&str, String), Vec<&MessageLatency>> = BTreeMap::new();
+    for latency in latencies {
+        let model = name_of(&latency.model);
+        by_day.entry((&latency.date, model.clone())).or_default().push(latency);
+        by_model.entry(model).or_default().push(latency);
+    }
+    let all: Vec<&MessageLatency> = latencies.iter().collect();
+    std::iter::once(LatencyStats::of(None, None, &all))
+        .chain(by_model.into_iter().map(|(model, selected)| LatencyStats::of(None, Some(model), &selected)))
+        .chain(by_day.into_iter().map(|((date, model), selected)| LatencyStats::of(Some(date.to_string()), Some(model), &selected)))
+        .collect()
+}
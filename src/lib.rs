@@ -0,0 +1,74 @@
+//! 解析 Claude Code 的会话日志（~/.claude/projects 下的 JSONL 文件）并聚合 token 用量与费用，
+//! 也可读取 OpenAI Codex CLI（[`codex`]）与 Google Gemini CLI（[`gemini`]）的会话记录，
+//! 其他工具的 JSONL 日志可以通过配置的字段映射（[`mapping`]）读取。
+//!
+//! [`file_processor::FileProcessor`] 扫描日志得到 [`file_processor::ProcessOutput`]。
+//! 输入来自 [`source::DataSource`]（数据目录、指定的路径、标准输入或内存中的记录），
+//! 结果中是按 (模型, 日期)、(项目, 模型, 日期)、小时等维度合并的使用量以及每个会话的汇总；
+//! 其余公开模块在此基础上计算各类报告（按月或周汇总、5 小时窗口、趋势、分布等）。
+//! 标记为隐藏的模块只供 cccost 命令行程序使用，不属于稳定的接口。
+#![deny(missing_docs)]
+
+#[cfg(feature = "async")]
+pub mod async_scan;
+pub mod blocks;
+pub mod budget;
+pub mod bucketing;
+pub mod cache_report;
+pub mod codex;
+pub mod compare;
+pub mod daily;
+pub mod diagnostics;
+pub mod distribution;
+pub mod export;
+pub mod file_processor;
+pub mod gemini;
+pub mod i18n;
+pub mod import;
+pub mod item;
+pub mod latency;
+pub mod limit;
+pub mod mapping;
+pub mod matrix;
+pub mod merge;
+pub mod notify;
+pub mod model;
+pub mod parse;
+pub mod period;
+pub mod pricing;
+pub mod remote;
+pub mod render;
+pub mod source;
+pub mod trend;
+
+#[doc(hidden)]
+#[cfg(feature = "table")]
+pub mod app;
+#[doc(hidden)]
+pub mod archive;
+#[doc(hidden)]
+pub mod backup;
+#[doc(hidden)]
+pub mod cache;
+#[doc(hidden)]
+pub mod claude_json;
+#[doc(hidden)]
+pub mod cli;
+#[doc(hidden)]
+pub mod config;
+#[doc(hidden)]
+pub mod doctor;
+#[doc(hidden)]
+pub mod fixtures;
+#[doc(hidden)]
+pub mod lock;
+#[doc(hidden)]
+pub mod project;
+#[doc(hidden)]
+pub mod tail;
+#[doc(hidden)]
+#[cfg(feature = "table")]
+pub mod table_renderer;
+#[doc(hidden)]
+#[cfg(feature = "tui")]
+pub mod tui;
@@ -0,0 +1,138 @@
+//! token 用量上限的检查与预计
+
+use std::fmt;
+use std::str::FromStr;
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use crate::item::DailyUsage;
+use crate::period::billing_cycle;
+use crate::tr;
+
+/// 计算 token 上限的计费窗口
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum LimitWindow {
+    /// 计费月：默认为本月 1 日至月底，设置 --billing-day 时从该日起算
+    #[default]
+    Month,
+    /// 滚动窗口：包括今天在内的最近 N 天
+    Rolling(u32),
+}
+
+impl FromStr for LimitWindow {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("month") {
+            return Ok(LimitWindow::Month);
+        }
+        value
+            .strip_suffix('d')
+            .and_then(|days| days.parse::<u32>().ok())
+            .filter(|days| *days > 0)
+            .map(LimitWindow::Rolling)
+            .ok_or_else(|| format!("无效的窗口: {}（应为 month 或天数如 30d）", value))
+    }
+}
+
+impl TryFrom<String> for LimitWindow {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<LimitWindow> for String {
+    /// 与 --limit-window 接受的写法相同：month 或 30d
+    fn from(window: LimitWindow) -> Self {
+        match window {
+            LimitWindow::Month => "month".to_string(),
+            LimitWindow::Rolling(days) => format!("{}d", days),
+        }
+    }
+}
+
+impl fmt::Display for LimitWindow {
+    /// 当前语言的窗口名称，如 This month、Last 30 days
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitWindow::Month => write!(f, "{}", tr!("report.limit_window_month")),
+            LimitWindow::Rolling(days) => write!(f, "{}", tr!("report.limit_window_rolling", days)),
+        }
+    }
+}
+
+/// 当前窗口内的用量与上限
+#[derive(Debug, Serialize)]
+pub struct LimitStatus {
+    /// 上限的时间窗口
+    pub window: LimitWindow,
+    /// 窗口内的 token 上限
+    pub limit: u64,
+    /// 窗口内已用的 token 数
+    pub consumed: u64,
+    /// 窗口的第一天
+    pub window_start: NaiveDate,
+    /// 计费月的最后一天，滚动窗口没有固定的结束日期，为 None
+    pub window_end: Option<NaiveDate>,
+    /// 按当前的日均用量预计达到上限的日期；已超出或没有用量时为 None
+    pub projected: Option<NaiveDate>,
+    /// 按当前的日均用量预计到计费月结束时的总用量
+    pub projected_total: Option<u64>,
+}
+
+impl LimitStatus {
+    /// 根据 (名称, 日期) 使用量计算截至 today 的窗口用量，计费月从每月 billing_day 日开始
+    pub fn evaluate(
+        usage: &[DailyUsage],
+        limit: u64,
+        window: LimitWindow,
+        billing_day: u32,
+        today: NaiveDate,
+    ) -> Self {
+        let (window_start, window_end) = match window {
+            LimitWindow::Month => {
+                let (start, end) = billing_cycle(today, billing_day);
+                (start, Some(end))
+            }
+            LimitWindow::Rolling(days) => (today - Duration::days(days as i64 - 1), None),
+        };
+
+        let consumed: u64 = usage
+            .iter()
+            .filter(|daily| {
+                NaiveDate::parse_from_str(&daily.date, "%Y-%m-%d")
+                    .is_ok_and(|date| date >= window_start && date <= today)
+            })
+            .map(|daily| daily.usage.usage.total())
+            .sum();
+
+        let elapsed_days = (today - window_start).num_days() + 1;
+        let daily_rate = consumed / elapsed_days.max(1) as u64;
+        let projected = (consumed < limit && daily_rate > 0)
+            .then(|| today + Duration::days((limit - consumed).div_ceil(daily_rate) as i64));
+        let projected_total = window_end.map(|end| consumed + daily_rate * (end - today).num_days().max(0) as u64);
+
+        Self {
+            window,
+            limit,
+            consumed,
+            window_start,
+            window_end,
+            projected,
+            projected_total,
+        }
+    }
+
+    /// 是否已超出上限
+    pub fn exceeded(&self) -> bool {
+        self.consumed >= self.limit
+    }
+
+    /// 已用量占上限的百分比
+    pub fn percentage(&self) -> f64 {
+        self.consumed as f64 * 100.0 / self.limit.max(1) as f64
+    }
+}
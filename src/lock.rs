@@ -0,0 +1,109 @@
+//! 缓存与归档文件的建议锁，避免同时运行的多个 cccost 进程（如定时任务与交互运行）互相破坏。
+//!
+//! 锁加在数据文件旁的 `<文件名>.lock` 上而不是数据文件本身：缓存通过重命名替换，
+//! 锁住旧文件挡不住读取新文件的进程。读取取共享锁，写入取排他锁；
+//! 在 [`LOCK_TIMEOUT`] 内拿不到锁时返回 ErrorKind::TimedOut，由调用方决定跳过还是报错，不会无限等待
+
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use crate::tr;
+
+/// 等待锁的最长时间
+pub const LOCK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 两次尝试取锁之间的间隔
+const RETRY_INTERVAL: Duration = Duration::from_millis(20);
+
+/// 持有中的锁，drop 时释放
+#[derive(Debug)]
+pub struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    /// 读取 path 前取共享锁，多个读取者可以同时持有
+    pub fn shared(path: &Path) -> io::Result<Self> {
+        Self::acquire(path, File::try_lock_shared)
+    }
+
+    /// 写入 path 前取排他锁
+    pub fn exclusive(path: &Path) -> io::Result<Self> {
+        Self::acquire(path, File::try_lock)
+    }
+
+    fn acquire(path: &Path, try_lock: fn(&File) -> Result<(), TryLockError>) -> io::Result<Self> {
+        let lock_path = lock_path(path);
+        if let Some(parent) = lock_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).truncate(false).write(true).open(&lock_path)?;
+        let deadline = Instant::now() + LOCK_TIMEOUT;
+        loop {
+            match try_lock(&file) {
+                Ok(()) => return Ok(Self { _file: file }),
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => std::thread::sleep(RETRY_INTERVAL),
+                Err(TryLockError::WouldBlock) => {
+                    return Err(io::Error::new(io::ErrorKind::TimedOut, tr!("error.lock_timeout", path.display())));
+                }
+                Err(TryLockError::Error(e)) => return Err(e),
+            }
+        }
+    }
+}
+
+/// path 对应的锁文件：同目录下的 `<文件名>.lock`
+pub fn lock_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(".lock");
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use super::*;
+
+    #[test]
+    fn second_exclusive_lock_times_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+        let _held = FileLock::exclusive(&path).unwrap();
+
+        let started = Instant::now();
+        let error = FileLock::exclusive(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(started.elapsed() >= LOCK_TIMEOUT);
+        // 排他锁同样挡住读取者
+        assert_eq!(FileLock::shared(&path).unwrap_err().kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn shared_locks_coexist() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+        let started = Instant::now();
+        let _first = FileLock::shared(&path).unwrap();
+        let _second = FileLock::shared(&path).unwrap();
+        assert!(started.elapsed() < LOCK_TIMEOUT);
+        assert!(lock_path(&path).exists());
+    }
+
+    #[test]
+    fn exclusive_lock_is_acquired_once_released() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+        let held = FileLock::shared(&path).unwrap();
+        let releasing = thread::spawn(move || {
+            thread::sleep(LOCK_TIMEOUT / 4);
+            drop(held);
+        });
+
+        let started = Instant::now();
+        let _lock = FileLock::exclusive(&path).unwrap();
+        assert!(started.elapsed() < LOCK_TIMEOUT);
+        releasing.join().unwrap();
+    }
+}
@@ -0,0 +1,242 @@
+//! 按配置的字段映射解析任意工具的 JSONL 日志
+//!
+//! 在配置文件中为每种日志写一个 `[sources.<名称>]` 表，用 JSON Pointer（RFC 6901）指出时间戳、模型
+//! 与各 token 字段的位置，`--source custom:<名称>` 即按该映射把每一行转换为与 Claude Code 相同的 [`Item`]：
+//!
+//! ```toml
+//! [sources.mytool]
+//! directory = "~/.mytool/logs"        # 省略时需要在命令行给出 PATH
+//! label = "mytool"                    # --group-by source 中的名称，默认为映射名
+//! timestamp = "/ts"                   # 必填：RFC 3339 字符串，或 Unix 秒 / 毫秒数
+//! model = "/response/model"           # 与 model_name 至少填一个，记录中找不到时使用 model_name
+//! model_name = "my-model"
+//! input_tokens = "/usage/prompt"      # 必填：不含命中缓存的输入
+//! output_tokens = "/usage/completion" # 必填
+//! cache_read_tokens = "/usage/cached" # 可选，与 cache_creation_tokens 一样省略时记为 0
+//! project = "/cwd"                    # 可选，作为记录所属的项目路径
+//! ```
+//!
+//! 时间戳或必填的 token 字段解析不出的行不计入统计，按 type 字段归入跳过的记录。
+
+use std::path::PathBuf;
+use chrono::DateTime;
+use serde::Deserialize;
+use serde_json::Value;
+use crate::codex::UNKNOWN_MODEL;
+use crate::item::{Item, Usage};
+
+/// 一种日志的字段映射，字段值为 JSON Pointer
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldMapping {
+    /// 默认扫描的目录（递归查找 json、jsonl 文件），以 ~/ 开头时相对于主目录
+    pub directory: Option<PathBuf>,
+    /// 按工具分组时显示的名称，省略时由调用方使用映射名
+    pub label: Option<String>,
+    /// 时间戳的位置
+    pub timestamp: String,
+    /// 模型名的位置
+    pub model: Option<String>,
+    /// 固定的模型名，记录中没有模型名时使用
+    pub model_name: Option<String>,
+    /// 输入 token 数（不含命中缓存的部分）的位置
+    pub input_tokens: String,
+    /// 输出 token 数的位置
+    pub output_tokens: String,
+    /// 写入缓存的 token 数的位置
+    pub cache_creation_tokens: Option<String>,
+    /// 读取缓存的 token 数的位置
+    pub cache_read_tokens: Option<String>,
+    /// 项目路径的位置
+    pub project: Option<String>,
+}
+
+impl FieldMapping {
+    /// 检查映射是否可用：所有位置都是合法的 JSON Pointer，且能确定模型名；返回的错误信息指出字段名
+    pub fn validate(&self) -> Result<(), String> {
+        if self.model.is_none() && self.model_name.is_none() {
+            return Err("model 与 model_name 至少需要一个".to_string());
+        }
+        let pointers = [
+            ("timestamp", Some(&self.timestamp)),
+            ("model", self.model.as_ref()),
+            ("input_tokens", Some(&self.input_tokens)),
+            ("output_tokens", Some(&self.output_tokens)),
+            ("cache_creation_tokens", self.cache_creation_tokens.as_ref()),
+            ("cache_read_tokens", self.cache_read_tokens.as_ref()),
+            ("project", self.project.as_ref()),
+        ];
+        for (field, pointer) in pointers {
+            if let Some(pointer) = pointer
+                && !pointer.starts_with('/') {
+                return Err(format!("{} 应为以 / 开头的 JSON Pointer（如 \"/usage/input\"），实际为 {:?}", field, pointer));
+            }
+        }
+        Ok(())
+    }
+
+    /// 扫描的目录，展开开头的 ~/
+    pub fn directory(&self) -> Option<PathBuf> {
+        let directory = self.directory.as_ref()?;
+        match directory.strip_prefix("~") {
+            Ok(rest) => {
+                let home_dir = std::env::var("HOME").unwrap_or_else(|_| String::from("~"));
+                Some(PathBuf::from(home_dir).join(rest))
+            }
+            Err(_) => Some(directory.clone()),
+        }
+    }
+
+    /// 按映射把一行记录转换为 Item；时间戳、输入或输出 token 数解析不出时返回 None
+    pub fn parse_value(&self, value: &Value) -> Option<Item> {
+        let timestamp = timestamp(value.pointer(&self.timestamp)?)?;
+        let tokens = |pointer: &str| value.pointer(pointer).and_then(token_count);
+        let optional_tokens = |pointer: &Option<String>| match pointer {
+            Some(pointer) => tokens(pointer),
+            None => Some(0),
+        };
+        let usage = Usage {
+            input_tokens: Some(tokens(&self.input_tokens)?),
+            output_tokens: Some(tokens(&self.output_tokens)?),
+            cache_creation_input_tokens: optional_tokens(&self.cache_creation_tokens),
+            cache_read_input_tokens: optional_tokens(&self.cache_read_tokens),
+        };
+        let text = |pointer: &Option<String>| {
+            pointer.as_deref().and_then(|pointer| value.pointer(pointer)).and_then(Value::as_str).map(str::to_string)
+        };
+        let model = text(&self.model)
+            .or_else(|| self.model_name.clone())
+            .unwrap_or_else(|| UNKNOWN_MODEL.to_string());
+
+        Some(Item {
+            message_id: None,
+            model,
+            timestamp,
+            usage: Some(usage),
+            tools: Vec::new(),
+            stop_reason: None,
+            version: None,
+            git_branch: None,
+            cwd: text(&self.project),
+            duration_ms: None,
+            ttft_ms: None,
+        })
+    }
+}
+
+/// RFC 3339 字符串原样使用；数字按 Unix 时间解释，超过 10^11 的视为毫秒
+fn timestamp(value: &Value) -> Option<String> {
+    if let Some(text) = value.as_str() {
+        return Some(text.to_string());
+    }
+    let number = value.as_i64()?;
+    let parsed = if number.abs() >= 100_000_000_000 {
+        DateTime::from_timestamp_millis(number)
+    } else {
+        DateTime::from_timestamp(number, 0)
+    };
+    parsed.map(|timestamp| timestamp.to_rfc3339())
+}
+
+/// 数字或数字字符串形式的 token 数
+fn token_count(value: &Value) -> Option<u64> {
+    value.as_u64().or_else(|| value.as_str()?.trim().parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::config::Config;
+    use crate::file_processor::FileProcessor;
+    use crate::source::{PathsSource, SourceTool};
+    use super::*;
+
+    /// 某个非 Anthropic 工具的日志：时间为 Unix 秒或毫秒，token 数有的是字符串，还有一行无法转换的心跳记录
+    const FIXTURE: &str = r#"{"ts":1757494800,"response":{"model":"llama-3.1-70b"},"usage":{"prompt":1200,"completion":300,"cached":4000},"cwd":"/home/dev/app"}
+{"ts":1757498400000,"response":{},"usage":{"prompt":"800","completion":"200"},"cwd":"/home/dev/app"}
+{"type":"heartbeat","ts":1757498500}
+not json
+"#;
+
+    const CONFIG: &str = r#"
+[sources.mytool]
+label = "My Tool"
+timestamp = "/ts"
+model = "/response/model"
+model_name = "my-model"
+input_tokens = "/usage/prompt"
+output_tokens = "/usage/completion"
+cache_read_tokens = "/usage/cached"
+project = "/cwd"
+"#;
+
+    fn mapping() -> FieldMapping {
+        toml::from_str::<Config>(CONFIG).unwrap().sources.remove("mytool").unwrap()
+    }
+
+    #[test]
+    fn records_are_converted_through_the_pointers() {
+        let mapping = mapping();
+        let lines: Vec<Value> = FIXTURE.lines().filter_map(|line| serde_json::from_str(line).ok()).collect();
+
+        let first = mapping.parse_value(&lines[0]).unwrap();
+        assert_eq!(first.model, "llama-3.1-70b");
+        assert_eq!(first.timestamp, "2025-09-10T09:00:00+00:00");
+        assert_eq!(first.cwd.as_deref(), Some("/home/dev/app"));
+        let usage = first.usage.unwrap();
+        assert_eq!(
+            (usage.input_tokens, usage.output_tokens, usage.cache_creation_input_tokens, usage.cache_read_input_tokens),
+            (Some(1200), Some(300), Some(0), Some(4000))
+        );
+
+        // 毫秒时间戳、字符串形式的 token 数，记录中没有模型时使用 model_name
+        let second = mapping.parse_value(&lines[1]).unwrap();
+        assert_eq!((second.model.as_str(), second.timestamp.as_str()), ("my-model", "2025-09-10T10:00:00+00:00"));
+        assert_eq!(second.usage.unwrap().total(), 1000);
+        // 缺少必填的 token 字段
+        assert!(mapping.parse_value(&lines[2]).is_none());
+    }
+
+    #[test]
+    fn invalid_mappings_are_rejected_at_load_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let load = |content: &str| {
+            let path = dir.path().join("config.toml");
+            fs::write(&path, content).unwrap();
+            Config::load(&path)
+        };
+        assert!(load(CONFIG).is_ok());
+
+        let without_model = CONFIG.replace("model = \"/response/model\"\nmodel_name = \"my-model\"\n", "");
+        assert!(load(&without_model).unwrap_err().contains("sources.mytool: model 与 model_name 至少需要一个"));
+        let bad_pointer = CONFIG.replace("\"/usage/prompt\"", "\"usage.prompt\"");
+        assert!(load(&bad_pointer).unwrap_err().contains("input_tokens 应为以 / 开头的 JSON Pointer"));
+        let without_timestamp = CONFIG.replace("timestamp = \"/ts\"\n", "");
+        assert!(load(&without_timestamp).unwrap_err().contains("timestamp"));
+        let unknown_field = format!("{}tokens = \"/usage\"\n", CONFIG);
+        assert!(load(&unknown_field).is_err());
+    }
+
+    #[test]
+    fn custom_logs_are_scanned_end_to_end() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("mytool.jsonl");
+        fs::write(&log, FIXTURE).unwrap();
+
+        let output = FileProcessor::builder(dir.path().to_path_buf())
+            .with_field_mapping(mapping())
+            .build()
+            .process(PathsSource::new(vec![log], dir.path().to_path_buf()).with_tool(SourceTool::Custom))
+            .unwrap();
+
+        let usage: Vec<(&str, &str, u64)> = output
+            .usage
+            .iter()
+            .map(|day| (day.date.as_str(), day.model.as_str(), day.usage.usage.total()))
+            .collect();
+        assert_eq!(usage, [("2025-09-10", "llama-3.1-70b", 5500), ("2025-09-10", "my-model", 1000)]);
+        assert_eq!(output.sources.iter().map(|source| source.group.as_str()).collect::<Vec<_>>(), ["My Tool"]);
+        assert_eq!(output.diagnostics.total_entries(), 2);
+        assert_eq!(output.diagnostics.total_failed_lines(), 1);
+    }
+}
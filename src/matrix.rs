@@ -0,0 +1,192 @@
+//! 项目 × 计费月的用量透视表
+
+use std::collections::HashMap;
+use chrono::NaiveDate;
+use serde::Serialize;
+use crate::cli::Metric;
+use crate::daily::DayValue;
+use crate::item::ProjectModelUsage;
+use crate::period::billing_month_of;
+use crate::pricing::cost_for;
+
+/// 矩阵中的一列：一个计费月
+#[derive(Debug, Clone, Serialize)]
+pub struct MatrixMonth {
+    /// 计费月的标签
+    pub month: String,
+    /// 计费月的第一天
+    pub start: NaiveDate,
+    /// 计费月的最后一天
+    pub end: NaiveDate,
+}
+
+/// 矩阵中的一行：一个项目在各月的用量
+#[derive(Debug, Clone, Serialize)]
+pub struct MatrixRow {
+    /// 项目路径
+    pub project: String,
+    /// 各月的用量，与 ProjectMatrix::months 一一对应
+    pub months: Vec<MatrixCell>,
+    /// 该项目在所有月份的合计
+    pub total: MatrixCell,
+}
+
+/// 矩阵中一个单元格的用量
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct MatrixCell {
+    /// token 总量
+    pub total_tokens: u64,
+    /// 有价格的模型的费用合计，没有任何模型有价格时为 None
+    pub cost_usd: Option<f64>,
+}
+
+impl MatrixCell {
+    fn add(&mut self, tokens: u64, cost: Option<f64>) {
+        self.total_tokens += tokens;
+        if let Some(cost) = cost {
+            self.cost_usd = Some(self.cost_usd.unwrap_or(0.0) + cost);
+        }
+    }
+
+    /// 按 metric 取值，费用缺失时为 0
+    pub fn get(&self, metric: Metric) -> f64 {
+        DayValue { tokens: self.total_tokens, cost_usd: self.cost_usd }.get(metric)
+    }
+}
+
+/// 项目 × 月的用量透视表，包括行合计与列合计
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectMatrix {
+    /// 各列对应的计费月，按时间顺序排列
+    pub months: Vec<MatrixMonth>,
+    /// 按 metric 的合计降序排列
+    pub projects: Vec<MatrixRow>,
+    /// 各月所有项目的合计
+    pub month_totals: Vec<MatrixCell>,
+    /// 所有项目在所有月份的合计
+    pub total: MatrixCell,
+}
+
+impl ProjectMatrix {
+    /// 以 end 所在的计费月为最后一列，向前共 months 个月；更早的数据不计入。
+    /// 费用按 (项目, 模型, 日期) 计算后累加，列合计与 monthly 报告中同一月份的数字一致
+    pub fn build(
+        project_models: &[ProjectModelUsage],
+        months: usize,
+        end: NaiveDate,
+        billing_day: u32,
+        metric: Metric,
+    ) -> Self {
+        let month_of = billing_month_of(billing_day);
+        let mut columns: Vec<MatrixMonth> = Vec::with_capacity(months);
+        let mut date = end;
+        for _ in 0..months {
+            let (month, start, end) = month_of(date);
+            columns.push(MatrixMonth { month, start, end });
+            let Some(previous) = start.pred_opt() else {
+                break;
+            };
+            date = previous;
+        }
+        columns.reverse();
+        let column_of = |date: NaiveDate| columns.iter().position(|month| month.start <= date && date <= month.end);
+
+        let mut rows: HashMap<&str, MatrixRow> = HashMap::new();
+        let mut month_totals = vec![MatrixCell::default(); columns.len()];
+        let mut total = MatrixCell::default();
+        for usage in project_models {
+            let Some(column) = NaiveDate::parse_from_str(&usage.date, "%Y-%m-%d").ok().and_then(column_of) else {
+                continue;
+            };
+            let tokens = usage.aggregate.usage.total();
+            let cost = cost_for(&usage.model, &usage.aggregate.usage);
+            let row = rows.entry(&usage.project).or_insert_with(|| MatrixRow {
+                project: usage.project.clone(),
+                months: vec![MatrixCell::default(); columns.len()],
+                total: MatrixCell::default(),
+            });
+            row.months[column].add(tokens, cost);
+            row.total.add(tokens, cost);
+            month_totals[column].add(tokens, cost);
+            total.add(tokens, cost);
+        }
+
+        let mut projects: Vec<MatrixRow> = rows.into_values().collect();
+        projects.sort_by(|a, b| {
+            b.total
+                .get(metric)
+                .total_cmp(&a.total.get(metric))
+                .then_with(|| a.project.cmp(&b.project))
+        });
+        Self {
+            months: columns,
+            projects,
+            month_totals,
+            total,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::item::{Usage, UsageAggregate};
+    use super::*;
+
+    fn entry(project: &str, date: &str, output_tokens: u64) -> ProjectModelUsage {
+        let aggregate = UsageAggregate {
+            usage: Usage { output_tokens: Some(output_tokens), ..Usage::default() },
+            messages: 1,
+            ..UsageAggregate::default()
+        };
+        ProjectModelUsage {
+            project: project.to_string(),
+            model: "claude-sonnet-4-20250514".to_string(),
+            date: date.to_string(),
+            aggregate,
+        }
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn tokens(cells: &[MatrixCell]) -> Vec<u64> {
+        cells.iter().map(|cell| cell.total_tokens).collect()
+    }
+
+    #[test]
+    fn rows_and_columns_sum_to_the_total() {
+        let entries = [
+            entry("/work/small", "2025-09-01", 5),
+            entry("/work/big", "2025-08-31", 100),
+            entry("/work/big", "2025-09-15", 20),
+            entry("/work/big", "2025-07-01", 1),
+            // 早于第一列的数据不计入
+            entry("/work/big", "2025-06-30", 1_000),
+        ];
+
+        let matrix = ProjectMatrix::build(&entries, 3, date("2025-09-20"), 1, Metric::Tokens);
+
+        let months: Vec<&str> = matrix.months.iter().map(|month| month.month.as_str()).collect();
+        assert_eq!(months, ["2025-07", "2025-08", "2025-09"]);
+        let projects: Vec<&str> = matrix.projects.iter().map(|row| row.project.as_str()).collect();
+        assert_eq!(projects, ["/work/big", "/work/small"]);
+        assert_eq!(tokens(&matrix.projects[0].months), [1, 100, 20]);
+        assert_eq!(matrix.projects[0].total.total_tokens, 121);
+        assert_eq!(tokens(&matrix.month_totals), [1, 100, 25]);
+        assert_eq!(matrix.total.total_tokens, 126);
+        assert!(matrix.total.cost_usd.is_some());
+    }
+
+    #[test]
+    fn columns_follow_the_billing_day() {
+        let entries = [entry("/work/app", "2025-09-14", 1), entry("/work/app", "2025-09-15", 10)];
+
+        let matrix = ProjectMatrix::build(&entries, 2, date("2025-09-20"), 15, Metric::Tokens);
+
+        assert_eq!(matrix.months[0].start, date("2025-08-15"));
+        assert_eq!(matrix.months[0].end, date("2025-09-14"));
+        assert_eq!(matrix.months[1].start, date("2025-09-15"));
+        assert_eq!(tokens(&matrix.month_totals), [1, 10]);
+    }
+}
@@ -0,0 +1,214 @@
+//! cccost merge：合并多人或多台机器各自导出的 `cccost --json`，不需要共享原始日志
+//!
+//! 每份导出是默认报告的 JSON 输出，meta.schema_version 必须等于 [`JSON_SCHEMA_VERSION`]，
+//! 因此导出时不能使用 --no-footer；meta.label（导出时的 --label）标识导出者，没有时使用文件名。
+//! 导出中只有按日、按模型的 token 数，合并后的费用按本机的价格表重新计算，消息数记为 0。
+//! 各份导出相加的方式见 [`crate::file_processor::ProcessOutput::from_exports`]。
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use serde_json::Value;
+use crate::item::{DailyUsage, Usage, UsageAggregate};
+use crate::render::JSON_SCHEMA_VERSION;
+
+/// 一份导出中的数据
+#[derive(Debug, Clone)]
+pub struct ExportedUsage {
+    /// 导出文件
+    pub path: PathBuf,
+    /// 导出者的标签
+    pub label: String,
+    /// 按 (模型, 日期) 的用量，按模型、日期排序
+    pub usage: Vec<DailyUsage>,
+}
+
+/// 导出中的 meta，只读取合并需要的字段
+#[derive(Debug, Deserialize)]
+struct ExportMeta {
+    schema_version: Option<u32>,
+    label: Option<String>,
+}
+
+/// 导出中一天的一个模型
+#[derive(Debug, Deserialize)]
+struct ExportModel {
+    model: String,
+    input_tokens: Option<u64>,
+    output_tokens: Option<u64>,
+    cache_creation_input_tokens: Option<u64>,
+    cache_read_input_tokens: Option<u64>,
+}
+
+/// 导出中的一天，--fill-gaps 补充的日期没有模型
+#[derive(Debug, Deserialize)]
+struct ExportDay {
+    date: String,
+    #[serde(default)]
+    models: Vec<ExportModel>,
+}
+
+/// 解析 path 的内容 content；格式版本不符或不是默认报告的导出时返回错误信息
+pub fn parse_export(path: &Path, content: &str) -> Result<ExportedUsage, String> {
+    let value: Value = serde_json::from_str(content).map_err(|e| format!("不是合法的 JSON: {}", e))?;
+    let meta = value
+        .get("meta")
+        .ok_or("缺少 meta，请使用 cccost --json 导出，且不要指定 --no-footer")?;
+    let meta = ExportMeta::deserialize(meta).map_err(|e| format!("meta 格式错误: {}", e))?;
+    match meta.schema_version {
+        Some(JSON_SCHEMA_VERSION) => {}
+        Some(version) => {
+            return Err(format!(
+                "导出的格式版本为 {}，当前版本的 cccost 只能合并格式版本 {} 的导出，请用相同版本的 cccost 重新导出",
+                version, JSON_SCHEMA_VERSION
+            ));
+        }
+        None => {
+            return Err(format!(
+                "缺少 meta.schema_version，导出可能来自较早版本的 cccost，请重新导出（格式版本 {}）",
+                JSON_SCHEMA_VERSION
+            ));
+        }
+    }
+    let days = value
+        .get("data")
+        .ok_or("缺少 data 数组，请使用默认报告的 --json 输出，而不是子命令的输出")?;
+    let days = Vec::<ExportDay>::deserialize(days).map_err(|e| format!("每日数据格式错误: {}", e))?;
+
+    let mut usage: BTreeMap<(String, String), UsageAggregate> = BTreeMap::new();
+    for day in days {
+        if chrono::NaiveDate::parse_from_str(&day.date, "%Y-%m-%d").is_err() {
+            return Err(format!("无法识别的日期: {}", day.date));
+        }
+        for model in day.models {
+            let aggregate = UsageAggregate {
+                usage: Usage {
+                    input_tokens: model.input_tokens,
+                    output_tokens: model.output_tokens,
+                    cache_creation_input_tokens: model.cache_creation_input_tokens,
+                    cache_read_input_tokens: model.cache_read_input_tokens,
+                },
+                ..UsageAggregate::default()
+            };
+            let entry = usage.entry((model.model, day.date.clone())).or_default();
+            *entry += &aggregate;
+        }
+    }
+
+    let label = meta
+        .label
+        .filter(|label| !label.is_empty())
+        .or_else(|| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| path.display().to_string());
+    Ok(ExportedUsage {
+        path: path.to_path_buf(),
+        label,
+        usage: usage.into_iter().map(|((model, date), usage)| DailyUsage { date, model, usage }).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::file_processor::ProcessOutput;
+    use super::*;
+
+    /// 一天中各模型的 (模型, 输入, 输出)
+    type Models<'a> = &'a [(&'a str, u64, u64)];
+
+    /// 一份导出：label 为 None 时 meta 中没有标签；days 为 (日期, 各模型)
+    fn export(label: Option<&str>, days: &[(&str, Models)]) -> String {
+        let data: Vec<Value> = days
+            .iter()
+            .map(|(date, models)| {
+                let models: Vec<Value> = models
+                    .iter()
+                    .map(|(model, input, output)| json!({ "model": model, "input_tokens": input, "output_tokens": output }))
+                    .collect();
+                json!({ "date": date, "total_tokens": 0, "models": models })
+            })
+            .collect();
+        json!({ "meta": { "schema_version": JSON_SCHEMA_VERSION, "label": label }, "data": data }).to_string()
+    }
+
+    fn total(usage: &[DailyUsage]) -> u64 {
+        usage.iter().map(|daily| daily.usage.usage.total()).sum()
+    }
+
+    #[test]
+    fn exports_are_read_per_model_and_day() {
+        let content = export(Some("alice"), &[
+            ("2025-09-10", &[("claude-sonnet-4-20250514", 100, 10), ("claude-opus-4-1-20250805", 1, 1)]),
+            ("2025-09-11", &[]),
+        ]);
+        let exported = parse_export(Path::new("exports/a.json"), &content).unwrap();
+        assert_eq!(exported.label, "alice");
+        let usage: Vec<(&str, &str, u64)> = exported
+            .usage
+            .iter()
+            .map(|daily| (daily.model.as_str(), daily.date.as_str(), daily.usage.usage.total()))
+            .collect();
+        assert_eq!(usage, [("claude-opus-4-1-20250805", "2025-09-10", 2), ("claude-sonnet-4-20250514", "2025-09-10", 110)]);
+
+        let unlabelled = parse_export(Path::new("exports/bob-laptop.json"), &export(None, &[])).unwrap();
+        assert_eq!(unlabelled.label, "bob-laptop");
+    }
+
+    #[test]
+    fn other_schema_versions_are_rejected() {
+        let path = Path::new("a.json");
+        let mut value: Value = serde_json::from_str(&export(Some("alice"), &[])).unwrap();
+        value["meta"]["schema_version"] = json!(JSON_SCHEMA_VERSION + 1);
+        let error = parse_export(path, &value.to_string()).unwrap_err();
+        assert!(error.contains(&format!("导出的格式版本为 {}", JSON_SCHEMA_VERSION + 1)), "{}", error);
+
+        value["meta"].as_object_mut().unwrap().remove("schema_version");
+        assert!(parse_export(path, &value.to_string()).unwrap_err().contains("缺少 meta.schema_version"));
+        assert!(parse_export(path, r#"{"data":[]}"#).unwrap_err().contains("缺少 meta"));
+        assert!(parse_export(path, "[]").unwrap_err().contains("缺少 meta"));
+        assert!(parse_export(path, "{").unwrap_err().contains("不是合法的 JSON"));
+        let bad_date = export(Some("alice"), &[("Sep 10", &[("claude-sonnet-4-20250514", 1, 1)])]);
+        assert!(parse_export(path, &bad_date).unwrap_err().contains("无法识别的日期: Sep 10"));
+    }
+
+    #[test]
+    fn merged_totals_equal_the_sum_of_the_inputs() {
+        let sonnet = "claude-sonnet-4-20250514";
+        let inputs = [
+            ("a.json", export(Some("alice"), &[("2025-09-10", &[(sonnet, 100, 10)]), ("2025-09-11", &[(sonnet, 5, 5)])])),
+            ("b.json", export(Some("bob"), &[("2025-09-10", &[(sonnet, 200, 20), ("claude-opus-4-1-20250805", 7, 3)])])),
+            // 与第一份标签相同，按同一人相加
+            ("c.json", export(Some("alice"), &[("2025-09-10", &[(sonnet, 1000, 0)])])),
+        ];
+        let exports: Vec<ExportedUsage> = inputs
+            .iter()
+            .map(|(path, content)| parse_export(Path::new(path), content).unwrap())
+            .collect();
+        let input_total: u64 = exports.iter().map(|export| total(&export.usage)).sum();
+
+        let merged = ProcessOutput::from_exports(exports);
+        assert_eq!(total(&merged.usage), input_total);
+        assert_eq!(input_total, 1350);
+        let by_model: Vec<(&str, &str, u64)> = merged
+            .usage
+            .iter()
+            .map(|daily| (daily.model.as_str(), daily.date.as_str(), daily.usage.usage.total()))
+            .collect();
+        assert_eq!(by_model, [
+            ("claude-opus-4-1-20250805", "2025-09-10", 10),
+            (sonnet, "2025-09-10", 1330),
+            (sonnet, "2025-09-11", 10),
+        ]);
+        let by_person: Vec<(String, String, u64)> = merged
+            .projects()
+            .into_iter()
+            .map(|row| (row.group, row.date, row.usage.usage.total()))
+            .collect();
+        assert_eq!(by_person, [
+            ("alice".to_string(), "2025-09-10".to_string(), 1110),
+            ("alice".to_string(), "2025-09-11".to_string(), 10),
+            ("bob".to_string(), "2025-09-10".to_string(), 230),
+        ]);
+        assert_eq!(merged.diagnostics.merged.len(), 3);
+    }
+}
@@ -0,0 +1,244 @@
+//! 模型 id 的解析：系列、版本号与快照日期
+
+use std::cmp::{Ordering, Reverse};
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+use chrono::NaiveDate;
+use crate::item::normalize_model_id;
+
+/// 模型系列
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ModelFamily {
+    /// Opus
+    Opus,
+    /// Sonnet
+    Sonnet,
+    /// Haiku
+    Haiku,
+    /// 无法识别的系列，保留模型 id 去掉 claude- 前缀后的部分
+    Unknown(String),
+}
+
+impl ModelFamily {
+    /// 语义顺序中的位置：opus、sonnet、haiku、其他
+    fn rank(&self) -> usize {
+        match self {
+            ModelFamily::Opus => 0,
+            ModelFamily::Sonnet => 1,
+            ModelFamily::Haiku => 2,
+            ModelFamily::Unknown(_) => 3,
+        }
+    }
+}
+
+impl FromStr for ModelFamily {
+    type Err = Infallible;
+
+    /// 不区分大小写的 opus、sonnet、haiku，其他名称为 Unknown
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(match value.to_ascii_lowercase().as_str() {
+            "opus" => ModelFamily::Opus,
+            "sonnet" => ModelFamily::Sonnet,
+            "haiku" => ModelFamily::Haiku,
+            _ => ModelFamily::Unknown(value.to_string()),
+        })
+    }
+}
+
+impl fmt::Display for ModelFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelFamily::Opus => f.write_str("opus"),
+            ModelFamily::Sonnet => f.write_str("sonnet"),
+            ModelFamily::Haiku => f.write_str("haiku"),
+            ModelFamily::Unknown(name) => f.write_str(name),
+        }
+    }
+}
+
+/// 解析后的模型 id，如 claude-sonnet-4-20250514、claude-3-5-haiku-20241022，
+/// 以及 Bedrock / Vertex 格式的 id。
+///
+/// Display 输出表格中使用的简化名称（sonnet4、3-5-haiku 等）；
+/// 排序按系列（opus、sonnet、haiku、其他），同系列内版本较新的在前，最后按原始 id
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParsedModel {
+    /// 模型系列
+    pub family: ModelFamily,
+    /// 版本号的各段，如 4-5 为 [4, 5]；快照日期不计入
+    pub version: Vec<u64>,
+    /// 快照日期，id 不以 8 位日期结尾时为 None
+    pub snapshot_date: Option<NaiveDate>,
+    /// 原始的模型 id
+    pub raw: String,
+    // 去掉 claude- 前缀和快照日期后的部分，保持原有顺序
+    name: String,
+    // id 是否以快照日期结尾（日期本身不一定合法）
+    has_snapshot: bool,
+}
+
+impl ParsedModel {
+    /// 解析模型 id，任何字符串都能得到结果，无法识别的系列为 ModelFamily::Unknown
+    pub fn parse(model_id: &str) -> Self {
+        let normalized = normalize_model_id(model_id);
+        let without_prefix = normalized.strip_prefix("claude-").unwrap_or(&normalized);
+        let parts: Vec<&str> = without_prefix.split('-').collect();
+
+        // 只有至少三段时才把结尾的 8 位数字视为快照日期，sonnet-20250514 这样的 id 保持原样
+        let snapshot = parts
+            .last()
+            .filter(|last| parts.len() >= 3 && last.len() == 8 && last.chars().all(|c| c.is_numeric()));
+        let name = match snapshot {
+            Some(_) => parts[..parts.len() - 1].join("-"),
+            None => without_prefix.to_string(),
+        };
+
+        let family = if normalized.contains("opus") {
+            ModelFamily::Opus
+        } else if normalized.contains("sonnet") {
+            ModelFamily::Sonnet
+        } else if normalized.contains("haiku") {
+            ModelFamily::Haiku
+        } else {
+            ModelFamily::Unknown(name.clone())
+        };
+        let version = parts
+            .iter()
+            .filter(|part| part.len() < 8)
+            .filter_map(|part| part.parse::<u64>().ok())
+            .collect();
+
+        Self {
+            family,
+            version,
+            snapshot_date: snapshot.and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok()),
+            raw: model_id.to_string(),
+            name,
+            has_snapshot: snapshot.is_some(),
+        }
+    }
+}
+
+impl FromStr for ParsedModel {
+    type Err = Infallible;
+
+    fn from_str(model_id: &str) -> Result<Self, Self::Err> {
+        Ok(Self::parse(model_id))
+    }
+}
+
+impl fmt::Display for ParsedModel {
+    /// 简化的名称：去掉 claude- 前缀与快照日期，{系列}-{版本} 或 {版本}-{系列} 两段时合并为 sonnet4、opus3
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<&str> = self.name.split('-').collect();
+        match parts.as_slice() {
+            [first, second] if self.has_snapshot => {
+                if first.chars().all(|c| c.is_numeric()) {
+                    write!(f, "{}{}", second, first)
+                } else if second.chars().all(|c| c.is_numeric()) {
+                    write!(f, "{}{}", first, second)
+                } else {
+                    f.write_str(&self.name)
+                }
+            }
+            _ => f.write_str(&self.name),
+        }
+    }
+}
+
+impl Ord for ParsedModel {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let key = |model: &Self| (model.family.rank(), Reverse(model.version.clone()));
+        key(self).cmp(&key(other)).then_with(|| self.raw.cmp(&other.raw))
+    }
+}
+
+impl PartialOrd for ParsedModel {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_is_the_simplified_name() {
+        let names = [
+            ("claude-sonnet-4-20250514", "sonnet4"),
+            ("claude-opus-4-20250514", "opus4"),
+            ("claude-opus-4-1-20250805", "opus-4-1"),
+            ("claude-sonnet-4-5-20250929", "sonnet-4-5"),
+            ("claude-3-opus-20240229", "opus3"),
+            ("claude-3-5-haiku-20241022", "3-5-haiku"),
+            ("claude-3-7-sonnet-20250219", "3-7-sonnet"),
+            ("claude-sonnet-4-5", "sonnet-4-5"),
+            ("sonnet-20250514", "sonnet-20250514"),
+            ("us.anthropic.claude-sonnet-4-20250514-v1:0", "sonnet4"),
+            ("claude-opus-4-1@20250805", "opus-4-1"),
+            ("gpt-5", "gpt-5"),
+            ("<synthetic>", "<synthetic>"),
+        ];
+        for (model_id, expected) in names {
+            assert_eq!(ParsedModel::parse(model_id).to_string(), expected, "{}", model_id);
+        }
+    }
+
+    #[test]
+    fn parse_extracts_family_version_and_snapshot() {
+        let model: ParsedModel = "anthropic.claude-3-5-haiku-20241022-v1:0".parse().unwrap();
+        assert_eq!(model.family, ModelFamily::Haiku);
+        assert_eq!(model.version, [3, 5]);
+        assert_eq!(model.snapshot_date, NaiveDate::from_ymd_opt(2024, 10, 22));
+        assert_eq!(model.raw, "anthropic.claude-3-5-haiku-20241022-v1:0");
+
+        let undated = ParsedModel::parse("claude-opus-4-5");
+        assert_eq!((undated.family, undated.version, undated.snapshot_date), (ModelFamily::Opus, vec![4, 5], None));
+        // 结尾的 8 位数字不是合法日期时仍视为快照，只是没有日期
+        let invalid_date = ParsedModel::parse("claude-sonnet-4-20251399");
+        assert_eq!((invalid_date.to_string(), invalid_date.snapshot_date), ("sonnet4".to_string(), None));
+        assert_eq!(ParsedModel::parse("gpt-5").family, ModelFamily::Unknown("gpt-5".to_string()));
+    }
+
+    #[test]
+    fn family_names_round_trip() {
+        for family in [ModelFamily::Opus, ModelFamily::Sonnet, ModelFamily::Haiku] {
+            assert_eq!(family.to_string().parse::<ModelFamily>().unwrap(), family);
+        }
+        assert_eq!("SONNET".parse::<ModelFamily>().unwrap(), ModelFamily::Sonnet);
+        assert_eq!("mistral".parse::<ModelFamily>().unwrap(), ModelFamily::Unknown("mistral".to_string()));
+    }
+
+    #[test]
+    fn models_sort_by_family_then_newest_version() {
+        let mut models: Vec<ParsedModel> = [
+            "gpt-5",
+            "claude-3-haiku-20240307",
+            "claude-sonnet-4-20250514",
+            "claude-3-opus-20240229",
+            "claude-3-5-haiku-20241022",
+            "claude-opus-4-1-20250805",
+            "claude-sonnet-4-5-20250929",
+            "claude-opus-4-5-20251101",
+            "claude-sonnet-4-20250601",
+        ]
+        .into_iter()
+        .map(ParsedModel::parse)
+        .collect();
+        models.sort();
+        let order: Vec<&str> = models.iter().map(|model| model.raw.as_str()).collect();
+        assert_eq!(order, [
+            "claude-opus-4-5-20251101",
+            "claude-opus-4-1-20250805",
+            "claude-3-opus-20240229",
+            "claude-sonnet-4-5-20250929",
+            "claude-sonnet-4-20250514",
+            "claude-sonnet-4-20250601",
+            "claude-3-5-haiku-20241022",
+            "claude-3-haiku-20240307",
+            "gpt-5",
+        ]);
+    }
+}
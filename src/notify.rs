@@ -0,0 +1,355 @@
+//! cccost notify：满足条件时把用量概要以 JSON 发送到 Slack 兼容的 incoming webhook
+//!
+//! 发送的内容为 [`NotifyPayload`]，例如：
+//!
+//! ```json
+//! {
+//!   "text": "cccost 2025-09-10: 今日 $12.34（1234567 tokens），本计费月 $120.50，本月上限已用 82.0%",
+//!   "date": "2025-09-10",
+//!   "today": { "tokens": 1234567, "cost_usd": 12.34 },
+//!   "month_to_date": { "start": "2025-09-01", "tokens": 20000000, "cost_usd": 120.5 },
+//!   "budget": { "window": "month", "limit": 30000000, "consumed": 24600000, "percentage": 82.0 },
+//!   "triggers": ["daily_cost", "budget"]
+//! }
+//! ```
+//!
+//! text 供 Slack 直接显示，其余字段供其他接收方解析。没有任何有价格的模型时 cost_usd 为 null，
+//! 没有设置 token 上限时 budget 为 null。
+
+use chrono::NaiveDate;
+use serde::Serialize;
+use crate::item::DailyUsage;
+use crate::limit::LimitStatus;
+use crate::period::billing_cycle;
+use crate::pricing::cost_for;
+use crate::tr;
+
+/// 一段时间内的 token 总量与费用
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageTotals {
+    /// token 总量
+    pub tokens: u64,
+    /// 估算费用，所有模型都没有价格时为 None
+    pub cost_usd: Option<f64>,
+}
+
+impl UsageTotals {
+    /// usage 中日期在 [start, end] 内的合计
+    fn between(usage: &[DailyUsage], start: NaiveDate, end: NaiveDate) -> Self {
+        let in_range: Vec<&DailyUsage> = usage
+            .iter()
+            .filter(|daily| {
+                NaiveDate::parse_from_str(&daily.date, "%Y-%m-%d").is_ok_and(|date| date >= start && date <= end)
+            })
+            .collect();
+        let costs: Vec<f64> = in_range.iter().filter_map(|daily| cost_for(&daily.model, &daily.usage.usage)).collect();
+        Self {
+            tokens: in_range.iter().map(|daily| daily.usage.usage.total()).sum(),
+            cost_usd: (!costs.is_empty()).then(|| costs.iter().sum()),
+        }
+    }
+}
+
+/// 计费月开始至今的合计
+#[derive(Debug, Clone, Serialize)]
+pub struct MonthToDate {
+    /// 计费月的第一天
+    pub start: NaiveDate,
+    /// 合计
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+/// token 上限的使用情况
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetStatus {
+    /// 上限的时间窗口，写法与 --limit-window 相同
+    pub window: String,
+    /// 窗口内的 token 上限
+    pub limit: u64,
+    /// 窗口内已用的 token 数
+    pub consumed: u64,
+    /// 已用量占上限的百分比
+    pub percentage: f64,
+}
+
+impl From<&LimitStatus> for BudgetStatus {
+    fn from(status: &LimitStatus) -> Self {
+        Self {
+            window: String::from(status.window),
+            limit: status.limit,
+            consumed: status.consumed,
+            percentage: status.percentage(),
+        }
+    }
+}
+
+/// 发送通知的条件，满足任意一个即发送
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NotifyConditions {
+    /// 今天的费用超过该值（美元）
+    pub daily_cost_above: Option<f64>,
+    /// token 上限已用的百分比超过该值
+    pub budget_above: Option<f64>,
+    /// 不检查条件，总是发送
+    pub always: bool,
+}
+
+impl NotifyConditions {
+    /// 是否设置了任何条件
+    pub fn is_empty(&self) -> bool {
+        !self.always && self.daily_cost_above.is_none() && self.budget_above.is_none()
+    }
+}
+
+/// 发送到 webhook 的内容，格式见模块文档
+#[derive(Debug, Clone, Serialize)]
+pub struct NotifyPayload {
+    /// Slack 显示的一行概要
+    pub text: String,
+    /// 今天的日期（UTC）
+    pub date: NaiveDate,
+    /// 今天的合计
+    pub today: UsageTotals,
+    /// 计费月至今的合计
+    pub month_to_date: MonthToDate,
+    /// token 上限的使用情况，没有设置上限时为 None
+    pub budget: Option<BudgetStatus>,
+    /// 满足的条件：daily_cost、budget 或 always
+    pub triggers: Vec<&'static str>,
+}
+
+impl NotifyPayload {
+    /// 根据截至 today 的按日用量构造，计费月从每月 billing_day 日开始
+    pub fn build(usage: &[DailyUsage], limit: Option<&LimitStatus>, today: NaiveDate, billing_day: u32) -> Self {
+        let (month_start, _) = billing_cycle(today, billing_day);
+        let today_totals = UsageTotals::between(usage, today, today);
+        let month_to_date = MonthToDate { start: month_start, totals: UsageTotals::between(usage, month_start, today) };
+        let budget = limit.map(BudgetStatus::from);
+
+        let cost = |totals: &UsageTotals| totals.cost_usd.map_or_else(|| "—".to_string(), |cost| format!("${:.2}", cost));
+        let mut text = tr!(
+            "notify.summary",
+            today,
+            cost(&today_totals),
+            today_totals.tokens,
+            cost(&month_to_date.totals)
+        );
+        if let Some(status) = limit {
+            text.push_str(&tr!("notify.limit", status.window, format!("{:.1}", status.percentage())));
+        }
+
+        Self {
+            text,
+            date: today,
+            today: today_totals,
+            month_to_date,
+            budget,
+            triggers: Vec::new(),
+        }
+    }
+
+    /// 按 conditions 记录满足的条件，返回是否应当发送
+    pub fn evaluate(&mut self, conditions: &NotifyConditions) -> bool {
+        self.triggers.clear();
+        if conditions
+            .daily_cost_above
+            .is_some_and(|threshold| self.today.cost_usd.is_some_and(|cost| cost > threshold)) {
+            self.triggers.push("daily_cost");
+        }
+        if let (Some(threshold), Some(budget)) = (conditions.budget_above, &self.budget)
+            && budget.percentage > threshold {
+            self.triggers.push("budget");
+        }
+        if conditions.always {
+            self.triggers.push("always");
+        }
+        !self.triggers.is_empty()
+    }
+}
+
+/// 发送失败的原因
+#[cfg(feature = "notify")]
+#[derive(Debug)]
+pub enum NotifyError {
+    /// 连接失败、超时等，没有得到响应
+    Request(String),
+    /// webhook 返回了非 2xx 的状态码
+    Status(u16),
+}
+
+#[cfg(feature = "notify")]
+impl std::fmt::Display for NotifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotifyError::Request(e) => f.write_str(&tr!("error.notify_request", e)),
+            NotifyError::Status(code) => f.write_str(&tr!("error.notify_status", code)),
+        }
+    }
+}
+
+/// 以 POST 发送 payload，整个请求（含连接与读取响应）不超过 timeout
+#[cfg(feature = "notify")]
+pub fn send(url: &str, payload: &NotifyPayload, timeout: std::time::Duration) -> Result<(), NotifyError> {
+    let body = serde_json::to_string(payload).map_err(|e| NotifyError::Request(e.to_string()))?;
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(timeout))
+        .http_status_as_error(false)
+        .build()
+        .into();
+    let response = agent
+        .post(url)
+        .header("Content-Type", "application/json")
+        .send(body)
+        .map_err(|e| NotifyError::Request(e.to_string()))?;
+    let status = response.status();
+    if status.is_success() {
+        Ok(())
+    } else {
+        Err(NotifyError::Status(status.as_u16()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::item::{Usage, UsageAggregate};
+    use super::*;
+
+    const MODEL: &str = "claude-sonnet-4-20250514";
+
+    fn daily(date: &str, output_tokens: u64) -> DailyUsage {
+        DailyUsage {
+            date: date.to_string(),
+            model: MODEL.to_string(),
+            usage: UsageAggregate {
+                usage: Usage { output_tokens: Some(output_tokens), ..Usage::default() },
+                messages: 1,
+                ..UsageAggregate::default()
+            },
+        }
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn payload() -> NotifyPayload {
+        let usage = [daily("2025-08-31", 7), daily("2025-09-01", 1_000_000), daily("2025-09-10", 200_000)];
+        NotifyPayload::build(&usage, None, date("2025-09-10"), 1)
+    }
+
+    #[test]
+    fn payload_sums_today_and_the_billing_month() {
+        let payload = payload();
+        assert_eq!(payload.today.tokens, 200_000);
+        assert_eq!(payload.month_to_date.start, date("2025-09-01"));
+        assert_eq!(payload.month_to_date.totals.tokens, 1_200_000);
+        assert!(payload.today.cost_usd.unwrap() > 0.0);
+        assert!(payload.budget.is_none());
+        assert!(payload.text.contains("2025-09-10"));
+    }
+
+    #[test]
+    fn evaluate_records_the_met_conditions() {
+        let mut payload = payload();
+        assert!(!payload.evaluate(&NotifyConditions { daily_cost_above: Some(1_000.0), ..NotifyConditions::default() }));
+        assert!(payload.triggers.is_empty());
+
+        let conditions = NotifyConditions { daily_cost_above: Some(0.01), budget_above: Some(50.0), always: true };
+        assert!(payload.evaluate(&conditions));
+        // 没有设置上限时 budget 条件不成立
+        assert_eq!(payload.triggers, vec!["daily_cost", "always"]);
+    }
+
+    #[cfg(feature = "notify")]
+    mod webhook {
+        use std::io::{BufRead, BufReader, Read, Write};
+        use std::net::TcpListener;
+        use std::thread::{self, JoinHandle};
+        use std::time::{Duration, Instant};
+        use serde_json::Value;
+        use super::*;
+
+        /// 本地的 webhook：依次以 statuses 中的状态码回应各个请求，返回收到的各请求的路径与 JSON 内容
+        fn mock_webhook(statuses: Vec<u16>) -> (String, JoinHandle<Vec<(String, Value)>>) {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}/hooks/cccost", listener.local_addr().unwrap());
+            let server = thread::spawn(move || {
+                statuses
+                    .into_iter()
+                    .map(|status| {
+                        let (stream, _) = listener.accept().unwrap();
+                        let mut reader = BufReader::new(stream);
+                        let mut request_line = String::new();
+                        reader.read_line(&mut request_line).unwrap();
+                        let mut content_length = 0;
+                        loop {
+                            let mut header = String::new();
+                            reader.read_line(&mut header).unwrap();
+                            if header.trim().is_empty() {
+                                break;
+                            }
+                            if let Some((name, value)) = header.split_once(':')
+                                && name.eq_ignore_ascii_case("content-length") {
+                                content_length = value.trim().parse().unwrap();
+                            }
+                        }
+                        let mut body = vec![0; content_length];
+                        reader.read_exact(&mut body).unwrap();
+                        let response = format!("HTTP/1.1 {} Mock\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+                        reader.get_mut().write_all(response.as_bytes()).unwrap();
+                        (request_line, serde_json::from_slice(&body).unwrap())
+                    })
+                    .collect()
+            });
+            (url, server)
+        }
+
+        #[test]
+        fn posts_the_documented_payload() {
+            let (url, server) = mock_webhook(vec![200]);
+            let mut payload = payload();
+            payload.evaluate(&NotifyConditions { always: true, ..NotifyConditions::default() });
+
+            send(&url, &payload, Duration::from_secs(5)).unwrap();
+
+            let requests = server.join().unwrap();
+            assert!(requests[0].0.starts_with("POST /hooks/cccost "));
+            let body = &requests[0].1;
+            assert_eq!(body["text"], payload.text.as_str());
+            assert_eq!(body["date"], "2025-09-10");
+            assert_eq!(body["today"]["tokens"], 200_000);
+            assert!(body["today"]["cost_usd"].is_f64());
+            assert_eq!(body["month_to_date"]["start"], "2025-09-01");
+            assert_eq!(body["month_to_date"]["tokens"], 1_200_000);
+            assert!(body["budget"].is_null());
+            assert_eq!(body["triggers"], serde_json::json!(["always"]));
+        }
+
+        #[test]
+        fn error_status_is_returned_and_later_sends_still_work() {
+            let (url, server) = mock_webhook(vec![500, 204]);
+            let payload = payload();
+
+            let error = send(&url, &payload, Duration::from_secs(5)).unwrap_err();
+            assert!(matches!(error, NotifyError::Status(500)));
+            assert!(error.to_string().contains("500"));
+            send(&url, &payload, Duration::from_secs(5)).unwrap();
+            assert_eq!(server.join().unwrap().len(), 2);
+        }
+
+        #[test]
+        fn unresponsive_webhook_times_out() {
+            // 接受连接但从不回应
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}/", listener.local_addr().unwrap());
+            let started = Instant::now();
+
+            let error = send(&url, &payload(), Duration::from_millis(300)).unwrap_err();
+
+            assert!(matches!(error, NotifyError::Request(_)));
+            assert!(started.elapsed() < Duration::from_secs(5));
+            drop(listener);
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! 将一行日志解析为 Item，扫描文件时使用的也是这里的规则
+
+use std::fmt;
+use serde::Deserialize;
+use serde_json::Value;
+use crate::item::{normalize_model_id, Item, LogEntry};
+
+/// Claude Code 为本地生成的消息（如中断、API 错误）记录的模型名，这些消息没有实际调用 API
+pub const SYNTHETIC_MODEL: &str = "<synthetic>";
+
+/// 解析时的容错选项
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// 记录不符合 Claude Code 的嵌套格式（message.model、message.usage）时，
+    /// 再尝试按顶层的 model、timestamp、usage 字段解析
+    pub flat_fallback: bool,
+    /// 丢弃模型为 <synthetic> 的记录
+    pub skip_synthetic: bool,
+}
+
+impl Default for ParseOptions {
+    /// 与扫描时相同：接受扁平格式，保留 <synthetic> 记录
+    fn default() -> Self {
+        Self {
+            flat_fallback: true,
+            skip_synthetic: false,
+        }
+    }
+}
+
+/// 无法从一行日志中得到 Item 的原因
+#[derive(Debug)]
+pub enum ParseError {
+    /// 该行不是合法的 JSON
+    Json(serde_json::Error),
+    /// 记录中没有模型名称
+    MissingModel,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Json(e) => write!(f, "无法解析为 JSON: {}", e),
+            ParseError::MissingModel => write!(f, "记录中没有模型名称"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::Json(e) => Some(e),
+            ParseError::MissingModel => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for ParseError {
+    fn from(e: serde_json::Error) -> Self {
+        ParseError::Json(e)
+    }
+}
+
+/// 解析 JSONL 中的一行。行尾的换行符会被去掉；
+/// 不是合法 JSON 时返回错误，合法但不是一条消息（如 summary 记录）时返回 Ok(None)
+///
+/// ```
+/// use cccost::parse::{parse_line, ParseOptions};
+///
+/// let line = r#"{"type":"assistant","timestamp":"2025-09-10T09:00:00.000Z","sessionId":"s1","message":{"id":"msg_1","model":"claude-sonnet-4-20250514","usage":{"input_tokens":10,"output_tokens":250}}}"#;
+/// let item = parse_line(line, &ParseOptions::default()).unwrap().unwrap();
+/// assert_eq!(item.model, "claude-sonnet-4-20250514");
+/// assert_eq!(item.usage.unwrap().output_tokens, Some(250));
+///
+/// let summary = r#"{"type":"summary","summary":"Fix the build","leafUuid":"u1"}"#;
+/// assert!(parse_line(summary, &ParseOptions::default()).unwrap().is_none());
+/// assert!(parse_line("{not json", &ParseOptions::default()).is_err());
+/// ```
+pub fn parse_line(line: &str, options: &ParseOptions) -> Result<Option<Item>, ParseError> {
+    let value: Value = serde_json::from_str(line.trim_end_matches(['\r', '\n']))?;
+    Ok(parse_value(&value, options, |_| {}))
+}
+
+/// 从一条 JSON 记录中提取 Item；记录符合嵌套格式时先交给 observe（用于会话统计）
+pub(crate) fn parse_value(value: &Value, options: &ParseOptions, observe: impl FnOnce(&LogEntry)) -> Option<Item> {
+    let item = match LogEntry::deserialize(value) {
+        Ok(entry) => {
+            observe(&entry);
+            Item::try_from(entry).ok()
+        }
+        Err(_) if options.flat_fallback => Item::deserialize(value).ok().map(|item| Item {
+            model: normalize_model_id(&item.model),
+            ..item
+        }),
+        Err(_) => None,
+    };
+    item.filter(|item| !(options.skip_synthetic && item.model == SYNTHETIC_MODEL))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NESTED: &str = r#"{"type":"assistant","timestamp":"2025-09-10T09:00:00.000Z","sessionId":"s1","version":"1.0.98","gitBranch":"main","cwd":"/home/dev/app","message":{"id":"msg_1","model":"claude-sonnet-4-20250514","stop_reason":"tool_use","content":[{"type":"text","text":"Reading"},{"type":"tool_use","id":"t1","name":"Read","input":{}}],"usage":{"input_tokens":10,"output_tokens":250,"cache_read_input_tokens":4000}}}"#;
+    const FLAT: &str = r#"{"model":"us.anthropic.claude-sonnet-4-20250514-v1:0","timestamp":"2025-09-10T09:00:00Z","usage":{"prompt_tokens":10,"completion_tokens":20}}"#;
+    const SYNTHETIC: &str = r#"{"type":"assistant","timestamp":"2025-09-10T09:00:00Z","message":{"id":"msg_2","model":"<synthetic>","usage":{"input_tokens":0,"output_tokens":0}}}"#;
+
+    fn parse(line: &str, options: ParseOptions) -> Option<Item> {
+        parse_line(line, &options).unwrap()
+    }
+
+    #[test]
+    fn nested_records_keep_their_metadata() {
+        let item = parse(&format!("{}\r\n", NESTED), ParseOptions::default()).unwrap();
+        assert_eq!(item.model, "claude-sonnet-4-20250514");
+        assert_eq!(item.timestamp, "2025-09-10T09:00:00.000Z");
+        assert_eq!(item.tools, ["Read"]);
+        assert_eq!(item.stop_reason.as_deref(), Some("tool_use"));
+        assert_eq!(item.version.as_deref(), Some("1.0.98"));
+        assert_eq!(item.git_branch.as_deref(), Some("main"));
+        assert_eq!(item.cwd.as_deref(), Some("/home/dev/app"));
+        let usage = item.usage.unwrap();
+        assert_eq!((usage.input_tokens, usage.output_tokens, usage.cache_read_input_tokens), (Some(10), Some(250), Some(4000)));
+    }
+
+    #[test]
+    fn flat_records_are_only_accepted_with_the_fallback() {
+        let item = parse(FLAT, ParseOptions::default()).unwrap();
+        assert_eq!(item.model, "claude-sonnet-4-20250514");
+        assert_eq!(item.usage.unwrap().output_tokens, Some(20));
+        assert!(parse(FLAT, ParseOptions { flat_fallback: false, ..ParseOptions::default() }).is_none());
+    }
+
+    #[test]
+    fn synthetic_records_are_skipped_on_request() {
+        assert_eq!(parse(SYNTHETIC, ParseOptions::default()).unwrap().model, SYNTHETIC_MODEL);
+        assert!(parse(SYNTHETIC, ParseOptions { skip_synthetic: true, ..ParseOptions::default() }).is_none());
+    }
+
+    #[test]
+    fn non_messages_and_invalid_json() {
+        assert!(parse(r#"{"type":"user","timestamp":"2025-09-10T09:00:00Z","message":{"role":"user","content":"hi"}}"#, ParseOptions::default()).is_none());
+        assert!(parse("[]", ParseOptions::default()).is_none());
+        assert!(matches!(parse_line("{\"type\":", &ParseOptions::default()), Err(ParseError::Json(_))));
+    }
+
+    #[test]
+    fn log_entry_without_a_model_is_rejected() {
+        let entry: LogEntry = serde_json::from_str(r#"{"type":"assistant","timestamp":"2025-09-10T09:00:00Z","message":{"usage":{"input_tokens":1}}}"#).unwrap();
+        assert!(matches!(Item::try_from(entry), Err(ParseError::MissingModel)));
+        let entry: LogEntry = serde_json::from_str(NESTED).unwrap();
+        assert_eq!(Item::try_from(entry).unwrap().model, "claude-sonnet-4-20250514");
+    }
+}
@@ -0,0 +1,357 @@
+//! 按日、周、月（计费月）汇总使用量
+
+use std::collections::{BTreeMap, BTreeSet};
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use serde::Serialize;
+use crate::item::{DailyUsage, Usage};
+use crate::pricing::cost_for;
+
+/// 多天合计时使用 u64，避免单个 Usage 的 u32 字段溢出
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TokenTotals {
+    /// 输入 token 数
+    pub input_tokens: u64,
+    /// 输出 token 数
+    pub output_tokens: u64,
+    /// 写入提示缓存的 token 数
+    pub cache_creation_input_tokens: u64,
+    /// 读取提示缓存的 token 数
+    pub cache_read_input_tokens: u64,
+    /// 四项之和
+    pub total_tokens: u64,
+}
+
+impl TokenTotals {
+    /// 累加单次的使用量，缺失的字段按 0 计
+    pub fn add(&mut self, usage: &Usage) {
+        self.input_tokens += usage.input_tokens.unwrap_or(0);
+        self.output_tokens += usage.output_tokens.unwrap_or(0);
+        self.cache_creation_input_tokens += usage.cache_creation_input_tokens.unwrap_or(0);
+        self.cache_read_input_tokens += usage.cache_read_input_tokens.unwrap_or(0);
+        self.total_tokens += usage.total();
+    }
+
+    /// 累加另一个合计
+    pub fn merge(&mut self, other: &TokenTotals) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+        self.total_tokens += other.total_tokens;
+    }
+}
+
+/// 某个时间段内单个模型的用量
+#[derive(Debug, Clone, Serialize)]
+pub struct PeriodModel {
+    /// 模型名称
+    pub model: String,
+    /// 该模型的 token 合计
+    #[serde(flatten)]
+    pub tokens: TokenTotals,
+    /// 未知模型没有价格时为 None
+    pub cost_usd: Option<f64>,
+}
+
+/// 汇总的时间段类型，决定标签的键名、表头以及是否显示环比
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeriodKind {
+    /// 按日
+    Day,
+    /// 按月（或计费月）
+    Month,
+    /// 按周
+    Week,
+}
+
+impl PeriodKind {
+    /// JSON 中时间段标签的键名
+    pub fn key(self) -> &'static str {
+        match self {
+            PeriodKind::Day => "date",
+            PeriodKind::Month => "month",
+            PeriodKind::Week => "week",
+        }
+    }
+
+    /// 表格中时间段列的表头
+    pub fn header(self) -> &'static str {
+        match self {
+            PeriodKind::Day => "Date",
+            PeriodKind::Month => "Month",
+            PeriodKind::Week => "Week",
+        }
+    }
+
+    /// 表格的标题
+    pub fn title(self) -> &'static str {
+        match self {
+            PeriodKind::Day => "Daily Usage",
+            PeriodKind::Month => "Monthly Usage",
+            PeriodKind::Week => "Weekly Usage",
+        }
+    }
+}
+
+/// 一个时间段（月、周）的汇总
+#[derive(Debug, Clone, Serialize)]
+pub struct PeriodSummary {
+    /// 时间段的标签，如 2025-09；JSON 中由调用方以 month、week 等键输出
+    #[serde(skip)]
+    pub label: String,
+    /// 时间段的第一天
+    pub start: NaiveDate,
+    /// 时间段的最后一天
+    pub end: NaiveDate,
+    /// 是否为尚未结束的当前时间段
+    pub partial: bool,
+    /// 有用量的天数
+    pub active_days: u64,
+    /// 时间段内的 token 合计
+    #[serde(flatten)]
+    pub tokens: TokenTotals,
+    /// 有价格的模型的费用合计，没有任何模型有价格时为 None
+    pub cost_usd: Option<f64>,
+    /// 上一个时间段的 token 总量，由 fill_previous_totals 填充；最早的时间段没有可比较的数据时为 None
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_total_tokens: Option<u64>,
+    /// 各模型的用量，按模型名称排序
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<PeriodModel>,
+}
+
+impl PeriodSummary {
+    /// 形如 Sep 8–14 或 Sep 29–Oct 5 的日期范围
+    pub fn date_range(&self) -> String {
+        date_range(self.start, self.end)
+    }
+}
+
+/// 形如 Sep 8–14 或 Sep 29–Oct 5 的日期范围
+pub fn date_range(start: NaiveDate, end: NaiveDate) -> String {
+    if start.month() == end.month() {
+        format!("{}–{}", start.format("%b %-d"), end.format("%-d"))
+    } else {
+        format!("{}–{}", start.format("%b %-d"), end.format("%b %-d"))
+    }
+}
+
+/// 日期本身作为一个时间段：(YYYY-MM-DD, 当天, 当天)
+pub fn day_of(date: NaiveDate) -> (String, NaiveDate, NaiveDate) {
+    (date.to_string(), date, date)
+}
+
+/// 日期所在的自然月：(标签, 第一天, 最后一天)
+pub fn month_of(date: NaiveDate) -> (String, NaiveDate, NaiveDate) {
+    let start = date.with_day(1).unwrap_or(date);
+    let end = start
+        .checked_add_months(Months::new(1))
+        .and_then(|next| next.pred_opt())
+        .unwrap_or(date);
+    (start.format("%Y-%m").to_string(), start, end)
+}
+
+/// 日期所在的计费周期：从 billing_day 日开始到下个月 billing_day 的前一天。
+/// 当月没有 billing_day（如 31 日遇到 30 天的月份）时以当月最后一天为起始日
+pub fn billing_cycle(date: NaiveDate, billing_day: u32) -> (NaiveDate, NaiveDate) {
+    let start_in = |month: NaiveDate| {
+        let first = month.with_day(1).unwrap_or(month);
+        let last = month_of(first).2;
+        first.with_day(billing_day.min(last.day())).unwrap_or(last)
+    };
+    let current = start_in(date);
+    let start = if date >= current {
+        current
+    } else {
+        date.checked_sub_months(Months::new(1)).map_or(current, start_in)
+    };
+    let end = start
+        .with_day(1)
+        .and_then(|first| first.checked_add_months(Months::new(1)))
+        .map(start_in)
+        .and_then(|next| next.pred_opt())
+        .unwrap_or(start);
+    (start, end)
+}
+
+/// 日期所在的计费月：(标签, 第一天, 最后一天)。billing_day 为 1 时即自然月，标签为 YYYY-MM；
+/// 否则标签为 2025-06-15 – 2025-07-14 形式的日期范围
+pub fn billing_month_of(billing_day: u32) -> impl Fn(NaiveDate) -> (String, NaiveDate, NaiveDate) {
+    move |date| {
+        if billing_day <= 1 {
+            return month_of(date);
+        }
+        let (start, end) = billing_cycle(date, billing_day);
+        (format!("{} – {}", start, end), start, end)
+    }
+}
+
+/// 日期所在的周（从 week_start 开始的 7 天）：(ISO 周标签, 第一天, 最后一天)。
+/// 周的起始日不是周一时，以该周的第四天所在的 ISO 周作为标签，与 ISO 以周四定归属的规则一致；
+/// 因此从周日开始时，周日这一天的 ISO 周（每日表格的 Wk 列）比所在周的标签早一周
+pub fn week_of(week_start: Weekday) -> impl Fn(NaiveDate) -> (String, NaiveDate, NaiveDate) {
+    move |date| {
+        let offset = date.weekday().days_since(week_start) as u64;
+        let start = date.checked_sub_days(Days::new(offset)).unwrap_or(date);
+        let end = start.checked_add_days(Days::new(6)).unwrap_or(date);
+        let middle = start.checked_add_days(Days::new(3)).unwrap_or(date);
+        (iso_week_label(middle), start, end)
+    }
+}
+
+/// 日期所在的 ISO 8601 周的标签，如 2025-W37。年份是 ISO 年：2025-12-29 为 2026-W01，
+/// 2027-01-01 为 2026-W53
+pub fn iso_week_label(date: NaiveDate) -> String {
+    let week = date.iso_week();
+    format!("{}-W{:02}", week.year(), week.week())
+}
+
+/// 为每个时间段填入紧邻的上一个时间段的 token 总量；上一个时间段没有数据时记为 0。
+/// 需要在按 --last 等条件截取之前调用，使第一个显示的时间段也有可比较的数据
+pub fn fill_previous_totals(periods: &mut [PeriodSummary]) {
+    let totals: BTreeMap<NaiveDate, u64> = periods
+        .iter()
+        .map(|period| (period.end, period.tokens.total_tokens))
+        .collect();
+    let first_start = periods.first().map(|period| period.start);
+    for period in periods.iter_mut() {
+        if Some(period.start) == first_start {
+            continue;
+        }
+        let previous_end = period.start.pred_opt().unwrap_or(period.start);
+        period.previous_total_tokens = Some(totals.get(&previous_end).copied().unwrap_or(0));
+    }
+}
+
+/// 将 (模型, 日期) 使用量按 period_of 给出的时间段汇总，按时间顺序排列
+pub fn summarize_periods(
+    usage: &[DailyUsage],
+    period_of: impl Fn(NaiveDate) -> (String, NaiveDate, NaiveDate),
+    today: NaiveDate,
+) -> Vec<PeriodSummary> {
+    struct Accumulator {
+        label: String,
+        end: NaiveDate,
+        days: BTreeSet<NaiveDate>,
+        models: BTreeMap<String, PeriodModel>,
+    }
+
+    let mut periods: BTreeMap<NaiveDate, Accumulator> = BTreeMap::new();
+    for DailyUsage { model, date, usage: aggregate } in usage {
+        let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") else {
+            continue;
+        };
+        let (label, start, end) = period_of(date);
+        let period = periods.entry(start).or_insert_with(|| Accumulator {
+            label,
+            end,
+            days: BTreeSet::new(),
+            models: BTreeMap::new(),
+        });
+        period.days.insert(date);
+
+        let entry = period.models.entry(model.clone()).or_insert_with(|| PeriodModel {
+            model: model.clone(),
+            tokens: TokenTotals::default(),
+            cost_usd: None,
+        });
+        entry.tokens.add(&aggregate.usage);
+        // 按天计算费用再累加，避免先合计使用量时 u32 溢出
+        if let Some(cost) = cost_for(model, &aggregate.usage) {
+            entry.cost_usd = Some(entry.cost_usd.unwrap_or(0.0) + cost);
+        }
+    }
+
+    periods
+        .into_iter()
+        .map(|(start, period)| {
+            let models: Vec<PeriodModel> = period.models.into_values().collect();
+            let mut tokens = TokenTotals::default();
+            for model in &models {
+                tokens.merge(&model.tokens);
+            }
+            let costs: Vec<f64> = models.iter().filter_map(|model| model.cost_usd).collect();
+
+            PeriodSummary {
+                label: period.label,
+                start,
+                end: period.end,
+                partial: start <= today && today <= period.end,
+                active_days: period.days.len() as u64,
+                tokens,
+                cost_usd: (!costs.is_empty()).then(|| costs.iter().sum()),
+                previous_total_tokens: None,
+                models,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn iso_week_labels_use_the_iso_year() {
+        assert_eq!(iso_week_label(date("2025-09-10")), "2025-W37");
+        assert_eq!(iso_week_label(date("2025-12-28")), "2025-W52");
+        assert_eq!(iso_week_label(date("2025-12-29")), "2026-W01");
+        assert_eq!(iso_week_label(date("2027-01-01")), "2026-W53");
+        assert_eq!(iso_week_label(date("2021-01-03")), "2020-W53");
+    }
+
+    #[test]
+    fn weeks_starting_on_monday_match_the_iso_week() {
+        let week = week_of(Weekday::Mon);
+        assert_eq!(week(date("2025-12-31")), ("2026-W01".to_string(), date("2025-12-29"), date("2026-01-04")));
+        assert_eq!(week(date("2025-09-14")), ("2025-W37".to_string(), date("2025-09-08"), date("2025-09-14")));
+        // 周一开始时，周内每一天的 ISO 周都与周标签相同
+        let (label, start, _) = week(date("2025-09-10"));
+        assert!(start.iter_days().take(7).all(|day| iso_week_label(day) == label));
+    }
+
+    #[test]
+    fn weeks_starting_on_sunday_are_labelled_by_their_fourth_day() {
+        let week = week_of(Weekday::Sun);
+        // 周日 2025-09-14 属于 ISO 的 W37，但它开始的这一周以周三 09-17 所在的 W38 为标签
+        assert_eq!(week(date("2025-09-14")), ("2025-W38".to_string(), date("2025-09-14"), date("2025-09-20")));
+        assert_eq!(iso_week_label(date("2025-09-14")), "2025-W37");
+        assert_eq!(week(date("2025-09-13")).0, "2025-W37");
+        // 跨年的一周：2025-12-28（ISO 2025-W52）开始的一周为 2026-W01
+        assert_eq!(week(date("2026-01-03")), ("2026-W01".to_string(), date("2025-12-28"), date("2026-01-03")));
+    }
+
+    #[test]
+    fn months_end_on_their_last_day() {
+        assert_eq!(month_of(date("2024-02-10")), ("2024-02".to_string(), date("2024-02-01"), date("2024-02-29")));
+        assert_eq!(month_of(date("2025-12-31")), ("2025-12".to_string(), date("2025-12-01"), date("2025-12-31")));
+    }
+
+    #[test]
+    fn billing_cycles_run_from_the_billing_day() {
+        assert_eq!(billing_cycle(date("2025-09-20"), 15), (date("2025-09-15"), date("2025-10-14")));
+        assert_eq!(billing_cycle(date("2025-09-15"), 15), (date("2025-09-15"), date("2025-10-14")));
+        assert_eq!(billing_cycle(date("2025-09-14"), 15), (date("2025-08-15"), date("2025-09-14")));
+        assert_eq!(billing_cycle(date("2025-01-05"), 15), (date("2024-12-15"), date("2025-01-14")));
+        assert_eq!(billing_cycle(date("2025-09-20"), 1), (date("2025-09-01"), date("2025-09-30")));
+    }
+
+    #[test]
+    fn billing_days_past_the_month_end_use_the_last_day() {
+        assert_eq!(billing_cycle(date("2025-04-30"), 31), (date("2025-04-30"), date("2025-05-30")));
+        assert_eq!(billing_cycle(date("2025-05-30"), 31), (date("2025-04-30"), date("2025-05-30")));
+        assert_eq!(billing_cycle(date("2025-05-31"), 31), (date("2025-05-31"), date("2025-06-29")));
+        assert_eq!(billing_cycle(date("2025-03-01"), 30), (date("2025-02-28"), date("2025-03-29")));
+        assert_eq!(billing_cycle(date("2024-02-29"), 30), (date("2024-02-29"), date("2024-03-29")));
+    }
+
+    #[test]
+    fn billing_month_labels() {
+        assert_eq!(billing_month_of(1)(date("2025-09-20")).0, "2025-09");
+        assert_eq!(billing_month_of(15)(date("2025-09-20")).0, "2025-09-15 – 2025-10-14");
+    }
+}
@@ -0,0 +1,348 @@
+//! 各模型的价格与费用计算
+//!
+//! 内置价格表通过 [`lookup`] 与 [`cost_of`] 使用；自定义的价格文件可读取为 [`PricingTable`]，
+//! 它提供同样的查询与计算方法，不依赖任何全局状态。
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::item::Usage;
+use crate::model::{ModelFamily, ParsedModel};
+
+/// 单个模型各方向的价格，单位为美元 / 百万 token
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Price {
+    /// 输入
+    pub input: f64,
+    /// 输出
+    pub output: f64,
+    /// 写入提示缓存（5 分钟缓存）
+    pub cache_write: f64,
+    /// 读取提示缓存
+    pub cache_read: f64,
+}
+
+impl Price {
+    const fn new(input: f64, output: f64, cache_write: f64, cache_read: f64) -> Self {
+        Self { input, output, cache_write, cache_read }
+    }
+
+    /// 按该价格计算一组使用量的费用（美元），未上报的字段按 0 计算
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        self.breakdown(usage).total()
+    }
+
+    /// 按该价格计算各方向的费用，未上报的字段按 0 计算
+    pub fn breakdown(&self, usage: &Usage) -> CostBreakdown {
+        let per_token = |tokens: Option<u64>, price: f64| tokens.unwrap_or(0) as f64 * price / 1_000_000.0;
+        CostBreakdown {
+            input: per_token(usage.input_tokens, self.input),
+            output: per_token(usage.output_tokens, self.output),
+            cache_write: per_token(usage.cache_creation_input_tokens, self.cache_write),
+            cache_read: per_token(usage.cache_read_input_tokens, self.cache_read),
+        }
+    }
+}
+
+/// 一组使用量按方向拆分的费用，单位为美元
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct CostBreakdown {
+    /// 输入
+    pub input: f64,
+    /// 输出
+    pub output: f64,
+    /// 写入提示缓存
+    pub cache_write: f64,
+    /// 读取提示缓存
+    pub cache_read: f64,
+}
+
+impl CostBreakdown {
+    /// 各方向费用之和
+    pub fn total(&self) -> f64 {
+        self.input + self.output + self.cache_write + self.cache_read
+    }
+}
+
+/// 使用量中有字段未上报时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CostMode {
+    /// 未上报的字段按 0 计算，与命令行的各报告一致
+    #[default]
+    Lenient,
+    /// 任何字段未上报时返回 PricingError::MissingField
+    Strict,
+}
+
+/// 查询价格、计算费用或读取价格文件时的错误
+#[derive(Debug)]
+pub enum PricingError {
+    /// 价格表中没有匹配该模型 id 的条目
+    UnknownModel(String),
+    /// Strict 模式下使用量缺少该字段
+    MissingField(&'static str),
+    /// 读取价格文件失败
+    Io {
+        /// 价格文件
+        path: PathBuf,
+        /// 底层的 I/O 错误
+        source: io::Error,
+    },
+    /// 价格文件格式错误
+    Invalid {
+        /// 价格文件
+        path: PathBuf,
+        /// 解析错误的描述
+        message: String,
+    },
+}
+
+impl fmt::Display for PricingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PricingError::UnknownModel(model) => write!(f, "没有模型 {} 的价格", model),
+            PricingError::MissingField(field) => write!(f, "使用量缺少 {} 字段", field),
+            PricingError::Io { path, source } => write!(f, "读取价格文件 {} 失败: {}", path.display(), source),
+            PricingError::Invalid { path, message } => write!(f, "价格文件 {} 格式错误: {}", path.display(), message),
+        }
+    }
+}
+
+impl std::error::Error for PricingError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PricingError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+const OPUS_4_5: Price = Price::new(5.0, 25.0, 6.25, 0.50);
+const OPUS: Price = Price::new(15.0, 75.0, 18.75, 1.50);
+const SONNET: Price = Price::new(3.0, 15.0, 3.75, 0.30);
+const HAIKU_4_5: Price = Price::new(1.0, 5.0, 1.25, 0.10);
+const HAIKU_3_5: Price = Price::new(0.80, 4.0, 1.0, 0.08);
+const HAIKU: Price = Price::new(0.25, 1.25, 0.30, 0.03);
+
+/// 内置价格：按模型系列与版本号匹配，未列出的版本使用该系列较早的价格
+fn builtin_price(model: &ParsedModel) -> Option<&'static Price> {
+    match (&model.family, model.version.as_slice()) {
+        (ModelFamily::Opus, [4, 5, ..]) => Some(&OPUS_4_5),
+        (ModelFamily::Opus, _) => Some(&OPUS),
+        (ModelFamily::Sonnet, _) => Some(&SONNET),
+        (ModelFamily::Haiku, [4, 5, ..]) => Some(&HAIKU_4_5),
+        (ModelFamily::Haiku, [3, 5, ..]) => Some(&HAIKU_3_5),
+        (ModelFamily::Haiku, _) => Some(&HAIKU),
+        (ModelFamily::Unknown(_), _) => None,
+    }
+}
+
+/// 价格文件中的一项
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PriceEntry {
+    pattern: String,
+    #[serde(flatten)]
+    price: Price,
+}
+
+/// 价格文件的内容
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PricingFile {
+    #[serde(default)]
+    model: Vec<PriceEntry>,
+}
+
+/// 按模型 id 中的片段匹配价格的价格表，条目按顺序匹配，第一个被模型 id 包含的片段生效；
+/// 包含内置价格时，所有条目都不匹配再按模型系列与版本号查内置价格
+#[derive(Debug, Clone, PartialEq)]
+pub struct PricingTable {
+    entries: Vec<(String, Price)>,
+    builtin: bool,
+}
+
+impl Default for PricingTable {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl PricingTable {
+    /// 内置的价格表
+    pub fn builtin() -> Self {
+        Self {
+            entries: Vec::new(),
+            builtin: true,
+        }
+    }
+
+    /// 读取 TOML 价格文件，文件中每个 [[model]] 表给出 pattern 与四个方向的价格：
+    ///
+    /// ```toml
+    /// [[model]]
+    /// pattern = "opus-4-5"
+    /// input = 5.0
+    /// output = 25.0
+    /// cache_write = 6.25
+    /// cache_read = 0.5
+    /// ```
+    pub fn load(path: &Path) -> Result<Self, PricingError> {
+        let content = fs::read_to_string(path).map_err(|source| PricingError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let file: PricingFile = toml::from_str(&content).map_err(|e| PricingError::Invalid {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })?;
+        Ok(Self {
+            entries: file.model.into_iter().map(|entry| (entry.pattern, entry.price)).collect(),
+            builtin: false,
+        })
+    }
+
+    /// 本表中没有匹配的模型再到 fallback 中查找，例如自定义价格文件以内置价格表作为后备
+    pub fn with_fallback(mut self, fallback: PricingTable) -> Self {
+        self.entries.extend(fallback.entries);
+        self.builtin |= fallback.builtin;
+        self
+    }
+
+    /// 查找模型的价格，没有匹配的条目时为 None
+    pub fn lookup(&self, model_id: &str) -> Option<&Price> {
+        self.entries
+            .iter()
+            .find(|(pattern, _)| model_id.contains(pattern.as_str()))
+            .map(|(_, price)| price)
+            .or_else(|| self.builtin.then(|| lookup(model_id)).flatten())
+    }
+
+    /// 计算某个模型一组使用量各方向的费用
+    pub fn cost_of(&self, model_id: &str, usage: &Usage, mode: CostMode) -> Result<CostBreakdown, PricingError> {
+        let price = self.lookup(model_id).ok_or_else(|| PricingError::UnknownModel(model_id.to_string()))?;
+        breakdown(price, usage, mode)
+    }
+}
+
+fn breakdown(price: &Price, usage: &Usage, mode: CostMode) -> Result<CostBreakdown, PricingError> {
+    if mode == CostMode::Strict
+        && let Some(field) = usage.missing_fields().next() {
+        return Err(PricingError::MissingField(field));
+    }
+    Ok(price.breakdown(usage))
+}
+
+/// 在内置价格表中查找模型的价格，未知模型返回 None
+pub fn lookup(model_id: &str) -> Option<&'static Price> {
+    builtin_price(&ParsedModel::parse(model_id))
+}
+
+/// 按内置价格表计算某个模型一组使用量各方向的费用
+pub fn cost_of(model_id: &str, usage: &Usage, mode: CostMode) -> Result<CostBreakdown, PricingError> {
+    let price = lookup(model_id).ok_or_else(|| PricingError::UnknownModel(model_id.to_string()))?;
+    breakdown(price, usage, mode)
+}
+
+/// 查找模型的价格，未知模型返回 None
+pub fn pricing_for(model: &str) -> Option<Price> {
+    lookup(model).copied()
+}
+
+/// 计算某个模型一组使用量的费用，未知模型返回 None
+pub fn cost_for(model: &str, usage: &Usage) -> Option<f64> {
+    pricing_for(model).map(|pricing| pricing.cost(usage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(input: u64, output: u64, cache_write: u64, cache_read: u64) -> Usage {
+        Usage {
+            input_tokens: Some(input),
+            output_tokens: Some(output),
+            cache_creation_input_tokens: Some(cache_write),
+            cache_read_input_tokens: Some(cache_read),
+        }
+    }
+
+    #[test]
+    fn builtin_prices_follow_family_and_version() {
+        assert_eq!(lookup("claude-sonnet-4-20250514"), Some(&SONNET));
+        assert_eq!(lookup("claude-opus-4-5-20251101"), Some(&OPUS_4_5));
+        assert_eq!(lookup("claude-opus-4-1-20250805"), Some(&OPUS));
+        assert_eq!(lookup("claude-haiku-4-5-20251001"), Some(&HAIKU_4_5));
+        assert_eq!(lookup("claude-3-5-haiku-20241022"), Some(&HAIKU_3_5));
+        assert_eq!(lookup("claude-3-haiku-20240307"), Some(&HAIKU));
+        assert_eq!(lookup("gpt-5"), None);
+    }
+
+    #[test]
+    fn breakdown_prices_each_direction_per_million_tokens() {
+        let cost = cost_of("claude-sonnet-4-20250514", &usage(1_000_000, 100_000, 200_000, 1_000_000), CostMode::Strict).unwrap();
+        assert_eq!(cost, CostBreakdown { input: 3.0, output: 1.5, cache_write: 0.75, cache_read: 0.3 });
+        assert!((cost.total() - 5.55).abs() < 1e-9);
+        assert_eq!(cost_for("claude-sonnet-4-20250514", &usage(1_000_000, 0, 0, 0)), Some(3.0));
+    }
+
+    #[test]
+    fn strict_mode_rejects_missing_fields() {
+        let partial = Usage { input_tokens: Some(1_000_000), ..Usage::default() };
+        let lenient = cost_of("claude-sonnet-4-20250514", &partial, CostMode::Lenient).unwrap();
+        assert_eq!(lenient.total(), 3.0);
+        assert!(matches!(
+            cost_of("claude-sonnet-4-20250514", &partial, CostMode::Strict),
+            Err(PricingError::MissingField("output_tokens"))
+        ));
+        assert!(matches!(
+            cost_of("gpt-5", &partial, CostMode::Lenient),
+            Err(PricingError::UnknownModel(model)) if model == "gpt-5"
+        ));
+    }
+
+    #[test]
+    fn loaded_table_matches_patterns_in_order_and_falls_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pricing.toml");
+        fs::write(&path, r#"
+            [[model]]
+            pattern = "sonnet-4-5"
+            input = 1.0
+            output = 2.0
+            cache_write = 0.0
+            cache_read = 0.0
+
+            [[model]]
+            pattern = "sonnet"
+            input = 10.0
+            output = 20.0
+            cache_write = 0.0
+            cache_read = 0.0
+        "#).unwrap();
+
+        let custom = PricingTable::load(&path).unwrap();
+        assert_eq!(custom.lookup("claude-sonnet-4-5-20250929").map(|price| price.input), Some(1.0));
+        assert_eq!(custom.lookup("claude-sonnet-4-20250514").map(|price| price.input), Some(10.0));
+        assert_eq!(custom.lookup("claude-opus-4-1-20250805"), None);
+        assert!(matches!(custom.cost_of("claude-opus-4-1-20250805", &usage(1, 1, 1, 1), CostMode::Lenient), Err(PricingError::UnknownModel(_))));
+
+        let chained = custom.with_fallback(PricingTable::builtin());
+        assert_eq!(chained.lookup("claude-sonnet-4-20250514").map(|price| price.input), Some(10.0));
+        assert_eq!(chained.lookup("claude-opus-4-1-20250805"), Some(&OPUS));
+        assert_eq!(PricingTable::default().lookup("claude-opus-4-1-20250805"), lookup("claude-opus-4-1-20250805"));
+    }
+
+    #[test]
+    fn invalid_pricing_files_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.toml");
+        assert!(matches!(PricingTable::load(&missing), Err(PricingError::Io { path, .. }) if path == missing));
+
+        let invalid = dir.path().join("invalid.toml");
+        fs::write(&invalid, "[[model]]\npattern = \"opus\"\ninput = 1.0\n").unwrap();
+        assert!(matches!(PricingTable::load(&invalid), Err(PricingError::Invalid { path, .. }) if path == invalid));
+    }
+}
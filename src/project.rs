@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// 按 Claude Code 的规则编码项目路径：字母、数字和 - 以外的字符（/、. 等）都替换为 -
+pub fn encode_project_path(path: &str) -> String {
+    path.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '-' })
+        .collect()
+}
+
+/// 将 ~/.claude/projects 下的编码目录名还原为项目路径。
+///
+/// 编码是有损的（/、. 和原本的 - 都变成 -），因此沿着文件系统逐级查找实际存在的目录：
+/// 每一级优先匹配最长的、以 - 或 . 连接的片段，找不到时剩余部分按 / 拼接。
+/// 有条目中的 cwd 时应优先使用 cwd，这里只是后备方案。
+pub fn decode_project_dir(encoded: &str) -> String {
+    let Some(rest) = encoded.strip_prefix('-') else {
+        return encoded.to_string();
+    };
+
+    // 空片段来自 "/." 编码出的 "--"，与下一个片段合并为隐藏目录名
+    let mut segments: Vec<String> = Vec::new();
+    let mut hidden = false;
+    for segment in rest.split('-') {
+        if segment.is_empty() {
+            hidden = true;
+        } else if hidden {
+            segments.push(format!(".{}", segment));
+            hidden = false;
+        } else {
+            segments.push(segment.to_string());
+        }
+    }
+
+    let mut path = PathBuf::from("/");
+    let mut start = 0;
+    'outer: while start < segments.len() {
+        for end in (start + 1..=segments.len()).rev() {
+            let parts = &segments[start..end];
+            for separator in ["-", ".", "_"] {
+                let candidate = path.join(parts.join(separator));
+                if candidate.exists() {
+                    path = candidate;
+                    start = end;
+                    continue 'outer;
+                }
+                if parts.len() == 1 {
+                    break;
+                }
+            }
+        }
+        // 文件系统中找不到（项目已删除或在其他机器上），剩余片段逐级作为目录
+        for segment in &segments[start..] {
+            path.push(segment);
+        }
+        break;
+    }
+
+    path.to_string_lossy().into_owned()
+}
+
+/// 为一组项目路径生成简短的显示名：默认取最后两级，
+/// 不同路径的简称相同时逐级加长，直到可以区分或已是完整路径
+pub fn short_project_names<'a>(paths: impl IntoIterator<Item = &'a str>) -> HashMap<String, String> {
+    let components: HashMap<&str, Vec<String>> = paths
+        .into_iter()
+        .map(|path| {
+            let parts = Path::new(path)
+                .components()
+                .filter_map(|component| match component {
+                    std::path::Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+                    _ => None,
+                })
+                .collect();
+            (path, parts)
+        })
+        .collect();
+
+    let mut depths: HashMap<&str, usize> = components.keys().map(|path| (*path, 2)).collect();
+    let short_name = |parts: &[String], depth: usize| -> String {
+        let skip = parts.len().saturating_sub(depth);
+        parts[skip..].join("/")
+    };
+
+    loop {
+        let mut by_name: HashMap<String, Vec<&str>> = HashMap::new();
+        for (path, parts) in &components {
+            by_name.entry(short_name(parts, depths[path])).or_default().push(path);
+        }
+
+        let mut extended = false;
+        for paths in by_name.values().filter(|paths| paths.len() > 1) {
+            for path in paths {
+                if depths[path] < components[path].len() {
+                    *depths.get_mut(path).unwrap() += 1;
+                    extended = true;
+                }
+            }
+        }
+
+        if !extended {
+            return components
+                .iter()
+                .map(|(path, parts)| {
+                    let name = short_name(parts, depths[path]);
+                    let name = if name.is_empty() { path.to_string() } else { name };
+                    (path.to_string(), name)
+                })
+                .collect();
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! 通过 ssh 读取另一台机器上的 Claude Code 日志，远程不需要安装 cccost。
+//!
+//! 远程只执行 POSIX sh 的 cd、find 与 cat：每个文件以 `\036<相对路径>` 一行开始，随后是文件内容和一个换行，
+//! 全部输出后以 `\036\036` 一行结束。本地按该格式切分为每个文件一个流，按与本地文件相同的方式解析；
+//! 没有读到结束标记说明连接中途断开，此时报告已收到的文件数而不是返回不完整的统计
+
+use std::io::{self, BufRead, BufReader, Cursor, Read};
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+use crate::file_processor::ProcessError;
+use crate::source::{DataSource, FileFilter, SourceInput};
+use crate::tr;
+
+/// 远程的默认数据目录
+pub const DEFAULT_REMOTE_DIRECTORY: &str = "~/.claude/projects";
+
+/// 每个文件开始与全部结束的标记（ASCII 记录分隔符），不会出现在 JSONL 中
+const SEPARATOR: u8 = 0x1e;
+
+/// 远程数据目录不存在时脚本的退出码
+const EXIT_MISSING_DIRECTORY: i32 = 3;
+
+/// 读取文件失败时脚本的退出码
+const EXIT_READ_FAILED: i32 = 4;
+
+/// ssh 自身失败（无法连接、认证失败等）时的退出码
+const EXIT_SSH_FAILED: i32 = 255;
+
+/// 远程主机上的 Claude Code 数据目录
+pub struct RemoteSource {
+    host: String,
+    directory: String,
+}
+
+impl RemoteSource {
+    /// host 为 ssh 的目标（如 user@devbox 或 ~/.ssh/config 中的别名），数据目录为 ~/.claude/projects
+    pub fn new(host: impl Into<String>) -> Self {
+        Self {
+            host: host.into(),
+            directory: DEFAULT_REMOTE_DIRECTORY.to_string(),
+        }
+    }
+
+    /// 设置远程数据目录，以 ~/ 开始时相对于远程用户的主目录
+    pub fn with_directory(mut self, directory: impl Into<String>) -> Self {
+        self.directory = directory.into();
+        self
+    }
+
+    /// 在远程执行的 sh 脚本，列出并输出 extensions 中扩展名的所有文件
+    fn script(&self, extensions: &[String]) -> String {
+        let directory = match self.directory.strip_prefix('~') {
+            Some("") => "\"$HOME\"".to_string(),
+            Some(rest) if rest.starts_with('/') => format!("\"$HOME\"{}", quote(rest)),
+            _ => quote(&self.directory),
+        };
+        let names: Vec<String> = extensions
+            .iter()
+            .map(|extension| format!("-name {}", quote(&format!("*.{}", extension))))
+            .collect();
+        format!(
+            "cd {directory} 2>/dev/null || exit {missing}\n\
+             find . -type f \\( {names} \\) | while IFS= read -r f; do \
+             printf '\\036%s\\n' \"${{f#./}}\"; cat \"$f\" || exit {failed}; printf '\\n'; \
+             done || exit {failed}\n\
+             printf '\\036\\036\\n'\n",
+            missing = EXIT_MISSING_DIRECTORY,
+            failed = EXIT_READ_FAILED,
+            names = names.join(" -o "),
+        )
+    }
+
+    fn error(&self, message: String) -> ProcessError {
+        ProcessError::Remote { host: self.host.clone(), message }
+    }
+}
+
+impl DataSource for RemoteSource {
+    /// 读取全部远程文件后才返回，ssh 失败或输出不完整时返回 [`ProcessError::Remote`]
+    fn inputs(self, filter: &FileFilter) -> Result<Vec<SourceInput>, ProcessError> {
+        let mut child = Command::new("ssh")
+            .arg(&self.host)
+            .arg(self.script(filter.extensions))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| self.error(tr!("error.remote_spawn", e)))?;
+
+        // 在另一个线程中读取错误输出，避免其管道写满后 ssh 阻塞
+        let mut stderr = child.stderr.take();
+        let stderr = thread::spawn(move || {
+            let mut output = String::new();
+            if let Some(stderr) = stderr.as_mut() {
+                let _ = stderr.read_to_string(&mut output);
+            }
+            output
+        });
+        let frames = child.stdout.take().map(|stdout| read_frames(BufReader::new(stdout)));
+        let status = child.wait().map_err(|e| self.error(tr!("error.remote_spawn", e)))?;
+        let stderr = stderr.join().unwrap_or_default();
+        let frames = match frames {
+            Some(Ok(frames)) => frames,
+            Some(Err(e)) => return Err(self.error(tr!("error.remote_read", e))),
+            None => Frames::default(),
+        };
+
+        match status.code() {
+            Some(EXIT_SSH_FAILED) => return Err(self.error(tr!("error.remote_connect", last_line(&stderr)))),
+            Some(EXIT_MISSING_DIRECTORY) => return Err(self.error(tr!("error.remote_missing_directory", self.directory))),
+            _ => {}
+        }
+        if !frames.complete {
+            let message = match &frames.current {
+                Some(path) => tr!("error.remote_partial_in", frames.files.len().saturating_sub(1), path, describe(status, &stderr)),
+                None => tr!("error.remote_partial", frames.files.len(), describe(status, &stderr)),
+            };
+            return Err(self.error(message));
+        }
+
+        Ok(frames
+            .files
+            .into_iter()
+            .map(|(path, content)| {
+                // 与本地相同，项目目录是数据目录下的第一级目录
+                let project_dir = path.split_once('/').map(|(project, _)| project.to_string());
+                SourceInput::Stream {
+                    name: format!("{}:{}/{}", self.host, self.directory.trim_end_matches('/'), path),
+                    project_dir,
+                    reader: Box::new(Cursor::new(content)),
+                }
+            })
+            .collect())
+    }
+}
+
+/// 按标记切分后的远程输出
+#[derive(Default)]
+struct Frames {
+    /// 每个文件的相对路径与内容
+    files: Vec<(String, Vec<u8>)>,
+    /// 最后一个开始输出的文件，读到结束标记后为 None
+    current: Option<String>,
+    /// 是否读到了结束标记
+    complete: bool,
+}
+
+fn read_frames(mut reader: impl BufRead) -> io::Result<Frames> {
+    let mut frames = Frames::default();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        let Some(header) = line.strip_prefix(&[SEPARATOR]) else {
+            // 第一个文件之前的输出（如远程 shell 的启动信息）被忽略
+            if let Some((_, content)) = frames.files.last_mut() {
+                content.extend_from_slice(&line);
+            }
+            continue;
+        };
+        finish(&mut frames);
+        let header = header.strip_suffix(b"\n").unwrap_or(header);
+        if header == [SEPARATOR] {
+            frames.current = None;
+            frames.complete = true;
+            break;
+        }
+        let path = String::from_utf8_lossy(header).into_owned();
+        frames.current = Some(path.clone());
+        frames.files.push((path, Vec::new()));
+    }
+    Ok(frames)
+}
+
+/// 去掉脚本在文件内容后追加的换行，保留文件末尾原本未写完的行
+fn finish(frames: &mut Frames) {
+    if frames.current.is_some()
+        && let Some((_, content)) = frames.files.last_mut()
+        && content.last() == Some(&b'\n') {
+        content.pop();
+    }
+}
+
+/// sh 的单引号转义
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// 错误输出的最后一个非空行，ssh 把失败原因写在这里
+fn last_line(stderr: &str) -> String {
+    stderr.lines().rev().map(str::trim).find(|line| !line.is_empty()).unwrap_or_default().to_string()
+}
+
+/// 退出状态与错误输出的最后一行
+fn describe(status: ExitStatus, stderr: &str) -> String {
+    match last_line(stderr) {
+        line if line.is_empty() => status.to_string(),
+        line => format!("{}: {}", status, line),
+    }
+}
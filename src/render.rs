@@ -0,0 +1,370 @@
+//! 每日用量报告及其输出方式
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use crate::cli::{GroupBy, Metric};
+use crate::daily::{DailySeries, DayAverage};
+use crate::diagnostics::Diagnostics;
+use crate::item::{DailyUsage, GroupUsage, UsageAggregate};
+use crate::period::{day_of, summarize_periods, PeriodSummary, TokenTotals};
+use crate::pricing::cost_for;
+use crate::tr;
+
+/// 报告中所有行的合计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReportTotals {
+    /// 计入统计的消息数
+    pub messages: u64,
+    /// token 合计
+    #[serde(flatten)]
+    pub tokens: TokenTotals,
+    /// 有价格的模型的费用合计，没有任何模型有价格时为 None
+    pub cost_usd: Option<f64>,
+}
+
+/// 每日用量表格中的一行：某一天的一个分组，数值未经格式化。
+/// 同一天显示名称相同的分组（如同一模型的不同快照）合并为一行
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageLine {
+    /// 日期键 YYYY-MM-DD
+    pub date: String,
+    /// 分组的显示名称
+    pub group: String,
+    /// 当天最早一条消息的时间
+    pub first_seen: Option<DateTime<Utc>>,
+    /// 当天最晚一条消息的时间
+    pub last_seen: Option<DateTime<Utc>>,
+    /// 计入统计的消息数
+    pub messages: u64,
+    /// 输入 token 数，未上报时为 None
+    pub input_tokens: Option<u64>,
+    /// 输出 token 数，未上报时为 None
+    pub output_tokens: Option<u64>,
+    /// 写入提示缓存的 token 数，未上报时为 None
+    pub cache_creation_input_tokens: Option<u64>,
+    /// 读取提示缓存的 token 数，未上报时为 None
+    pub cache_read_input_tokens: Option<u64>,
+    /// 四类 token 的合计，未上报的按 0 计算
+    pub total_tokens: u64,
+    /// 费用，只在按模型分组且模型有价格时才有
+    pub cost_usd: Option<f64>,
+    /// 没有任何记录、由 --fill-gaps 补充的日期，分组为空，各数值为 0 或 None
+    pub gap: bool,
+}
+
+impl UsageLine {
+    fn from_aggregate(date: String, group: String, aggregate: &UsageAggregate, cost_usd: Option<f64>) -> Self {
+        let usage = &aggregate.usage;
+        Self {
+            date,
+            group,
+            first_seen: aggregate.first_seen,
+            last_seen: aggregate.last_seen,
+            messages: aggregate.messages,
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+            cache_creation_input_tokens: usage.cache_creation_input_tokens,
+            cache_read_input_tokens: usage.cache_read_input_tokens,
+            total_tokens: usage.total(),
+            cost_usd,
+            gap: false,
+        }
+    }
+}
+
+/// 默认命令输出的每日用量报告
+#[derive(Serialize)]
+pub struct Report {
+    /// 第二列的分组维度
+    pub group_by: GroupBy,
+    /// 各分组每天的使用量，按 (分组名, 日期) 排序；分组名是模型、git 分支或项目
+    pub rows: Vec<GroupUsage>,
+    /// 所有行的合计
+    pub totals: ReportTotals,
+    /// 报告中最早和最晚的日期，没有数据时为 None
+    pub range: Option<(NaiveDate, NaiveDate)>,
+    /// 按日汇总的各模型用量
+    pub days: Vec<PeriodSummary>,
+    /// 每日合计，累计、移动平均等派生数据基于它计算
+    pub series: DailySeries,
+    /// 从所选范围起始日累计时使用的指标，None 时不输出累计
+    pub cumulative: Option<Metric>,
+    /// 移动平均的窗口天数及每日的平均值
+    pub moving_average: Option<(u32, BTreeMap<NaiveDate, Option<DayAverage>>)>,
+    /// 是否输出相对前一个日历日的变化
+    pub day_change: bool,
+    /// 范围内没有任何记录、在表格中补充为空行的日期
+    pub gap_days: Vec<NaiveDate>,
+}
+
+impl Report {
+    /// 由按 group_by 分组的行与按模型的每日用量构造报告；费用与按日汇总总是按模型计算
+    pub fn new(group_by: GroupBy, rows: Vec<GroupUsage>, usage: &[DailyUsage], today: NaiveDate) -> Self {
+        let days = summarize_periods(usage, day_of, today);
+        let mut totals = ReportTotals {
+            messages: rows.iter().map(|row| row.usage.messages).sum(),
+            ..ReportTotals::default()
+        };
+        for day in &days {
+            totals.tokens.merge(&day.tokens);
+            if let Some(cost) = day.cost_usd {
+                totals.cost_usd = Some(totals.cost_usd.unwrap_or(0.0) + cost);
+            }
+        }
+        let range = days.first().zip(days.last()).map(|(first, last)| (first.start, last.end));
+
+        Self {
+            group_by,
+            rows,
+            totals,
+            range,
+            days,
+            series: DailySeries::from_usage(usage),
+            cumulative: None,
+            moving_average: None,
+            day_change: false,
+            gap_days: Vec::new(),
+        }
+    }
+
+    /// 表格中的各行，按日期、显示名称排序；display_name 给出分组名的显示名称，
+    /// 所有 token 都为 0 的行不包括在内，补充的空日期各有一行
+    pub fn lines(&self, display_name: impl Fn(&str) -> String) -> Vec<UsageLine> {
+        let mut merged: BTreeMap<(String, String), (UsageAggregate, Option<f64>)> = BTreeMap::new();
+        for GroupUsage { group, date, usage: aggregate } in &self.rows {
+            if aggregate.usage.total() == 0 {
+                continue;
+            }
+            let cost = match self.group_by {
+                GroupBy::Model => cost_for(group, &aggregate.usage),
+                GroupBy::Branch | GroupBy::Project | GroupBy::Source | GroupBy::Person => None,
+            };
+            let (total, total_cost) = merged.entry((date.clone(), display_name(group))).or_default();
+            *total += aggregate;
+            if let Some(cost) = cost {
+                *total_cost = Some(total_cost.unwrap_or(0.0) + cost);
+            }
+        }
+        let mut lines: Vec<UsageLine> = merged
+            .into_iter()
+            .map(|((date, group), (aggregate, cost))| UsageLine::from_aggregate(date, group, &aggregate, cost))
+            .collect();
+        if !self.gap_days.is_empty() {
+            lines.extend(self.gap_days.iter().map(|date| UsageLine {
+                date: date.to_string(),
+                gap: true,
+                ..UsageLine::default()
+            }));
+            lines.sort_by(|a, b| a.date.cmp(&b.date));
+        }
+        lines
+    }
+
+    /// 为 [start, end] 内没有任何记录的日期补充空行，累计、移动平均等附加列在这些日期同样有值；
+    /// 需要在 with_moving_average 之前调用
+    pub fn with_filled_gaps(mut self, range: Option<(NaiveDate, NaiveDate)>) -> Self {
+        if let Some((start, end)) = range {
+            self.gap_days = self.series.fill_gaps(start, end);
+        }
+        self
+    }
+
+    /// 增加从所选范围起始日累计的数据
+    pub fn with_cumulative(mut self, metric: Option<Metric>) -> Self {
+        self.cumulative = metric;
+        self
+    }
+
+    /// 增加 window 天移动平均，从 since（未指定时为最早的数据）开始计算
+    pub fn with_moving_average(mut self, window: Option<u32>, since: Option<NaiveDate>) -> Self {
+        self.moving_average = window.map(|window| (window, self.series.moving_average(window, since)));
+        self
+    }
+
+    /// 增加相对前一个日历日的变化
+    pub fn with_day_change(mut self, day_change: bool) -> Self {
+        self.day_change = day_change;
+        self
+    }
+}
+
+/// JSON 输出的格式版本，记录在 meta.schema_version 中；每日数据的字段有不兼容的变化时递增，
+/// cccost merge 只合并版本相同的导出
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// 报告的来源信息，用于截图和审计时确认数据的出处
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportMeta {
+    /// JSON 输出的格式版本，见 [`JSON_SCHEMA_VERSION`]
+    pub schema_version: u32,
+    /// 生成报告的时间
+    pub generated_at: DateTime<Utc>,
+    /// 扫描的数据目录或文件，标准输入为 <stdin>
+    pub sources: Vec<String>,
+    /// 计入统计的文件数
+    pub files: u64,
+    /// 计入统计的记录数
+    pub entries: u64,
+    /// 按日分组使用的时区
+    pub timezone: String,
+    /// 费用使用的价格来源，没有任何费用时为 None
+    pub pricing: Option<String>,
+    /// 导出者（人员或机器）的标签，由 --label 指定
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl ReportMeta {
+    /// 由扫描的来源与诊断信息构造；usage 中有模型有价格时记录价格来源
+    pub fn new(generated_at: DateTime<Utc>, sources: Vec<String>, diagnostics: &Diagnostics, usage: &[DailyUsage]) -> Self {
+        let has_cost = usage.iter().any(|day| cost_for(&day.model, &day.usage.usage).is_some());
+        Self {
+            schema_version: JSON_SCHEMA_VERSION,
+            generated_at,
+            sources,
+            files: diagnostics.files.len() as u64,
+            entries: diagnostics.total_entries(),
+            timezone: "UTC".to_string(),
+            pricing: has_cost.then(|| "builtin".to_string()),
+            label: None,
+        }
+    }
+
+    /// 记录按日分组使用的时区，默认为 UTC
+    pub fn with_timezone(mut self, tz: Tz) -> Self {
+        self.timezone = tz.name().to_string();
+        self
+    }
+
+    /// 记录导出者的标签
+    pub fn with_label(mut self, label: Option<String>) -> Self {
+        self.label = label;
+        self
+    }
+}
+
+impl fmt::Display for ReportMeta {
+    /// 报告末尾的一行说明
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&tr!(
+            "report.footer",
+            self.generated_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            self.sources.join(", "),
+            self.files,
+            self.entries,
+            self.timezone
+        ))?;
+        if let Some(label) = &self.label {
+            f.write_str(&tr!("report.footer_label", label))?;
+        }
+        if let Some(pricing) = &self.pricing {
+            match pricing.as_str() {
+                "builtin" => f.write_str(&tr!("report.footer_builtin_pricing"))?,
+                other => f.write_str(&tr!("report.footer_pricing", other))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 每日用量报告的一种输出方式
+pub trait Renderer {
+    /// 将完整的报告（包括标题）写入 out
+    fn render(&self, report: &Report, out: &mut dyn Write) -> io::Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::item::Usage;
+    use crate::limit::LimitWindow;
+    use super::*;
+
+    fn row(group: &str, date: &str, input_tokens: u64, output_tokens: u64) -> GroupUsage {
+        GroupUsage {
+            group: group.to_string(),
+            date: date.to_string(),
+            usage: UsageAggregate {
+                usage: Usage { input_tokens: Some(input_tokens), output_tokens: Some(output_tokens), ..Usage::default() },
+                messages: 1,
+                first_seen: Some("2025-09-10T09:00:00Z".parse().unwrap()),
+                last_seen: Some("2025-09-10T17:30:00Z".parse().unwrap()),
+            },
+        }
+    }
+
+    fn report(group_by: GroupBy, rows: Vec<GroupUsage>) -> Report {
+        let usage: Vec<DailyUsage> = rows
+            .iter()
+            .map(|row| DailyUsage { date: row.date.clone(), model: row.group.clone(), usage: row.usage.clone() })
+            .collect();
+        Report::new(group_by, rows, &usage, NaiveDate::from_ymd_opt(2025, 9, 30).unwrap())
+    }
+
+    #[test]
+    fn usage_line_serializes_numbers_not_display_strings() {
+        let report = report(GroupBy::Branch, vec![row("main", "2025-09-10", 1_200_000, 34)]);
+        let lines = report.lines(|group| group.to_string());
+        assert_eq!(serde_json::to_value(&lines).unwrap(), json!([{
+            "date": "2025-09-10",
+            "group": "main",
+            "first_seen": "2025-09-10T09:00:00Z",
+            "last_seen": "2025-09-10T17:30:00Z",
+            "messages": 1,
+            "input_tokens": 1_200_000,
+            "output_tokens": 34,
+            "cache_creation_input_tokens": null,
+            "cache_read_input_tokens": null,
+            "total_tokens": 1_200_034,
+            "cost_usd": null,
+            "gap": false,
+        }]));
+    }
+
+    #[test]
+    fn report_serializes_with_snake_case_fields() {
+        let report = report(GroupBy::Model, vec![row("claude-sonnet-4-20250514", "2025-09-10", 1_000_000, 0)])
+            .with_cumulative(Some(Metric::Tokens))
+            .with_filled_gaps(Some((NaiveDate::from_ymd_opt(2025, 9, 9).unwrap(), NaiveDate::from_ymd_opt(2025, 9, 10).unwrap())));
+        let json = serde_json::to_value(&report).unwrap();
+
+        let mut fields: Vec<&str> = json.as_object().unwrap().keys().map(String::as_str).collect();
+        fields.sort_unstable();
+        assert_eq!(fields, [
+            "cumulative", "day_change", "days", "gap_days", "group_by", "moving_average", "range", "rows", "series", "totals",
+        ]);
+        assert_eq!(json["group_by"], "model");
+        assert_eq!(json["cumulative"], "tokens");
+        assert_eq!(json["range"], json!(["2025-09-10", "2025-09-10"]));
+        assert_eq!(json["gap_days"], json!(["2025-09-09"]));
+        assert_eq!(json["totals"], json!({
+            "messages": 1,
+            "input_tokens": 1_000_000,
+            "output_tokens": 0,
+            "cache_creation_input_tokens": 0,
+            "cache_read_input_tokens": 0,
+            "total_tokens": 1_000_000,
+            "cost_usd": 3.0,
+        }));
+    }
+
+    #[test]
+    fn cost_is_only_present_when_grouped_by_a_priced_model() {
+        let by_model = report(GroupBy::Model, vec![row("claude-sonnet-4-20250514", "2025-09-10", 1_000_000, 0)]);
+        assert_eq!(by_model.lines(|group| group.to_string())[0].cost_usd, Some(3.0));
+        let unpriced = report(GroupBy::Model, vec![row("unknown-model", "2025-09-10", 1_000_000, 0)]);
+        assert_eq!(unpriced.lines(|group| group.to_string())[0].cost_usd, None);
+        assert_eq!(unpriced.totals.cost_usd, None);
+    }
+
+    #[test]
+    fn limit_window_serializes_to_its_cli_form() {
+        assert_eq!(serde_json::to_value(LimitWindow::Month).unwrap(), "month");
+        assert_eq!(serde_json::to_value(LimitWindow::Rolling(30)).unwrap(), "30d");
+        assert_eq!(serde_json::from_value::<LimitWindow>(json!("7d")).unwrap(), LimitWindow::Rolling(7));
+    }
+}
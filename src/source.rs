@@ -0,0 +1,451 @@
+//! FileProcessor 的输入来源：数据目录、命令行指定的路径、标准输入等流，以及内存中的记录
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Cursor, Read};
+use std::path::{Path, PathBuf};
+use dashmap::DashMap;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::file_processor::ProcessError;
+use crate::tr;
+
+/// 默认接受的日志文件扩展名
+pub(crate) const DEFAULT_EXTENSIONS: [&str; 2] = ["json", "jsonl"];
+
+/// 内容嗅探最多读取的字节数，不会为了拒绝一个文件而读完整个文件
+const SNIFF_BYTES: usize = 4096;
+
+/// 写入日志的工具，决定文件按哪种格式解析
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceTool {
+    /// Claude Code（~/.claude/projects）
+    #[default]
+    Claude,
+    /// OpenAI Codex CLI（~/.codex/sessions），见 [`crate::codex`]
+    Codex,
+    /// Google Gemini CLI（~/.gemini/tmp），见 [`crate::gemini`]
+    Gemini,
+    /// 按配置的字段映射解析的其他工具，见 [`crate::mapping`]
+    Custom,
+}
+
+impl fmt::Display for SourceTool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SourceTool::Claude => "claude",
+            SourceTool::Codex => "codex",
+            SourceTool::Gemini => "gemini",
+            SourceTool::Custom => "custom",
+        })
+    }
+}
+
+/// 数据源提供的一个输入，每个输入对应一个会话
+pub enum SourceInput {
+    /// 磁盘上的日志文件：并行解析，启用缓存时未变化的文件直接使用缓存结果
+    File {
+        /// 文件路径
+        path: PathBuf,
+        /// 文件元数据，用于大小上限、进度与缓存指纹
+        metadata: fs::Metadata,
+        /// 文件所属项目目录的编码名，不在项目目录中时为 None
+        project_dir: Option<String>,
+        /// 写入该文件的工具
+        tool: SourceTool,
+    },
+    /// 逐行读取的 Claude Code JSONL 流，不使用缓存
+    Stream {
+        /// 在诊断信息中标识该输入，也是缺少会话 id 时的会话名
+        name: String,
+        /// 流所属项目目录的编码名，不属于任何项目时为 None
+        project_dir: Option<String>,
+        /// 流的内容
+        reader: Box<dyn BufRead>,
+    },
+}
+
+/// FileProcessor 的输入来源，只负责给出要解析的输入，解析与聚合由 FileProcessor 完成
+pub trait DataSource {
+    /// 列出全部输入；从目录中查找的文件应经过 filter 筛选
+    fn inputs(self, filter: &FileFilter) -> Result<Vec<SourceInput>, ProcessError>;
+}
+
+/// 按扩展名（以及启用时的内容嗅探）判断目录中的文件是否为日志文件，不处理的按扩展名计数
+pub struct FileFilter<'a> {
+    pub(crate) extensions: &'a [String],
+    pub(crate) sniff: bool,
+    pub(crate) skipped: &'a DashMap<String, u64>,
+}
+
+impl FileFilter<'_> {
+    /// 是否处理该文件；不处理的文件计入诊断信息中按扩展名分类的跳过数。
+    /// gzip 压缩的文件（如 cccost backup --compress 的备份 x.jsonl.gz）按去掉 .gz 后的扩展名判断
+    pub fn accepts(&self, path: &Path) -> bool {
+        let extension = path.extension().map(|ext| ext.to_string_lossy().into_owned());
+        let inner = if is_gzip(path) { path.file_stem().map(Path::new).and_then(Path::extension) } else { path.extension() };
+        if let Some(inner) = inner
+            && self.extensions.iter().any(|extension| inner == extension.as_str()) {
+            return true;
+        }
+        if self.sniff && path.is_file() && looks_like_jsonl(path) {
+            return true;
+        }
+
+        if path.is_file() {
+            *self.skipped
+                .entry(extension.unwrap_or_else(|| "(none)".to_string()))
+                .or_insert(0) += 1;
+        }
+        false
+    }
+}
+
+/// 是否为 gzip 压缩的文件（按 .gz 扩展名判断），读取时解压
+pub(crate) fn is_gzip(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "gz")
+}
+
+/// 文件开头是否像 JSONL：以 { 开始，并且换行后紧跟第二个对象
+fn looks_like_jsonl(path: &Path) -> bool {
+    let Ok(file) = fs::File::open(path) else {
+        return false;
+    };
+    let mut head = Vec::with_capacity(SNIFF_BYTES);
+    if io::Read::take(file, SNIFF_BYTES as u64).read_to_end(&mut head).is_err() {
+        return false;
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let head = head.trim_start_matches('\u{feff}').trim_start();
+    head.starts_with('{')
+        && head
+            .split('\n')
+            .skip(1)
+            .any(|line| line.trim_start().starts_with('{'))
+}
+
+/// 文件所属的项目目录名（数据目录下的第一级目录），文件直接位于数据目录下或不在其中时为 None
+fn project_dir_name(root: &Path, file_path: &Path) -> Option<String> {
+    let relative = file_path.strip_prefix(root).ok()?;
+    let mut components = relative.components();
+    let first = components.next()?;
+    components.next()?;
+    Some(first.as_os_str().to_string_lossy().into_owned())
+}
+
+/// 默认的数据目录（~/.claude/projects）：每个项目目录中的日志文件
+pub struct DirectorySource {
+    directory: PathBuf,
+}
+
+impl DirectorySource {
+    /// 扫描 directory 下各项目目录中的日志文件
+    pub fn new(directory: PathBuf) -> Self {
+        Self { directory }
+    }
+}
+
+impl DataSource for DirectorySource {
+    fn inputs(self, filter: &FileFilter) -> Result<Vec<SourceInput>, ProcessError> {
+        if !self.directory.exists() {
+            return Err(ProcessError::MissingDirectory(self.directory));
+        }
+
+        // 获取所有子目录
+        let subdirs: Vec<_> = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .collect(),
+            Err(source) => {
+                return Err(ProcessError::Io {
+                    path: self.directory,
+                    source,
+                });
+            }
+        };
+
+        // 从所有子目录收集所有文件及其元数据
+        let files: Vec<(PathBuf, fs::Metadata)> = subdirs
+            .par_iter()
+            .flat_map(|dir| {
+                fs::read_dir(dir)
+                    .ok()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .filter(|path| filter.accepts(path))
+                    .filter_map(|path| fs::metadata(&path).ok().map(|metadata| (path, metadata)))
+                    .filter(|(_, metadata)| metadata.is_file())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        Ok(files
+            .into_iter()
+            .map(|(path, metadata)| SourceInput::File {
+                project_dir: project_dir_name(&self.directory, &path),
+                path,
+                metadata,
+                tool: SourceTool::Claude,
+            })
+            .collect())
+    }
+}
+
+/// 命令行指定的文件或目录：文件直接解析，目录递归查找日志文件；同一文件只处理一次
+pub struct PathsSource {
+    paths: Vec<PathBuf>,
+    root: PathBuf,
+    tool: SourceTool,
+}
+
+impl PathsSource {
+    /// 处理 paths 中的文件或目录；位于数据目录 root 下的文件按其第一级目录确定所属项目
+    pub fn new(paths: Vec<PathBuf>, root: PathBuf) -> Self {
+        Self { paths, root, tool: SourceTool::Claude }
+    }
+
+    /// 这些文件由 tool 写入，默认为 Claude Code
+    pub fn with_tool(mut self, tool: SourceTool) -> Self {
+        self.tool = tool;
+        self
+    }
+}
+
+impl DataSource for PathsSource {
+    fn inputs(self, filter: &FileFilter) -> Result<Vec<SourceInput>, ProcessError> {
+        let missing: Vec<PathBuf> = self.paths
+            .iter()
+            .filter(|path| !path.exists())
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            return Err(ProcessError::MissingPaths(missing));
+        }
+
+        let mut files = Vec::new();
+        for path in &self.paths {
+            if path.is_dir() {
+                walk_directory(path, filter, &mut files)?;
+            } else {
+                files.push(path.clone());
+            }
+        }
+
+        let mut seen = HashSet::new();
+        Ok(files
+            .into_iter()
+            .filter(|path| seen.insert(fs::canonicalize(path).unwrap_or_else(|_| path.clone())))
+            .filter_map(|path| fs::metadata(&path).ok().map(|metadata| (path, metadata)))
+            .filter(|(_, metadata)| metadata.is_file())
+            .map(|(path, metadata)| SourceInput::File {
+                project_dir: project_dir_name(&self.root, &path),
+                path,
+                metadata,
+                tool: self.tool,
+            })
+            .collect())
+    }
+}
+
+/// OpenAI Codex CLI 的会话目录（~/.codex/sessions）：按 年/月/日 分目录，递归查找其中的日志文件
+pub struct CodexSource {
+    directory: PathBuf,
+}
+
+impl CodexSource {
+    /// 扫描 directory 下的 Codex 会话文件
+    pub fn new(directory: PathBuf) -> Self {
+        Self { directory }
+    }
+}
+
+impl DataSource for CodexSource {
+    fn inputs(self, filter: &FileFilter) -> Result<Vec<SourceInput>, ProcessError> {
+        if !self.directory.exists() {
+            return Err(ProcessError::MissingDirectory(self.directory));
+        }
+        let mut files = Vec::new();
+        walk_directory(&self.directory, filter, &mut files)?;
+        Ok(files
+            .into_iter()
+            .filter_map(|path| fs::metadata(&path).ok().map(|metadata| (path, metadata)))
+            .filter(|(_, metadata)| metadata.is_file())
+            .map(|(path, metadata)| SourceInput::File {
+                path,
+                metadata,
+                project_dir: None,
+                tool: SourceTool::Codex,
+            })
+            .collect())
+    }
+}
+
+/// Google Gemini CLI 的临时目录（~/.gemini/tmp）：每个项目一个以路径哈希命名的目录，
+/// 只读取其中 chats 目录下的会话文件，检查点、日志等其他 JSON 文件不是会话记录
+pub struct GeminiSource {
+    directory: PathBuf,
+}
+
+impl GeminiSource {
+    /// 扫描 directory 下各项目的 chats 目录
+    pub fn new(directory: PathBuf) -> Self {
+        Self { directory }
+    }
+}
+
+impl DataSource for GeminiSource {
+    fn inputs(self, filter: &FileFilter) -> Result<Vec<SourceInput>, ProcessError> {
+        if !self.directory.exists() {
+            return Err(ProcessError::MissingDirectory(self.directory));
+        }
+        let mut files = Vec::new();
+        walk_directory(&self.directory, filter, &mut files)?;
+        Ok(files
+            .into_iter()
+            .filter(|path| path.parent().and_then(Path::file_name).is_some_and(|name| name == "chats"))
+            .filter_map(|path| fs::metadata(&path).ok().map(|metadata| (path, metadata)))
+            .filter(|(_, metadata)| metadata.is_file())
+            .map(|(path, metadata)| SourceInput::File {
+                path,
+                metadata,
+                project_dir: None,
+                tool: SourceTool::Gemini,
+            })
+            .collect())
+    }
+}
+
+/// 两个数据源的输入合在一起（如 Claude Code 与 Codex 的日志目录，可以嵌套合并更多来源）；
+/// 其中一个的目录不存在时只使用另一个，都不存在时返回第一个的错误
+pub struct CombinedSource<A, B>(pub A, pub B);
+
+impl<A: DataSource, B: DataSource> DataSource for CombinedSource<A, B> {
+    fn inputs(self, filter: &FileFilter) -> Result<Vec<SourceInput>, ProcessError> {
+        match (self.0.inputs(filter), self.1.inputs(filter)) {
+            (Ok(mut first), Ok(second)) => {
+                first.extend(second);
+                Ok(first)
+            }
+            (Ok(inputs), Err(ProcessError::MissingDirectory(_))) | (Err(ProcessError::MissingDirectory(_)), Ok(inputs)) => Ok(inputs),
+            (Err(e), _) | (_, Err(e)) => Err(e),
+        }
+    }
+}
+
+fn walk_directory(directory: &Path, filter: &FileFilter, files: &mut Vec<PathBuf>) -> Result<(), ProcessError> {
+    let entries = fs::read_dir(directory).map_err(|source| ProcessError::Io {
+        path: directory.to_path_buf(),
+        source,
+    })?;
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            walk_directory(&path, filter, files)?;
+        } else if filter.accepts(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// 单个 JSONL 流，如标准输入。每次只持有一行，内存占用与输入大小无关
+pub struct ReaderSource {
+    name: String,
+    reader: Box<dyn BufRead>,
+}
+
+impl ReaderSource {
+    /// name 用于在诊断信息中标识该输入
+    pub fn new(name: impl Into<String>, reader: impl BufRead + 'static) -> Self {
+        Self { name: name.into(), reader: Box::new(reader) }
+    }
+}
+
+impl DataSource for ReaderSource {
+    fn inputs(self, _filter: &FileFilter) -> Result<Vec<SourceInput>, ProcessError> {
+        Ok(vec![SourceInput::Stream { name: self.name, project_dir: None, reader: self.reader }])
+    }
+}
+
+/// 内存中的 JSONL 记录，每个元素是一行，作为一个会话处理
+pub struct MemorySource {
+    name: String,
+    lines: Vec<String>,
+}
+
+impl MemorySource {
+    /// name 用于在诊断信息中标识该输入
+    pub fn new(name: impl Into<String>, lines: Vec<String>) -> Self {
+        Self { name: name.into(), lines }
+    }
+}
+
+impl DataSource for MemorySource {
+    fn inputs(self, _filter: &FileFilter) -> Result<Vec<SourceInput>, ProcessError> {
+        let mut content = self.lines.join("\n");
+        content.push('\n');
+        Ok(vec![SourceInput::Stream {
+            name: self.name,
+            project_dir: None,
+            reader: Box::new(Cursor::new(content.into_bytes())),
+        }])
+    }
+}
+
+/// 按会话 id 前缀查找会话文件失败的原因
+#[derive(Debug)]
+pub enum SessionLookupError {
+    /// 没有 id 以该前缀开始的会话
+    NotFound(String),
+    /// 有多个会话的 id 以该前缀开始，附带这些文件
+    Ambiguous(String, Vec<PathBuf>),
+}
+
+impl fmt::Display for SessionLookupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionLookupError::NotFound(prefix) => f.write_str(&tr!("error.session_not_found", prefix)),
+            SessionLookupError::Ambiguous(prefix, paths) => {
+                let paths: Vec<String> = paths.iter().map(|path| path.display().to_string()).collect();
+                f.write_str(&tr!("error.session_ambiguous", prefix, paths.join("\n  ")))
+            }
+        }
+    }
+}
+
+impl std::error::Error for SessionLookupError {}
+
+/// 在 inputs 中查找 id 以 prefix 开始的 Claude Code 会话文件。会话 id 为文件名中第一个 . 之前的部分，
+/// 因此压缩的备份（x.jsonl.gz）同样可以找到；有会话的 id 与 prefix 完全相同时直接返回该文件
+pub fn find_session(inputs: Vec<SourceInput>, prefix: &str) -> Result<PathBuf, SessionLookupError> {
+    let mut matches: Vec<(String, PathBuf)> = inputs
+        .into_iter()
+        .filter_map(|input| match input {
+            SourceInput::File { path, tool: SourceTool::Claude, .. } => {
+                let name = path.file_name()?.to_string_lossy();
+                let id = name.split('.').next().unwrap_or_default().to_string();
+                id.starts_with(prefix).then_some((id, path))
+            }
+            _ => None,
+        })
+        .collect();
+    if let Some(index) = matches.iter().position(|(id, _)| id == prefix) {
+        return Ok(matches.swap_remove(index).1);
+    }
+    match matches.len() {
+        0 => Err(SessionLookupError::NotFound(prefix.to_string())),
+        1 => Ok(matches.remove(0).1),
+        _ => {
+            let mut paths: Vec<PathBuf> = matches.into_iter().map(|(_, path)| path).collect();
+            paths.sort();
+            Err(SessionLookupError::Ambiguous(prefix.to_string(), paths))
+        }
+    }
+}
@@ -1,18 +1,54 @@
 use tabled::{
-    settings::{object::{Columns, Rows}, Alignment, Modify, Style, themes::Colorization, Color}, Table, Tabled
+    grid::config::HorizontalLine, grid::records::vec_records::{Text, VecRecords}, settings::{object::{Columns, Object, Rows}, Alignment, Modify, Style, themes::{Colorization, Theme}, Color}, Table, Tabled
 };
 use tabled::settings::formatting::TrimStrategy;
 use tabled::settings::object::Segment;
-use tabled::settings::Width;
-use crate::item::Usage;
+use tabled::settings::{Format, Remove, Width};
+use tabled::settings::location::ByColumnName;
+use tabled::builder::Builder;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+use chrono::{DateTime, Datelike, NaiveDate, TimeDelta, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{self, IsTerminal, Write};
+use crate::cli::{ColorChoice, GroupBy, Layout, Metric, TableStyle};
+use crate::diagnostics::Diagnostics;
+use crate::distribution::{weekday_abbreviation, weekday_name, HourOfDay, MessageSizeStats, WeekdayUsage};
+use crate::file_processor::{DayExplanation, MessageLatency, NewModel};
+use crate::{bucketing, i18n};
+use crate::import::IMPORT_PROJECT;
+use crate::blocks::Block;
+use crate::budget::{BudgetReport, ProjectBudgetStatus};
+use crate::cache_report::CacheReport;
+use crate::compare::Comparison;
+use crate::daily::{DailySeries, DateSpan, DayAverage, HourUsage, Streaks, TodaySummary};
+use crate::trend::Trend;
+use crate::limit::LimitStatus;
+use crate::matrix::{MatrixCell, ProjectMatrix};
+use crate::model::{ModelFamily, ParsedModel};
+use crate::pricing::cost_for;
+use crate::period::{billing_month_of, date_range, iso_week_label, PeriodKind, PeriodSummary, TokenTotals};
+use crate::item::{DailyCount, DailyUsage, GroupUsage, ProjectModelUsage, SessionSummary, UsageAggregate};
+use crate::latency::{latency_stats, LatencyStats};
+use crate::project::short_project_names;
+use crate::render::{Renderer, Report, ReportMeta, UsageLine};
+use crate::tr;
 use terminal_size::{Width as TermWidth, terminal_size};
 
 #[derive(Tabled)]
 pub struct UsageRow {
     #[tabled(rename = "Date")]
     pub date: String,
+    #[tabled(rename = "Wk")]
+    pub week: String,
     #[tabled(rename = "Model")]
     pub model: String,
+    #[tabled(rename = "Active")]
+    pub active: String,
+    #[tabled(rename = "Msgs")]
+    pub messages: String,
     #[tabled(rename = "Input")]
     pub input_tokens: String,
     #[tabled(rename = "Output")]
@@ -27,228 +63,3863 @@ pub struct UsageRow {
 
 impl UsageRow {
     /// 表格的列数
-    const COLUMN_COUNT: usize = 7;
+    const COLUMN_COUNT: usize = 10;
     
     /// 获取表格的列数
     pub fn column_count() -> usize {
         Self::COLUMN_COUNT
     }
     
-    pub fn from_data(date: String, model: String, usage: Usage) -> Self {
-        let input = usage.input_tokens.unwrap_or(0);
-        let output = usage.output_tokens.unwrap_or(0);
-        let cache_creation = usage.cache_creation_input_tokens.unwrap_or(0);
-        let cache_read = usage.cache_read_input_tokens.unwrap_or(0);
-        let total = input + output + cache_creation + cache_read;
-        
+    /// 将一行的数值格式化为表格单元格，活动时间显示为 tz 中的本地时间
+    pub fn from_line(line: &UsageLine, tz: &Tz) -> Self {
         UsageRow {
-            date,
-            model,
-            input_tokens: Self::format_number(input),
-            output_tokens: Self::format_number(output),
-            cache_creation_input_tokens: Self::format_number(cache_creation),
-            cache_read_input_tokens: Self::format_number(cache_read),
-            total_tokens: Self::format_number(total),
+            date: line.date.clone(),
+            week: Self::format_week(&line.date),
+            model: line.group.clone(),
+            active: Self::format_activity(line, tz),
+            messages: format_count(line.messages),
+            input_tokens: Self::format_tokens(line.input_tokens),
+            output_tokens: Self::format_tokens(line.output_tokens),
+            cache_creation_input_tokens: Self::format_tokens(line.cache_creation_input_tokens),
+            cache_read_input_tokens: Self::format_tokens(line.cache_read_input_tokens),
+            total_tokens: Self::format_number(line.total_tokens),
         }
     }
     
-    fn format_number(n: u32) -> String {
-        if n >= 1_000_000 {
-            format!("{:.1}M", n as f64 / 1_000_000.0)
-        } else if n >= 1_000 {
-            format!("{:.1}K", n as f64 / 1_000.0)
-        } else {
-            n.to_string()
+    /// 多行的合计行（总计或当天小计），未上报的字段按 0 计算
+    fn total_of(date: String, model: String, lines: &[UsageLine]) -> Self {
+        let sum = |field: fn(&UsageLine) -> Option<u64>| -> u64 {
+            lines.iter().map(|line| field(line).unwrap_or(0)).sum()
+        };
+        UsageRow {
+            week: Self::format_week(&date),
+            date,
+            model,
+            active: String::new(),
+            messages: format_count(lines.iter().map(|line| line.messages).sum()),
+            input_tokens: Self::format_number(sum(|line| line.input_tokens)),
+            output_tokens: Self::format_number(sum(|line| line.output_tokens)),
+            cache_creation_input_tokens: Self::format_number(sum(|line| line.cache_creation_input_tokens)),
+            cache_read_input_tokens: Self::format_number(sum(|line| line.cache_read_input_tokens)),
+            total_tokens: Self::format_number(lines.iter().map(|line| line.total_tokens).sum::<u64>()),
         }
     }
-}
 
-pub struct TableRenderer;
+    /// 日期键所在的 ISO 周，如 W37；年末的几天可能是下一年的 W01，年初的几天可能是上一年的 W52/W53。
+    /// 不是日期（合计行等）时为空
+    fn format_week(date: &str) -> String {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map(|day| format!("W{:02}", day.iso_week().week()))
+            .unwrap_or_default()
+    }
 
-impl TableRenderer {
-    pub fn new() -> Self {
-        Self
+    /// 将最早/最晚活动时间格式化为 "09:12–18:47"
+    fn format_activity(line: &UsageLine, tz: &Tz) -> String {
+        match (line.first_seen, line.last_seen) {
+            (Some(first), Some(last)) => format!("{}–{}", first.with_timezone(tz).format("%H:%M"), last.with_timezone(tz).format("%H:%M")),
+            _ => "—".to_string(),
+        }
     }
-    
-    /// 简化模型名称，去除冗余的前后缀
-    fn simplify_model_name(model: &str) -> String {
-        // 移除 claude- 前缀
-        let without_prefix = model.strip_prefix("claude-").unwrap_or(model);
-        
-        // 尝试匹配常见模式并简化
-        // 模式1: {model}-{version}-{date} 例如: sonnet-4-20250514
-        // 模式2: {version}-{model}-{date} 例如: 3-opus-20240229
-        
-        // 分割成部分
-        let parts: Vec<&str> = without_prefix.split('-').collect();
-        
-        if parts.len() >= 3 {
-            // 检查最后一部分是否是日期（8位数字）
-            let last_part = parts.last().unwrap();
-            if last_part.len() == 8 && last_part.chars().all(|c| c.is_numeric()) {
-                // 去掉日期部分
-                let without_date = &parts[..parts.len() - 1];
-                
-                // 重新组合，优化显示
-                if without_date.len() == 2 {
-                    // 可能是 model-version 或 version-model
-                    let first = without_date[0];
-                    let second = without_date[1];
-                    
-                    // 检查哪个是版本号
-                    if first.chars().all(|c| c.is_numeric()) {
-                        // version-model 格式，如 3-opus
-                        format!("{}{}", second, first)
-                    } else if second.chars().all(|c| c.is_numeric()) {
-                        // model-version 格式，如 sonnet-4
-                        format!("{}{}", first, second)
-                    } else {
-                        // 都不是数字，保持原样
-                        without_date.join("-")
-                    }
-                } else {
-                    // 其他情况，直接连接
-                    without_date.join("-")
-                }
-            } else {
-                // 最后一部分不是日期，保持原样
-                without_prefix.to_string()
+
+    /// 未上报的字段显示为 —，与上报了 0 区分
+    fn format_tokens(tokens: Option<u64>) -> String {
+        tokens.map(Self::format_number).unwrap_or_else(|| "—".to_string())
+    }
+
+    /// 缩写为一位小数的 K/M/B。先四舍五入再选单位，舍入后达到 1000 时进位到下一个单位：
+    /// 999 -> "999"，1000 -> "1.0K"，999_949 -> "999.9K"，999_950 -> "1.0M"，
+    /// 1_000_000 -> "1.0M"，999_950_000 -> "1.0B"
+    fn format_number(n: impl Into<u64>) -> String {
+        let n = n.into();
+        if n < 1_000 {
+            return n.to_string();
+        }
+        // 以 0.1 个单位为刻度的整数运算，避免浮点舍入；u128 防止 u64 乘 10 溢出
+        let mut tenths = 0;
+        let mut suffix = "";
+        for (unit, unit_suffix) in [(1_000u128, "K"), (1_000_000, "M"), (1_000_000_000, "B")] {
+            tenths = (u128::from(n) * 10 + unit / 2) / unit;
+            suffix = unit_suffix;
+            if tenths < 10_000 {
+                break;
             }
-        } else {
-            // 部分太少，保持原样
-            without_prefix.to_string()
         }
+        format!("{}.{}{}", tenths / 10, tenths % 10, suffix)
     }
+}
 
-    pub fn render_usage_table(&self, data: Vec<((String, String), Usage)>) {
-        if data.is_empty() {
-            println!("没有可显示的使用数据。");
-            return;
-        }
+#[derive(Tabled)]
+pub struct SessionRow {
+    #[tabled(rename = "Session")]
+    pub session: String,
+    #[tabled(rename = "Start")]
+    pub start: String,
+    #[tabled(rename = "Duration")]
+    pub duration: String,
+    #[tabled(rename = "Project")]
+    pub project: String,
+    #[tabled(rename = "Models")]
+    pub models: String,
+    #[tabled(rename = "Turns")]
+    pub turns: String,
+    #[tabled(rename = "Msgs")]
+    pub messages: String,
+    #[tabled(rename = "Total")]
+    pub total_tokens: String,
+    #[tabled(rename = "Tokens/Turn")]
+    pub tokens_per_turn: String,
+    #[tabled(rename = "Cost")]
+    pub cost: String,
+}
 
-        // 用于计算总计的变量
-        let mut total_input: u32 = 0;
-        let mut total_output: u32 = 0;
-        let mut total_cache_creation: u32 = 0;
-        let mut total_cache_read: u32 = 0;
+#[derive(Tabled)]
+pub struct VersionRow {
+    #[tabled(rename = "Version")]
+    pub version: String,
+    #[tabled(rename = "Days")]
+    pub days: String,
+    #[tabled(rename = "Msgs")]
+    pub messages: String,
+    #[tabled(rename = "Total")]
+    pub total_tokens: String,
+    #[tabled(rename = "Avg/Msg")]
+    pub average_tokens: String,
+}
 
-        // 按日期分组数据
-        use std::collections::BTreeMap;
-        let mut grouped_data: BTreeMap<String, Vec<(String, Usage)>> = BTreeMap::new();
-        
-        for ((model, date), usage) in data {
-            // 过滤掉所有值都为0的数据
-            if usage.input_tokens.unwrap_or(0) == 0 && 
-               usage.output_tokens.unwrap_or(0) == 0 &&
-               usage.cache_creation_input_tokens.unwrap_or(0) == 0 &&
-               usage.cache_read_input_tokens.unwrap_or(0) == 0 {
-                continue;
-            }
-            
-            // 累计总和
-            total_input += usage.input_tokens.unwrap_or(0);
-            total_output += usage.output_tokens.unwrap_or(0);
-            total_cache_creation += usage.cache_creation_input_tokens.unwrap_or(0);
-            total_cache_read += usage.cache_read_input_tokens.unwrap_or(0);
-            
-            grouped_data.entry(date).or_insert_with(Vec::new).push((model, usage));
+#[derive(Tabled)]
+pub struct ProjectRow {
+    #[tabled(rename = "Project")]
+    pub project: String,
+    #[tabled(rename = "Last Active")]
+    pub last_active: String,
+    #[tabled(rename = "Msgs")]
+    pub messages: String,
+    #[tabled(rename = "Total")]
+    pub total_tokens: String,
+}
+
+/// 单个项目在整个范围内的汇总
+#[derive(Serialize)]
+pub struct ProjectSummary {
+    pub project: String,
+    pub last_active: Option<DateTime<Utc>>,
+    pub messages: u64,
+    pub total_tokens: u64,
+}
+
+/// 追加在每日用量表格末尾的派生列，按日期填充，Total 行使用 total
+pub struct ExtraColumn {
+    pub header: String,
+    pub cells: HashMap<String, String>,
+    pub total: String,
+}
+
+/// 单个项目在所选范围内的用量和费用，用于按项目分摊
+#[derive(Serialize)]
+pub struct ProjectCost {
+    pub project: String,
+    pub sessions: u64,
+    pub active_days: u64,
+    #[serde(flatten)]
+    pub tokens: TokenTotals,
+    pub cost_usd: Option<f64>,
+    /// 占全部费用的百分比，没有任何费用时按 token 总量计算
+    pub share_percent: f64,
+    /// 配置了项目预算时，该项目本计费月的费用与预算
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget: Option<ProjectBudgetStatus>,
+}
+
+/// 单个模型在所选范围内的合计；简化后同名的多个模型 id 合为一项
+#[derive(Serialize)]
+pub struct ModelTotal {
+    pub model: String,
+    pub model_ids: Vec<String>,
+    pub active_days: u64,
+    pub messages: u64,
+    #[serde(flatten)]
+    pub tokens: TokenTotals,
+    pub cost_usd: Option<f64>,
+    /// 占全部费用的百分比，没有任何费用时按 token 总量计算
+    pub share_percent: f64,
+}
+
+/// 时间段内单个模型的 token 占比
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelShare {
+    pub model: String,
+    pub total_tokens: u64,
+    pub share_percent: f64,
+}
+
+/// 单个 Claude Code 版本在整个范围内的汇总
+#[derive(Serialize)]
+pub struct VersionSummary {
+    pub version: String,
+    pub days_active: u64,
+    pub messages: u64,
+    pub total_tokens: u64,
+    pub avg_tokens_per_message: Option<u64>,
+}
+
+/// 按名称计数的输出项（工具调用、stop_reason 等），汇总模式下没有日期
+pub struct NamedCount {
+    pub date: Option<String>,
+    pub name: String,
+    pub count: u64,
+}
+
+/// 按数字段比较版本号（1.0.10 排在 1.0.9 之后），非数字部分按字符串比较
+fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let parts = |version: &str| -> Vec<(u64, String)> {
+        version
+            .split('.')
+            .map(|part| (part.parse::<u64>().unwrap_or(u64::MAX), part.to_string()))
+            .collect()
+    };
+    parts(a).cmp(&parts(b))
+}
+
+/// 模型的语义顺序：按系列（opus、sonnet、haiku、其他），同系列内版本较新的在前
+fn compare_models(a: &str, b: &str) -> std::cmp::Ordering {
+    ParsedModel::parse(a).cmp(&ParsedModel::parse(b))
+}
+
+/// 时长格式化为 1h 05m、12m 30s 或 45s
+pub fn format_duration(duration: TimeDelta) -> String {
+    let seconds = duration.num_seconds().max(0);
+    let (hours, minutes, seconds) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+    if hours > 0 {
+        format!("{}h {:02}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {:02}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// 相对对比值的变化百分比；对比值为 0 而当前有用量时为 new，两者都为 0 时为 —
+pub fn format_change(current: f64, previous: f64) -> String {
+    if previous > 0.0 {
+        format!("{:+.1}%", (current / previous - 1.0) * 100.0)
+    } else if current > 0.0 {
+        "new".to_string()
+    } else {
+        "—".to_string()
+    }
+}
+
+/// 按比例（0–1）填充的文本进度条
+pub fn format_bar(fraction: f64, width: usize) -> String {
+    let filled = ((fraction * width as f64).round().max(0.0) as usize).min(width);
+    format!("{}{}", "█".repeat(filled), "░".repeat(width - filled))
+}
+
+/// 费用格式化为美元，未知时为 —
+pub fn format_cost(cost: Option<f64>) -> String {
+    cost.map(|cost| format!("${:.2}", cost)).unwrap_or_else(|| "—".to_string())
+}
+
+/// 将字节数格式化为易读的形式（按 1024 进制）
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// 毫秒数格式化为 850ms 或 12.3s
+fn format_millis(ms: u64) -> String {
+    if ms < 1000 {
+        format!("{}ms", ms)
+    } else {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    }
+}
+
+/// 文本在终端中占的列数：中日韩文字和大多数 emoji 占两列，组合字符不占列
+pub fn display_width(text: &str) -> usize {
+    text.width()
+}
+
+/// 按显示宽度在右侧补空格到 width 列，已经足够宽时原样返回
+pub fn pad_to_width(text: &str, width: usize) -> String {
+    format!("{}{}", text, " ".repeat(width.saturating_sub(display_width(text))))
+}
+
+/// 超过 max_width 列时只保留结尾部分，开头以 … 代替，结果不超过 max_width 列
+pub fn truncate_start(text: &str, max_width: usize) -> String {
+    if display_width(text) <= max_width {
+        return text.to_string();
+    }
+    let tail = take_width(text.chars().rev(), max_width.saturating_sub(ELLIPSIS_WIDTH));
+    std::iter::once('…').chain(tail.into_iter().rev()).collect()
+}
+
+/// 超过 max_width 列时只保留开头部分，结尾以 … 代替，结果不超过 max_width 列
+pub fn truncate_end(text: &str, max_width: usize) -> String {
+    if display_width(text) <= max_width {
+        return text.to_string();
+    }
+    let head = take_width(text.chars(), max_width.saturating_sub(ELLIPSIS_WIDTH));
+    head.into_iter().chain(std::iter::once('…')).collect()
+}
+
+/// 超过 max_width 列时保留开头和结尾，中间以 … 代替，结果不超过 max_width 列
+pub fn truncate_middle(text: &str, max_width: usize) -> String {
+    if display_width(text) <= max_width {
+        return text.to_string();
+    }
+    let budget = max_width.saturating_sub(ELLIPSIS_WIDTH);
+    let tail = take_width(text.chars().rev(), budget / 2);
+    let head = take_width(text.chars(), budget - tail.iter().map(|c| c.width().unwrap_or(0)).sum::<usize>());
+    head.into_iter().chain(std::iter::once('…')).chain(tail.into_iter().rev()).collect()
+}
+
+/// … 占的列数
+const ELLIPSIS_WIDTH: usize = 1;
+
+/// 按顺序取字符直到总宽度将超过 max_width，整字符截断，不会切开多字节字符或双宽字符
+fn take_width(chars: impl Iterator<Item = char>, max_width: usize) -> Vec<char> {
+    let mut taken = Vec::new();
+    let mut width = 0;
+    for c in chars {
+        let char_width = c.width().unwrap_or(0);
+        if width + char_width > max_width {
+            break;
         }
+        taken.push(c);
+        width += char_width;
+    }
+    taken
+}
 
-        // 创建表格行，相同日期的多个模型会合并显示
-        let mut rows: Vec<UsageRow> = Vec::new();
-        for (date, models) in grouped_data {
-            if models.len() == 1 {
-                // 只有一个模型，正常显示
-                let (model, usage) = models.into_iter().next().unwrap();
-                rows.push(UsageRow::from_data(date, Self::simplify_model_name(&model), usage));
-            } else {
-                // 多个模型，需要合并显示
-                let mut combined_models = Vec::new();
-                let mut combined_input = Vec::new();
-                let mut combined_output = Vec::new();
-                let mut combined_cache_create = Vec::new();
-                let mut combined_cache_read = Vec::new();
-                let mut combined_total = Vec::new();
-                
-                for (model, usage) in models {
-                    combined_models.push(Self::simplify_model_name(&model));
-                    
-                    let input = usage.input_tokens.unwrap_or(0);
-                    let output = usage.output_tokens.unwrap_or(0);
-                    let cache_creation = usage.cache_creation_input_tokens.unwrap_or(0);
-                    let cache_read = usage.cache_read_input_tokens.unwrap_or(0);
-                    let total = input + output + cache_creation + cache_read;
-                    
-                    combined_input.push(UsageRow::format_number(input));
-                    combined_output.push(UsageRow::format_number(output));
-                    combined_cache_create.push(UsageRow::format_number(cache_creation));
-                    combined_cache_read.push(UsageRow::format_number(cache_read));
-                    combined_total.push(UsageRow::format_number(total));
-                }
-                
-                rows.push(UsageRow {
-                    date,
-                    model: combined_models.join("\n"),
-                    input_tokens: combined_input.join("\n"),
-                    output_tokens: combined_output.join("\n"),
-                    cache_creation_input_tokens: combined_cache_create.join("\n"),
-                    cache_read_input_tokens: combined_cache_read.join("\n"),
-                    total_tokens: combined_total.join("\n"),
-                });
+/// 按显示宽度将文本折成不超过 width 的多行，与表格的折行方式相同（按字符断开，不保留单词）
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for source in text.lines() {
+        let mut line = String::new();
+        let mut line_width = 0;
+        for c in source.chars() {
+            let char_width = c.width().unwrap_or(0);
+            if line_width + char_width > width.max(1) && !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+                line_width = 0;
             }
+            line.push(c);
+            line_width += char_width;
         }
-        
-        // 添加总计行
-        let total_all = total_input + total_output + total_cache_creation + total_cache_read;
-        rows.push(UsageRow {
-            date: "Total".to_string(),
-            model: String::new(), // 模型列为空
-            input_tokens: UsageRow::format_number(total_input),
-            output_tokens: UsageRow::format_number(total_output),
-            cache_creation_input_tokens: UsageRow::format_number(total_cache_creation),
-            cache_read_input_tokens: UsageRow::format_number(total_cache_read),
-            total_tokens: UsageRow::format_number(total_all),
-        });
+        lines.push(line);
+    }
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines
+}
 
-        let num_columns = UsageRow::column_count();
-        // 获取表格行数（包括表头）
-        let total_rows = rows.len() + 1; // +1 因为有表头
-        
-        let mut table = Table::new(rows);
+/// 为整数添加千位分隔符，例如 84211 -> "84,211"
+pub fn format_count(n: u64) -> String {
+    let digits = n.to_string();
+    let mut result = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            result.push(',');
+        }
+        result.push(c);
+    }
+    result
+}
+
+pub struct TableRenderer {
+    // 是否显示消息数列
+    show_messages: bool,
+    // 是否显示最早/最晚活动时间列
+    show_activity: bool,
+    // 使用量表格第二列的分组维度
+    group_by: GroupBy,
+    // 是否保留同一模型不同快照的独立行
+    split_snapshots: bool,
+    // ~/.claude.json 中记录的启动次数，显示在摘要中
+    app_startups: Option<u64>,
+    // 是否显示完整的模型 id 而不是简化名称
+    full_model_names: bool,
+    // 同一天的多个分组是否合并到一行的单元格中，而不是各占一行
+    merged_cells: bool,
+    // 按行显示时，多个分组的日期是否附加小计行
+    day_subtotals: bool,
+    // 每日用量按表格还是纵向排列
+    layout: Layout,
+    // Layout::Auto 时，终端宽度小于该列数则纵向排列
+    vertical_threshold: usize,
+    // 表格的边框样式
+    style: TableStyle,
+    // 隔一天加底色时的 SGR 参数
+    zebra: Option<String>,
+    // 是否保留所有行都为 0 的数值列
+    all_columns: bool,
+    // 日期前是否显示星期
+    weekday: bool,
+    // 周六、周日的行是否显示为暗色
+    dim_weekends: bool,
+    // 按模型系列覆盖的模型名称颜色（SGR 参数）
+    model_colors: BTreeMap<String, String>,
+    // 报告的来源信息，输出在末尾的说明行和 JSON 的 meta 中
+    meta: Option<ReportMeta>,
+    // 按计费月分隔并附加月小计时，计费月的起始日
+    month_subtotals: Option<u32>,
+    // 是否在日期后显示 ISO 周数（Wk 列）
+    week_number: bool,
+    // --width 指定的输出宽度，优先于终端宽度与 COLUMNS
+    width: Option<usize>,
+    // 何时输出 ANSI 颜色
+    color: ColorChoice,
+    // 日期键所用的时区，决定哪一天是今天以及显示时间的时区
+    timezone: Tz,
+}
+
+/// --zebra 默认的底色：256 色中的深灰背景
+pub const DEFAULT_ZEBRA_COLOR: &str = "48;5;236";
+
+/// --layout auto 默认的纵向排列阈值（终端列数）
+pub const DEFAULT_VERTICAL_THRESHOLD: usize = 60;
+
+/// 输出到管道或文件、也没有设置 COLUMNS 时按该宽度（列数）排版
+pub const DEFAULT_OUTPUT_WIDTH: usize = 120;
+
+/// 使用量表格各列的最小宽度：日期（含今天的 * 标记）、周数、分组名称、活动时间与数值
+const DATE_MIN_WIDTH: usize = 11;
+const WEEK_MIN_WIDTH: usize = 4;
+const MODEL_MIN_WIDTH: usize = 9;
+const ACTIVITY_MIN_WIDTH: usize = 11;
+const NUMBER_MIN_WIDTH: usize = 6;
+
+/// 终端宽度不足时按顺序隐藏的列
+const HIDEABLE_COLUMNS: [&str; 2] = ["Cache Create", "Cache Read"];
+
+/// 终端表格的列宽安排
+struct UsageLayout {
+    // 显示的各列：(表头, 宽度)，按显示顺序
+    widths: Vec<(String, usize)>,
+    // 因宽度不足隐藏的列
+    hidden: Vec<&'static str>,
+}
+
+impl UsageLayout {
+    fn width_of(&self, header: &str) -> Option<usize> {
+        self.widths.iter().find(|(name, _)| name == header).map(|(_, width)| *width)
+    }
+}
 
-        // 应用样式
-        table.with(Style::modern());
+/// 把 spare 格宽度平均加到各列上，除不尽的余数从左起每列多分 1 格
+fn distribute(columns: Vec<(String, usize)>, spare: usize) -> Vec<(String, usize)> {
+    let count = columns.len().max(1);
+    columns
+        .into_iter()
+        .enumerate()
+        .map(|(index, (header, width))| (header, width + spare / count + usize::from(index < spare % count)))
+        .collect()
+}
+
+/// 尚未设置样式的每日用量表格
+struct UsageTable {
+    table: Table,
+    // 显示的列数
+    num_columns: usize,
+    // 行数，含表头
+    total_rows: usize,
+    // 除第一天外每天第一行的行号，按行显示时在这些行之前画分隔线
+    day_starts: Vec<usize>,
+    // 今天（按 with_timezone 的时区）的各行，当天尚未结束，日期后标记 *
+    today_rows: Option<std::ops::Range<usize>>,
+    // --fill-gaps 补充的空日期所在的行
+    gap_rows: Vec<usize>,
+    // 启用 dim_weekends 时，周六、周日第一行的行号
+    weekend_rows: Vec<usize>,
+    // 各行（不含表头）分组列的内容，合并单元格时为换行分隔的多个名称
+    groups: Vec<String>,
+    // 除第一个月外每个计费月第一行的行号，在这些行之前画双线
+    month_starts: Vec<usize>,
+    // 月小计行的行号
+    month_rows: Vec<usize>,
+}
 
-        // 获取终端宽度并调整表格
-        if let Some((TermWidth(width), ..)) = terminal_size() {
-            let term_width = width as usize;
+impl Renderer for TableRenderer {
+    /// 终端表格：按终端宽度调整列宽，数字列右对齐，表头与合计行着色
+    fn render(&self, report: &Report, out: &mut dyn Write) -> io::Result<()> {
+        // markdown 样式与 --format markdown 使用同一种输出
+        if self.style == TableStyle::Markdown {
+            return MarkdownRenderer(self).render(report, out);
+        }
+        if self.vertical() {
+            return self.render_vertical(report, out);
+        }
+        let extra_columns = self.extra_columns(report);
+        let empty_columns = self.empty_columns(report);
+        let layout = self.usage_layout(self.output_width(), &extra_columns, &empty_columns);
+        let Some(UsageTable {
+            mut table,
+            num_columns,
+            total_rows,
+            day_starts,
+            today_rows,
+            gap_rows,
+            weekend_rows,
+            groups,
+            month_starts,
+            month_rows,
+        }) = self.usage_table(report, &extra_columns, &empty_columns, Some(&layout)) else {
+            return writeln!(out, "{}", tr!("report.no_usage"));
+        };
 
-            // 使用终端宽度的70%，最大200
-            let table_width = (term_width * 7 / 10).min(200);
-            let cell_width = table_width / num_columns;
-            // 设置单元格宽度，增大到给定单元格大小
-            table.with(Modify::new(Segment::all()).with(Width::increase(cell_width)));
-            table.with(Modify::new(Segment::all()).with(Width::wrap(cell_width)));
+        // 应用样式；按行显示时同一天的各行之间不画线，月份之间画双线
+        let mut theme = if self.merged_cells {
+            self.theme()
         } else {
-            // 无法获取终端大小时的后备方案
-            table.with(Width::wrap(10));
+            self.sub_row_theme(&day_starts, &month_rows, total_rows)
+        };
+        if let Some(separator) = self.month_separator() {
+            for &row in &month_starts {
+                theme.insert_horizontal_line(row, separator);
+            }
+        }
+        table.with(theme);
+
+        // 按输出宽度调整表格：设置单元格宽度，增大到给定单元格大小
+        for (column, (_, width)) in layout.widths.iter().enumerate() {
+            table.with(Modify::new(Columns::one(column)).with(Width::increase(*width)));
+            table.with(Modify::new(Columns::one(column)).with(Width::wrap(*width)));
         }
         
-        // 数字列右对齐（从第3列开始，即索引2-6）
+        // 数字列右对齐（从第3列开始，即索引2及之后）
         // increase的MinWidth的布局时 需要使用TrimStrategy协助右对齐
         table.with(
-            Modify::new(Columns::new(2..num_columns))
+            Modify::new(Columns::new(self.group_column() + 1..num_columns))
                 .with(Alignment::right())
                 .with(TrimStrategy::Horizontal)
         );
         
         // 为表头行添加背景色
-        table.with(Colorization::exact([Color::FG_BRIGHT_GREEN], Rows::new(0..1)));
+        localize_header(&mut table);
+        self.colorize(&mut table, Color::FG_BRIGHT_GREEN, Rows::new(0..1));
         
         // 为 Total 行添加特殊样式（黄色前景色，加粗效果）
-        table.with(Colorization::exact([Color::FG_YELLOW], Rows::new((total_rows-1)..total_rows)));
+        self.colorize(&mut table, Color::FG_YELLOW, Rows::new((total_rows-1)..total_rows));
+
+        // 模型名称按系列着色
+        if self.group_by == GroupBy::Model && self.colors_enabled() {
+            let groups = groups.iter().enumerate().filter(|(row, _)| !month_rows.contains(&(row + 1)));
+            for (row, group) in groups.filter(|(_, group)| !group.is_empty() && *group != i18n::label("Subtotal")) {
+                let painted: Vec<String> = group.lines().map(|line| self.paint_model(line)).collect();
+                table.modify((row + 1, self.group_column()), painted.join("\n"));
+            }
+        }
+
+        // 今天的行加粗，提示这一天的数据还不完整；补充的空日期与 --dim-weekends 时的周末显示为暗色；
+        // --zebra 时隔一天加底色，同一天的各行作为整体
+        let zebra = self
+            .zebra
+            .as_ref()
+            .filter(|_| self.colors_enabled())
+            .map(|sgr| Color::new(format!("\x1b[{}m", sgr), "\x1b[0m"));
+        let starts = std::iter::once(1).chain(day_starts.iter().copied());
+        let ends = day_starts.iter().copied().chain(std::iter::once(total_rows - 1));
+        for (i, rows) in starts.zip(ends).map(|(start, end)| start..end).enumerate() {
+            let shade = zebra.clone().filter(|_| i % 2 == 1);
+            let bold = today_rows.as_ref().is_some_and(|today| today.start == rows.start).then_some(Color::BOLD);
+            let dim = (gap_rows.contains(&rows.start) || weekend_rows.contains(&rows.start)).then(dim);
+            if let Some(color) = [shade, bold, dim].into_iter().flatten().reduce(|a, b| a | b) {
+                self.colorize(&mut table, color, Rows::new(rows));
+            }
+        }
+        // 月小计行为青色，覆盖所在日期的底色
+        for &row in &month_rows {
+            self.colorize(&mut table, Color::FG_CYAN, Rows::one(row));
+        }
+
+        writeln!(out, " === {} ===", i18n::label("Usage Summary"))?;
+        writeln!(out, "{}", table)?;
+        if today_rows.is_some() {
+            writeln!(out, "{}", partial_day_note())?;
+        }
+        if !layout.hidden.is_empty() {
+            writeln!(out, "{}", tr!("report.hidden_columns", column_list(&layout.hidden)))?;
+        }
+        if !empty_columns.is_empty() {
+            writeln!(out, "{}", empty_columns_note(&empty_columns))?;
+        }
+        Ok(())
+    }
+}
+
+/// 隐藏全为 0 的列时，表格下方的说明
+fn empty_columns_note(empty_columns: &[&str]) -> String {
+    tr!("report.empty_columns", column_list(empty_columns))
+}
+
+/// 以当前语言的列名和分隔符列出的列
+fn column_list(columns: &[&str]) -> String {
+    columns.iter().map(|column| i18n::label(column)).collect::<Vec<_>>().join(&tr!("report.list_separator"))
+}
+
+/// 无法识别的模型系列轮流使用的前景色
+const UNKNOWN_MODEL_PALETTE: [&str; 5] = ["38;5;180", "38;5;140", "38;5;109", "38;5;216", "38;5;152"];
+
+/// 补充的空日期使用的暗色
+fn dim() -> Color {
+    Color::new("\x1b[2m", "\x1b[22m")
+}
+
+/// 今天的日期后附加 * 时，表格下方的说明
+fn partial_day_note() -> String {
+    tr!("report.partial_day")
+}
+
+/// 将表头一行换成当前语言的标签；单元格已按宽度补齐空格时保留补齐的空格
+fn localize_header(table: &mut Table) {
+    table.with(Modify::new(Rows::first()).with(Format::content(|content| {
+        let text = content.trim();
+        content.replacen(text, i18n::label(text), 1)
+    })));
+}
+
+/// 以 JSON 输出每日用量：每天一项，包含各模型明细以及启用的累计、移动平均等字段
+pub struct JsonRenderer<'a>(pub &'a TableRenderer);
+
+impl Renderer for JsonRenderer<'_> {
+    fn render(&self, report: &Report, out: &mut dyn Write) -> io::Result<()> {
+        let json = self.0.daily_json(
+            &report.days,
+            &report.series,
+            report.cumulative,
+            report.moving_average.as_ref().map(|(_, averages)| averages),
+            report.day_change,
+        );
+        serde_json::to_writer_pretty(&mut *out, &self.0.with_json_meta(json))?;
+        writeln!(out)
+    }
+}
+
+/// 以 CSV 输出每日用量：每个 (日期, 分组) 一行，数值不缩写，按模型分组时附带费用。
+/// 累计、移动平均等按日的附加列不适用于逐行的明细，不会输出
+pub struct CsvRenderer<'a>(pub &'a TableRenderer);
+
+impl Renderer for CsvRenderer<'_> {
+    fn render(&self, report: &Report, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "date,{}{}{},messages,input_tokens,output_tokens,cache_creation_input_tokens,cache_read_input_tokens,total_tokens,cost_usd",
+            if self.0.weekday { "weekday," } else { "" },
+            if self.0.week_number { "iso_week," } else { "" },
+            report.group_by.label().to_lowercase()
+        )?;
+        for GroupUsage { group: name, date, usage: aggregate } in &report.rows {
+            let usage = &aggregate.usage;
+            if usage.total() == 0 {
+                continue;
+            }
+            let cost = match report.group_by {
+                GroupBy::Model => cost_for(name, usage).map(|cost| format!("{:.6}", cost)).unwrap_or_default(),
+                GroupBy::Branch | GroupBy::Project | GroupBy::Source | GroupBy::Person => String::new(),
+            };
+            let day = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok();
+            let weekday = match day {
+                Some(day) if self.0.weekday => format!("{},", weekday_abbreviation(day.weekday())),
+                _ if self.0.weekday => ",".to_string(),
+                _ => String::new(),
+            };
+            let week = match day {
+                Some(day) if self.0.week_number => format!("{},", iso_week_label(day)),
+                _ if self.0.week_number => ",".to_string(),
+                _ => String::new(),
+            };
+            writeln!(
+                out,
+                "{},{}{}{},{},{},{},{},{},{},{}",
+                date,
+                weekday,
+                week,
+                csv_field(name),
+                aggregate.messages,
+                usage.input_tokens.unwrap_or(0),
+                usage.output_tokens.unwrap_or(0),
+                usage.cache_creation_input_tokens.unwrap_or(0),
+                usage.cache_read_input_tokens.unwrap_or(0),
+                usage.total(),
+                cost,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// CSV 字段：含有逗号、引号或换行时加引号，内部的引号加倍
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// 以 Markdown 表格输出每日用量，列与终端表格相同；合并单元格时同一天多个模型的明细以 <br> 分隔
+pub struct MarkdownRenderer<'a>(pub &'a TableRenderer);
+
+impl Renderer for MarkdownRenderer<'_> {
+    fn render(&self, report: &Report, out: &mut dyn Write) -> io::Result<()> {
+        let extra_columns = self.0.extra_columns(report);
+        let empty_columns = self.0.empty_columns(report);
+        let Some(UsageTable { mut table, num_columns, today_rows, .. }) = self.0.usage_table(report, &extra_columns, &empty_columns, None) else {
+            return writeln!(out, "{}", tr!("report.no_usage"));
+        };
+        localize_header(&mut table);
+        table.with(Modify::new(Segment::all()).with(Format::content(|content| content.replace('\n', "<br>"))));
+        table.with(Style::markdown());
+        table.with(Modify::new(Columns::new(self.0.group_column() + 1..num_columns)).with(Alignment::right()));
+
+        writeln!(out, "## {}", i18n::label("Usage Summary"))?;
+        writeln!(out)?;
+        writeln!(out, "{}", table)?;
+        if today_rows.is_some() {
+            writeln!(out)?;
+            writeln!(out, "{}", partial_day_note())?;
+        }
+        if !empty_columns.is_empty() {
+            writeln!(out)?;
+            writeln!(out, "{}", empty_columns_note(&empty_columns))?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for TableRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TableRenderer {
+    pub fn new() -> Self {
+        Self {
+            show_messages: false,
+            show_activity: false,
+            group_by: GroupBy::Model,
+            split_snapshots: false,
+            app_startups: None,
+            full_model_names: false,
+            merged_cells: false,
+            day_subtotals: false,
+            layout: Layout::Auto,
+            vertical_threshold: DEFAULT_VERTICAL_THRESHOLD,
+            width: None,
+            style: TableStyle::Modern,
+            zebra: None,
+            all_columns: false,
+            weekday: false,
+            dim_weekends: false,
+            model_colors: BTreeMap::new(),
+            meta: None,
+            month_subtotals: None,
+            week_number: false,
+            color: ColorChoice::Auto,
+            timezone: Tz::UTC,
+        }
+    }
+
+    /// 何时输出 ANSI 颜色，默认在输出是终端且没有设置 NO_COLOR 时
+    pub fn with_color(mut self, color: ColorChoice) -> Self {
+        self.color = color;
+        self
+    }
 
-        println!(" === Usage Summary ===");
-        println!("{}", table);
+    /// 写入 stdout 的报告是否着色
+    fn colors_enabled(&self) -> bool {
+        self.colors_for(io::stdout().is_terminal())
+    }
+
+    /// 写入 terminal 表示是否为终端的输出时是否着色：ColorChoice::Auto 时还需要没有设置 NO_COLOR
+    fn colors_for(&self, terminal: bool) -> bool {
+        match self.color {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => terminal && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()),
+        }
+    }
+
+    /// 给表格中的 rows 着色，不着色时不做任何修改
+    fn colorize<O: Object<VecRecords<Text<String>>>>(&self, table: &mut Table, color: Color, rows: O) {
+        if self.colors_enabled() {
+            table.with(Colorization::exact([color], rows));
+        }
+    }
+
+    /// 报告中的日期按 tz 分组（见 FileProcessorBuilder::with_timezone），今天的标记与显示的时间使用同一时区，默认为 UTC
+    pub fn with_timezone(mut self, tz: Tz) -> Self {
+        self.timezone = tz;
+        self
+    }
+
+    /// with_timezone 时区中的今天
+    fn today(&self) -> NaiveDate {
+        bucketing::today(&self.timezone)
+    }
+
+    /// 使用量表格按模型还是 git 分支分组
+    pub fn with_group_by(mut self, group_by: GroupBy) -> Self {
+        self.group_by = group_by;
+        self
+    }
+
+    /// 按显示名称区分同一模型的不同快照（如 sonnet4 20250514 与 20250601）
+    pub fn with_split_snapshots(mut self, split_snapshots: bool) -> Self {
+        self.split_snapshots = split_snapshots;
+        self
+    }
+
+    /// 在摘要中显示 Claude Code 的启动次数
+    pub fn with_app_startups(mut self, app_startups: Option<u64>) -> Self {
+        self.app_startups = app_startups;
+        self
+    }
+
+    /// 显示完整的模型 id（如 claude-sonnet-4-20250514），不做简化
+    pub fn with_full_model_names(mut self, full_model_names: bool) -> Self {
+        self.full_model_names = full_model_names;
+        self
+    }
+
+    /// 同一天的多个分组合并到一行，各列以换行分隔（旧的显示方式）
+    pub fn with_merged_cells(mut self, merged_cells: bool) -> Self {
+        self.merged_cells = merged_cells;
+        self
+    }
+
+    /// 有多个分组的日期附加一行当天的小计，合并单元格时不适用
+    pub fn with_day_subtotals(mut self, day_subtotals: bool) -> Self {
+        self.day_subtotals = day_subtotals;
+        self
+    }
+
+    /// 每日用量的排列方式，Layout::Auto 时按终端宽度选择
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Layout::Auto 时改为纵向排列的终端宽度（列数）
+    pub fn with_vertical_threshold(mut self, width: usize) -> Self {
+        self.vertical_threshold = width;
+        self
+    }
+
+    /// 排版使用的输出宽度（列数），None 时依次取终端宽度、COLUMNS 环境变量与 DEFAULT_OUTPUT_WIDTH
+    pub fn with_width(mut self, width: Option<usize>) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// 所有表格的边框样式
+    pub fn with_style(mut self, style: TableStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// 终端表格中每日用量隔一天加底色，参数为 SGR 参数（如 "48;5;236"）；
+    /// 同一天的多行作为整体着色。不着色时（见 with_color）无效
+    pub fn with_zebra(mut self, color: Option<String>) -> Self {
+        self.zebra = color;
+        self
+    }
+
+    /// 保留所有行都为 0 或未上报的数值列，默认不显示这些列
+    pub fn with_all_columns(mut self, all_columns: bool) -> Self {
+        self.all_columns = all_columns;
+        self
+    }
+
+    /// 日期前显示星期的缩写（如 Sat 2025-09-13），CSV 中为单独的 weekday 列
+    pub fn with_weekday(mut self, weekday: bool) -> Self {
+        self.weekday = weekday;
+        self
+    }
+
+    /// 在日期后增加 ISO 周数列（Wk，如 W37），与 weekly 默认（周一开始）的周标签一致；
+    /// CSV 中为单独的 iso_week 列，值为完整的标签（如 2025-W37）
+    pub fn with_week_number(mut self, week_number: bool) -> Self {
+        self.week_number = week_number;
+        self
+    }
+
+    /// 周六、周日的行显示为暗色
+    pub fn with_dim_weekends(mut self, dim_weekends: bool) -> Self {
+        self.dim_weekends = dim_weekends;
+        self
+    }
+
+    /// 覆盖模型名称的颜色，键为模型系列（opus、sonnet、haiku 或无法识别的系列名称），值为前景色的 SGR 参数
+    pub fn with_model_colors(mut self, model_colors: BTreeMap<String, String>) -> Self {
+        self.model_colors = model_colors;
+        self
+    }
+
+    /// 报告的来源信息：文本输出在末尾加一行暗色的说明（见 render_footer），JSON 输出附带 meta 字段
+    pub fn with_meta(mut self, meta: Option<ReportMeta>) -> Self {
+        self.meta = meta;
+        self
+    }
+
+    /// 每日用量表格在计费月之间画双线，已结束的月份附加一行小计；参数为计费月的起始日，
+    /// 1 为自然月。只影响终端表格、纵向排列与 Markdown，CSV 与 JSON 不输出小计
+    pub fn with_month_subtotals(mut self, billing_day: Option<u32>) -> Self {
+        self.month_subtotals = billing_day;
+        self
+    }
+
+    /// 在 JSON 中附加来源信息：对象增加 meta 字段，其他值放在 data 字段中
+    fn with_json_meta(&self, value: Value) -> Value {
+        let Some(meta) = &self.meta else {
+            return value;
+        };
+        let meta = serde_json::to_value(meta).unwrap_or(Value::Null);
+        match value {
+            Value::Object(mut object) => {
+                object.insert("meta".to_string(), meta);
+                Value::Object(object)
+            }
+            data => serde_json::json!({ "meta": meta, "data": data }),
+        }
+    }
+
+    /// 输出报告末尾的来源说明，终端中显示为暗色
+    pub fn render_footer(&self, out: &mut dyn Write) -> io::Result<()> {
+        if let Some(meta) = &self.meta {
+            let line = meta.to_string();
+            if self.colors_enabled() {
+                writeln!(out, "{}", dim().colorize(&line))?;
+            } else {
+                writeln!(out, "{}", line)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 模型名称的前景色：按解析出的系列取色，同一系列的不同快照颜色相同；
+    /// 无法识别的系列按名称的哈希从固定的调色板中取色，每次运行都相同
+    fn model_color(&self, model: &str) -> String {
+        let family = ParsedModel::parse(model).family;
+        if let Some(color) = self.model_colors.get(&family.to_string()) {
+            return color.clone();
+        }
+        match family {
+            ModelFamily::Opus => "38;5;167".to_string(),
+            ModelFamily::Sonnet => "38;5;75".to_string(),
+            ModelFamily::Haiku => "38;5;114".to_string(),
+            ModelFamily::Unknown(name) => {
+                // FNV-1a，不依赖标准库哈希的实现细节
+                let hash = name.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+                    (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+                });
+                UNKNOWN_MODEL_PALETTE[(hash % UNKNOWN_MODEL_PALETTE.len() as u64) as usize].to_string()
+            }
+        }
+    }
+
+    /// 着色时给模型名称加上颜色，不着色时（见 with_color）原样返回
+    fn paint_model(&self, model: &str) -> String {
+        if model.is_empty() || !self.colors_enabled() {
+            return model.to_string();
+        }
+        format!("\x1b[{}m{}\x1b[39m", self.model_color(model), model)
     }
-}
\ No newline at end of file
+
+    /// 日期列中显示的日期，启用星期时加上星期的缩写；日期键无法解析时原样显示
+    fn date_label(&self, date: &str) -> String {
+        match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+            Ok(day) if self.weekday => format!("{} {}", weekday_abbreviation(day.weekday()), date),
+            _ => date.to_string(),
+        }
+    }
+
+    /// 每日用量的各行按计费月分段，日期按报告的日期键（即按日分组时的时区）归属；
+    /// 未启用月小计时只有一段
+    fn month_groups<'a>(&self, lines: &'a [UsageLine]) -> Vec<&'a [UsageLine]> {
+        let Some(billing_day) = self.month_subtotals else {
+            return vec![lines];
+        };
+        let month_of = billing_month_of(billing_day);
+        let start = |line: &UsageLine| NaiveDate::parse_from_str(&line.date, "%Y-%m-%d").ok().map(|date| month_of(date).1);
+        lines.chunk_by(|a, b| start(a) == start(b)).collect()
+    }
+
+    /// 计费月的小计行：日期列为月份（自定义计费日时为起止日期），分组列为 Month 与该月的费用。
+    /// 该月尚未结束、表格只有这一个月或未启用月小计时为 None
+    fn month_subtotal(&self, month: &[UsageLine], months: usize, report: &Report, today: NaiveDate) -> Option<UsageRow> {
+        let billing_day = self.month_subtotals.filter(|_| months > 1)?;
+        let date = NaiveDate::parse_from_str(&month.first()?.date, "%Y-%m-%d").ok()?;
+        let (label, start, end) = billing_month_of(billing_day)(date);
+        if end >= today {
+            return None;
+        }
+        let label = if billing_day <= 1 {
+            label
+        } else {
+            format!("{}–{}", start.format("%m-%d"), end.format("%m-%d"))
+        };
+        let cost = report
+            .days
+            .iter()
+            .filter(|day| (start..=end).contains(&day.start))
+            .filter_map(|day| day.cost_usd)
+            .reduce(|a, b| a + b);
+        let group = match cost {
+            Some(cost) => format!("{} {}", i18n::label("Month"), format_cost(Some(cost))),
+            None => i18n::label("Month").to_string(),
+        };
+        Some(UsageRow::total_of(label, group, month))
+    }
+
+    /// 日期键是否为周六或周日
+    fn is_weekend(date: &str) -> bool {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok_and(|day| day.weekday().number_from_monday() > 5)
+    }
+
+    /// 边框样式对应的 tabled 主题
+    fn theme(&self) -> Theme {
+        match self.style {
+            TableStyle::Modern => Theme::from_style(Style::modern()),
+            TableStyle::Ascii => Theme::from_style(Style::ascii()),
+            TableStyle::Rounded => Theme::from_style(Style::rounded()),
+            TableStyle::Markdown => Theme::from_style(Style::markdown()),
+            TableStyle::Blank => Theme::from_style(Style::blank()),
+            TableStyle::Compact => Theme::from_style(Style::psql()),
+        }
+    }
+
+    /// 按行显示每日用量时的主题：有行间横线的样式只保留表头、月小计与合计行之前以及日期之间的横线，
+    /// 日期之间的横线在 modern 样式中为虚线；其他样式不变
+    fn sub_row_theme(&self, day_starts: &[usize], month_rows: &[usize], total_rows: usize) -> Theme {
+        let mut theme = self.theme();
+        let borders = *theme.get_borders();
+        let Some(main) = borders.horizontal else {
+            return theme;
+        };
+        let line = |main| HorizontalLine::new(Some(main), borders.intersection, borders.left_intersection, borders.right_intersection);
+        let day_separator = match self.style {
+            TableStyle::Modern => '┄',
+            _ => main,
+        };
+        theme.remove_horizontal_lines();
+        theme.insert_horizontal_line(1, line(main));
+        for &row in day_starts {
+            theme.insert_horizontal_line(row, line(day_separator));
+        }
+        for &row in month_rows {
+            theme.insert_horizontal_line(row, line(main));
+        }
+        theme.insert_horizontal_line(total_rows - 1, line(main));
+        theme
+    }
+
+    /// 计费月之间的双线；没有横线的样式为 None
+    fn month_separator(&self) -> Option<HorizontalLine<char>> {
+        let borders = *self.theme().get_borders();
+        match self.style {
+            TableStyle::Modern | TableStyle::Rounded => Some(HorizontalLine::new(Some('═'), Some('╪'), Some('╞'), Some('╡'))),
+            TableStyle::Ascii | TableStyle::Compact => {
+                Some(HorizontalLine::new(Some('='), borders.intersection, borders.left_intersection, borders.right_intersection))
+            }
+            TableStyle::Markdown | TableStyle::Blank => None,
+        }
+    }
+
+    /// 使用量表格中分组列的位置：日期之后，显示周数时在 Wk 列之后
+    fn group_column(&self) -> usize {
+        1 + usize::from(self.week_number)
+    }
+
+    /// 边框与内边距占用的宽度：每列两侧各 1 格内边距，加上列之间的分隔线与左右外框
+    fn border_width(&self, num_columns: usize) -> usize {
+        let borders = *self.theme().get_borders();
+        let separators = if borders.vertical.is_some() { num_columns.saturating_sub(1) } else { 0 };
+        num_columns * 2 + separators + usize::from(borders.left.is_some()) + usize::from(borders.right.is_some())
+    }
+
+    /// 每日用量是否纵向排列
+    fn vertical(&self) -> bool {
+        match self.layout {
+            Layout::Table => false,
+            Layout::Vertical => true,
+            Layout::Auto => self.output_width() < self.vertical_threshold,
+        }
+    }
+
+    /// 排版使用的输出宽度：--width，其次为终端宽度；输出不是终端时取 COLUMNS 环境变量，都没有时为 DEFAULT_OUTPUT_WIDTH
+    fn output_width(&self) -> usize {
+        self.width
+            .or_else(|| terminal_size().map(|(TermWidth(width), ..)| width as usize))
+            .or_else(|| std::env::var("COLUMNS").ok().and_then(|columns| columns.trim().parse().ok()))
+            .filter(|width| *width > 0)
+            .unwrap_or(DEFAULT_OUTPUT_WIDTH)
+    }
+
+    /// 单个模型的显示名称
+    pub fn model_name(&self, model: &str) -> String {
+        if self.full_model_names {
+            model.to_string()
+        } else {
+            Self::simplify_model_name(model)
+        }
+    }
+
+    /// 一组模型的显示名称。拆分快照时，简化后重名的模型附带快照日期以示区分
+    fn model_display_names<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> HashMap<String, String> {
+        let names: Vec<&str> = names.into_iter().collect();
+        let mut simplified: HashMap<String, Vec<&str>> = HashMap::new();
+        for name in &names {
+            simplified.entry(self.model_name(name)).or_default().push(name);
+        }
+        names
+            .iter()
+            .map(|name| {
+                let mut display = self.model_name(name);
+                if self.split_snapshots
+                    && simplified[&display].iter().any(|other| other != name)
+                    && let Some(snapshot) = name.rsplit('-').next() {
+                    display = format!("{} ({})", display, snapshot);
+                }
+                (name.to_string(), display)
+            })
+            .collect()
+    }
+
+    /// 分组列的显示名称：模型需要简化，项目使用能区分重名的简称
+    fn display_names<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> HashMap<String, String> {
+        match self.group_by {
+            GroupBy::Model => self.model_display_names(names),
+            GroupBy::Branch | GroupBy::Source | GroupBy::Person => names.into_iter().map(|name| (name.to_string(), name.to_string())).collect(),
+            GroupBy::Project => short_project_names(names),
+        }
+    }
+
+    /// 显示每行最早和最晚的活动时间（Active 列）
+    pub fn with_activity_column(mut self, show_activity: bool) -> Self {
+        self.show_activity = show_activity;
+        self
+    }
+
+    /// 显示每行对应的消息条数（Msgs 列）
+    pub fn with_message_column(mut self, show_messages: bool) -> Self {
+        self.show_messages = show_messages;
+        self
+    }
+    
+    /// 简化模型名称，去除冗余的前后缀
+    fn simplify_model_name(model: &str) -> String {
+        ParsedModel::parse(model).to_string()
+    }
+
+    /// 报告中启用的附加列：累计、移动平均与相对前一天的变化
+    fn extra_columns(&self, report: &Report) -> Vec<ExtraColumn> {
+        let mut columns = Vec::new();
+        if let Some(metric) = report.cumulative {
+            columns.push(self.cumulative_column(&report.series, metric));
+        }
+        if let Some((window, averages)) = &report.moving_average {
+            columns.push(self.moving_average_column(averages, *window));
+        }
+        if report.day_change {
+            columns.push(self.day_change_column(&report.series));
+        }
+        columns
+    }
+
+    /// 所有行都为 0 或未上报、不显示的数值列（Total 列总是显示）；with_all_columns 时为空
+    fn empty_columns(&self, report: &Report) -> Vec<&'static str> {
+        if self.all_columns {
+            return Vec::new();
+        }
+        let rows: Vec<&GroupUsage> = report.rows.iter().filter(|row| row.usage.usage.total() > 0).collect();
+        let value = |header: &str, aggregate: &UsageAggregate| -> u64 {
+            let usage = &aggregate.usage;
+            match header {
+                "Msgs" => aggregate.messages,
+                "Input" => usage.input_tokens.map_or(0, u64::from),
+                "Output" => usage.output_tokens.map_or(0, u64::from),
+                "Cache Create" => usage.cache_creation_input_tokens.map_or(0, u64::from),
+                _ => usage.cache_read_input_tokens.map_or(0, u64::from),
+            }
+        };
+        ["Msgs", "Input", "Output", "Cache Create", "Cache Read"]
+            .into_iter()
+            .filter(|header| *header != "Msgs" || self.show_messages)
+            .filter(|header| !rows.is_empty() && rows.iter().all(|row| value(header, &row.usage) == 0))
+            .collect()
+    }
+
+    /// 使用量表格的各列：(表头, 最小宽度)，按显示顺序，不含未启用的可选列和全为 0 的列
+    fn usage_columns(&self, extra_columns: &[ExtraColumn], empty_columns: &[&str]) -> Vec<(String, usize)> {
+        let date_width = if self.weekday { DATE_MIN_WIDTH + 4 } else { DATE_MIN_WIDTH };
+        let mut columns = vec![("Date", date_width)];
+        if self.week_number {
+            columns.push(("Wk", WEEK_MIN_WIDTH));
+        }
+        columns.push(("Model", MODEL_MIN_WIDTH));
+        if self.show_activity {
+            columns.push(("Active", ACTIVITY_MIN_WIDTH));
+        }
+        if self.show_messages {
+            columns.push(("Msgs", NUMBER_MIN_WIDTH));
+        }
+        for header in ["Input", "Output", "Cache Create", "Cache Read", "Total"] {
+            columns.push((header, NUMBER_MIN_WIDTH));
+        }
+        columns.retain(|(header, _)| !empty_columns.contains(header));
+        let mut columns: Vec<(String, usize)> = columns
+            .into_iter()
+            .map(|(header, width)| (header.to_string(), width))
+            .collect();
+        columns.extend(extra_columns.iter().map(|column| (column.header.clone(), NUMBER_MIN_WIDTH)));
+        columns
+    }
+
+    /// 按输出宽度安排各列的宽度。
+    ///
+    /// 未指定 --width 时与以前相同：表格内容占终端宽度的 70%（最多 200），各列等宽；
+    /// 指定 --width 时整个表格（含边框与内边距）正好占满该宽度，按实际显示的列数扣除边框后分给各列，
+    /// 除不尽的余数从左起每列多分 1 格。
+    /// 等宽时有列小于其最小宽度的，各列先取最小宽度，表格最多占满整个终端，剩余的宽度平均分给各列；
+    /// 最小宽度之和仍然放不下时，依次隐藏 Cache Create、Cache Read 列，而不是把所有列折成碎片。
+    /// 全为 0 的列不参与安排，其宽度分给其余的列
+    fn usage_layout(&self, term_width: usize, extra_columns: &[ExtraColumn], empty_columns: &[&str]) -> UsageLayout {
+        let mut columns = self.usage_columns(extra_columns, empty_columns);
+        let table_width = |columns: &[(String, usize)]| {
+            columns.iter().map(|(_, width)| width).sum::<usize>() + self.border_width(columns.len())
+        };
+        // 各列内容可用的总宽度
+        let target_width = match self.width {
+            Some(_) => term_width.saturating_sub(self.border_width(columns.len())),
+            None => (term_width * 7 / 10).min(200),
+        };
+        let cell_width = target_width / columns.len();
+        if columns.iter().all(|(_, min_width)| cell_width >= *min_width) {
+            // 未指定 --width 时各列等宽，余数不分配
+            let total = if self.width.is_some() { target_width } else { cell_width * columns.len() };
+            return UsageLayout {
+                widths: distribute(columns.into_iter().map(|(header, _)| (header, 0)).collect(), total),
+                hidden: Vec::new(),
+            };
+        }
+
+        let mut hidden = Vec::new();
+        for header in HIDEABLE_COLUMNS {
+            if table_width(&columns) <= term_width {
+                break;
+            }
+            if columns.iter().any(|(name, _)| name == header) {
+                columns.retain(|(name, _)| name != header);
+                hidden.push(header);
+            }
+        }
+
+        let spare = match self.width {
+            Some(_) => term_width.saturating_sub(table_width(&columns)),
+            None => target_width.saturating_sub(table_width(&columns)) / columns.len() * columns.len(),
+        };
+        UsageLayout {
+            widths: distribute(columns, spare),
+            hidden,
+        }
+    }
+
+    /// 合并单元格中的一个子行：各列按布局中的宽度折行后用空行补齐到相同的行数，
+    /// 这样同一天的多个分组在所有列中逐行对应，表格按同一宽度折行时不会再改变行数
+    fn wrap_sub_row(&self, row: &mut UsageRow, layout: &UsageLayout) {
+        let fields = [
+            ("Model", &mut row.model),
+            ("Active", &mut row.active),
+            ("Msgs", &mut row.messages),
+            ("Input", &mut row.input_tokens),
+            ("Output", &mut row.output_tokens),
+            ("Cache Create", &mut row.cache_creation_input_tokens),
+            ("Cache Read", &mut row.cache_read_input_tokens),
+            ("Total", &mut row.total_tokens),
+        ];
+        // 未启用或被隐藏的列不在布局中，不参与补齐
+        let fields: Vec<(usize, &mut String)> = fields
+            .into_iter()
+            .filter_map(|(header, field)| layout.width_of(header).map(|width| (width, field)))
+            .collect();
+        let wrapped: Vec<Vec<String>> = fields.iter().map(|(width, field)| wrap_text(field, *width)).collect();
+        let height = wrapped.iter().map(Vec::len).max().unwrap_or(1);
+        for ((_, field), mut lines) in fields.into_iter().zip(wrapped) {
+            lines.resize(height, String::new());
+            *field = lines.join("\n");
+        }
+    }
+
+    /// 纵向排列的每日用量：每个 (日期, 分组) 一个键值块，块之间空一行，最后是合计块。
+    /// 内容与表格相同（可选列、小计、附加列与今天的标记），着色方式也相同：键与表头同色，合计为黄色，今天加粗
+    fn render_vertical(&self, report: &Report, out: &mut dyn Write) -> io::Result<()> {
+        if report.rows.is_empty() {
+            return writeln!(out, "{}", tr!("report.no_usage"));
+        }
+        let extra_columns = self.extra_columns(report);
+        let empty_columns = self.empty_columns(report);
+        let display_names = self.display_names(report.rows.iter().map(|row| row.group.as_str()));
+        let lines = report.lines(|name| display_names.get(name).cloned().unwrap_or_else(|| name.to_string()));
+
+        let color = self.colors_enabled();
+        let paint = |style: Option<&Color>, text: &str| match style {
+            Some(style) if color => style.colorize(text),
+            _ => text.to_string(),
+        };
+        let key_width = self
+            .vertical_fields(&UsageRow::total_of(String::new(), String::new(), &[]), &empty_columns)
+            .iter()
+            .map(|(key, _)| display_width(i18n::label(key)))
+            .chain(extra_columns.iter().map(|column| display_width(i18n::label(&column.header))))
+            .max()
+            .unwrap_or(0);
+        let write_block = |out: &mut dyn Write, fields: Vec<(String, String)>, style: Option<Color>| -> io::Result<()> {
+            writeln!(out)?;
+            for (key, value) in fields {
+                let key = pad_to_width(i18n::label(&key), key_width);
+                writeln!(out, "{}  {}", paint(Some(&Color::FG_BRIGHT_GREEN), &key), paint(style.as_ref(), &value))?;
+            }
+            Ok(())
+        };
+
+        writeln!(out, " === {} ===", i18n::label("Usage Summary"))?;
+        let today = self.today().to_string();
+        let mut partial = false;
+        let months = self.month_groups(&lines);
+        for month in &months {
+            for day in month.chunk_by(|a, b| a.date == b.date) {
+                let is_today = day[0].date == today;
+                partial |= is_today;
+                let style = if day[0].gap || (self.dim_weekends && Self::is_weekend(&day[0].date)) {
+                    Some(dim())
+                } else {
+                    is_today.then_some(Color::BOLD)
+                };
+                let mut rows: Vec<UsageRow> = day.iter().map(|line| UsageRow::from_line(line, &self.timezone)).collect();
+                if self.day_subtotals && day.len() > 1 {
+                    rows.push(UsageRow::total_of(day[0].date.clone(), i18n::label("Subtotal").to_string(), day));
+                }
+                for (i, mut row) in rows.into_iter().enumerate() {
+                    row.date = self.date_label(&row.date);
+                    if is_today {
+                        row.date.push('*');
+                    }
+                    let mut fields = self.vertical_fields(&row, &empty_columns);
+                    // 按日的附加列只显示在当天的第一块中
+                    if i == 0 {
+                        for column in &extra_columns {
+                            if let Some(cell) = column.cells.get(&day[0].date) {
+                                fields.push((column.header.clone(), cell.clone()));
+                            }
+                        }
+                    }
+                    write_block(out, fields, style.clone())?;
+                }
+            }
+            if let Some(subtotal) = self.month_subtotal(month, months.len(), report, self.today()) {
+                write_block(out, self.vertical_fields(&subtotal, &empty_columns), Some(Color::FG_CYAN))?;
+            }
+        }
+
+        let mut fields = self.vertical_fields(&UsageRow::total_of(i18n::label("Total").to_string(), String::new(), &lines), &empty_columns);
+        fields.retain(|(key, _)| key != self.group_by.label() && key != "Wk");
+        for column in extra_columns.iter().filter(|column| !column.total.is_empty()) {
+            fields.push((column.header.clone(), column.total.clone()));
+        }
+        write_block(out, fields, Some(Color::FG_YELLOW))?;
+        if partial {
+            writeln!(out)?;
+            writeln!(out, "{}", partial_day_note())?;
+        }
+        if !empty_columns.is_empty() {
+            writeln!(out)?;
+            writeln!(out, "{}", empty_columns_note(&empty_columns))?;
+        }
+        Ok(())
+    }
+
+    /// 纵向排列时一块中的各项：(键, 值)，与表格的列相同
+    fn vertical_fields(&self, row: &UsageRow, empty_columns: &[&str]) -> Vec<(String, String)> {
+        let mut fields = vec![("Date", &row.date)];
+        if self.week_number {
+            fields.push(("Wk", &row.week));
+        }
+        fields.push((self.group_by.label(), &row.model));
+        if self.show_activity {
+            fields.push(("Active", &row.active));
+        }
+        if self.show_messages {
+            fields.push(("Msgs", &row.messages));
+        }
+        fields.extend([
+            ("Input", &row.input_tokens),
+            ("Output", &row.output_tokens),
+            ("Cache Create", &row.cache_creation_input_tokens),
+            ("Cache Read", &row.cache_read_input_tokens),
+            ("Total", &row.total_tokens),
+        ]);
+        fields
+            .into_iter()
+            .filter(|(key, _)| !empty_columns.contains(key))
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect()
+    }
+
+    /// 每日用量表格，尚未设置样式。没有数据时为 None。
+    /// 指定 layout 时移除其中隐藏的列；合并单元格时同一天多个分组的单元格预先按布局的列宽折行并补齐行数
+    fn usage_table(
+        &self,
+        report: &Report,
+        extra_columns: &[ExtraColumn],
+        empty_columns: &[&str],
+        layout: Option<&UsageLayout>,
+    ) -> Option<UsageTable> {
+        if report.rows.is_empty() {
+            return None;
+        }
+
+        // 显示名称需要看到所有名称才能区分重名
+        let display_names = self.display_names(report.rows.iter().map(|row| row.group.as_str()));
+        let mut lines = report.lines(|name| display_names.get(name).cloned().unwrap_or_else(|| name.to_string()));
+        // 分组名称超过列宽时截断而不是折行：模型保留开头的系列和结尾的快照，项目保留路径末尾，分支保留开头
+        if let Some(width) = layout.and_then(|layout| layout.width_of("Model")) {
+            let truncate = match self.group_by {
+                GroupBy::Model => truncate_middle,
+                GroupBy::Project => truncate_start,
+                GroupBy::Branch | GroupBy::Source | GroupBy::Person => truncate_end,
+            };
+            for line in &mut lines {
+                line.group = truncate(&line.group, width);
+            }
+        }
+
+        // 创建表格行：每个分组一行，日期只显示在当天的第一行；
+        // 合并单元格时相同日期的多个分组合并为一行显示
+        let mut rows: Vec<UsageRow> = Vec::new();
+        let mut day_starts = Vec::new();
+        let today = self.today().to_string();
+        let mut today_rows = None;
+        let mut gap_rows = Vec::new();
+        let mut weekend_rows = Vec::new();
+        let mut month_starts = Vec::new();
+        let mut month_rows = Vec::new();
+        let months = self.month_groups(&lines);
+        for (i, month) in months.iter().enumerate() {
+            if i > 0 {
+                month_starts.push(rows.len() + 1);
+            }
+            for day in month.chunk_by(|a, b| a.date == b.date) {
+                if !rows.is_empty() {
+                    day_starts.push(rows.len() + 1);
+                }
+                if day[0].gap {
+                    gap_rows.push(rows.len() + 1);
+                }
+                if self.dim_weekends && Self::is_weekend(&day[0].date) {
+                    weekend_rows.push(rows.len() + 1);
+                }
+                if day[0].date == today {
+                    // 表格行号从表头之后开始，包括当天的各行与小计行
+                    today_rows = Some(rows.len() + 1..rows.len() + 1 + if self.merged_cells {
+                        1
+                    } else {
+                        day.len() + usize::from(self.day_subtotals && day.len() > 1)
+                    });
+                }
+                if !self.merged_cells {
+                    for (i, line) in day.iter().enumerate() {
+                        let mut row = UsageRow::from_line(line, &self.timezone);
+                        if i > 0 {
+                            row.date.clear();
+                            row.week.clear();
+                        }
+                        rows.push(row);
+                    }
+                    if self.day_subtotals && day.len() > 1 {
+                        rows.push(UsageRow::total_of(String::new(), i18n::label("Subtotal").to_string(), day));
+                    }
+                    continue;
+                }
+                let mut cells: Vec<UsageRow> = day.iter().map(|line| UsageRow::from_line(line, &self.timezone)).collect();
+                if let Some(layout) = layout.filter(|_| cells.len() > 1) {
+                    for cell in &mut cells {
+                        self.wrap_sub_row(cell, layout);
+                    }
+                }
+                let join = |cell: fn(&UsageRow) -> &String| {
+                    cells.iter().map(cell).cloned().collect::<Vec<_>>().join("\n")
+                };
+                rows.push(UsageRow {
+                    date: day[0].date.clone(),
+                    week: UsageRow::format_week(&day[0].date),
+                    model: join(|row| &row.model),
+                    active: join(|row| &row.active),
+                    messages: join(|row| &row.messages),
+                    input_tokens: join(|row| &row.input_tokens),
+                    output_tokens: join(|row| &row.output_tokens),
+                    cache_creation_input_tokens: join(|row| &row.cache_creation_input_tokens),
+                    cache_read_input_tokens: join(|row| &row.cache_read_input_tokens),
+                    total_tokens: join(|row| &row.total_tokens),
+                });
+            }
+            if let Some(subtotal) = self.month_subtotal(month, months.len(), report, self.today()) {
+                month_rows.push(rows.len() + 1);
+                rows.push(subtotal);
+            }
+        }
+        
+        // 添加总计行，模型列为空
+        rows.push(UsageRow::total_of(i18n::label("Total").to_string(), String::new(), &lines));
+
+        let hidden = layout.map(|layout| layout.hidden.as_slice()).unwrap_or_default();
+        let num_columns = self.usage_columns(extra_columns, empty_columns).len() - hidden.len();
+        // 获取表格行数（包括表头）
+        let total_rows = rows.len() + 1; // +1 因为有表头
+
+        let dates: Vec<String> = rows.iter().map(|row| row.date.clone()).collect();
+        let groups: Vec<String> = rows.iter().map(|row| row.model.clone()).collect();
+        let mut builder = Table::builder(rows);
+        for column in extra_columns {
+            let mut cells = vec![column.header.clone()];
+            for date in &dates[..dates.len() - 1] {
+                cells.push(column.cells.get(date).cloned().unwrap_or_default());
+            }
+            cells.push(column.total.clone());
+            builder.push_column(cells);
+        }
+        let mut table = builder.build();
+
+        // 未启用的可选列直接移除
+        if !self.week_number {
+            table.with(Remove::column(ByColumnName::new("Wk")));
+        }
+        if !self.show_activity {
+            table.with(Remove::column(ByColumnName::new("Active")));
+        }
+        if !self.show_messages {
+            table.with(Remove::column(ByColumnName::new("Msgs")));
+        }
+        for header in hidden.iter().chain(empty_columns) {
+            table.with(Remove::column(ByColumnName::new(*header)));
+        }
+        // 分组列的表头随分组维度变化
+        table.modify((0, self.group_column()), self.group_by.label());
+        // 日期列按日期键排序和匹配附加列，星期与今天的标记在最后才加到显示的文字上
+        for (row, date) in dates[..dates.len() - 1].iter().enumerate().filter(|(_, date)| !date.is_empty()) {
+            let mut label = self.date_label(date);
+            if *date == today {
+                label.push('*');
+            }
+            if label != *date {
+                table.modify((row + 1, 0), label);
+            }
+        }
+
+        Some(UsageTable {
+            table,
+            num_columns,
+            total_rows,
+            day_starts,
+            today_rows,
+            gap_rows,
+            weekend_rows,
+            groups,
+            month_starts,
+            month_rows,
+        })
+    }
+
+
+    /// 从所选范围起始日累计的列
+    pub fn cumulative_column(&self, series: &DailySeries, metric: Metric) -> ExtraColumn {
+        let format = |value: f64| match metric {
+            Metric::Tokens => UsageRow::format_number(value as u64),
+            Metric::Cost => format_cost(Some(value)),
+        };
+        let cumulative = series.cumulative(metric);
+        ExtraColumn {
+            header: "Cumulative".to_string(),
+            cells: cumulative.iter().map(|(date, value)| (date.to_string(), format(*value))).collect(),
+            total: cumulative.values().next_back().map(|value| format(*value)).unwrap_or_default(),
+        }
+    }
+
+    /// N 天移动平均列，窗口不完整的日期显示 —
+    pub fn moving_average_column(&self, averages: &BTreeMap<NaiveDate, Option<DayAverage>>, window: u32) -> ExtraColumn {
+        ExtraColumn {
+            header: format!("MA{}", window),
+            cells: averages
+                .iter()
+                .map(|(date, average)| {
+                    let cell = average
+                        .map(|average| UsageRow::format_number(average.tokens.round() as u64))
+                        .unwrap_or_else(|| "—".to_string());
+                    (date.to_string(), cell)
+                })
+                .collect(),
+            total: String::new(),
+        }
+    }
+
+    /// 相对前一个日历日的变化列，前一天没有用量时为 new
+    pub fn day_change_column(&self, series: &DailySeries) -> ExtraColumn {
+        ExtraColumn {
+            header: "Δ%".to_string(),
+            cells: series
+                .previous_day_tokens()
+                .into_iter()
+                .map(|(date, previous)| {
+                    (date.to_string(), format_change(series.tokens(date) as f64, previous as f64))
+                })
+                .collect(),
+            total: String::new(),
+        }
+    }
+
+    /// 每日用量的 JSON 表示：每天一项，包含各模型明细；
+    /// 指定 cumulative 时附带 cumulative_total，指定移动平均时附带 moving_avg_tokens 和 moving_avg_cost，
+    /// 指定 day_change 时附带 previous_day_tokens 和 day_change_percent（前一天为 0 时为 null）
+    pub fn daily_json(
+        &self,
+        days: &[PeriodSummary],
+        series: &DailySeries,
+        cumulative: Option<Metric>,
+        moving_averages: Option<&BTreeMap<NaiveDate, Option<DayAverage>>>,
+        day_change: bool,
+    ) -> Value {
+        let mut json = self.periods_json(PeriodKind::Day, days, true);
+        let cumulative = cumulative.map(|metric| series.cumulative(metric));
+        let previous_days = day_change.then(|| series.previous_day_tokens());
+        if let Value::Array(items) = &mut json {
+            for (item, day) in items.iter_mut().zip(days) {
+                if let Value::Object(object) = item {
+                    for field in ["start", "end", "partial", "active_days"] {
+                        object.remove(field);
+                    }
+                    if let Some(value) = cumulative.as_ref().and_then(|cumulative| cumulative.get(&day.start)) {
+                        object.insert("cumulative_total".to_string(), Value::from(*value));
+                    }
+                    if let Some(average) = moving_averages.and_then(|averages| averages.get(&day.start)) {
+                        object.insert("moving_avg_tokens".to_string(), Value::from(average.map(|average| average.tokens)));
+                        if let Some(cost) = average.and_then(|average| average.cost_usd) {
+                            object.insert("moving_avg_cost".to_string(), Value::from(cost));
+                        }
+                    }
+                    if let Some(previous) = previous_days.as_ref().and_then(|previous| previous.get(&day.start)) {
+                        let change = (*previous > 0)
+                            .then(|| (day.tokens.total_tokens as f64 / *previous as f64 - 1.0) * 100.0);
+                        object.insert("previous_day_tokens".to_string(), Value::from(*previous));
+                        object.insert("day_change_percent".to_string(), Value::from(change));
+                    }
+                }
+            }
+        }
+        json
+    }
+
+    /// 在表格之后输出扫描诊断摘要，命令行程序写入 stderr
+    pub fn render_diagnostics(
+        &self,
+        diagnostics: &Diagnostics,
+        verbose: bool,
+        max_file_size: u64,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        // merge 时没有扫描日志，只说明合并了哪些导出
+        if !diagnostics.merged.is_empty() {
+            writeln!(out)?;
+            writeln!(out, "{}", tr!("report.merged", format_count(diagnostics.merged.len() as u64)))?;
+            for export in &diagnostics.merged {
+                writeln!(
+                    out,
+                    "  {}",
+                    tr!(
+                        "report.merged_export",
+                        export.label,
+                    export.path.display(),
+                        format_count(export.days as u64),
+                        format_count(export.tokens)
+                    )
+                )?;
+            }
+            let mut labels: Vec<&str> = diagnostics.merged.iter().map(|export| export.label.as_str()).collect();
+            labels.sort();
+            let total = labels.len();
+            labels.dedup();
+            if labels.len() < total {
+                writeln!(out, "{}", tr!("report.merged_same_label"))?;
+            }
+            return Ok(());
+        }
+
+        let failed_files: Vec<_> = diagnostics.files_with_failures().collect();
+
+        let mut summary = tr!(
+            "report.scan_summary",
+            format_count(diagnostics.files.len() as u64),
+            format_count(diagnostics.total_entries())
+        );
+        let filtered = diagnostics.total_filtered();
+        if filtered > 0 {
+            summary.push_str(&tr!("report.scan_filtered", format_count(filtered)));
+        }
+        let duplicates = diagnostics.total_duplicates();
+        if duplicates > 0 {
+            summary.push_str(&tr!("report.scan_duplicates", format_count(duplicates)));
+        }
+        if !failed_files.is_empty() {
+            summary.push_str(&tr!(
+                "report.scan_failed_lines",
+                format_count(failed_files.len() as u64),
+                format_count(diagnostics.total_failed_lines())
+            ));
+        }
+        if !diagnostics.read_errors.is_empty() {
+            summary.push_str(&tr!(
+                "report.scan_read_errors",
+                format_count(diagnostics.read_errors.len() as u64)
+            ));
+        }
+        if let Some(startups) = self.app_startups {
+            summary.push_str(&tr!("report.scan_startups", format_count(startups)));
+        }
+        if !verbose && (!failed_files.is_empty() || !diagnostics.read_errors.is_empty()) {
+            summary.push_str(&tr!("report.scan_verbose_hint"));
+        }
+        summary.push_str(&tr!("report.sentence_end"));
+
+        writeln!(out)?;
+        writeln!(out, "{}", summary)?;
+
+        if verbose {
+            for (path, stats) in &failed_files {
+                writeln!(
+                    out,
+                    "  {}",
+                    tr!(
+                        "report.file_failed_lines",
+                        path.display(),
+                        format_count(stats.failed_lines),
+                        format_count(stats.lines)
+                    )
+                )?;
+            }
+            for (path, error) in &diagnostics.read_errors {
+                writeln!(out, "  {}", tr!("report.file_read_failed", path.display(), error))?;
+            }
+
+            let skipped = diagnostics.skipped_by_type();
+            if !skipped.is_empty() {
+                let parts: Vec<String> = skipped
+                    .iter()
+                    .map(|(entry_type, count)| format!("{} {}", entry_type, format_count(*count)))
+                    .collect();
+                writeln!(out, "{}", tr!("report.skipped_types", parts.join(", ")))?;
+            }
+
+            let missing = diagnostics.missing_fields();
+            if !missing.is_empty() {
+                let parts: Vec<String> = missing
+                    .iter()
+                    .map(|(field, count)| format!("{} {}", field, format_count(*count)))
+                    .collect();
+                writeln!(out, "{}", tr!("report.missing_fields", parts.join(", ")))?;
+            }
+
+            if !diagnostics.skipped_files.is_empty() {
+                let parts: Vec<String> = diagnostics.skipped_files
+                    .iter()
+                    .map(|(extension, count)| format!("{} {}", extension, format_count(*count)))
+                    .collect();
+                writeln!(out, "{}", tr!("report.skipped_files", parts.join(", ")))?;
+            }
+        }
+
+        if let Some(error) = &diagnostics.cache_error {
+            writeln!(out, "{}", tr!("report.cache_write_failed", error))?;
+        }
+
+        // 时间戳不合理的记录始终提示，-v 时逐条列出所在的文件与行号
+        let quarantined: Vec<_> = diagnostics.quarantined().collect();
+        if !quarantined.is_empty() {
+            writeln!(
+                out,
+                "{}{}",
+                tr!("report.quarantined", format_count(quarantined.len() as u64)),
+                if verbose { tr!("report.list_follows") } else { tr!("report.quarantined_hint") }
+            )?;
+            if verbose {
+                for (path, entry) in &quarantined {
+                    match entry.line {
+                        Some(line) => writeln!(out, "  {}:{}  {}", path.display(), line, entry.timestamp)?,
+                        None => writeln!(out, "  {}  {}", path.display(), entry.timestamp)?,
+                    }
+                }
+            }
+        }
+
+        // 导入的数据没有项目和会话信息，始终说明其来源
+        for imported in &diagnostics.imported {
+            writeln!(
+                out,
+                "{}",
+                tr!(
+                    "report.imported",
+                    imported.path.display(),
+                    format_count(imported.days as u64),
+                    format_count(imported.rows as u64),
+                    IMPORT_PROJECT
+                )
+            )?;
+            if !imported.overlapping_dates.is_empty() {
+                writeln!(
+                    out,
+                    "{}",
+                    tr!(
+                        "report.imported_overlap",
+                        imported.overlapping_dates.len(),
+                        imported.overlapping_dates.join(", ")
+                    )
+                )?;
+            }
+        }
+
+        // 格式无法识别的文件与超大文件一样始终提示，因为它们的数据被排除在统计之外
+        let unsupported: Vec<_> = diagnostics.unsupported_files().collect();
+        if !unsupported.is_empty() {
+            writeln!(
+                out,
+                "{}{}",
+                tr!("report.unsupported_files", unsupported.len()),
+                if verbose { tr!("report.list_follows") } else { tr!("report.unsupported_files_hint") }
+            )?;
+            if verbose {
+                for path in &unsupported {
+                    writeln!(out, "  {}", path.display())?;
+                }
+            }
+        }
+
+        // 处理时 panic 的文件同样始终提示，并附带 panic 信息便于报告问题
+        if !diagnostics.panicked_files.is_empty() {
+            writeln!(out, "{}", tr!("report.panicked_files", diagnostics.panicked_files.len()))?;
+            for (path, message) in &diagnostics.panicked_files {
+                writeln!(out, "  {}: {}", path.display(), message)?;
+            }
+        }
+
+        // 被跳过的超大文件始终醒目提示，因为它们的数据被排除在统计之外
+        if !diagnostics.oversized_files.is_empty() {
+            writeln!(
+                out,
+                "{}",
+                tr!("report.oversized_files", diagnostics.oversized_files.len(), format_bytes(max_file_size))
+            )?;
+            for (path, size) in &diagnostics.oversized_files {
+                writeln!(out, "  {} ({})", path.display(), format_bytes(*size))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 将 (日期, 名称) 计数整理为输出项；汇总模式下按次数降序，按日模式下按日期、次数排序
+    fn named_counts(counts: &[DailyCount], daily: bool) -> Vec<NamedCount> {
+        let mut named: Vec<NamedCount> = if daily {
+            counts
+                .iter()
+                .map(|count| NamedCount {
+                    date: Some(count.date.clone()),
+                    name: count.name.clone(),
+                    count: count.count,
+                })
+                .collect()
+        } else {
+            let mut totals: BTreeMap<&str, u64> = BTreeMap::new();
+            for count in counts {
+                *totals.entry(&count.name).or_insert(0) += count.count;
+            }
+            totals
+                .into_iter()
+                .map(|(name, count)| NamedCount {
+                    date: None,
+                    name: name.to_string(),
+                    count,
+                })
+                .collect()
+        };
+        named.sort_by(|a, b| a.date.cmp(&b.date).then(b.count.cmp(&a.count)).then(a.name.cmp(&b.name)));
+        named
+    }
+
+    /// 渲染计数及占比表格；title 与名称列的表头 label 由调用方按当前语言给出
+    pub fn render_count_table(
+        &self,
+        title: &str,
+        label: &str,
+        counts: &[DailyCount],
+        daily: bool,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        let named = Self::named_counts(counts, daily);
+        if named.is_empty() {
+            writeln!(out, "{}", tr!("report.no_data"))?;
+            return Ok(());
+        }
+
+        // 占比按日计算时以当天总数为分母，汇总时以整个范围为分母
+        let mut totals: BTreeMap<Option<&str>, u64> = BTreeMap::new();
+        for count in &named {
+            *totals.entry(count.date.as_deref()).or_insert(0) += count.count;
+        }
+
+        let mut builder = Builder::default();
+        let (count, share) = (i18n::label("Count"), i18n::label("Share"));
+        if daily {
+            builder.push_record([i18n::label("Date"), label, count, share]);
+        } else {
+            builder.push_record([label, count, share]);
+        }
+        for count in &named {
+            let total = totals[&count.date.as_deref()];
+            let share = format!("{:.1}%", count.count as f64 * 100.0 / total as f64);
+            let mut record = Vec::new();
+            if let Some(date) = &count.date {
+                record.push(date.clone());
+            }
+            record.extend([count.name.clone(), format_count(count.count), share]);
+            builder.push_record(record);
+        }
+
+        let mut table = builder.build();
+        table.with(self.theme());
+        table.with(Modify::new(Columns::new(if daily { 2.. } else { 1.. })).with(Alignment::right()));
+        self.colorize(&mut table, Color::FG_BRIGHT_GREEN, Rows::new(0..1));
+
+        writeln!(out, " === {} ===", title)?;
+        writeln!(out, "{}", table)?;
+        Ok(())
+    }
+
+    /// 计数的 JSON 表示，key 为名称字段的键名（如 tool、reason）
+    pub fn counts_json(&self, key: &str, counts: &[DailyCount], daily: bool) -> Value {
+        Value::Array(
+            Self::named_counts(counts, daily)
+                .into_iter()
+                .map(|count| {
+                    let mut object = Map::new();
+                    if let Some(date) = count.date {
+                        object.insert("date".to_string(), Value::from(date));
+                    }
+                    object.insert(key.to_string(), Value::from(count.name));
+                    object.insert("count".to_string(), Value::from(count.count));
+                    Value::Object(object)
+                })
+                .collect(),
+        )
+    }
+
+    /// 附带 meta 后以格式化的 JSON 写入 out，无法序列化时返回错误
+    pub fn print_json(&self, value: &impl Serialize, out: &mut dyn Write) -> io::Result<()> {
+        let json = serde_json::to_value(value)
+            .map(|value| self.with_json_meta(value))
+            .and_then(|json| serde_json::to_string_pretty(&json))
+            .map_err(|e| io::Error::other(tr!("report.json_failed", e)))?;
+        writeln!(out, "{}", json)
+    }
+
+    pub fn render_session_table(&self, sessions: &[SessionSummary], out: &mut dyn Write) -> io::Result<()> {
+        if sessions.is_empty() {
+            writeln!(out, "{}", tr!("report.no_sessions"))?;
+            return Ok(());
+        }
+
+        let project_names = short_project_names(sessions.iter().filter_map(|session| session.project.as_deref()));
+        let rows: Vec<SessionRow> = sessions
+            .iter()
+            .map(|session| {
+                let mut models: Vec<String> = session.models.iter().map(|model| self.model_name(model)).collect();
+                models.dedup();
+                SessionRow {
+                    // 会话 id 只显示前 8 位，足以区分
+                    session: session.session_id.chars().take(8).collect(),
+                    start: session
+                        .started_at
+                        .map(|started| started.with_timezone(&self.timezone).format("%Y-%m-%d %H:%M").to_string())
+                        .unwrap_or_else(|| "—".to_string()),
+                    duration: session.duration().map(format_duration).unwrap_or_else(|| "—".to_string()),
+                    project: session
+                        .project
+                        .as_ref()
+                        .and_then(|project| project_names.get(project).cloned())
+                        .unwrap_or_else(|| "—".to_string()),
+                    models: models.join(", "),
+                    turns: format_count(session.turns),
+                    messages: format_count(session.user_messages + session.assistant_messages),
+                    total_tokens: UsageRow::format_number(session.aggregate.usage.total()),
+                    tokens_per_turn: session
+                        .tokens_per_turn()
+                        .map(UsageRow::format_number)
+                        .unwrap_or_else(|| "—".to_string()),
+                    cost: format_cost(session.cost_usd),
+                }
+            })
+            .collect();
+
+        let mut table = Table::new(rows);
+        table.with(self.theme());
+        table.with(Modify::new(Columns::new(5..)).with(Alignment::right()));
+        table.with(Modify::new(Columns::new(2..3)).with(Alignment::right()));
+        localize_header(&mut table);
+        self.colorize(&mut table, Color::FG_BRIGHT_GREEN, Rows::new(0..1));
+
+        writeln!(out, " === {} ===", i18n::label("Sessions"))?;
+        writeln!(out, "{}", table)?;
+        Ok(())
+    }
+
+    pub fn render_session_json(&self, sessions: &[SessionSummary], out: &mut dyn Write) -> io::Result<()> {
+        #[derive(Serialize)]
+        struct SessionJson<'a> {
+            #[serde(flatten)]
+            session: &'a SessionSummary,
+            duration_seconds: Option<i64>,
+            total_tokens: u64,
+            tokens_per_turn: Option<u64>,
+        }
+
+        let sessions: Vec<SessionJson> = sessions
+            .iter()
+            .map(|session| SessionJson {
+                session,
+                duration_seconds: session.duration().map(|duration| duration.num_seconds()),
+                total_tokens: session.aggregate.usage.total(),
+                tokens_per_turn: session.tokens_per_turn(),
+            })
+            .collect();
+        self.print_json(&sessions, out)
+    }
+
+    /// 将 (版本, 日期) 使用量汇总为每个版本一项，按版本号升序
+    fn version_summaries(versions: &[GroupUsage]) -> Vec<VersionSummary> {
+        let mut grouped: BTreeMap<&str, (u64, UsageAggregate)> = BTreeMap::new();
+        for GroupUsage { group: version, usage: aggregate, .. } in versions {
+            // 键中每个日期只出现一次，出现次数即活跃天数
+            let (days, total) = grouped.entry(version).or_default();
+            *days += 1;
+            *total += aggregate;
+        }
+
+        let mut summaries: Vec<VersionSummary> = grouped
+            .into_iter()
+            .map(|(version, (days, aggregate))| {
+                let total_tokens = aggregate.usage.total();
+                VersionSummary {
+                    version: version.to_string(),
+                    days_active: days,
+                    messages: aggregate.messages,
+                    total_tokens,
+                    avg_tokens_per_message: (aggregate.messages > 0).then(|| total_tokens / aggregate.messages),
+                }
+            })
+            .collect();
+        summaries.sort_by(|a, b| compare_versions(&a.version, &b.version));
+        summaries
+    }
+
+    pub fn render_version_table(&self, versions: &[GroupUsage], out: &mut dyn Write) -> io::Result<()> {
+        let summaries = Self::version_summaries(versions);
+        if summaries.is_empty() {
+            writeln!(out, "{}", tr!("report.no_data"))?;
+            return Ok(());
+        }
+
+        let rows: Vec<VersionRow> = summaries
+            .into_iter()
+            .map(|summary| VersionRow {
+                version: summary.version,
+                days: format_count(summary.days_active),
+                messages: format_count(summary.messages),
+                total_tokens: format_count(summary.total_tokens),
+                average_tokens: summary
+                    .avg_tokens_per_message
+                    .map(format_count)
+                    .unwrap_or_else(|| "—".to_string()),
+            })
+            .collect();
+
+        let mut table = Table::new(rows);
+        table.with(self.theme());
+        table.with(Modify::new(Columns::new(1..)).with(Alignment::right()));
+        localize_header(&mut table);
+        self.colorize(&mut table, Color::FG_BRIGHT_GREEN, Rows::new(0..1));
+
+        writeln!(out, " === {} ===", i18n::label("Versions"))?;
+        writeln!(out, "{}", table)?;
+        Ok(())
+    }
+
+    pub fn versions_json(&self, versions: &[GroupUsage]) -> Value {
+        serde_json::to_value(Self::version_summaries(versions)).unwrap_or(Value::Null)
+    }
+
+    /// 响应时间的分位数，模型按显示名称合并
+    fn latency_summaries(&self, latencies: &[MessageLatency]) -> Vec<LatencyStats> {
+        latency_stats(latencies, |model| self.model_name(model))
+    }
+
+    /// 响应时间的分位数，前几行是整个范围（日期列为 All），随后按日期和模型列出
+    pub fn render_latency_table(&self, latencies: &[MessageLatency], out: &mut dyn Write) -> io::Result<()> {
+        let summaries = self.latency_summaries(latencies);
+        if summaries.is_empty() {
+            writeln!(out, "{}", tr!("report.no_latency"))?;
+            return Ok(());
+        }
+
+        let all = i18n::label("All");
+        let mut builder = Builder::default();
+        builder.push_record(["Date", "Model", "Msgs", "Time p50", "Time p95", "TTFT p50", "TTFT p95", "Out Tok/s"]);
+        for stats in &summaries {
+            builder.push_record([
+                stats.date.clone().unwrap_or_else(|| all.to_string()),
+                stats.model.clone().unwrap_or_else(|| all.to_string()),
+                format_count(stats.samples),
+                format_millis(stats.duration_p50_ms),
+                format_millis(stats.duration_p95_ms),
+                stats.ttft_p50_ms.map_or_else(|| "—".to_string(), format_millis),
+                stats.ttft_p95_ms.map_or_else(|| "—".to_string(), format_millis),
+                stats.output_tokens_per_second.map_or_else(|| "—".to_string(), |rate| format!("{:.1}", rate)),
+            ]);
+        }
+
+        let mut table = builder.build();
+        table.with(self.theme());
+        table.with(Modify::new(Columns::new(2..)).with(Alignment::right()));
+        localize_header(&mut table);
+        self.colorize(&mut table, Color::FG_BRIGHT_GREEN, Rows::new(0..1));
+        let range_rows = summaries.iter().take_while(|stats| stats.date.is_none()).count();
+        self.colorize(&mut table, Color::FG_YELLOW, Rows::new(1..range_rows + 1));
+
+        writeln!(out, " === {} ===", i18n::label("Response Time"))?;
+        writeln!(out, "{}", table)?;
+        writeln!(out, "{}", tr!("report.latency_count", format_count(summaries[0].samples)))?;
+        Ok(())
+    }
+
+    pub fn latency_json(&self, latencies: &[MessageLatency]) -> Value {
+        serde_json::to_value(self.latency_summaries(latencies)).unwrap_or(Value::Null)
+    }
+
+    /// 将 (项目, 日期) 使用量汇总为每个项目一项，按最近活动时间降序。
+    /// ~/.claude.json 中记录的打开时间比日志更新时以其为准
+    fn project_summaries(
+        projects: &[GroupUsage],
+        last_opened: &HashMap<String, DateTime<Utc>>,
+    ) -> Vec<ProjectSummary> {
+        let mut grouped: BTreeMap<&str, UsageAggregate> = BTreeMap::new();
+        for GroupUsage { group: project, usage: aggregate, .. } in projects {
+            let total = grouped.entry(project).or_default();
+            *total += aggregate;
+        }
+
+        let mut summaries: Vec<ProjectSummary> = grouped
+            .into_iter()
+            .map(|(project, aggregate)| ProjectSummary {
+                project: project.to_string(),
+                last_active: aggregate.last_seen.max(last_opened.get(project).copied()),
+                messages: aggregate.messages,
+                total_tokens: aggregate.usage.total(),
+            })
+            .collect();
+        summaries.sort_by(|a, b| b.last_active.cmp(&a.last_active).then(a.project.cmp(&b.project)));
+        summaries
+    }
+
+    pub fn render_project_table(
+        &self,
+        projects: &[GroupUsage],
+        last_opened: &HashMap<String, DateTime<Utc>>,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        let summaries = Self::project_summaries(projects, last_opened);
+        if summaries.is_empty() {
+            writeln!(out, "{}", tr!("report.no_data"))?;
+            return Ok(());
+        }
+
+        let names = short_project_names(summaries.iter().map(|summary| summary.project.as_str()));
+        let rows: Vec<ProjectRow> = summaries
+            .iter()
+            .map(|summary| ProjectRow {
+                project: names.get(&summary.project).cloned().unwrap_or_else(|| summary.project.clone()),
+                last_active: summary
+                    .last_active
+                    .map(|time| time.with_timezone(&self.timezone).format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_else(|| "—".to_string()),
+                messages: format_count(summary.messages),
+                total_tokens: format_count(summary.total_tokens),
+            })
+            .collect();
+
+        let mut table = Table::new(rows);
+        table.with(self.theme());
+        table.with(Modify::new(Columns::new(2..)).with(Alignment::right()));
+        localize_header(&mut table);
+        self.colorize(&mut table, Color::FG_BRIGHT_GREEN, Rows::new(0..1));
+
+        writeln!(out, " === {} ===", i18n::label("Projects"))?;
+        writeln!(out, "{}", table)?;
+        Ok(())
+    }
+
+    /// 将 (模型, 日期) 使用量汇总为每个模型一项，按模型的语义顺序排列
+    pub fn model_totals(&self, usage: &[DailyUsage]) -> Vec<ModelTotal> {
+        let names = self.model_display_names(usage.iter().map(|daily| daily.model.as_str()));
+        #[derive(Default)]
+        struct Accumulator<'a> {
+            ids: BTreeSet<&'a str>,
+            days: BTreeSet<&'a str>,
+            messages: u64,
+            tokens: TokenTotals,
+            cost: Option<f64>,
+        }
+
+        let mut grouped: BTreeMap<&str, Accumulator> = BTreeMap::new();
+        for DailyUsage { model, date, usage: aggregate } in usage {
+            let entry = grouped.entry(names[model].as_str()).or_default();
+            entry.ids.insert(model);
+            entry.days.insert(date);
+            entry.messages += aggregate.messages;
+            entry.tokens.add(&aggregate.usage);
+            if let Some(cost) = cost_for(model, &aggregate.usage) {
+                entry.cost = Some(entry.cost.unwrap_or(0.0) + cost);
+            }
+        }
+
+        let total_cost: f64 = grouped.values().filter_map(|entry| entry.cost).sum();
+        let total_tokens: u64 = grouped.values().map(|entry| entry.tokens.total_tokens).sum();
+        let mut totals: Vec<ModelTotal> = grouped
+            .into_iter()
+            .map(|(model, entry)| {
+                let share = if total_cost > 0.0 {
+                    entry.cost.unwrap_or(0.0) / total_cost
+                } else if total_tokens > 0 {
+                    entry.tokens.total_tokens as f64 / total_tokens as f64
+                } else {
+                    0.0
+                };
+                ModelTotal {
+                    model: model.to_string(),
+                    model_ids: entry.ids.into_iter().map(str::to_string).collect(),
+                    active_days: entry.days.len() as u64,
+                    messages: entry.messages,
+                    tokens: entry.tokens,
+                    cost_usd: entry.cost,
+                    share_percent: share * 100.0,
+                }
+            })
+            .collect();
+        totals.sort_by(|a, b| compare_models(&a.model_ids[0], &b.model_ids[0]).then(a.model.cmp(&b.model)));
+        totals
+    }
+
+    pub fn render_model_total_table(&self, usage: &[DailyUsage], out: &mut dyn Write) -> io::Result<()> {
+        let totals = self.model_totals(usage);
+        if totals.is_empty() {
+            writeln!(out, "{}", tr!("report.no_usage"))?;
+            return Ok(());
+        }
+
+        let mut builder = Builder::default();
+        builder.push_record(["Model", "Days", "Msgs", "Input", "Output", "Cache Create", "Cache Read", "Total", "Cost", "Share"]);
+        let mut total = TokenTotals::default();
+        let mut total_cost = None;
+        let mut total_messages = 0;
+        for model in &totals {
+            builder.push_record([
+                self.paint_model(&model.model),
+                format_count(model.active_days),
+                format_count(model.messages),
+                UsageRow::format_number(model.tokens.input_tokens),
+                UsageRow::format_number(model.tokens.output_tokens),
+                UsageRow::format_number(model.tokens.cache_creation_input_tokens),
+                UsageRow::format_number(model.tokens.cache_read_input_tokens),
+                UsageRow::format_number(model.tokens.total_tokens),
+                format_cost(model.cost_usd),
+                format!("{:.1}%", model.share_percent),
+            ]);
+            total.merge(&model.tokens);
+            total_messages += model.messages;
+            if let Some(cost) = model.cost_usd {
+                total_cost = Some(total_cost.unwrap_or(0.0) + cost);
+            }
+        }
+        builder.push_record([
+            i18n::label("Total").to_string(),
+            String::new(),
+            format_count(total_messages),
+            UsageRow::format_number(total.input_tokens),
+            UsageRow::format_number(total.output_tokens),
+            UsageRow::format_number(total.cache_creation_input_tokens),
+            UsageRow::format_number(total.cache_read_input_tokens),
+            UsageRow::format_number(total.total_tokens),
+            format_cost(total_cost),
+            "100.0%".to_string(),
+        ]);
+
+        let mut table = builder.build();
+        table.with(self.theme());
+        table.with(Modify::new(Columns::new(1..)).with(Alignment::right()));
+        localize_header(&mut table);
+        self.colorize(&mut table, Color::FG_BRIGHT_GREEN, Rows::new(0..1));
+        self.colorize(&mut table, Color::FG_YELLOW, Rows::last());
+
+        writeln!(out, " === {} ===", i18n::label("Usage by Model"))?;
+        writeln!(out, "{}", table)?;
+        Ok(())
+    }
+
+    pub fn render_model_total_json(&self, usage: &[DailyUsage], out: &mut dyn Write) -> io::Result<()> {
+        self.print_json(&self.model_totals(usage), out)
+    }
+
+    /// 将 (项目, 模型, 日期) 使用量汇总为每个项目一项，按费用降序。
+    /// 会话按其所属项目计数；项目未知的记录同样单独成行，使各行之和等于总计
+    fn project_costs(
+        project_models: &[ProjectModelUsage],
+        sessions: &[SessionSummary],
+    ) -> Vec<ProjectCost> {
+        let mut grouped: BTreeMap<&str, (BTreeSet<&str>, TokenTotals, Option<f64>)> = BTreeMap::new();
+        for usage in project_models {
+            let (days, tokens, cost) = grouped.entry(&usage.project).or_default();
+            days.insert(&usage.date);
+            tokens.add(&usage.aggregate.usage);
+            if let Some(model_cost) = cost_for(&usage.model, &usage.aggregate.usage) {
+                *cost = Some(cost.unwrap_or(0.0) + model_cost);
+            }
+        }
+        let mut session_counts: HashMap<&str, u64> = HashMap::new();
+        for session in sessions {
+            *session_counts.entry(session.project.as_deref().unwrap_or("(unknown)")).or_default() += 1;
+        }
+
+        let total_cost: f64 = grouped.values().filter_map(|(_, _, cost)| *cost).sum();
+        let total_tokens: u64 = grouped.values().map(|(_, tokens, _)| tokens.total_tokens).sum();
+        let mut costs: Vec<ProjectCost> = grouped
+            .into_iter()
+            .map(|(project, (days, tokens, cost))| {
+                let share = if total_cost > 0.0 {
+                    cost.unwrap_or(0.0) / total_cost
+                } else if total_tokens > 0 {
+                    tokens.total_tokens as f64 / total_tokens as f64
+                } else {
+                    0.0
+                };
+                ProjectCost {
+                    project: project.to_string(),
+                    sessions: session_counts.get(project).copied().unwrap_or(0),
+                    active_days: days.len() as u64,
+                    tokens,
+                    cost_usd: cost,
+                    share_percent: share * 100.0,
+                    budget: None,
+                }
+            })
+            .collect();
+        costs.sort_by(|a, b| {
+            b.cost_usd
+                .unwrap_or(0.0)
+                .total_cmp(&a.cost_usd.unwrap_or(0.0))
+                .then(b.tokens.total_tokens.cmp(&a.tokens.total_tokens))
+                .then(a.project.cmp(&b.project))
+        });
+        costs
+    }
+
+    /// 按项目汇总的表格；budgets 为 Some 时增加 Budget 列（本计费月的费用 / 预算），超出预算的项目显示为红色
+    pub fn render_project_cost_table(
+        &self,
+        project_models: &[ProjectModelUsage],
+        sessions: &[SessionSummary],
+        budgets: Option<&BudgetReport>,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        let costs = Self::project_costs(project_models, sessions);
+        if costs.is_empty() {
+            writeln!(out, "{}", tr!("report.no_projects"))?;
+            return Ok(());
+        }
+
+        let names = short_project_names(costs.iter().map(|cost| cost.project.as_str()));
+        let mut builder = Builder::default();
+        let mut header = vec!["Project", "Sessions", "Days", "Input", "Output", "Cache Create", "Cache Read", "Total", "Cost", "Share"];
+        if budgets.is_some() {
+            header.push("Budget");
+        }
+        builder.push_record(header);
+        let mut total = TokenTotals::default();
+        let mut total_cost = None;
+        let mut total_sessions = 0;
+        let mut over_budget_rows = Vec::new();
+        for (row, cost) in costs.iter().enumerate() {
+            let status = budgets.and_then(|budgets| budgets.get(&cost.project));
+            if status.is_some_and(|status| status.over_budget) {
+                over_budget_rows.push(row + 1);
+            }
+            let mut record = vec![
+                names.get(&cost.project).cloned().unwrap_or_else(|| cost.project.clone()),
+                format_count(cost.sessions),
+                format_count(cost.active_days),
+                UsageRow::format_number(cost.tokens.input_tokens),
+                UsageRow::format_number(cost.tokens.output_tokens),
+                UsageRow::format_number(cost.tokens.cache_creation_input_tokens),
+                UsageRow::format_number(cost.tokens.cache_read_input_tokens),
+                UsageRow::format_number(cost.tokens.total_tokens),
+                format_cost(cost.cost_usd),
+                format!("{:.1}%", cost.share_percent),
+            ];
+            if budgets.is_some() {
+                record.push(match status {
+                    Some(ProjectBudgetStatus { spent_usd, budget_usd: Some(budget), .. }) => {
+                        format!("{} / {}", format_cost(Some(*spent_usd)), format_cost(Some(*budget)))
+                    }
+                    _ => "—".to_string(),
+                });
+            }
+            builder.push_record(record);
+            total.merge(&cost.tokens);
+            total_sessions += cost.sessions;
+            if let Some(cost) = cost.cost_usd {
+                total_cost = Some(total_cost.unwrap_or(0.0) + cost);
+            }
+        }
+        builder.push_record([
+            i18n::label("Total").to_string(),
+            format_count(total_sessions),
+            String::new(),
+            UsageRow::format_number(total.input_tokens),
+            UsageRow::format_number(total.output_tokens),
+            UsageRow::format_number(total.cache_creation_input_tokens),
+            UsageRow::format_number(total.cache_read_input_tokens),
+            UsageRow::format_number(total.total_tokens),
+            format_cost(total_cost),
+            "100.0%".to_string(),
+        ]
+        .into_iter()
+        .chain(budgets.map(|_| String::new())));
+
+        let mut table = builder.build();
+        table.with(self.theme());
+        table.with(Modify::new(Columns::new(1..)).with(Alignment::right()));
+        localize_header(&mut table);
+        self.colorize(&mut table, Color::FG_BRIGHT_GREEN, Rows::new(0..1));
+        self.colorize(&mut table, Color::FG_YELLOW, Rows::last());
+        for row in over_budget_rows {
+            self.colorize(&mut table, Color::FG_RED, Rows::one(row));
+        }
+
+        writeln!(out, " === {} ===", i18n::label("Usage by Project"))?;
+        writeln!(out, "{}", table)?;
+        Ok(())
+    }
+
+    /// 按项目汇总的 JSON；budgets 为 Some 时各项目附带 budget 字段
+    pub fn render_project_cost_json(
+        &self,
+        project_models: &[ProjectModelUsage],
+        sessions: &[SessionSummary],
+        budgets: Option<&BudgetReport>,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        let mut costs = Self::project_costs(project_models, sessions);
+        if let Some(budgets) = budgets {
+            for cost in &mut costs {
+                cost.budget = budgets.get(&cost.project).cloned();
+            }
+        }
+        self.print_json(&costs, out)
+    }
+
+    /// 各项目本计费月的费用与预算，超出预算的项目显示为红色
+    pub fn render_budget_table(&self, report: &BudgetReport, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, " === {} ===", tr!("report.title.budgets", report.window_start, report.window_end))?;
+        if report.projects.is_empty() {
+            writeln!(out, "{}", tr!("report.no_projects"))?;
+            return Ok(());
+        }
+
+        let names = short_project_names(report.projects.iter().map(|status| status.project.as_str()));
+        let mut builder = Builder::default();
+        builder.push_record(["Project", "Spent", "Budget", "Remaining", "Used"]);
+        for status in &report.projects {
+            let remaining = status.budget_usd.map(|budget| budget - status.spent_usd);
+            builder.push_record([
+                names.get(&status.project).cloned().unwrap_or_else(|| status.project.clone()),
+                format_cost(Some(status.spent_usd)),
+                format_cost(status.budget_usd),
+                match remaining {
+                    Some(remaining) if remaining < 0.0 => format!("-{}", format_cost(Some(-remaining))),
+                    remaining => format_cost(remaining),
+                },
+                status.percentage.map_or_else(|| "—".to_string(), |percentage| format!("{:.1}%", percentage)),
+            ]);
+        }
+
+        let mut table = builder.build();
+        table.with(self.theme());
+        table.with(Modify::new(Columns::new(1..)).with(Alignment::right()));
+        localize_header(&mut table);
+        self.colorize(&mut table, Color::FG_BRIGHT_GREEN, Rows::new(0..1));
+        for (row, status) in report.projects.iter().enumerate() {
+            if status.over_budget {
+                self.colorize(&mut table, Color::FG_RED, Rows::one(row + 1));
+            }
+        }
+        writeln!(out, "{}", table)?;
+        Ok(())
+    }
+
+    /// --explain：一天的用量来自哪些文件，每个文件的每个模型一行，最后是报告中这一天按模型的合计
+    pub fn render_explain_table(&self, explanation: &DayExplanation, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, " === {} ===", tr!("report.title.explain", explanation.date, self.timezone))?;
+        if explanation.files.is_empty() {
+            writeln!(out, "{}", tr!("report.explain_no_files", explanation.date))?;
+        } else {
+            let names = short_project_names(explanation.files.iter().map(|file| file.project.as_str()));
+            let mut builder = Builder::default();
+            builder.push_record(
+                ["File", "Project", "Model", "Entries", "Input", "Output", "Cache Create", "Cache Read", "Total", "Cost", "Excluded"],
+            );
+            for file in &explanation.files {
+                let mut record = vec![
+                    file.path.file_name().map_or_else(|| file.path.display().to_string(), |name| name.to_string_lossy().into_owned()),
+                    names.get(&file.project).cloned().unwrap_or_else(|| file.project.clone()),
+                ];
+                let excluded = format_count(file.filtered + file.no_usage);
+                if file.models.is_empty() {
+                    // 只有被排除的记录
+                    record.extend(std::iter::repeat_n("—".to_string(), 8));
+                    record.push(excluded);
+                    builder.push_record(record);
+                    continue;
+                }
+                for (index, model) in file.models.iter().enumerate() {
+                    if index > 0 {
+                        record = vec![String::new(), String::new()];
+                    }
+                    record.push(self.model_name(&model.model));
+                    record.push(format_count(model.entries));
+                    record.extend(Self::token_cells(&model.tokens, model.cost_usd));
+                    record.push(if index == 0 { excluded.clone() } else { String::new() });
+                    builder.push_record(record.clone());
+                }
+            }
+            for model in &explanation.models {
+                let mut record = vec![i18n::label("Total").to_string(), String::new(), self.model_name(&model.model), format_count(model.entries)];
+                record.extend(Self::token_cells(&model.tokens, model.cost_usd));
+                record.push(String::new());
+                builder.push_record(record);
+            }
+
+            let mut table = builder.build();
+            table.with(self.theme());
+            table.with(Modify::new(Columns::new(3..)).with(Alignment::right()));
+            localize_header(&mut table);
+            self.colorize(&mut table, Color::FG_BRIGHT_GREEN, Rows::new(0..1));
+            let rows = table.count_rows();
+            self.colorize(&mut table, Color::FG_YELLOW, Rows::new(rows - explanation.models.len()..rows));
+            writeln!(out, "{}", table)?;
+        }
+        if explanation.unattributed_tokens > 0 {
+            writeln!(out, "{}", tr!("report.explain_unattributed", UsageRow::format_number(explanation.unattributed_tokens)))?;
+        }
+        Ok(())
+    }
+
+    pub fn render_project_json(
+        &self,
+        projects: &[GroupUsage],
+        last_opened: &HashMap<String, DateTime<Utc>>,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.print_json(&Self::project_summaries(projects, last_opened), out)
+    }
+
+    /// 数据覆盖范围：实际找到的最早/最晚日期；请求的起始日期早于最早数据时醒目提示，
+    /// 因为 Claude Code 会清理旧日志，该范围实际只被部分覆盖
+    pub fn render_coverage(
+        &self,
+        coverage: Option<(NaiveDate, NaiveDate)>,
+        since: Option<NaiveDate>,
+        gaps: Option<&[NaiveDate]>,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        let Some((earliest, latest)) = coverage else {
+            return Ok(());
+        };
+        writeln!(out, "{}", tr!("report.coverage", earliest, latest))?;
+
+        if let Some(since) = since
+            && since < earliest {
+            writeln!(out, "{}", tr!("report.coverage_partial", since, earliest))?;
+        }
+
+        if let Some(gaps) = gaps {
+            if gaps.is_empty() {
+                writeln!(out, "{}", tr!("report.no_gaps"))?;
+            } else {
+                let days: Vec<String> = gaps.iter().map(|date| date.to_string()).collect();
+                writeln!(out, "{}", tr!("report.gaps", gaps.len(), days.join(", ")))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 归档与当前日志不一致的键，统计中已取较大值
+    pub fn render_archive_conflicts(
+        &self,
+        conflicts: &[((String, String, String), u64, u64)],
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        if conflicts.is_empty() {
+            return Ok(());
+        }
+        writeln!(out, "{}", tr!("report.archive_conflicts", conflicts.len()))?;
+        for ((project, model, date), live, archived) in conflicts {
+            writeln!(
+                out,
+                "  {}",
+                tr!(
+                    "report.archive_conflict",
+                    date,
+                    self.model_name(model),
+                    project,
+                    format_count(*live),
+                    format_count(*archived)
+                )
+            )?;
+        }
+        Ok(())
+    }
+
+    /// 所选范围内首次出现的模型，提示默认模型可能被更换
+    pub fn render_new_models(&self, new_models: &[NewModel], out: &mut dyn Write) -> io::Result<()> {
+        for new_model in new_models {
+            writeln!(
+                out,
+                "{}",
+                tr!("report.new_model", new_model.model, new_model.first_seen, format_count(new_model.total_tokens))
+            )?;
+        }
+        Ok(())
+    }
+
+    /// 计费窗口内的用量进度条：低于 80% 为绿色，接近上限为黄色，超出为红色
+    pub fn render_limit_status(&self, status: &LimitStatus, out: &mut dyn Write) -> io::Result<()> {
+        const BAR_WIDTH: usize = 30;
+        let percentage = status.percentage();
+        let bar = format_bar(percentage / 100.0, BAR_WIDTH);
+        // 上限的状态写入 stderr
+        let bar = if self.colors_for(io::stderr().is_terminal()) {
+            let color = if status.exceeded() {
+                "31"
+            } else if percentage >= 80.0 {
+                "33"
+            } else {
+                "32"
+            };
+            format!("\x1b[{}m{}\x1b[0m", color, bar)
+        } else {
+            bar
+        };
+
+        writeln!(
+            out,
+            "{}",
+            tr!(
+                "report.limit",
+                status.window,
+                status.window_start,
+                format_count(status.consumed),
+                format_count(status.limit),
+                bar,
+                format!("{:.1}", percentage)
+            )
+        )?;
+        if status.exceeded() {
+            writeln!(out, "{}", tr!("report.limit_exceeded"))?;
+        } else if let Some(projected) = status.projected {
+            writeln!(out, "{}", tr!("report.limit_projected", projected))?;
+        }
+        if let (Some(end), Some(total)) = (status.window_end, status.projected_total) {
+            writeln!(out, "{}", tr!("report.limit_projected_total", end, format_count(total)))?;
+        }
+        Ok(())
+    }
+
+    /// 按时间段（月、周）汇总的表格；breakdown 时在每个时间段下列出各模型。
+    /// 周报的标签附带日期范围，并增加相对上一周的变化列
+    pub fn render_period_table(
+        &self,
+        kind: PeriodKind,
+        periods: &[PeriodSummary],
+        breakdown: bool,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        if periods.is_empty() {
+            writeln!(out, "{}", tr!("report.no_usage"))?;
+            return Ok(());
+        }
+
+        let show_delta = kind == PeriodKind::Week;
+        let mut header = vec![kind.header(), "Days", "Input", "Output", "Cache Create", "Cache Read", "Total", "Cost"];
+        if show_delta {
+            header.push("vs Prev");
+        }
+
+        let mut builder = Builder::default();
+        builder.push_record(header);
+        let mut total = TokenTotals::default();
+        let mut total_cost = None;
+        for period in periods {
+            let mut label = period.label.clone();
+            if kind == PeriodKind::Week {
+                label = format!("{} ({})", label, period.date_range());
+            }
+            if period.partial {
+                label.push_str(&tr!("report.partial_period"));
+            }
+            let mut record = Self::period_record(label, Some(period.active_days), &period.tokens, period.cost_usd);
+            if show_delta {
+                record.push(Self::format_delta(period));
+            }
+            builder.push_record(record);
+
+            if breakdown {
+                for model in &period.models {
+                    let name = format!("  {}", self.paint_model(&self.model_name(&model.model)));
+                    let mut record = Self::period_record(name, None, &model.tokens, model.cost_usd);
+                    if show_delta {
+                        record.push(String::new());
+                    }
+                    builder.push_record(record);
+                }
+            }
+            total.merge(&period.tokens);
+            if let Some(cost) = period.cost_usd {
+                total_cost = Some(total_cost.unwrap_or(0.0) + cost);
+            }
+        }
+        let active_days = periods.iter().map(|period| period.active_days).sum();
+        let mut record = Self::period_record(i18n::label("Total").to_string(), Some(active_days), &total, total_cost);
+        if show_delta {
+            record.push(String::new());
+        }
+        builder.push_record(record);
+
+        let mut table = builder.build();
+        table.with(self.theme());
+        table.with(Modify::new(Columns::new(1..)).with(Alignment::right()));
+        localize_header(&mut table);
+        self.colorize(&mut table, Color::FG_BRIGHT_GREEN, Rows::new(0..1));
+        self.colorize(&mut table, Color::FG_YELLOW, Rows::last());
+
+        writeln!(out, " === {} ===", i18n::label(kind.title()))?;
+        writeln!(out, "{}", table)?;
+        Ok(())
+    }
+
+    fn period_record(label: String, active_days: Option<u64>, tokens: &TokenTotals, cost: Option<f64>) -> Vec<String> {
+        let mut record = vec![label, active_days.map(format_count).unwrap_or_default()];
+        record.extend(Self::token_cells(tokens, cost));
+        record
+    }
+
+    /// Input、Output、Cache Create、Cache Read、Total、Cost 六列
+    pub fn token_cells(tokens: &TokenTotals, cost: Option<f64>) -> [String; 6] {
+        [
+            UsageRow::format_number(tokens.input_tokens),
+            UsageRow::format_number(tokens.output_tokens),
+            UsageRow::format_number(tokens.cache_creation_input_tokens),
+            UsageRow::format_number(tokens.cache_read_input_tokens),
+            UsageRow::format_number(tokens.total_tokens),
+            format_cost(cost),
+        ]
+    }
+
+    /// 相对上一个时间段的变化：上一个时间段没有用量时为 new，没有可比较的数据时为 —
+    fn format_delta(period: &PeriodSummary) -> String {
+        match period.previous_total_tokens {
+            Some(previous) => format_change(period.tokens.total_tokens as f64, previous as f64),
+            None => "—".to_string(),
+        }
+    }
+
+    /// 时间段汇总的 JSON 表示，时间段标签以 month、week 等键输出
+    pub fn periods_json(&self, kind: PeriodKind, periods: &[PeriodSummary], breakdown: bool) -> Value {
+        Value::Array(
+            periods
+                .iter()
+                .map(|period| {
+                    let mut object = Map::new();
+                    object.insert(kind.key().to_string(), Value::from(period.label.clone()));
+                    if let Ok(Value::Object(fields)) = serde_json::to_value(period) {
+                        object.extend(fields.into_iter().filter(|(name, _)| breakdown || name != "models"));
+                    }
+                    Value::Object(object)
+                })
+                .collect(),
+        )
+    }
+
+    /// 每个时间段内各模型（按显示名称合并）的 token 占比，模型按首次出现的顺序排列
+    fn model_shares(&self, periods: &[PeriodSummary]) -> (Vec<String>, Vec<Vec<ModelShare>>) {
+        let names = self.model_display_names(
+            periods.iter().flat_map(|period| period.models.iter().map(|model| model.model.as_str())),
+        );
+        let mut order: Vec<String> = Vec::new();
+        let shares = periods
+            .iter()
+            .map(|period| {
+                let mut tokens: BTreeMap<&str, u64> = BTreeMap::new();
+                // 在同一时间段内首次出现的多个模型按用量降序排列
+                let mut models: Vec<_> = period.models.iter().collect();
+                models.sort_by_key(|model| std::cmp::Reverse(model.tokens.total_tokens));
+                for model in models {
+                    let name = names[&model.model].as_str();
+                    *tokens.entry(name).or_default() += model.tokens.total_tokens;
+                    if !order.iter().any(|known| known == name) {
+                        order.push(name.to_string());
+                    }
+                }
+                let total = period.tokens.total_tokens.max(1) as f64;
+                order
+                    .iter()
+                    .filter_map(|name| {
+                        let total_tokens = tokens.get(name.as_str()).copied().filter(|tokens| *tokens > 0)?;
+                        Some(ModelShare {
+                            model: name.clone(),
+                            total_tokens,
+                            share_percent: total_tokens as f64 * 100.0 / total,
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+        (order, shares)
+    }
+
+    /// 各时间段中模型占比的堆叠条形图：每行一个时间段，每个模型用不同的字符（终端中另有颜色）表示
+    pub fn render_model_timeline(&self, kind: PeriodKind, periods: &[PeriodSummary], out: &mut dyn Write) -> io::Result<()> {
+        const CHART_WIDTH: usize = 50;
+        const SEGMENTS: [char; 8] = ['█', '▓', '▒', '░', '#', '=', '+', ':'];
+        const COLORS: [u8; 8] = [34, 32, 33, 35, 36, 31, 94, 92];
+        if periods.is_empty() {
+            writeln!(out, "{}", tr!("report.no_usage"))?;
+            return Ok(());
+        }
+
+        let (models, shares) = self.model_shares(periods);
+        let color = self.colors_enabled();
+        let segment = |index: usize, width: usize| {
+            let text: String = std::iter::repeat_n(SEGMENTS[index % SEGMENTS.len()], width).collect();
+            if color && width > 0 {
+                format!("\x1b[{}m{}\x1b[0m", COLORS[index % COLORS.len()], text)
+            } else {
+                text
+            }
+        };
+
+        let mut builder = Builder::default();
+        builder.push_record([kind.header(), "Total", "Share by Model"]);
+        for (period, period_shares) in periods.iter().zip(&shares) {
+            // 按最大余数分配字符数，使每行的总宽度恰好为 CHART_WIDTH
+            let exact: Vec<f64> = period_shares
+                .iter()
+                .map(|share| share.share_percent / 100.0 * CHART_WIDTH as f64)
+                .collect();
+            let mut widths: Vec<usize> = exact.iter().map(|width| width.floor() as usize).collect();
+            let mut by_remainder: Vec<usize> = (0..exact.len()).collect();
+            by_remainder.sort_by(|&a, &b| (exact[b] - exact[b].floor()).total_cmp(&(exact[a] - exact[a].floor())));
+            let missing = CHART_WIDTH.saturating_sub(widths.iter().sum());
+            for &index in by_remainder.iter().take(missing) {
+                widths[index] += 1;
+            }
+
+            let chart: String = period_shares
+                .iter()
+                .zip(widths)
+                .map(|(share, width)| {
+                    let index = models.iter().position(|model| *model == share.model).unwrap_or(0);
+                    segment(index, width)
+                })
+                .collect();
+            builder.push_record([
+                period.label.clone(),
+                UsageRow::format_number(period.tokens.total_tokens),
+                chart,
+            ]);
+        }
+
+        let mut table = builder.build();
+        table.with(self.theme());
+        table.with(Modify::new(Columns::new(1..2)).with(Alignment::right()));
+        localize_header(&mut table);
+        self.colorize(&mut table, Color::FG_BRIGHT_GREEN, Rows::new(0..1));
+
+        writeln!(out, " === {} ===", tr!("report.title.model_share", i18n::label(kind.header())))?;
+        writeln!(out, "{}", table)?;
+        writeln!(out, "{}", tr!("report.legend"))?;
+        for (index, model) in models.iter().enumerate() {
+            let total: u64 = shares
+                .iter()
+                .flatten()
+                .filter(|share| share.model == *model)
+                .map(|share| share.total_tokens)
+                .sum();
+            writeln!(out, "  {} {}", segment(index, 2), tr!("report.legend_entry", model, format_count(total)))?;
+        }
+        Ok(())
+    }
+
+    pub fn model_timeline_json(&self, kind: PeriodKind, periods: &[PeriodSummary]) -> Value {
+        let (_, shares) = self.model_shares(periods);
+        Value::Array(
+            periods
+                .iter()
+                .zip(shares)
+                .map(|(period, shares)| {
+                    serde_json::json!({
+                        kind.key(): period.label,
+                        "start": period.start,
+                        "end": period.end,
+                        "total_tokens": period.tokens.total_tokens,
+                        "models": shares,
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    pub fn render_block_table(&self, blocks: &[Block], out: &mut dyn Write) -> io::Result<()> {
+        if blocks.is_empty() {
+            writeln!(out, "{}", tr!("report.no_usage"))?;
+            return Ok(());
+        }
+
+        let mut builder = Builder::default();
+        builder.push_record(["Start", "End", "Status", "Models", "Msgs", "Total", "Cost"]);
+        for block in blocks {
+            let status = match (block.elapsed_seconds, block.remaining_seconds) {
+                (Some(elapsed), Some(remaining)) => tr!(
+                    "report.block_active",
+                    format_duration(TimeDelta::seconds(elapsed)),
+                    format_duration(TimeDelta::seconds(remaining))
+                ),
+                _ => String::new(),
+            };
+            let mut models: Vec<String> = block.models.iter().map(|model| self.model_name(model)).collect();
+            models.dedup();
+            builder.push_record([
+                block.start.with_timezone(&self.timezone).format("%Y-%m-%d %H:%M").to_string(),
+                block.end.with_timezone(&self.timezone).format("%H:%M").to_string(),
+                status,
+                models.join(", "),
+                format_count(block.messages),
+                UsageRow::format_number(block.tokens.total_tokens),
+                format_cost(block.cost_usd),
+            ]);
+        }
+
+        let mut table = builder.build();
+        table.with(self.theme());
+        table.with(Modify::new(Columns::new(4..)).with(Alignment::right()));
+        localize_header(&mut table);
+        self.colorize(&mut table, Color::FG_BRIGHT_GREEN, Rows::new(0..1));
+        if let Some(active) = blocks.iter().position(|block| block.active) {
+            self.colorize(&mut table, Color::FG_YELLOW, Rows::one(active + 1));
+        }
+
+        writeln!(out, " === {} ===", tr!("report.title.blocks", self.timezone))?;
+        writeln!(out, "{}", table)?;
+        Ok(())
+    }
+
+    /// 按一天中的小时合计的分布，比例条以用量最多的小时为满格
+    pub fn render_hour_of_day_table(&self, hours: &[HourOfDay], out: &mut dyn Write) -> io::Result<()> {
+        const BAR_WIDTH: usize = 30;
+        let max = hours.iter().map(|hour| hour.total_tokens).max().unwrap_or(0);
+        let mut builder = Builder::default();
+        builder.push_record(["Hour", "Msgs", "Total", "Cost", "Share", ""]);
+        for hour in hours {
+            builder.push_record([
+                format!("{:02}:00", hour.hour),
+                format_count(hour.messages),
+                UsageRow::format_number(hour.total_tokens),
+                format_cost(hour.cost_usd),
+                format!("{:.1}%", hour.share_percent),
+                format_bar(hour.total_tokens as f64 / max.max(1) as f64, BAR_WIDTH),
+            ]);
+        }
+
+        let mut table = builder.build();
+        table.with(self.theme());
+        table.with(Modify::new(Columns::new(1..5)).with(Alignment::right()));
+        localize_header(&mut table);
+        self.colorize(&mut table, Color::FG_BRIGHT_GREEN, Rows::new(0..1));
+
+        writeln!(out, " === {} ===", tr!("report.title.hour_of_day", self.timezone))?;
+        writeln!(out, "{}", table)?;
+        Ok(())
+    }
+
+    /// 单条消息大小的分位数，第一行为全部模型合计
+    pub fn render_message_size_table(&self, sizes: &[MessageSizeStats], out: &mut dyn Write) -> io::Result<()> {
+        if sizes.is_empty() {
+            writeln!(out, "{}", tr!("report.no_messages"))?;
+            return Ok(());
+        }
+        let mut builder = Builder::default();
+        builder.push_record([
+            "Model", "Msgs", "Out p50", "Out p90", "Out p99", "Out Max", "Total p50", "Total p90", "Total p99", "Total Max",
+        ]);
+        for stats in sizes {
+            let mut record = vec![
+                stats.model.clone().unwrap_or_else(|| i18n::label("All").to_string()),
+                format_count(stats.samples),
+            ];
+            for percentiles in [&stats.output_tokens, &stats.total_tokens] {
+                record.extend(
+                    [percentiles.p50, percentiles.p90, percentiles.p99, percentiles.max].map(UsageRow::format_number),
+                );
+            }
+            builder.push_record(record);
+        }
+
+        let mut table = builder.build();
+        table.with(self.theme());
+        table.with(Modify::new(Columns::new(1..)).with(Alignment::right()));
+        localize_header(&mut table);
+        self.colorize(&mut table, Color::FG_BRIGHT_GREEN, Rows::new(0..1));
+        self.colorize(&mut table, Color::FG_YELLOW, Rows::one(1));
+
+        writeln!(out, " === {} ===", i18n::label("Message Sizes (tokens per message)"))?;
+        writeln!(out, "{}", table)?;
+        writeln!(out, "{}", tr!("report.message_count", format_count(sizes[0].samples)))?;
+        Ok(())
+    }
+
+    /// 按星期合计的分布，比例条以日均用量最多的星期为满格
+    pub fn render_weekday_table(&self, weekdays: &[WeekdayUsage], out: &mut dyn Write) -> io::Result<()> {
+        const BAR_WIDTH: usize = 30;
+        let max = weekdays.iter().map(|weekday| weekday.average_tokens).fold(0.0, f64::max);
+        let mut builder = Builder::default();
+        builder.push_record(["Weekday", "Days", "Total", "Cost", "Avg/Day", "Avg Cost", "Share", ""]);
+        for weekday in weekdays {
+            builder.push_record([
+                weekday_name(weekday.weekday),
+                format_count(weekday.occurrences),
+                UsageRow::format_number(weekday.total_tokens),
+                format_cost(weekday.cost_usd),
+                UsageRow::format_number(weekday.average_tokens.round() as u64),
+                format_cost(weekday.average_cost_usd),
+                format!("{:.1}%", weekday.share_percent),
+                format_bar(if max > 0.0 { weekday.average_tokens / max } else { 0.0 }, BAR_WIDTH),
+            ]);
+        }
+
+        let mut table = builder.build();
+        table.with(self.theme());
+        table.with(Modify::new(Columns::new(1..7)).with(Alignment::right()));
+        localize_header(&mut table);
+        self.colorize(&mut table, Color::FG_BRIGHT_GREEN, Rows::new(0..1));
+
+        writeln!(out, " === {} ===", tr!("report.title.weekday", self.timezone))?;
+        writeln!(out, "{}", table)?;
+        Ok(())
+    }
+
+    /// 连续使用天数的概要，每段都附带起止日期便于核对
+    pub fn render_streaks(&self, streaks: &Streaks, out: &mut dyn Write) -> io::Result<()> {
+        let span = |span: &Option<DateSpan>| match span {
+            Some(span) => tr!("report.streak_span", span.days, span.start, span.end),
+            None => tr!("report.days", 0),
+        };
+        writeln!(out, "{}", tr!("report.streak_range", streaks.start, streaks.end, self.timezone, format_count(streaks.min_tokens)))?;
+        writeln!(out, "{}", tr!("report.streak_current", span(&streaks.current)))?;
+        writeln!(out, "{}", tr!("report.streak_longest", span(&streaks.longest)))?;
+        writeln!(
+            out,
+            "{}",
+            tr!(
+                "report.streak_active_days",
+                format_count(streaks.active_days),
+                format_count(streaks.calendar_days),
+                format!("{:.1}", streaks.active_days as f64 * 100.0 / streaks.calendar_days.max(1) as f64)
+            )
+        )?;
+        writeln!(out, "{}", tr!("report.streak_longest_gap", span(&streaks.longest_gap)))?;
+        Ok(())
+    }
+
+    /// 项目 × 月的透视表；项目名过长时截断，没有用量的单元格显示为 ·
+    pub fn render_project_matrix(&self, matrix: &ProjectMatrix, metric: Metric, out: &mut dyn Write) -> io::Result<()> {
+        const MAX_PROJECT_WIDTH: usize = 30;
+        if matrix.projects.is_empty() {
+            writeln!(out, "{}", tr!("report.no_usage"))?;
+            return Ok(());
+        }
+
+        let names = short_project_names(matrix.projects.iter().map(|row| row.project.as_str()));
+        let cell = |cell: &MatrixCell| {
+            if cell.total_tokens == 0 {
+                return "·".to_string();
+            }
+            match metric {
+                Metric::Tokens => UsageRow::format_number(cell.total_tokens),
+                Metric::Cost => format_cost(cell.cost_usd),
+            }
+        };
+        let mut header = vec!["Project".to_string()];
+        header.extend(matrix.months.iter().map(|month| month.month.clone()));
+        header.push(i18n::label("Total").to_string());
+
+        let mut builder = Builder::default();
+        builder.push_record(header);
+        for row in &matrix.projects {
+            let name = names.get(&row.project).cloned().unwrap_or_else(|| row.project.clone());
+            let mut record = vec![truncate_start(&name, MAX_PROJECT_WIDTH)];
+            record.extend(row.months.iter().map(cell));
+            record.push(cell(&row.total));
+            builder.push_record(record);
+        }
+        let mut record = vec![i18n::label("Total").to_string()];
+        record.extend(matrix.month_totals.iter().map(cell));
+        record.push(cell(&matrix.total));
+        builder.push_record(record);
+
+        let mut table = builder.build();
+        table.with(self.theme());
+        table.with(Modify::new(Columns::new(1..)).with(Alignment::right()));
+        localize_header(&mut table);
+        self.colorize(&mut table, Color::FG_BRIGHT_GREEN, Rows::new(0..1));
+        self.colorize(&mut table, Color::FG_YELLOW, Rows::last());
+
+        let title = match metric {
+            Metric::Tokens => "Tokens",
+            Metric::Cost => "Cost",
+        };
+        writeln!(out, " === {} ===", tr!("report.title.project_matrix", i18n::label(title)))?;
+        writeln!(out, "{}", table)?;
+        Ok(())
+    }
+
+    /// 按周的缓存报告；没有缓存字段的周显示为 n/a
+    pub fn render_cache_report(&self, report: &CacheReport, out: &mut dyn Write) -> io::Result<()> {
+        if report.weeks.is_empty() {
+            writeln!(out, "{}", tr!("report.no_usage"))?;
+            return Ok(());
+        }
+        let not_available = || "n/a".to_string();
+        let mut builder = Builder::default();
+        builder.push_record(["Week", "Dates", "Input", "Cache Create", "Cache Read", "Hit Ratio", "Savings"]);
+        for week in &report.weeks {
+            builder.push_record([
+                week.week.clone(),
+                date_range(week.start, week.end),
+                UsageRow::format_number(week.input_tokens),
+                week.cache_creation_input_tokens.map(UsageRow::format_number).unwrap_or_else(not_available),
+                week.cache_read_input_tokens.map(UsageRow::format_number).unwrap_or_else(not_available),
+                week.hit_ratio.map(|ratio| format!("{:.1}%", ratio * 100.0)).unwrap_or_else(not_available),
+                match (week.savings_usd, week.hit_ratio) {
+                    (Some(savings), _) => format_cost(Some(savings)),
+                    (None, Some(_)) => "—".to_string(),
+                    (None, None) => not_available(),
+                },
+            ]);
+        }
+
+        let mut table = builder.build();
+        table.with(self.theme());
+        table.with(Modify::new(Columns::new(2..)).with(Alignment::right()));
+        localize_header(&mut table);
+        self.colorize(&mut table, Color::FG_BRIGHT_GREEN, Rows::new(0..1));
+
+        writeln!(out, " === {} ===", tr!("report.title.cache_by_week", self.timezone))?;
+        writeln!(out, "{}", table)?;
+        if let Some(change) = report.hit_ratio_change_points {
+            let arrow = if change > 0.05 {
+                "↑"
+            } else if change < -0.05 {
+                "↓"
+            } else {
+                "→"
+            };
+            writeln!(out, "{}", tr!("report.hit_ratio_trend", arrow, format!("{:+.1}", change)))?;
+        }
+        Ok(())
+    }
+
+    /// 当前活跃窗口的概要：已用量、最近的消耗速率以及按该速率外推的窗口结束时用量
+    pub fn render_active_block(&self, block: Option<&Block>, out: &mut dyn Write) -> io::Result<()> {
+        let Some(block) = block else {
+            writeln!(out, "{}", tr!("report.no_active_block"))?;
+            return Ok(());
+        };
+        let mut models: Vec<String> = block.models.iter().map(|model| self.model_name(model)).collect();
+        models.dedup();
+        writeln!(
+            out,
+            "{}",
+            tr!(
+                "report.active_block",
+                block.start.with_timezone(&self.timezone).format("%Y-%m-%d %H:%M"),
+                block.end.with_timezone(&self.timezone).format("%H:%M"),
+                self.timezone,
+                format_duration(TimeDelta::seconds(block.elapsed_seconds.unwrap_or(0))),
+                format_duration(TimeDelta::seconds(block.remaining_seconds.unwrap_or(0)))
+            )
+        )?;
+        writeln!(
+            out,
+            "{}",
+            tr!(
+                "report.block_used",
+                format_count(block.tokens.total_tokens),
+                format_cost(block.cost_usd),
+                format_count(block.messages),
+                models.join(", ")
+            )
+        )?;
+        if let Some(projection) = &block.projection {
+            let rate = &projection.burn_rate;
+            writeln!(
+                out,
+                "{}",
+                tr!(
+                    "report.burn_rate",
+                    rate.window_minutes,
+                    format_count(rate.tokens_per_minute.round() as u64),
+                    format_cost(rate.cost_per_hour)
+                )
+            )?;
+            writeln!(
+                out,
+                "{}",
+                tr!(
+                    "report.projection",
+                    block.end.with_timezone(&self.timezone).format("%H:%M"),
+                    format_count(projection.projected_total_tokens),
+                    format_cost(projection.projected_cost_usd)
+                )
+            )?;
+        }
+        Ok(())
+    }
+
+    /// 所选范围与对比时间段按天对齐的对照表
+    pub fn render_comparison_table(&self, comparison: &Comparison, out: &mut dyn Write) -> io::Result<()> {
+        if comparison.days.is_empty() {
+            return Ok(());
+        }
+
+        let mut builder = Builder::default();
+        builder.push_record(["Date", "Compared To", "Total", "Prev Total", "Change", "Cost", "Prev Cost", "Change"]);
+        for day in &comparison.days {
+            builder.push_record([
+                day.date.to_string(),
+                day.compared_to.map(|date| date.to_string()).unwrap_or_else(|| "—".to_string()),
+                UsageRow::format_number(day.total_tokens),
+                UsageRow::format_number(day.previous_tokens),
+                format_change(day.total_tokens as f64, day.previous_tokens as f64),
+                format_cost(day.cost_usd),
+                format_cost(day.previous_cost),
+                format_change(day.cost_usd.unwrap_or(0.0), day.previous_cost.unwrap_or(0.0)),
+            ]);
+        }
+        let (current, previous) = (&comparison.current, &comparison.previous);
+        builder.push_record([
+            i18n::label("Total").to_string(),
+            String::new(),
+            UsageRow::format_number(current.total_tokens),
+            UsageRow::format_number(previous.total_tokens),
+            format_change(current.total_tokens as f64, previous.total_tokens as f64),
+            format_cost(current.cost_usd),
+            format_cost(previous.cost_usd),
+            format_change(current.cost_usd.unwrap_or(0.0), previous.cost_usd.unwrap_or(0.0)),
+        ]);
+
+        let mut table = builder.build();
+        table.with(self.theme());
+        table.with(Modify::new(Columns::new(2..)).with(Alignment::right()));
+        localize_header(&mut table);
+        self.colorize(&mut table, Color::FG_BRIGHT_GREEN, Rows::new(0..1));
+        self.colorize(&mut table, Color::FG_YELLOW, Rows::last());
+
+        writeln!(out, " === {} ===", i18n::label("Comparison"))?;
+        writeln!(out, "{}", table)?;
+        Ok(())
+    }
+
+    /// 对比摘要：总 token、费用和活跃天数的变化，命令行程序写入 stderr
+    pub fn render_comparison_summary(&self, comparison: &Comparison, out: &mut dyn Write) -> io::Result<()> {
+        let (current, previous) = (&comparison.current, &comparison.previous);
+        writeln!(
+            out,
+            "{}",
+            tr!(
+                "report.comparison",
+                previous.start,
+                previous.end,
+                format_count(current.total_tokens),
+                format_count(previous.total_tokens),
+                format_change(current.total_tokens as f64, previous.total_tokens as f64),
+                format_cost(current.cost_usd),
+                format_cost(previous.cost_usd),
+                format_change(current.cost_usd.unwrap_or(0.0), previous.cost_usd.unwrap_or(0.0)),
+                current.active_days,
+                previous.active_days,
+                format!("{:+}", current.active_days as i64 - previous.active_days as i64)
+            )
+        )?;
+        Ok(())
+    }
+
+    /// 趋势摘要：日均用量、上一等长区间的日均用量和拟合斜率的方向，命令行程序写入 stderr
+    pub fn render_trend(&self, trend: &Trend, out: &mut dyn Write) -> io::Result<()> {
+        let direction = match trend.change_percent {
+            Some(change) if change >= 0.5 => format!("↑ {:.0}%", change),
+            Some(change) if change <= -0.5 => format!("↓ {:.0}%", -change),
+            Some(_) => format!("→ {}", tr!("report.trend_flat")),
+            None => "—".to_string(),
+        };
+        writeln!(
+            out,
+            "{}",
+            tr!(
+                "report.trend",
+                UsageRow::format_number(trend.average_daily_tokens.round() as u64),
+                trend.days,
+                UsageRow::format_number(trend.previous_average_daily_tokens.round() as u64),
+                direction
+            )
+        )?;
+        Ok(())
+    }
+
+    /// 用量最高的若干天，每天下列出各模型；share 为各天占所选范围总量的百分比
+    pub fn render_top_days_table(&self, days: &[PeriodSummary], shares: &[f64], out: &mut dyn Write) -> io::Result<()> {
+        if days.is_empty() {
+            writeln!(out, "{}", tr!("report.no_usage"))?;
+            return Ok(());
+        }
+
+        let mut builder = Builder::default();
+        builder.push_record(["#", "Date", "Input", "Output", "Cache Create", "Cache Read", "Total", "Cost", "Share"]);
+        for (rank, (day, share)) in days.iter().zip(shares).enumerate() {
+            let mut record = vec![(rank + 1).to_string(), day.label.clone()];
+            record.extend(Self::token_cells(&day.tokens, day.cost_usd));
+            record.push(format!("{:.1}%", share));
+            builder.push_record(record);
+            for model in &day.models {
+                let mut record = vec![String::new(), format!("  {}", self.model_name(&model.model))];
+                record.extend(Self::token_cells(&model.tokens, model.cost_usd));
+                record.push(String::new());
+                builder.push_record(record);
+            }
+        }
+
+        let mut table = builder.build();
+        table.with(self.theme());
+        table.with(Modify::new(Columns::new(2..)).with(Alignment::right()));
+        localize_header(&mut table);
+        self.colorize(&mut table, Color::FG_BRIGHT_GREEN, Rows::new(0..1));
+
+        writeln!(out, " === {} ===", i18n::label("Top Days"))?;
+        writeln!(out, "{}", table)?;
+        Ok(())
+    }
+
+    pub fn top_days_json(&self, days: &[PeriodSummary], shares: &[f64]) -> Value {
+        let mut days = self.periods_json(PeriodKind::Day, days, true);
+        if let Value::Array(items) = &mut days {
+            for (item, share) in items.iter_mut().zip(shares) {
+                if let Value::Object(object) = item {
+                    for field in ["start", "end", "partial", "active_days"] {
+                        object.remove(field);
+                    }
+                    object.insert("share_percent".to_string(), Value::from(*share));
+                }
+            }
+        }
+        days
+    }
+
+    /// 用量最高的若干会话；会话按开始日期归属，share 为占所选范围内全部会话的百分比
+    pub fn render_top_sessions_table(
+        &self,
+        sessions: &[SessionSummary],
+        shares: &[f64],
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        if sessions.is_empty() {
+            writeln!(out, "{}", tr!("report.no_sessions"))?;
+            return Ok(());
+        }
+
+        let project_names = short_project_names(sessions.iter().filter_map(|session| session.project.as_deref()));
+        let mut builder = Builder::default();
+        builder.push_record(["#", "Session", "Date", "Project", "Model", "Total", "Cost", "Share"]);
+        for (rank, (session, share)) in sessions.iter().zip(shares).enumerate() {
+            builder.push_record([
+                (rank + 1).to_string(),
+                session.session_id.chars().take(8).collect(),
+                session
+                    .started_at
+                    .map(|started| started.with_timezone(&self.timezone).format("%Y-%m-%d").to_string())
+                    .unwrap_or_else(|| "—".to_string()),
+                session
+                    .project
+                    .as_ref()
+                    .and_then(|project| project_names.get(project).cloned())
+                    .unwrap_or_else(|| "—".to_string()),
+                session
+                    .dominant_model
+                    .as_deref()
+                    .map(|model| self.model_name(model))
+                    .unwrap_or_else(|| "—".to_string()),
+                UsageRow::format_number(session.aggregate.usage.total()),
+                format_cost(session.cost_usd),
+                format!("{:.1}%", share),
+            ]);
+        }
+
+        let mut table = builder.build();
+        table.with(self.theme());
+        table.with(Modify::new(Columns::new(5..)).with(Alignment::right()));
+        localize_header(&mut table);
+        self.colorize(&mut table, Color::FG_BRIGHT_GREEN, Rows::new(0..1));
+
+        writeln!(out, " === {} ===", i18n::label("Top Sessions"))?;
+        writeln!(out, "{}", table)?;
+        Ok(())
+    }
+
+    pub fn top_sessions_json(&self, sessions: &[SessionSummary], shares: &[f64]) -> Value {
+        #[derive(Serialize)]
+        struct TopSession<'a> {
+            session_id: &'a str,
+            date: Option<NaiveDate>,
+            project: Option<&'a str>,
+            dominant_model: Option<&'a str>,
+            total_tokens: u64,
+            cost_usd: Option<f64>,
+            share_percent: f64,
+        }
+
+        let sessions: Vec<TopSession> = sessions
+            .iter()
+            .zip(shares)
+            .map(|(session, share)| TopSession {
+                session_id: &session.session_id,
+                date: session.started_at.map(|started| started.with_timezone(&self.timezone).date_naive()),
+                project: session.project.as_deref(),
+                dominant_model: session.dominant_model.as_deref(),
+                total_tokens: session.aggregate.usage.total(),
+                cost_usd: session.cost_usd,
+                share_percent: *share,
+            })
+            .collect();
+        serde_json::to_value(sessions).unwrap_or(Value::Null)
+    }
+
+    /// 某一天按小时的用量，24 行加 Total 行
+    pub fn render_hourly_table(&self, date: NaiveDate, hours: &[HourUsage], out: &mut dyn Write) -> io::Result<()> {
+        let mut builder = Builder::default();
+        builder.push_record(["Hour", "Msgs", "Input", "Output", "Cache Create", "Cache Read", "Total", "Cost"]);
+        let mut total = TokenTotals::default();
+        let mut total_cost = None;
+        let mut total_messages = 0;
+        for hour in hours {
+            let mut record = vec![format!("{:02}:00", hour.hour), format_count(hour.messages)];
+            record.extend(Self::token_cells(&hour.tokens, hour.cost_usd));
+            builder.push_record(record);
+            total.merge(&hour.tokens);
+            total_messages += hour.messages;
+            if let Some(cost) = hour.cost_usd {
+                total_cost = Some(total_cost.unwrap_or(0.0) + cost);
+            }
+        }
+        let mut record = vec![i18n::label("Total").to_string(), format_count(total_messages)];
+        record.extend(Self::token_cells(&total, total_cost));
+        builder.push_record(record);
+
+        let mut table = builder.build();
+        table.with(self.theme());
+        table.with(Modify::new(Columns::new(1..)).with(Alignment::right()));
+        localize_header(&mut table);
+        self.colorize(&mut table, Color::FG_BRIGHT_GREEN, Rows::new(0..1));
+        self.colorize(&mut table, Color::FG_YELLOW, Rows::last());
+
+        writeln!(out, " === {} ===", tr!("report.title.hourly", date, self.timezone))?;
+        writeln!(out, "{}", table)?;
+        Ok(())
+    }
+
+    /// today 命令的简短概要，适合绑定到快捷键随时查看
+    pub fn render_today(&self, today: &TodaySummary, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "{}",
+            tr!(
+                "report.today",
+                today.date,
+                self.timezone,
+                format_count(today.total_tokens),
+                format_cost(today.cost_usd),
+                format_count(today.messages)
+            )
+        )?;
+        writeln!(out, "{}", tr!("report.today_sessions", today.sessions))?;
+        writeln!(
+            out,
+            "{}",
+            tr!(
+                "report.today_vs_yesterday",
+                format_change(today.total_tokens as f64, today.yesterday_total_tokens as f64),
+                format!("{:02}", today.hour),
+                format_count(today.yesterday_total_tokens),
+                format_cost(today.yesterday_cost_usd)
+            )
+        )?;
+        writeln!(
+            out,
+            "{}",
+            tr!(
+                "report.today_month",
+                today.month_start,
+                format_count(today.month_total_tokens),
+                format_cost(today.month_cost_usd)
+            )
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use crate::limit::{LimitStatus, LimitWindow};
+    use super::*;
+
+    /// 把 render 写入的内容作为字符串返回
+    fn rendered(render: impl FnOnce(&mut dyn Write) -> io::Result<()>) -> String {
+        let mut out = Vec::new();
+        render(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn print_json_without_meta_writes_the_value() {
+        let json = rendered(|out| TableRenderer::new().print_json(&serde_json::json!({ "days": 3 }), out));
+        assert_eq!(json, "{\n  \"days\": 3\n}\n");
+    }
+
+    #[test]
+    fn empty_session_table_is_a_single_line() {
+        let text = rendered(|out| TableRenderer::new().render_session_table(&[], out));
+        assert_eq!(text, format!("{}\n", tr!("report.no_sessions")));
+    }
+
+    #[test]
+    fn color_choice_controls_all_escape_codes() {
+        let hours = [HourOfDay { hour: 9, messages: 1, total_tokens: 10, cost_usd: None, share_percent: 100.0 }];
+        let render = |color: ColorChoice| rendered(|out| TableRenderer::new().with_color(color).render_hour_of_day_table(&hours, out));
+        assert!(!render(ColorChoice::Never).contains('\x1b'));
+        assert!(render(ColorChoice::Always).contains('\x1b'));
+    }
+
+    #[test]
+    fn limit_status_goes_to_the_given_writer() {
+        let today = NaiveDate::from_ymd_opt(2025, 9, 15).unwrap();
+        let status = LimitStatus::evaluate(&[], 1000, LimitWindow::Rolling(7), 1, today);
+        let text = rendered(|out| TableRenderer::new().render_limit_status(&status, out));
+        assert!(text.starts_with(&format!("{} (since 2025-09-09) usage 0 / 1,000", status.window)), "{}", text);
+        assert_eq!(text.lines().count(), 1);
+    }
+
+    #[test]
+    fn weekday_column_uses_the_localized_abbreviation() {
+        let saturday = NaiveDate::from_ymd_opt(2025, 9, 13).unwrap();
+        let expected = weekday_abbreviation(chrono::Weekday::Sat);
+        let usage = DailyUsage {
+            date: "2025-09-13".to_string(),
+            model: "claude-sonnet-4-20250514".to_string(),
+            usage: UsageAggregate {
+                usage: crate::item::Usage { output_tokens: Some(10), ..Default::default() },
+                messages: 1,
+                ..UsageAggregate::default()
+            },
+        };
+        let rows = vec![GroupUsage { group: usage.model.clone(), date: usage.date.clone(), usage: usage.usage.clone() }];
+        let report = Report::new(GroupBy::Model, rows, std::slice::from_ref(&usage), saturday);
+        let renderer = TableRenderer::new().with_weekday(true);
+
+        let csv = rendered(|out| CsvRenderer(&renderer).render(&report, out));
+        assert!(csv.lines().next().unwrap().starts_with("date,weekday,model,"), "{}", csv);
+        assert!(csv.lines().nth(1).unwrap().starts_with(&format!("2025-09-13,{},", expected)), "{}", csv);
+        assert_eq!(renderer.date_label("2025-09-13"), format!("{} 2025-09-13", expected));
+        assert_eq!(renderer.date_label("(unknown)"), "(unknown)");
+    }
+
+    #[test]
+    fn activity_column_shows_the_first_and_last_time() {
+        let line = UsageLine {
+            date: "2025-09-10".to_string(),
+            first_seen: Some("2025-09-10T09:12:59Z".parse().unwrap()),
+            last_seen: Some("2025-09-10T18:47:00Z".parse().unwrap()),
+            ..UsageLine::default()
+        };
+        assert_eq!(UsageRow::from_line(&line, &Tz::UTC).active, "09:12–18:47");
+        assert_eq!(UsageRow::from_line(&line, &Tz::Asia__Kolkata).active, "14:42–00:17");
+        let undated = UsageLine { first_seen: None, last_seen: None, ..line };
+        assert_eq!(UsageRow::from_line(&undated, &Tz::UTC).active, "—");
+    }
+
+    /// 一天中一个模型的用量
+    fn daily(date: &str, model: &str, input_tokens: u64, output_tokens: u64) -> DailyUsage {
+        DailyUsage {
+            date: date.to_string(),
+            model: model.to_string(),
+            usage: UsageAggregate {
+                usage: crate::item::Usage {
+                    input_tokens: Some(input_tokens),
+                    output_tokens: Some(output_tokens),
+                    ..Default::default()
+                },
+                messages: 1,
+                ..UsageAggregate::default()
+            },
+        }
+    }
+
+    /// 按模型分组的报告
+    fn model_report(usage: &[DailyUsage]) -> Report {
+        let rows = usage
+            .iter()
+            .map(|daily| GroupUsage { group: daily.model.clone(), date: daily.date.clone(), usage: daily.usage.clone() })
+            .collect();
+        Report::new(GroupBy::Model, rows, usage, NaiveDate::from_ymd_opt(2025, 9, 30).unwrap())
+    }
+
+    #[test]
+    fn snapshots_of_one_model_share_a_line_unless_split() {
+        let usage = [
+            daily("2025-09-10", "claude-sonnet-4-20250514", 100, 10),
+            daily("2025-09-10", "claude-sonnet-4-20250601", 200, 20),
+            daily("2025-09-10", "claude-opus-4-1-20250805", 1, 1),
+        ];
+        let report = model_report(&usage);
+        let lines = |renderer: &TableRenderer| {
+            let names = renderer.display_names(report.rows.iter().map(|row| row.group.as_str()));
+            report.lines(|name| names[name].clone())
+        };
+
+        let merged = lines(&TableRenderer::new());
+        let split = lines(&TableRenderer::new().with_split_snapshots(true));
+
+        assert_eq!(merged.len(), 2);
+        let sonnet = merged.iter().find(|line| line.group == TableRenderer::new().model_name(&usage[0].model)).unwrap();
+        assert_eq!((sonnet.input_tokens, sonnet.output_tokens, sonnet.messages), (Some(300), Some(30), 2));
+        assert_eq!(split.len(), 3);
+        assert!(split.iter().any(|line| line.group.ends_with("(20250514)")));
+        assert!(split.iter().any(|line| line.group.ends_with("(20250601)")));
+        // 合计与是否拆分无关
+        let total = |lines: &[UsageLine]| lines.iter().map(|line| line.total_tokens).sum::<u64>();
+        assert_eq!(total(&merged), total(&split));
+        assert_eq!(total(&merged), report.totals.tokens.total_tokens);
+    }
+
+    /// 去掉 ANSI 转义序列（着色）
+    fn plain(text: &str) -> String {
+        let mut plain = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '\u{1b}' {
+                chars.by_ref().find(|c| c.is_ascii_alphabetic());
+            } else {
+                plain.push(c);
+            }
+        }
+        plain
+    }
+
+    #[test]
+    fn vertical_layout_follows_the_color_choice() {
+        let usage = [daily("2025-09-10", "claude-sonnet-4-20250514", 100, 10)];
+        let render = |color: ColorChoice| {
+            let renderer = TableRenderer::new().with_layout(Layout::Vertical).with_color(color);
+            rendered(|out| renderer.render(&model_report(&usage), out))
+        };
+        let never = render(ColorChoice::Never);
+        assert!(!never.contains('\x1b'), "{}", never);
+        let always = render(ColorChoice::Always);
+        assert!(always.contains('\x1b'));
+        assert_eq!(plain(&always), never);
+    }
+
+    #[test]
+    fn merged_cells_stay_aligned_at_a_narrow_width() {
+        let usage = [
+            daily("2025-09-10", "claude-opus-4-1-20250805", 1_111, 2_222),
+            daily("2025-09-10", "claude-sonnet-4-20250514", 3_333_333, 4_444_444),
+        ];
+        let renderer = TableRenderer::new()
+            .with_merged_cells(true)
+            .with_full_model_names(true)
+            .with_layout(Layout::Table)
+            .with_width(Some(56));
+        let table = plain(&rendered(|out| renderer.render(&model_report(&usage), out)));
+
+        // 合并的一行中每个模型与它的数值在同一行
+        let body: Vec<Vec<&str>> = table
+            .lines()
+            .skip_while(|line| !line.starts_with('├'))
+            .skip(1)
+            .take_while(|line| !line.starts_with('├'))
+            .map(|line| line.split('│').map(str::trim).collect())
+            .collect();
+        let header: Vec<&str> = table.lines().find(|line| line.contains("Date")).unwrap().split('│').map(str::trim).collect();
+        let column = |name: &str| header.iter().position(|header| *header == name).unwrap();
+        let cells: Vec<(&str, &str)> = body.iter().map(|row| (row[column("Model")], row[column("Input")])).collect();
+        assert_eq!(cells.len(), 2, "{}", table);
+        assert!(cells[0].0.ends_with("0805") && cells[0].1 == "1.1K", "{}", table);
+        assert!(cells[1].0.ends_with("0514") && cells[1].1 == "3.3M", "{}", table);
+        assert!(table.lines().filter(|line| line.starts_with(['┌', '│', '└'])).all(|line| line.chars().count() == 56), "{}", table);
+    }
+
+    #[test]
+    fn wrapped_sub_rows_are_padded_to_the_same_height() {
+        let line = UsageLine {
+            date: "2025-09-10".to_string(),
+            group: "claude-sonnet".to_string(),
+            first_seen: Some("2025-09-10T09:00:00Z".parse().unwrap()),
+            last_seen: Some("2025-09-10T17:30:00Z".parse().unwrap()),
+            input_tokens: Some(1_000),
+            total_tokens: 1_000,
+            ..UsageLine::default()
+        };
+        let mut row = UsageRow::from_line(&line, &Tz::UTC);
+        let layout = UsageLayout {
+            widths: [("Model", 9), ("Active", 5), ("Input", 6), ("Total", 6)]
+                .into_iter()
+                .map(|(header, width)| (header.to_string(), width))
+                .collect(),
+            hidden: Vec::new(),
+        };
+        TableRenderer::new().with_activity_column(true).wrap_sub_row(&mut row, &layout);
+
+        let heights: Vec<usize> = [&row.model, &row.active, &row.input_tokens, &row.total_tokens]
+            .iter()
+            .map(|field| field.split('\n').count())
+            .collect();
+        assert!(heights[0] > 1 && heights[1] > 1, "{:?}", heights);
+        assert!(heights.iter().all(|height| *height == heights[0]), "{:?}", heights);
+        assert_eq!(row.model.split('\n').collect::<String>(), "claude-sonnet");
+        assert_eq!(row.input_tokens.lines().next(), Some("1.0K"));
+        // 不在布局中的列不补齐
+        assert!(!row.output_tokens.contains('\n'));
+    }
+
+    #[test]
+    fn narrow_widths_hide_cache_columns_instead_of_wrapping() {
+        let mut usage = [
+            daily("2025-09-10", "claude-opus-4-1-20250805", 1_111, 2_222),
+            daily("2025-09-10", "claude-sonnet-4-20250514", 3_333_333, 4_444_444),
+        ];
+        for day in &mut usage {
+            day.usage.usage.cache_creation_input_tokens = Some(55_555);
+            day.usage.usage.cache_read_input_tokens = Some(666_666);
+        }
+        let report = model_report(&usage);
+        for (width, hidden) in [(60, vec!["Cache Create", "Cache Read"]), (80, vec![]), (120, vec![])] {
+            let renderer = TableRenderer::new().with_layout(Layout::Table).with_width(Some(width));
+            let table = plain(&rendered(|out| renderer.render(&report, out)));
+            let header = table.lines().find(|line| line.contains("Date")).unwrap();
+            let headers: Vec<&str> = header.split('│').map(str::trim).filter(|cell| !cell.is_empty()).collect();
+
+            let mut expected = vec!["Date", "Model", "Input", "Output", "Cache Create", "Cache Read", "Total"];
+            expected.retain(|column| !hidden.contains(column));
+            // 较窄的表头可以折行，第一行是表头的开头
+            assert_eq!(headers.len(), expected.len(), "{}", table);
+            assert!(headers.iter().zip(&expected).all(|(header, column)| column.starts_with(header)), "{}", table);
+            // 每个模型一行，没有折行
+            let body = table.lines().skip_while(|line| !line.starts_with('├')).skip(1).take_while(|line| !line.starts_with('├'));
+            assert_eq!(body.count(), 2, "{}", table);
+            assert!(table.lines().filter(|line| line.starts_with(['┌', '│', '└'])).all(|line| line.chars().count() == width), "{}", table);
+            assert_eq!(table.contains(&tr!("report.hidden_columns", hidden.join(", "))), !hidden.is_empty(), "{}", table);
+        }
+    }
+
+    #[test]
+    fn week_column_matches_the_weekly_labels() {
+        for day in ["2025-09-10", "2025-12-29", "2027-01-01"] {
+            let (label, ..) = crate::period::week_of(chrono::Weekday::Mon)(day.parse().unwrap());
+            assert!(label.ends_with(&format!("-{}", UsageRow::format_week(day))), "{} {}", day, label);
+        }
+        assert_eq!(UsageRow::format_week("2025-12-29"), "W01");
+        assert_eq!(UsageRow::format_week("Total"), "");
+
+        let usage = [daily("2025-12-29", "claude-sonnet-4-20250514", 100, 10)];
+        let renderer = TableRenderer::new().with_week_number(true);
+        let csv = rendered(|out| CsvRenderer(&renderer).render(&model_report(&usage), out));
+        assert!(csv.lines().next().unwrap().starts_with("date,iso_week,model,"), "{}", csv);
+        assert!(csv.lines().nth(1).unwrap().starts_with("2025-12-29,2026-W01,"), "{}", csv);
+    }
+
+    #[test]
+    fn forced_widths_are_filled_exactly() {
+        let usage = [
+            daily("2025-09-10", "claude-opus-4-1-20250805", 1_111, 2_222),
+            daily("2025-09-11", "claude-sonnet-4-20250514", 3_333_333, 4_444_444),
+        ];
+        let report = model_report(&usage);
+        // (输出宽度, 是否显示 Msgs 列, 各列内容的宽度)：余数从左起每列多分 1 格
+        let cases = [
+            (100, false, vec![17, 17, 17, 17, 16]),
+            (100, true, vec![14, 14, 14, 13, 13, 13]),
+            (150, false, vec![27, 27, 27, 27, 26]),
+            // 平均分配时 Date 不足最小宽度：各列先取最小宽度，剩余的再平均分配
+            (61, false, vec![13, 11, 7, 7, 7]),
+        ];
+        for (width, messages, expected) in cases {
+            let renderer = TableRenderer::new()
+                .with_layout(Layout::Table)
+                .with_message_column(messages)
+                .with_width(Some(width));
+            let table = plain(&rendered(|out| renderer.render(&report, out)));
+            let borders: Vec<&str> = table.lines().filter(|line| line.starts_with(['┌', '├', '└'])).collect();
+            assert!(table.lines().filter(|line| line.starts_with(['┌', '│', '├', '└'])).all(|line| line.chars().count() == width), "{}", table);
+            // 去掉左右边框与每列两侧的内边距
+            let columns: Vec<usize> = borders[0]
+                .trim_matches(['┌', '┐'])
+                .split('┬')
+                .map(|column| column.chars().count() - 2)
+                .collect();
+            assert_eq!(columns, expected, "{} {}\n{}", width, messages, table);
+        }
+    }
+}
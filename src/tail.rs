@@ -0,0 +1,148 @@
+//! cccost tail：像 tail -f 一样跟踪一个会话的日志文件，逐条输出新写入的带用量的记录及会话累计的用量。
+//!
+//! 每次从上次读到的位置继续读取，只解析完整的行；Claude Code 正在写入的最后一行没有换行，留到下一次读取时再解析。
+//! 文件比上次读到的位置短时（被截断或重写）从头读取，合计也重新计算
+
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use crate::item::Usage;
+use crate::parse::{parse_line, ParseOptions};
+use crate::pricing::cost_for;
+
+/// 文件开头的 UTF-8 BOM
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// 一条新写入的带用量的记录
+#[derive(Debug, Clone)]
+pub struct TailMessage {
+    /// 记录的时间，无法解析时为 None
+    pub timestamp: Option<DateTime<Utc>>,
+    /// 模型名称
+    pub model: String,
+    /// 该条记录的 token 用量
+    pub usage: Usage,
+    /// 该条记录的估算费用，未知模型为 None
+    pub cost_usd: Option<f64>,
+    /// 会话到该条记录为止的 token 合计
+    pub session_tokens: u64,
+    /// 会话到该条记录为止的估算费用合计
+    pub session_cost_usd: Option<f64>,
+}
+
+/// 会话到目前为止的合计
+#[derive(Debug, Clone, Default)]
+pub struct TailTotals {
+    /// 带用量的记录数
+    pub messages: u64,
+    /// token 用量合计
+    pub usage: Usage,
+    /// 有价格的模型的估算费用合计
+    pub cost_usd: Option<f64>,
+    /// 最早一条记录的时间
+    pub started_at: Option<DateTime<Utc>>,
+    /// 最晚一条记录的时间
+    pub ended_at: Option<DateTime<Utc>>,
+    /// 使用过的模型
+    pub models: BTreeSet<String>,
+}
+
+impl TailTotals {
+    /// 最早与最晚一条记录的时间差
+    pub fn duration(&self) -> Option<chrono::TimeDelta> {
+        Some(self.ended_at? - self.started_at?)
+    }
+
+    fn add(&mut self, message: &TailMessage) {
+        self.messages += 1;
+        self.usage += &message.usage;
+        if let Some(cost) = message.cost_usd {
+            *self.cost_usd.get_or_insert(0.0) += cost;
+        }
+        if let Some(timestamp) = message.timestamp {
+            self.started_at = Some(self.started_at.map_or(timestamp, |started| started.min(timestamp)));
+            self.ended_at = Some(self.ended_at.map_or(timestamp, |ended| ended.max(timestamp)));
+        }
+        self.models.insert(message.model.clone());
+    }
+}
+
+/// 跟踪中的会话文件
+pub struct SessionTail {
+    path: PathBuf,
+    // 已读取的字节数
+    offset: u64,
+    // 已读取但尚未以换行结束的内容
+    partial: Vec<u8>,
+    totals: TailTotals,
+}
+
+impl SessionTail {
+    /// 从文件开头开始跟踪，第一次 poll 返回文件中已有的全部记录
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            offset: 0,
+            partial: Vec::new(),
+            totals: TailTotals::default(),
+        }
+    }
+
+    /// 跟踪的文件
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 到目前为止的合计
+    pub fn totals(&self) -> &TailTotals {
+        &self.totals
+    }
+
+    /// 读取上次之后写入的完整行，返回其中带用量的记录并计入合计；不是 JSON 或没有用量的行被忽略
+    pub fn poll(&mut self) -> io::Result<Vec<TailMessage>> {
+        let mut file = File::open(&self.path)?;
+        if file.metadata()?.len() < self.offset {
+            self.offset = 0;
+            self.partial.clear();
+            self.totals = TailTotals::default();
+        }
+        // 尚未结束的行之前的内容都已解析，两者长度相同说明它从文件开头开始
+        let from_beginning = self.partial.len() as u64 == self.offset;
+        file.seek(SeekFrom::Start(self.offset))?;
+        self.offset += file.read_to_end(&mut self.partial)? as u64;
+
+        let Some(end) = self.partial.iter().rposition(|&byte| byte == b'\n') else {
+            return Ok(Vec::new());
+        };
+        let rest = self.partial.split_off(end + 1);
+        let complete = std::mem::replace(&mut self.partial, rest);
+        let complete = if from_beginning { complete.strip_prefix(UTF8_BOM).unwrap_or(&complete) } else { &complete };
+
+        let options = ParseOptions::default();
+        let mut messages = Vec::new();
+        for line in complete.split(|&byte| byte == b'\n') {
+            let Ok(Some(item)) = parse_line(&String::from_utf8_lossy(line), &options) else {
+                continue;
+            };
+            let timestamp = item.parsed_timestamp();
+            let Some(usage) = item.usage else {
+                continue;
+            };
+            let mut message = TailMessage {
+                timestamp,
+                cost_usd: cost_for(&item.model, &usage),
+                model: item.model,
+                usage,
+                session_tokens: 0,
+                session_cost_usd: None,
+            };
+            self.totals.add(&message);
+            message.session_tokens = self.totals.usage.total();
+            message.session_cost_usd = self.totals.cost_usd;
+            messages.push(message);
+        }
+        Ok(messages)
+    }
+}
@@ -0,0 +1,82 @@
+//! 所选范围内每日用量的趋势
+
+use std::collections::BTreeMap;
+use chrono::{Days, NaiveDate};
+use serde::Serialize;
+use crate::item::DailyUsage;
+
+/// 所选范围内每日 token 用量的趋势
+#[derive(Debug, Clone, Serialize)]
+pub struct Trend {
+    /// 区间的第一天
+    pub start: NaiveDate,
+    /// 区间的最后一天
+    pub end: NaiveDate,
+    /// 区间的天数
+    pub days: u64,
+    /// 区间内的 token 总量
+    pub total_tokens: u64,
+    /// 区间内的日均 token 数
+    pub average_daily_tokens: f64,
+    /// 紧邻的上一个等长区间的日均用量
+    pub previous_start: NaiveDate,
+    /// 上一个区间的最后一天
+    pub previous_end: NaiveDate,
+    /// 上一个区间的日均 token 数
+    pub previous_average_daily_tokens: f64,
+    /// 每日总量的最小二乘斜率（tokens/天），没有记录的日期按 0 计入
+    pub slope_per_day: f64,
+    /// 拟合直线在区间末尾的值相对日均用量的变化百分比，日均为 0 时为 None
+    pub change_percent: Option<f64>,
+}
+
+impl Trend {
+    /// 计算 [start, end] 的趋势；usage 需包含上一个等长区间的数据
+    pub fn compute(usage: &[DailyUsage], start: NaiveDate, end: NaiveDate) -> Option<Self> {
+        if start > end {
+            return None;
+        }
+        let mut daily: BTreeMap<NaiveDate, u64> = BTreeMap::new();
+        for DailyUsage { date, usage: aggregate, .. } in usage {
+            if let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                *daily.entry(date).or_default() += aggregate.usage.total();
+            }
+        }
+
+        let days = (end - start).num_days() as u64 + 1;
+        let previous_end = start.pred_opt()?;
+        let previous_start = previous_end.checked_sub_days(Days::new(days - 1))?;
+        let total_tokens: u64 = daily.range(start..=end).map(|(_, tokens)| tokens).sum();
+        let previous_total: u64 = daily.range(previous_start..=previous_end).map(|(_, tokens)| tokens).sum();
+        let average = total_tokens as f64 / days as f64;
+
+        // x 为距起始日的天数，缺失的日期 y 为 0
+        let mean_x = (days - 1) as f64 / 2.0;
+        let (mut covariance, mut variance) = (0.0, 0.0);
+        for (offset, date) in start.iter_days().take(days as usize).enumerate() {
+            let dx = offset as f64 - mean_x;
+            let y = daily.get(&date).copied().unwrap_or(0) as f64;
+            covariance += dx * (y - average);
+            variance += dx * dx;
+        }
+        let slope = if variance > 0.0 { covariance / variance } else { 0.0 };
+
+        Some(Self {
+            start,
+            end,
+            days,
+            total_tokens,
+            average_daily_tokens: average,
+            previous_start,
+            previous_end,
+            previous_average_daily_tokens: previous_total as f64 / days as f64,
+            slope_per_day: slope,
+            // 拟合直线经过区间中点的日均值，末尾比中点高 slope * (days - 1) / 2；
+            // 用量不会为负，拟合值低于 0 时按 0 计，下降最多 100%
+            change_percent: (average > 0.0).then(|| {
+                let end_value = (average + slope * (days - 1) as f64 / 2.0).max(0.0);
+                (end_value / average - 1.0) * 100.0
+            }),
+        })
+    }
+}
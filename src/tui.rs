@@ -0,0 +1,371 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::ops::RangeFrom;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use chrono::{Local, NaiveDate};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::Text;
+use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState, Tabs};
+use ratatui::{DefaultTerminal, Frame};
+use crate::bucketing;
+use crate::file_processor::ProcessOutput;
+use crate::i18n;
+use crate::item::{DailyUsage, SessionSummary, UsageAggregate};
+use crate::period::{self, PeriodSummary};
+use crate::project::short_project_names;
+use crate::table_renderer::{display_width, format_cost, format_count, format_duration, TableRenderer};
+use crate::tr;
+
+/// 没有输入时检查自动刷新的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+const TOKEN_HEADERS: [&str; 6] = ["Input", "Output", "Cache Create", "Cache Read", "Total", "Cost"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum View {
+    Daily,
+    Monthly,
+    Models,
+}
+
+impl View {
+    const ALL: [View; 3] = [View::Daily, View::Monthly, View::Models];
+
+    fn title(self) -> &'static str {
+        match self {
+            View::Daily => i18n::label("Daily"),
+            View::Monthly => i18n::label("Monthly"),
+            View::Models => i18n::label("By Model"),
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            View::Daily => View::Monthly,
+            View::Monthly => View::Models,
+            View::Models => View::Daily,
+        }
+    }
+}
+
+/// 界面状态；表格内容在每次绘制时由 output 和筛选条件重新计算
+struct App<'a> {
+    renderer: &'a TableRenderer,
+    output: ProcessOutput,
+    billing_day: u32,
+    view: View,
+    table: TableState,
+    /// 正在查看会话的日期
+    day: Option<NaiveDate>,
+    filter: String,
+    editing_filter: bool,
+    status: String,
+}
+
+impl App<'_> {
+    /// 按筛选条件得到的 (模型, 日期) 使用量：模型名或项目路径包含筛选文本（不区分大小写）的记录
+    fn usage(&self) -> Vec<DailyUsage> {
+        if self.filter.is_empty() {
+            return self.output.usage.clone();
+        }
+        let filter = self.filter.to_lowercase();
+        let mut usage: BTreeMap<(String, String), UsageAggregate> = BTreeMap::new();
+        for project_usage in &self.output.project_models {
+            if !project_usage.project.to_lowercase().contains(&filter) && !project_usage.model.to_lowercase().contains(&filter) {
+                continue;
+            }
+            let entry = usage.entry((project_usage.model.clone(), project_usage.date.clone())).or_default();
+            *entry += &project_usage.aggregate;
+        }
+        usage
+            .into_iter()
+            .map(|((model, date), usage)| DailyUsage { date, model, usage })
+            .collect()
+    }
+
+    /// 在 day 当天有活动且符合筛选条件的会话，按开始时间排列
+    fn sessions(&self, day: NaiveDate) -> Vec<&SessionSummary> {
+        let filter = self.filter.to_lowercase();
+        let mut sessions: Vec<&SessionSummary> = self
+            .output
+            .sessions
+            .iter()
+            .filter(|session| {
+                let tz = &self.output.timezone;
+                session.started_at.is_some_and(|started| started.with_timezone(tz).date_naive() <= day)
+                    && session.ended_at.is_some_and(|ended| ended.with_timezone(tz).date_naive() >= day)
+            })
+            .filter(|session| {
+                filter.is_empty()
+                    || session.project.as_ref().is_some_and(|project| project.to_lowercase().contains(&filter))
+                    || session.models.iter().any(|model| model.to_lowercase().contains(&filter))
+            })
+            .collect();
+        sessions.sort_by_key(|session| session.started_at);
+        sessions
+    }
+
+    fn periods(&self) -> Vec<PeriodSummary> {
+        let today = bucketing::today(&self.output.timezone);
+        match self.view {
+            View::Monthly => period::summarize_periods(&self.usage(), period::billing_month_of(self.billing_day), today),
+            _ => period::summarize_periods(&self.usage(), period::day_of, today),
+        }
+    }
+
+    /// 当前表格的表头和各行
+    fn rows(&self) -> (Vec<String>, Vec<Vec<String>>) {
+        if let Some(day) = self.day {
+            let sessions = self.sessions(day);
+            let project_names = short_project_names(sessions.iter().filter_map(|session| session.project.as_deref()));
+            let header = ["Session", "Start", "Duration", "Project", "Models", "Total", "Cost"];
+            let rows = sessions
+                .iter()
+                .map(|session| {
+                    let mut models: Vec<String> = session.models.iter().map(|model| self.renderer.model_name(model)).collect();
+                    models.dedup();
+                    vec![
+                        session.session_id.chars().take(8).collect(),
+                        session
+                            .started_at
+                            .map(|started| started.with_timezone(&self.output.timezone).format("%H:%M").to_string())
+                            .unwrap_or_else(|| "—".to_string()),
+                        session.duration().map(format_duration).unwrap_or_else(|| "—".to_string()),
+                        session
+                            .project
+                            .as_ref()
+                            .and_then(|project| project_names.get(project).cloned())
+                            .unwrap_or_else(|| "—".to_string()),
+                        models.join(", "),
+                        format_count(session.aggregate.usage.total()),
+                        format_cost(session.cost_usd),
+                    ]
+                })
+                .collect();
+            return (header.map(|name| i18n::label(name).to_string()).to_vec(), rows);
+        }
+
+        let mut header = vec![i18n::label(match self.view {
+            View::Daily => "Date",
+            View::Monthly => "Month",
+            View::Models => "Model",
+        })
+        .to_string()];
+        header.push(i18n::label("Days").to_string());
+        header.extend(TOKEN_HEADERS.map(|name| i18n::label(name).to_string()));
+        let rows = match self.view {
+            View::Models => self
+                .renderer
+                .model_totals(&self.usage())
+                .into_iter()
+                .map(|total| {
+                    let mut row = vec![total.model, format_count(total.active_days)];
+                    row.extend(TableRenderer::token_cells(&total.tokens, total.cost_usd));
+                    row
+                })
+                .collect(),
+            View::Daily | View::Monthly => self
+                .periods()
+                .into_iter()
+                .map(|period| {
+                    let mut row = vec![period.label, format_count(period.active_days)];
+                    row.extend(TableRenderer::token_cells(&period.tokens, period.cost_usd));
+                    row
+                })
+                .collect(),
+        };
+        (header, rows)
+    }
+
+    /// 右对齐的数值列，与表格输出一致
+    fn numeric_columns(&self) -> RangeFrom<usize> {
+        if self.day.is_some() { 5.. } else { 1.. }
+    }
+
+    /// 切换视图或数据变化后选中最后一行（最近的日期、月份）
+    fn select_last(&mut self) {
+        let count = self.rows().1.len();
+        self.table.select(count.checked_sub(1));
+    }
+
+    fn selected_day(&self) -> Option<NaiveDate> {
+        let (_, rows) = self.rows();
+        let label = rows.get(self.table.selected()?)?.first()?;
+        NaiveDate::parse_from_str(label, "%Y-%m-%d").ok()
+    }
+
+    fn reload(&mut self, reload: &mut impl FnMut() -> Result<ProcessOutput, String>) {
+        match reload() {
+            Ok(output) => {
+                self.output = output;
+                self.status = tr!("tui.refreshed", Local::now().format("%H:%M:%S"));
+            }
+            Err(e) => self.status = tr!("tui.refresh_failed", e),
+        }
+        // 保留选中的位置，行数减少时退到最后一行
+        let count = self.rows().1.len();
+        if self.table.selected().is_none_or(|selected| selected >= count) {
+            self.table.select(count.checked_sub(1));
+        }
+    }
+
+    /// 处理一次按键，返回是否退出
+    fn handle_key(&mut self, key: KeyEvent, reload: &mut impl FnMut() -> Result<ProcessOutput, String>) -> bool {
+        // 原始模式下 Ctrl-C 不会产生信号，而是作为按键送达
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return true;
+        }
+        if self.editing_filter {
+            match key.code {
+                KeyCode::Enter => self.editing_filter = false,
+                KeyCode::Esc => {
+                    self.editing_filter = false;
+                    self.filter.clear();
+                }
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Char(c) => self.filter.push(c),
+                _ => return false,
+            }
+            self.select_last();
+            return false;
+        }
+        match key.code {
+            KeyCode::Char('q') => return true,
+            KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+            KeyCode::PageDown => self.table.scroll_down_by(10),
+            KeyCode::PageUp => self.table.scroll_up_by(10),
+            KeyCode::Home => self.table.select_first(),
+            KeyCode::End => self.select_last(),
+            KeyCode::Tab if self.day.is_none() => {
+                self.view = self.view.next();
+                self.select_last();
+            }
+            KeyCode::Enter if self.view == View::Daily && self.day.is_none() => {
+                if let Some(day) = self.selected_day() {
+                    self.day = Some(day);
+                    self.table.select(Some(0));
+                }
+            }
+            KeyCode::Esc | KeyCode::Backspace if self.day.is_some() => {
+                self.day = None;
+                self.select_last();
+            }
+            KeyCode::Char('/') => self.editing_filter = true,
+            KeyCode::Char('r') => self.reload(reload),
+            _ => {}
+        }
+        false
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [tabs_area, table_area, footer_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+
+        let tabs = Tabs::new(View::ALL.map(View::title))
+            .select(View::ALL.iter().position(|view| *view == self.view))
+            .highlight_style(Style::new().bold().reversed());
+        frame.render_widget(tabs, tabs_area);
+
+        let (header, rows) = self.rows();
+        let widths: Vec<Constraint> = (0..header.len())
+            .map(|column| {
+                let width = rows
+                    .iter()
+                    .map(|row| row.get(column).map_or(0, |cell| display_width(cell)))
+                    .chain([display_width(&header[column])])
+                    .max()
+                    .unwrap_or(0);
+                Constraint::Length(width as u16)
+            })
+            .collect();
+        let title = match self.day {
+            Some(day) => tr!("tui.sessions_title", day, self.output.timezone),
+            None => format!(" {} ", self.view.title()),
+        };
+        let numeric = self.numeric_columns();
+        let rows = rows.into_iter().map(|row| {
+            Row::new(row.into_iter().enumerate().map(|(column, cell)| {
+                let text = Text::from(cell);
+                Cell::from(if numeric.contains(&column) { text.right_aligned() } else { text })
+            }))
+        });
+        let table = Table::new(rows, widths)
+            .header(Row::new(header).style(Style::new().bold().green()))
+            .block(Block::new().borders(Borders::ALL).title(title))
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, table_area, &mut self.table);
+
+        let footer = if self.editing_filter {
+            tr!("tui.filter_editing", self.filter)
+        } else {
+            let filter = if self.filter.is_empty() {
+                String::new()
+            } else {
+                tr!("tui.filter", self.filter)
+            };
+            tr!("tui.help", filter, self.status)
+        };
+        frame.render_widget(Paragraph::new(footer).dim(), footer_area);
+    }
+}
+
+/// 运行交互式界面直到按 q 或 Ctrl-C 退出，或 interrupted 被置位（如收到 SIGINT）。
+/// refresh 为 Some 时按该间隔自动调用 reload 重新扫描。
+/// 终端的原始模式和备用屏幕在退出、出错和 panic 时都会恢复
+pub fn run(
+    output: ProcessOutput,
+    renderer: &TableRenderer,
+    billing_day: u32,
+    refresh: Option<Duration>,
+    interrupted: &AtomicBool,
+    mut reload: impl FnMut() -> Result<ProcessOutput, String>,
+) -> io::Result<()> {
+    let mut app = App {
+        renderer,
+        output,
+        billing_day,
+        view: View::Daily,
+        table: TableState::default(),
+        day: None,
+        filter: String::new(),
+        editing_filter: false,
+        status: String::new(),
+    };
+    app.select_last();
+
+    // ratatui::try_init 同时安装在 panic 时恢复终端的钩子
+    let mut terminal = ratatui::try_init()?;
+    let result = event_loop(&mut terminal, &mut app, refresh, interrupted, &mut reload);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    app: &mut App,
+    refresh: Option<Duration>,
+    interrupted: &AtomicBool,
+    reload: &mut impl FnMut() -> Result<ProcessOutput, String>,
+) -> io::Result<()> {
+    let mut last_refresh = Instant::now();
+    while !interrupted.load(Ordering::Relaxed) {
+        terminal.draw(|frame| app.draw(frame))?;
+        if event::poll(POLL_INTERVAL)?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+            && app.handle_key(key, reload) {
+            return Ok(());
+        }
+        if refresh.is_some_and(|interval| last_refresh.elapsed() >= interval) {
+            app.reload(reload);
+            last_refresh = Instant::now();
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,199 @@
+//! 以子进程运行 cccost，检查输出与各退出码。每个测试使用自己的 HOME，
+//! 日志、配置和缓存都在临时目录中，不读取本机的数据
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+use serde_json::Value;
+use tempfile::TempDir;
+
+const MODEL: &str = "claude-sonnet-4-20250514";
+
+/// 临时的 HOME，~/.claude/projects/-home-dev-app 中有一个会话，今天 1000 个输入与 500000 个输出 token
+fn home() -> TempDir {
+    let home = tempfile::tempdir().unwrap();
+    let project = home.path().join(".claude/projects/-home-dev-app");
+    fs::create_dir_all(&project).unwrap();
+    let record = format!(
+        r#"{{"type":"assistant","timestamp":"{}","sessionId":"s1","cwd":"/home/dev/app","message":{{"id":"msg_1","model":"{}","usage":{{"input_tokens":1000,"output_tokens":500000}}}}}}"#,
+        chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
+        MODEL
+    );
+    fs::write(project.join("s1.jsonl"), format!("{}\n", record)).unwrap();
+    home
+}
+
+/// 在 home 中运行 cccost 的命令，界面语言固定为英文
+fn cccost(home: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_cccost"));
+    command
+        .env("HOME", home)
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("XDG_CACHE_HOME")
+        .env_remove("XDG_DATA_HOME")
+        .env_remove("RUST_LOG")
+        .args(["--lang", "en"]);
+    command
+}
+
+fn run(home: &Path, args: &[&str]) -> Output {
+    cccost(home).args(args).output().unwrap()
+}
+
+#[test]
+fn json_report_contains_the_scanned_usage() {
+    let home = home();
+    let output = run(home.path(), &["--json"]);
+
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let days = report["data"].as_array().unwrap();
+    assert_eq!(days.len(), 1);
+    assert_eq!(days[0]["total_tokens"], 501_000);
+    assert_eq!(days[0]["models"][0]["model"], MODEL);
+}
+
+#[test]
+fn table_report_ends_with_the_footer() {
+    let home = home();
+    let output = run(home.path(), &[]);
+
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("501.0K"), "{}", stdout);
+    assert!(stdout.trim_end().lines().last().unwrap().starts_with("Generated "), "{}", stdout);
+}
+
+#[test]
+fn exceeding_the_token_limit_exits_with_3() {
+    let home = home();
+    assert_eq!(run(home.path(), &["--token-limit", "1000000"]).status.code(), Some(0));
+    assert_eq!(run(home.path(), &["--token-limit", "100"]).status.code(), Some(3));
+}
+
+#[test]
+fn project_over_budget_exits_with_4() {
+    let home = home();
+    let config = home.path().join(".config/cccost");
+    fs::create_dir_all(&config).unwrap();
+    fs::write(config.join("config.toml"), "[budgets.projects]\n\"/home/dev/app\" = 1.0\n").unwrap();
+
+    assert_eq!(run(home.path(), &[]).status.code(), Some(0));
+    let output = run(home.path(), &["--fail-over-budget"]);
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 projects are over this billing month's budget"));
+}
+
+#[test]
+fn unreadable_file_with_strict_exits_with_5() {
+    let home = home();
+    fs::write(home.path().join(".claude/projects/-home-dev-app/old.jsonl.gz"), "not gzip").unwrap();
+
+    // 没有 --strict 时读取失败只出现在诊断信息中
+    let output = run(home.path(), &["--json"]);
+    assert_eq!(output.status.code(), Some(0));
+    let output = run(home.path(), &["--strict"]);
+    assert_eq!(output.status.code(), Some(5));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--strict"));
+}
+
+#[cfg(unix)]
+#[test]
+fn ctrl_c_during_watch_exits_with_130() {
+    use std::process::Stdio;
+    use std::thread;
+    use std::time::Duration;
+
+    let home = home();
+    let child = cccost(home.path())
+        .args(["blocks", "--active", "--watch=60"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // 等第一次扫描完成、进入等待后再发送 SIGINT
+    thread::sleep(Duration::from_secs(1));
+    let killed = Command::new("kill").args(["-INT", &child.id().to_string()]).status().unwrap();
+    assert!(killed.success());
+
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(130));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Interrupted"));
+}
+
+#[test]
+fn merged_exports_add_up() {
+    let home = home();
+    for label in ["alice", "bob"] {
+        let output = run(home.path(), &["--json", "--label", label]);
+        assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+        fs::write(home.path().join(format!("{}.json", label)), output.stdout).unwrap();
+    }
+    let alice = home.path().join("alice.json");
+    let bob = home.path().join("bob.json");
+    let files = [alice.to_str().unwrap(), bob.to_str().unwrap()];
+
+    let output = run(home.path(), &["--json", "merge", files[0], files[1]]);
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["data"][0]["total_tokens"], 2 * 501_000);
+
+    let output = run(home.path(), &["merge", files[0], files[1], "--by-person"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("alice") && stdout.contains("bob"), "{}", stdout);
+
+    // 格式版本不同的导出无法合并
+    let mut export: Value = serde_json::from_str(&fs::read_to_string(&bob).unwrap()).unwrap();
+    export["meta"]["schema_version"] = Value::from(999);
+    fs::write(&bob, export.to_string()).unwrap();
+    let output = run(home.path(), &["merge", files[0], files[1]]);
+    assert_ne!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("999"), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+/// 输出中表格的各行（含边框线），去掉着色
+fn table_lines(stdout: &[u8]) -> Vec<String> {
+    let text = String::from_utf8_lossy(stdout);
+    let mut plain = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            chars.by_ref().find(|c| c.is_ascii_alphabetic());
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+        .lines()
+        .skip_while(|line| !line.starts_with('┌'))
+        .take_while(|line| line.starts_with(['┌', '│', '├', '└']))
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn piped_output_is_not_shredded() {
+    let home = home();
+    let render = |columns: Option<&str>, args: &[&str]| {
+        let mut command = cccost(home.path());
+        match columns {
+            Some(columns) => command.env("COLUMNS", columns),
+            None => command.env_remove("COLUMNS"),
+        };
+        let output = command.args(["--layout", "table"]).args(args).output().unwrap();
+        assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+        table_lines(&output.stdout)
+    };
+
+    // 输出不是终端：没有 COLUMNS 时按 120 列排版，COLUMNS 次之，--width 优先于两者
+    for (columns, args, width) in [(None, &[][..], 96), (Some("200"), &[][..], 156), (Some("200"), &["--width", "90"][..], 90)] {
+        let table = render(columns, args);
+        assert!(!table.is_empty());
+        assert!(table.iter().all(|line| line.chars().count() == width), "{:?} {:?}\n{}", columns, args, table.join("\n"));
+        // 表头、当天与合计各占一行，单元格没有被折断
+        let rows: Vec<&String> = table.iter().filter(|line| line.starts_with('│')).collect();
+        assert_eq!(rows.len(), 3, "{}", table.join("\n"));
+        assert!(rows[0].contains("Date") && rows[0].contains("Model") && rows[0].contains("Output"), "{}", rows[0]);
+        assert!(rows[1].contains("sonnet4") && rows[1].contains("500.0K") && rows[1].contains("501.0K"), "{}", rows[1]);
+    }
+}